@@ -0,0 +1,144 @@
+//! What the keys of the main view do. A key is first looked up in the keymap, and the
+//! [`Action`] it is bound to is then carried out by the app. Prompts and popups take
+//! their keys before the keymap is consulted.
+
+use crate::ui::player::{SPEED_STEP, VOLUME_STEP};
+use crossterm::event::KeyCode;
+
+/// Seconds `h`/`l` seek by
+const SHORT_SEEK: f32 = 5.0;
+
+/// Seconds `H`/`L` seek by
+const LONG_SEEK: f32 = 30.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Quit,
+    SelectPrevious,
+    SelectNext,
+    /// Expand the selected node, or play or queue what it stands for
+    Activate,
+    Collapse,
+    /// Pause or resume, or start the last song or station over once stopped
+    TogglePlayPause,
+    Stop,
+    NextTrack,
+    PreviousTrack,
+    ToggleStarredFilter,
+    /// Queue the marked items, or the selected playlist if nothing is marked
+    QueueSelected,
+    ToggleMark,
+    MarkRange,
+    /// Reveal the album (`true`) or artist of the playing song
+    RevealPlaying(bool),
+    RevealSelected,
+    /// Show the biography and similar artists of the selected artist
+    ShowArtistInfo,
+    /// Change the volume by this many percent
+    ChangeVolume(f64),
+    ToggleMute,
+    /// Change the playback speed by this much
+    ChangeSpeed(f64),
+    ResetSpeed,
+    /// Seek by this many seconds
+    Seek(f32),
+    /// Seek to this percentage of the song
+    SeekPercent(f32),
+    /// Change the volume of the playing song by this many percent
+    ChangeSongVolume(f64),
+    /// Wait for the letter of the index section to jump to
+    JumpToIndex,
+    OpenCommandLine,
+    ToggleCompact,
+    OpenSearch,
+    OpenFinder,
+    /// Replace the queue with the selected genre or playlist, shuffled if `true`
+    PlaySelected(bool),
+    NewRadioStation,
+    AddToPlaylist,
+    EditSelected,
+    DeleteSelected,
+    SaveToQuickPlaylist,
+    /// Save the marked songs and albums, or the selected one, for listening offline
+    Download,
+    /// Show where the file of the selected or playing song is, if it is on this machine
+    ShowFile,
+    ToggleScrobbling,
+    ToggleSongScrobbling,
+    ShowJobs,
+    /// Have the server rescan the library, reloading the artists once it is done
+    ScanLibrary,
+    StartRadio,
+    TogglePublic,
+    Undo,
+    /// Wait for the register to record keys into, or stop recording
+    RecordMacro,
+    /// Wait for the register to play back
+    ReplayMacro,
+    /// Put the ID of the selected item on the clipboard
+    CopyId,
+    /// Put the stream URL of the selected song or radio station on the clipboard
+    CopyUrl,
+}
+
+/// The action bound to `key` in the main view
+pub fn keymap(key: KeyCode) -> Option<Action> {
+    let action = match key {
+        KeyCode::Esc => Action::Quit,
+        KeyCode::Up => Action::SelectPrevious,
+        KeyCode::Down => Action::SelectNext,
+        KeyCode::Enter | KeyCode::Right => Action::Activate,
+        KeyCode::Left => Action::Collapse,
+        KeyCode::Char(' ') => Action::TogglePlayPause,
+        KeyCode::Char('s') => Action::Stop,
+        KeyCode::Char('n' | '>') => Action::NextTrack,
+        KeyCode::Char('p' | '<') => Action::PreviousTrack,
+        KeyCode::Char('F') => Action::ToggleStarredFilter,
+        KeyCode::Char('Q') => Action::QueueSelected,
+        KeyCode::Char('v') => Action::ToggleMark,
+        KeyCode::Char('V') => Action::MarkRange,
+        KeyCode::Char(c @ ('g' | 'G')) => Action::RevealPlaying(c == 'g'),
+        KeyCode::Char('o') => Action::RevealSelected,
+        KeyCode::Char('i') => Action::ShowArtistInfo,
+        KeyCode::Char('+' | '=') => Action::ChangeVolume(VOLUME_STEP),
+        KeyCode::Char('-') => Action::ChangeVolume(-VOLUME_STEP),
+        KeyCode::Char('M') => Action::ToggleMute,
+        KeyCode::Char('[') => Action::ChangeSpeed(-SPEED_STEP),
+        KeyCode::Char(']') => Action::ChangeSpeed(SPEED_STEP),
+        KeyCode::Backspace => Action::ResetSpeed,
+        KeyCode::Char(c @ ('h' | 'l' | 'H' | 'L')) => {
+            let step = if c.is_uppercase() { LONG_SEEK } else { SHORT_SEEK };
+            Action::Seek(if c.eq_ignore_ascii_case(&'l') { step } else { -step })
+        }
+        // Like mpv, 0 starts over and 5 jumps to the middle
+        KeyCode::Char(c @ '0'..='9') => Action::SeekPercent(c.to_digit(10).unwrap_or(0) as f32 * 10.0),
+        KeyCode::Char('(') => Action::ChangeSongVolume(-VOLUME_STEP),
+        KeyCode::Char(')') => Action::ChangeSongVolume(VOLUME_STEP),
+        KeyCode::Char('\'') => Action::JumpToIndex,
+        KeyCode::Char(':') => Action::OpenCommandLine,
+        KeyCode::Char('m') => Action::ToggleCompact,
+        KeyCode::Char('/') => Action::OpenSearch,
+        KeyCode::Char('f') => Action::OpenFinder,
+        KeyCode::Char(c @ ('P' | 'S')) => Action::PlaySelected(c == 'S'),
+        KeyCode::Char('a') => Action::NewRadioStation,
+        KeyCode::Char('A') => Action::AddToPlaylist,
+        KeyCode::Char('e') => Action::EditSelected,
+        KeyCode::Char('d') => Action::DeleteSelected,
+        KeyCode::Char('b') => Action::SaveToQuickPlaylist,
+        KeyCode::Char('D') => Action::Download,
+        KeyCode::Char('O') => Action::ShowFile,
+        KeyCode::Char('x') => Action::ToggleScrobbling,
+        KeyCode::Char('X') => Action::ToggleSongScrobbling,
+        KeyCode::Char('J') => Action::ShowJobs,
+        KeyCode::Char('U') => Action::ScanLibrary,
+        KeyCode::Char('R') => Action::StartRadio,
+        KeyCode::Char('t') => Action::TogglePublic,
+        KeyCode::Char('u') => Action::Undo,
+        KeyCode::Char('q') => Action::RecordMacro,
+        KeyCode::Char('@') => Action::ReplayMacro,
+        KeyCode::Char('y') => Action::CopyId,
+        KeyCode::Char('Y') => Action::CopyUrl,
+        _ => return None,
+    };
+    Some(action)
+}
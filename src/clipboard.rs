@@ -0,0 +1,19 @@
+//! Copying text through the terminal with the OSC 52 escape sequence, which works over
+//! SSH and without a clipboard utility, in terminals that allow it (most do, some
+//! after a setting like `set-clipboard on` in tmux).
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::io::{self, Write};
+
+/// Put `text` on the system clipboard of the terminal HighPass runs in
+pub fn copy(text: &str) -> io::Result<()> {
+    let mut sequence = format!("\x1b]52;c;{}\x07", STANDARD.encode(text));
+    // tmux only hands sequences on to the outer terminal wrapped in its own
+    if std::env::var_os("TMUX").is_some() {
+        sequence = format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"));
+    }
+    let mut stdout = io::stdout();
+    stdout.write_all(sequence.as_bytes())?;
+    stdout.flush()
+}
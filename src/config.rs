@@ -2,27 +2,395 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
 use log::{info, debug};
+use subsonic::{AuthMethod, Transcoding};
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct SubsonicConfig {
+    /// The API the server speaks
+    #[serde(default)]
+    pub backend: Backend,
+    /// Not needed by the local backend
+    #[serde(default)]
     pub server: String,
+    #[serde(default)]
     pub username: String,
+    #[serde(default)]
     pub password: String,
+    /// Most API requests started per second, 0 for no limit
+    #[serde(default)]
+    pub max_requests_per_second: f32,
+    /// How Subsonic requests authenticate, "password" or "hex" for servers older than 1.13
+    #[serde(default)]
+    pub auth: AuthMethod,
+}
+
+/// Another server whose library is listed next to the one of `[subsonic]`
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ServerConfig {
+    /// Shown next to its artists, albums and songs, and put in front of their IDs
+    pub name: String,
+    #[serde(flatten)]
+    pub connection: SubsonicConfig,
+}
+
+/// The kind of server the library comes from
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// Subsonic and compatible servers like Navidrome, Airsonic or Gonic
+    #[default]
+    Subsonic,
+    /// Jellyfin, in builds with the `jellyfin` feature
+    Jellyfin,
+    /// No server, only the music files in the `local_music` directory of `[library]`
+    Local,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct SessionConfig {
+    /// Seconds between automatic saves of the playback session
+    pub autosave_interval: u64,
+    /// Restore the saved queue and position on startup
+    pub restore: bool,
+    /// Also save the queue and position on the server and resume from there when it
+    /// is newer than the local session, to continue on another device
+    pub sync: bool,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            autosave_interval: 30,
+            restore: true,
+            sync: false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct CoverArtConfig {
+    /// Edge length in pixels requested from the server
+    pub size: u32,
+    /// Fetch the original image instead when the terminal can display graphics
+    pub full_size_on_graphics: bool,
+    /// Show tiny covers next to albums in the tree when the terminal can display graphics
+    pub thumbnails: bool,
+}
+
+impl Default for CoverArtConfig {
+    fn default() -> Self {
+        Self {
+            size: 200,
+            full_size_on_graphics: false,
+            thumbnails: false,
+        }
+    }
+}
+
+impl CoverArtConfig {
+    /// The size to request, `None` for the original image
+    pub fn request_size(&self, graphics: bool) -> Option<u32> {
+        if graphics && self.full_size_on_graphics {
+            None
+        } else {
+            Some(self.size)
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct ThemeConfig {
+    /// Tint the progress bar and selection with the dominant color of the current cover
+    pub accent_from_cover: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct MarqueeConfig {
+    /// Characters per second at which long titles scroll, 0 to truncate them instead
+    pub speed: f32,
+    /// Seconds to rest at either end before scrolling back
+    pub pause: f32,
+}
+
+impl Default for MarqueeConfig {
+    fn default() -> Self {
+        Self {
+            speed: 8.0,
+            pause: 2.0,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct LayoutConfig {
+    /// Collapse the player to a single line in terminals with fewer rows, 0 to never do so
+    pub compact_below: u16,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self { compact_below: 24 }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct LibraryConfig {
+    /// Sort and match artists ignoring case, accents and a leading "The ", instead of
+    /// keeping the server's order
+    pub collate_artists: bool,
+    /// Playlist `b` appends the playing song to, created when it doesn't exist yet
+    pub quick_playlist: String,
+    /// List the library by artist and album tags or by the folders it is stored in
+    pub browse: BrowseMode,
+    /// Name or ID of the music folder to list the library from, empty for all of them
+    pub music_folder: String,
+    /// Directory of music files listed and played next to the server's library, empty
+    /// for none. A leading `~/` stands for the home directory.
+    pub local_music: String,
+    /// Directory `D` saves songs to, empty for `local_music` or else `~/Music`
+    pub download_dir: String,
+}
+
+/// `path` with a leading `~/` standing for the home directory
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+impl LibraryConfig {
+    pub fn local_music_dir(&self) -> Option<PathBuf> {
+        (!self.local_music.is_empty()).then(|| expand_home(&self.local_music))
+    }
+
+    /// Where downloaded songs go, so they show up among the local music when it is set
+    pub fn download_dir(&self) -> PathBuf {
+        if !self.download_dir.is_empty() {
+            return expand_home(&self.download_dir);
+        }
+        self.local_music_dir().unwrap_or_else(|| expand_home("~/Music"))
+    }
+}
+
+impl Default for LibraryConfig {
+    fn default() -> Self {
+        Self {
+            collate_artists: true,
+            quick_playlist: "Liked Later".to_string(),
+            browse: BrowseMode::default(),
+            music_folder: String::new(),
+            local_music: String::new(),
+            download_dir: String::new(),
+        }
+    }
+}
+
+/// How the library below the sections of the tree is organized
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BrowseMode {
+    /// Artists and their albums, from the tags (`getArtists`)
+    #[default]
+    Tags,
+    /// The directories of the music folders (`getIndexes`), for libraries organized by
+    /// folder or servers without tag-based browsing
+    Folders,
+}
+
+/// Which destructive actions ask before they run
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct ConfirmConfig {
+    pub clear_queue: bool,
+    /// Unstarring more than one item at once
+    pub unstar_many: bool,
+    pub delete_radio_station: bool,
+    pub delete_playlist: bool,
+}
+
+impl Default for ConfirmConfig {
+    fn default() -> Self {
+        Self {
+            clear_queue: true,
+            unstar_many: true,
+            delete_radio_station: true,
+            delete_playlist: true,
+        }
+    }
+}
+
+/// Which ReplayGain values to normalize loudness with
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplayGainMode {
+    #[default]
+    Off,
+    Track,
+    /// Keep the loudness differences between the songs of an album
+    Album,
+}
+
+impl ReplayGainMode {
+    /// Value of mpv's `replaygain` option
+    pub fn mpv_value(self) -> &'static str {
+        match self {
+            ReplayGainMode::Off => "no",
+            ReplayGainMode::Track => "track",
+            ReplayGainMode::Album => "album",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct PlaybackConfig {
+    /// Normalize with the gains the server computed, or the stream's ReplayGain tags
+    /// when it has none
+    pub replaygain: ReplayGainMode,
+    /// dB added to every gain
+    pub replaygain_preamp: f32,
+    /// Report what is playing and record plays on the server
+    pub scrobble: bool,
+    /// A play is recorded after this percentage of a track has played...
+    pub scrobble_percent: f32,
+    /// ...or this many seconds, whichever comes first
+    pub scrobble_seconds: u32,
+    /// Start out playing on the server's jukebox instead of this machine
+    pub jukebox: bool,
+    /// Pause when the system is about to suspend
+    pub pause_on_suspend: bool,
+    /// Pause when the audio output fails or an audio device goes away, e.g. unplugged headphones
+    pub pause_on_device_loss: bool,
+    /// Pause while the screen is locked, resuming once it is unlocked
+    pub pause_on_lock: bool,
+    /// Keep the system from suspending for idleness while playing
+    pub inhibit_idle: bool,
+}
+
+impl Default for PlaybackConfig {
+    fn default() -> Self {
+        Self {
+            replaygain: ReplayGainMode::default(),
+            replaygain_preamp: 0.0,
+            scrobble: true,
+            scrobble_percent: 50.0,
+            scrobble_seconds: 240,
+            jukebox: false,
+            pause_on_suspend: true,
+            pause_on_device_loss: true,
+            pause_on_lock: false,
+            inhibit_idle: true,
+        }
+    }
+}
+
+/// How songs are streamed from the server, `:transcode` switches it off and on
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct StreamingConfig {
+    /// Highest bitrate in kbit/s the server streams at, 0 for no limit
+    pub max_bit_rate: u32,
+    /// Format the server transcodes to, like "opus" or "mp3", empty for its choice
+    pub format: String,
+}
+
+impl StreamingConfig {
+    /// What the server is asked for, `None` when neither option is set
+    pub fn transcoding(&self) -> Option<Transcoding> {
+        if self.max_bit_rate == 0 && self.format.is_empty() {
+            return None;
+        }
+        Some(Transcoding {
+            max_bit_rate: self.max_bit_rate,
+            format: (!self.format.is_empty()).then(|| self.format.clone()),
+        })
+    }
+}
+
+/// How HighPass reaches the servers
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// HTTP or SOCKS proxy for API requests and streams, empty for those of the environment
+    pub proxy: String,
+}
+
+impl NetworkConfig {
+    pub fn proxy(&self) -> Option<&str> {
+        (!self.proxy.is_empty()).then_some(self.proxy.as_str())
+    }
+}
+
+/// Format of the `--debug` log file
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// One JSON object per line, including the structured fields of each event
+    Json,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Config {
+    #[serde(default)]
+    pub log_format: LogFormat,
     pub subsonic: SubsonicConfig,
+    /// More servers merged into the library, `[[servers]]` in the file
+    #[serde(default)]
+    pub servers: Vec<ServerConfig>,
+    #[serde(default)]
+    pub session: SessionConfig,
+    #[serde(default)]
+    pub cover_art: CoverArtConfig,
+    #[serde(default)]
+    pub theme: ThemeConfig,
+    #[serde(default)]
+    pub marquee: MarqueeConfig,
+    #[serde(default)]
+    pub layout: LayoutConfig,
+    #[serde(default)]
+    pub library: LibraryConfig,
+    #[serde(default)]
+    pub confirm: ConfirmConfig,
+    #[serde(default)]
+    pub playback: PlaybackConfig,
+    #[serde(default)]
+    pub streaming: StreamingConfig,
+    #[serde(default)]
+    pub network: NetworkConfig,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            log_format: LogFormat::default(),
             subsonic: SubsonicConfig {
+                backend: Backend::default(),
                 server: "http://demo.subsonic.org".to_string(),
                 username: "guest".to_string(),
                 password: "guest".to_string(),
+                max_requests_per_second: 0.0,
+                auth: AuthMethod::default(),
             },
+            servers: Vec::new(),
+            session: SessionConfig::default(),
+            cover_art: CoverArtConfig::default(),
+            theme: ThemeConfig::default(),
+            marquee: MarqueeConfig::default(),
+            layout: LayoutConfig::default(),
+            library: LibraryConfig::default(),
+            confirm: ConfirmConfig::default(),
+            playback: PlaybackConfig::default(),
+            streaming: StreamingConfig::default(),
+            network: NetworkConfig::default(),
         }
     }
 }
@@ -56,6 +424,23 @@ impl Config {
         Err(error_msg.into())
     }
     
+    /// Turn off one of the `[confirm]` options in the config file in use, keeping the
+    /// rest of the file (including comments) as it is
+    pub fn disable_confirmation(name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Self::get_config_paths()
+            .into_iter()
+            .find(|path| path.exists())
+            .ok_or("No configuration file to save to")?;
+        let mut document: toml_edit::DocumentMut = fs::read_to_string(&path)?.parse()?;
+        if !document.contains_table("confirm") {
+            document["confirm"] = toml_edit::table();
+        }
+        document["confirm"][name] = toml_edit::value(false);
+        fs::write(&path, document.to_string())?;
+        info!("Disabled confirmation for {} in {}", name, path.display());
+        Ok(())
+    }
+
     /// Get the list of possible configuration file paths in search order
     fn get_config_paths() -> Vec<PathBuf> {
         let mut paths = Vec::new();
@@ -101,10 +486,40 @@ impl Config {
     fn load_from_file(path: &PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
         let content = fs::read_to_string(path)?;
         let config: Config = toml::from_str(&content)?;
+        if config.subsonic.backend == Backend::Jellyfin && !cfg!(feature = "jellyfin") {
+            return Err("backend = \"jellyfin\" needs HighPass built with `--features jellyfin`".into());
+        }
+        if config.subsonic.backend == Backend::Local {
+            if config.library.local_music.is_empty() {
+                return Err("backend = \"local\" needs the `local_music` directory in [library]".into());
+            }
+        } else if config.subsonic.server.is_empty() {
+            return Err("The server URL is missing, set `server` in [subsonic]".into());
+        }
+        let mut names = std::collections::HashSet::new();
+        for server in &config.servers {
+            let name = server.name.as_str();
+            if name.is_empty() || name.contains(':') || name == "local" || !names.insert(name) {
+                return Err(format!("[[servers]] need unique names without \":\" other than \"local\", not {:?}", name).into());
+            }
+            match server.connection.backend {
+                Backend::Local => return Err(format!("Server {} cannot be local, use `local_music` in [library]", name).into()),
+                Backend::Jellyfin if !cfg!(feature = "jellyfin") => {
+                    return Err(format!("Server {} needs HighPass built with `--features jellyfin`", name).into());
+                }
+                _ if server.connection.server.is_empty() => {
+                    return Err(format!("The URL of server {} is missing", name).into());
+                }
+                _ => {}
+            }
+        }
         
         info!("Successfully loaded configuration:");
-        info!("  Server: {}", config.subsonic.server);
+        info!("  Server: {} ({:?})", config.subsonic.server, config.subsonic.backend);
         info!("  Username: {}", config.subsonic.username);
+        for server in &config.servers {
+            info!("  Also {}: {} ({:?})", server.name, server.connection.server, server.connection.backend);
+        }
         // Don't log the password for security
         info!("  Password: [configured]");
         
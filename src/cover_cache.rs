@@ -0,0 +1,106 @@
+use log::{debug, info, warn};
+use std::fs;
+use std::path::PathBuf;
+
+/// The cache is trimmed to this size on startup, oldest files first
+const MAX_CACHE_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Cover art fetched from the server, stored on disk by cover art ID and size
+/// in `$XDG_CACHE_HOME/highpass/covers`
+#[derive(Debug, Clone)]
+pub struct CoverCache {
+    dir: Option<PathBuf>,
+}
+
+impl CoverCache {
+    pub fn new() -> Self {
+        Self { dir: Self::get_cache_dir() }
+    }
+
+    fn path(&self, cover_art_id: &str, size: Option<u32>) -> Option<PathBuf> {
+        // IDs are opaque to us, keep them from escaping the cache directory
+        let id: String = cover_art_id
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+            .collect();
+        let size = size.map_or("full".to_string(), |s| s.to_string());
+        self.dir.as_ref().map(|dir| dir.join(format!("{}-{}", id, size)))
+    }
+
+    pub fn get(&self, cover_art_id: &str, size: Option<u32>) -> Option<Vec<u8>> {
+        let path = self.path(cover_art_id, size)?;
+        let bytes = fs::read(&path).ok()?;
+        debug!("Cover art cache hit: {}", path.display());
+        Some(bytes)
+    }
+
+    pub fn put(&self, cover_art_id: &str, size: Option<u32>, bytes: &[u8]) {
+        let Some(path) = self.path(cover_art_id, size) else {
+            return;
+        };
+        let result = path
+            .parent()
+            .map(fs::create_dir_all)
+            .unwrap_or(Ok(()))
+            .and_then(|_| fs::write(&path, bytes));
+        if let Err(e) = result {
+            warn!("Failed to cache cover art at {}: {}", path.display(), e);
+        }
+    }
+
+    /// Delete the least recently written covers until the cache fits its size limit
+    pub fn prune(&self) {
+        let Some(entries) = self.dir.as_ref().and_then(|dir| fs::read_dir(dir).ok()) else {
+            return;
+        };
+
+        let mut files: Vec<_> = entries
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let metadata = entry.metadata().ok()?;
+                Some((metadata.modified().ok()?, metadata.len(), entry.path()))
+            })
+            .collect();
+        let mut total: u64 = files.iter().map(|(_, len, _)| len).sum();
+        if total <= MAX_CACHE_BYTES {
+            return;
+        }
+
+        files.sort();
+        let mut removed = 0;
+        for (_, len, path) in files {
+            if total <= MAX_CACHE_BYTES {
+                break;
+            }
+            if fs::remove_file(&path).is_ok() {
+                total -= len;
+                removed += 1;
+            }
+        }
+        info!("Pruned {} cached covers", removed);
+    }
+
+    /// Get the cache directory (~/.cache/highpass/covers on Unix)
+    fn get_cache_dir() -> Option<PathBuf> {
+        #[cfg(unix)]
+        {
+            std::env::var("XDG_CACHE_HOME")
+                .map(PathBuf::from)
+                .or_else(|_| std::env::var("HOME").map(|home| PathBuf::from(home).join(".cache")))
+                .ok()
+                .map(|dir| dir.join("highpass").join("covers"))
+        }
+
+        #[cfg(windows)]
+        {
+            std::env::var("LOCALAPPDATA")
+                .ok()
+                .map(|dir| PathBuf::from(dir).join("highpass").join("cache").join("covers"))
+        }
+
+        #[cfg(not(any(unix, windows)))]
+        {
+            None
+        }
+    }
+}
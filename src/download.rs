@@ -0,0 +1,104 @@
+//! Saving songs to a local directory for listening offline. Each song goes to
+//! `Artist/Album/01 Title.ext` below the download directory; songs already there are
+//! skipped, and a song is only given its name once it is complete. Downloaded files can
+//! be shown in the file manager.
+
+use crate::jobs::JobReporter;
+use crate::source::MusicSource;
+use log::{info, warn};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use subsonic::Song;
+
+/// Bytes downloaded between two progress reports
+const REPORT_EVERY: u64 = 256 * 1024;
+
+/// A name of a file or directory made from a tag
+fn clean(name: &str, fallback: &str) -> String {
+    let name = name.replace(['/', '\\', '\0'], "-");
+    let name = name.trim().trim_start_matches('.').trim();
+    if name.is_empty() {
+        fallback.to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Where a song is saved below the download directory
+fn song_path(song: &Song) -> PathBuf {
+    let artist = clean(song.artist.as_deref().unwrap_or_default(), "Unknown Artist");
+    let album = clean(song.album.as_deref().unwrap_or_default(), "Unknown Album");
+    let title = clean(&song.title, &song.id);
+    let mut file = match song.track {
+        Some(track) => format!("{:02} {}", track, title),
+        None => title,
+    };
+    let path_extension = song.path.as_deref().and_then(|path| Path::new(path).extension()?.to_str());
+    if let Some(extension) = song.suffix.as_deref().or(path_extension) {
+        file = format!("{}.{}", file, extension);
+    }
+    PathBuf::from(artist).join(album).join(file)
+}
+
+/// The file of a song downloaded to `dir`, if it is there
+pub fn downloaded(song: &Song, dir: &Path) -> Option<PathBuf> {
+    Some(dir.join(song_path(song))).filter(|path| path.exists())
+}
+
+/// Open the directory a file is in with the desktop's file manager
+pub fn open_folder(path: &Path) -> std::io::Result<()> {
+    let opener = if cfg!(target_os = "macos") { "open" } else { "xdg-open" };
+    let mut child = Command::new(opener)
+        .arg(path.parent().unwrap_or(path))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // Reap it once the opener hands over to the file manager
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// Save `songs` below `dir` one after the other, reporting the bytes done. Stops at the
+/// first song that fails.
+pub async fn save(client: &dyn MusicSource, songs: Vec<Song>, dir: &Path, reporter: &JobReporter) -> Result<(), String> {
+    let total = songs.iter().map(|song| song.size).sum::<Option<u64>>();
+    let mut done = 0;
+    for song in songs {
+        let path = dir.join(song_path(&song));
+        if path.exists() {
+            info!("{} is already downloaded", path.display());
+            done += song.size.unwrap_or(0);
+            reporter.bytes(done, total);
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Cannot create {}: {}", parent.display(), e))?;
+        }
+
+        let partial = path.with_extension("part");
+        let mut file = File::create(&partial).map_err(|e| format!("Cannot create {}: {}", partial.display(), e))?;
+        let mut reported = done;
+        let result = client
+            .download(&song.id, &mut |chunk, size| {
+                done += chunk.len() as u64;
+                if done - reported >= REPORT_EVERY {
+                    reported = done;
+                    reporter.bytes(done, total.or(size));
+                }
+                file.write_all(chunk)
+            })
+            .await;
+        if let Err(e) = result.map_err(|e| e.to_string()).and_then(|()| file.sync_all().map_err(|e| e.to_string())) {
+            warn!("Downloading {} failed: {}", song.title, e);
+            let _ = fs::remove_file(&partial);
+            return Err(format!("{}: {}", song.title, e));
+        }
+        fs::rename(&partial, &path).map_err(|e| format!("Cannot save {}: {}", path.display(), e))?;
+        info!("Downloaded {} to {}", song.title, path.display());
+        reporter.bytes(done, total);
+    }
+    Ok(())
+}
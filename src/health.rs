@@ -0,0 +1,81 @@
+//! Whether the server can be reached. It is pinged on startup and every
+//! [`PING_INTERVAL`] after that, and the tree's title shows the outcome, so a wrong URL
+//! or password shows up as such instead of as an empty library.
+
+use crate::source::MusicSource;
+use crate::Message;
+use log::{info, warn};
+use std::sync::Arc;
+use std::time::Duration;
+use subsonic::Error;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio_util::sync::CancellationToken;
+
+/// Time between two pings
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The last thing the server said about the connection
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Connection {
+    /// Not pinged yet
+    #[default]
+    Unknown,
+    Online,
+    /// The server did not answer, with the error
+    Offline(String),
+    /// The server answered but turned down the credentials
+    Unauthorized(String),
+}
+
+impl Connection {
+    pub fn from_ping(result: &subsonic::Result<()>) -> Self {
+        match result {
+            Ok(()) => Connection::Online,
+            // Wrong credentials or an authentication scheme the server doesn't speak
+            Err(e @ Error::Api { error, .. }) if error.is_auth() => Connection::Unauthorized(e.to_string()),
+            Err(Error::Http(e)) if e.status().is_some_and(|status| status.as_u16() == 401) => {
+                Connection::Unauthorized(e.to_string())
+            }
+            Err(e) => Connection::Offline(e.to_string()),
+        }
+    }
+
+    /// A symbol and word for the tree's title, with the color to draw them in
+    pub fn label(&self) -> Option<(&'static str, ratatui::style::Color)> {
+        use ratatui::style::Color;
+        match self {
+            Connection::Unknown => None,
+            Connection::Online => Some(("● online", Color::Green)),
+            Connection::Offline(_) => Some(("○ offline", Color::Red)),
+            Connection::Unauthorized(_) => Some(("✗ login failed", Color::Red)),
+        }
+    }
+}
+
+/// Ping `source` now and every [`PING_INTERVAL`] until `token` is cancelled, sending
+/// each change of the connection to the app as [`Message::Connection`]
+pub fn watch(source: Arc<dyn MusicSource>, sender: UnboundedSender<Message>, token: CancellationToken) {
+    tokio::spawn(async move {
+        token
+            .run_until_cancelled(async move {
+                let mut last = Connection::Unknown;
+                loop {
+                    let connection = Connection::from_ping(&source.ping().await);
+                    if connection != last {
+                        match &connection {
+                            Connection::Online => info!("Server is online"),
+                            Connection::Offline(e) => warn!("Server is offline: {}", e),
+                            Connection::Unauthorized(e) => warn!("Server refused the login: {}", e),
+                            Connection::Unknown => {}
+                        }
+                        if sender.send(Message::Connection(connection.clone())).is_err() {
+                            break;
+                        }
+                        last = connection;
+                    }
+                    tokio::time::sleep(PING_INTERVAL).await;
+                }
+            })
+            .await;
+    });
+}
@@ -0,0 +1,157 @@
+use crate::Message;
+use log::{debug, info, warn};
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+
+/// Actions a second `highpass` process hands over to the running instance
+#[derive(Debug, Clone, PartialEq)]
+pub enum RemoteCommand {
+    EnqueueAlbum(String),
+    PlayAlbum(String),
+    /// Play the playlist with this name (or ID)
+    PlayPlaylist(String),
+}
+
+impl RemoteCommand {
+    /// The action requested on the command line, if any
+    pub fn from_args(args: &[String]) -> Option<Self> {
+        let value = |flag: &str| {
+            args.iter()
+                .position(|arg| arg == flag)
+                .and_then(|i| args.get(i + 1))
+                .cloned()
+        };
+        value("--enqueue-album")
+            .map(RemoteCommand::EnqueueAlbum)
+            .or_else(|| value("--play-album").map(RemoteCommand::PlayAlbum))
+            .or_else(|| value("--play-playlist").map(RemoteCommand::PlayPlaylist))
+            .or_else(|| args.iter().skip(1).find_map(|arg| Self::from_uri(arg)))
+    }
+
+    /// Parse `subsonic://album/<id>` and `subsonic://playlist/<name>`
+    fn from_uri(uri: &str) -> Option<Self> {
+        let (kind, target) = uri.strip_prefix("subsonic://")?.split_once('/')?;
+        let target = urlencoding::decode(target.trim_end_matches('/')).ok()?.into_owned();
+        if target.is_empty() {
+            return None;
+        }
+        match kind {
+            "album" => Some(RemoteCommand::PlayAlbum(target)),
+            "playlist" => Some(RemoteCommand::PlayPlaylist(target)),
+            _ => None,
+        }
+    }
+
+    /// Parse one line of the socket protocol, e.g. `enqueue-album <id>`
+    fn parse(line: &str) -> Option<Self> {
+        let (name, argument) = line.trim().split_once(' ')?;
+        match name {
+            "enqueue-album" => Some(RemoteCommand::EnqueueAlbum(argument.to_string())),
+            "play-album" => Some(RemoteCommand::PlayAlbum(argument.to_string())),
+            "play-playlist" => Some(RemoteCommand::PlayPlaylist(argument.to_string())),
+            _ => None,
+        }
+    }
+
+    fn to_line(&self) -> String {
+        match self {
+            RemoteCommand::EnqueueAlbum(id) => format!("enqueue-album {}\n", id),
+            RemoteCommand::PlayAlbum(id) => format!("play-album {}\n", id),
+            RemoteCommand::PlayPlaylist(name) => format!("play-playlist {}\n", name),
+        }
+    }
+}
+
+/// Socket of the running instance ($XDG_RUNTIME_DIR/highpass-$USER.sock)
+fn socket_path() -> PathBuf {
+    let dir = std::env::var("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| std::env::temp_dir());
+    let user = std::env::var("USER").unwrap_or_default();
+    let name = if user.is_empty() { "highpass.sock".to_string() } else { format!("highpass-{}.sock", user) };
+    dir.join(name)
+}
+
+/// Listens for commands from other instances and removes the socket when dropped
+pub struct Server {
+    path: PathBuf,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(unix)]
+mod imp {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{UnixListener, UnixStream};
+
+    /// Hand `command` to a running instance. Returns `false` if no instance is running.
+    pub async fn send(command: Option<&RemoteCommand>) -> bool {
+        let path = socket_path();
+        let Ok(mut stream) = UnixStream::connect(&path).await else {
+            return false;
+        };
+
+        info!("Found running instance at {}", path.display());
+        if let Some(command) = command {
+            if let Err(e) = stream.write_all(command.to_line().as_bytes()).await {
+                warn!("Failed to send command to running instance: {}", e);
+            }
+        }
+        true
+    }
+
+    /// Accept commands from other instances and forward them as messages
+    pub fn serve(sender: mpsc::UnboundedSender<Message>) -> Option<Server> {
+        let path = socket_path();
+
+        // Nobody answered on the socket, so any file left there is from a crashed instance
+        let _ = std::fs::remove_file(&path);
+        let listener = match UnixListener::bind(&path) {
+            Ok(listener) => listener,
+            Err(e) => {
+                warn!("Failed to listen on {}: {}", path.display(), e);
+                return None;
+            }
+        };
+        info!("Listening for commands on {}", path.display());
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    let mut lines = BufReader::new(stream).lines();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        match RemoteCommand::parse(&line) {
+                            Some(command) => {
+                                let _ = sender.send(Message::Remote(command));
+                            }
+                            None => debug!("Ignoring unknown command: {}", line),
+                        }
+                    }
+                });
+            }
+        });
+
+        Some(Server { path })
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    use super::*;
+
+    pub async fn send(_command: Option<&RemoteCommand>) -> bool {
+        false
+    }
+
+    pub fn serve(_sender: mpsc::UnboundedSender<Message>) -> Option<Server> {
+        None
+    }
+}
+
+pub use imp::{send, serve};
@@ -0,0 +1,227 @@
+use crate::Message;
+use log::{info, warn};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc::UnboundedSender, Semaphore};
+use tokio::task::{AbortHandle, JoinSet};
+use tokio_util::sync::CancellationToken;
+
+/// Finished jobs kept for the panel, older ones are forgotten
+const MAX_FINISHED: usize = 20;
+
+pub type JobId = u64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobStatus {
+    Running,
+    Done,
+    Failed(String),
+    Cancelled,
+}
+
+/// A long-running piece of background work shown in the jobs panel
+#[derive(Debug)]
+pub struct Job {
+    pub id: JobId,
+    pub name: String,
+    /// Steps done so far and, when known up front, how many there are
+    pub progress: (usize, Option<usize>),
+    /// `progress` counts bytes rather than steps
+    pub in_bytes: bool,
+    pub status: JobStatus,
+    pub started: Instant,
+    pub finished: Option<Instant>,
+    task: Option<AbortHandle>,
+}
+
+impl Job {
+    /// How long the job ran, or has been running
+    pub fn elapsed(&self) -> Duration {
+        self.finished.unwrap_or_else(Instant::now) - self.started
+    }
+}
+
+/// Sent from a job's task back to the app, as [`Message::Job`]
+#[derive(Debug, Clone)]
+pub enum JobUpdate {
+    Progress(JobId, usize, Option<usize>),
+    /// Progress counted in bytes, e.g. of a download
+    Bytes(JobId, u64, Option<u64>),
+    Finished(JobId, Result<(), String>),
+}
+
+/// Handed to a job's task to report on it
+#[derive(Debug, Clone)]
+pub struct JobReporter {
+    id: JobId,
+    sender: UnboundedSender<Message>,
+}
+
+impl JobReporter {
+    pub fn progress(&self, done: usize, total: Option<usize>) {
+        let _ = self.sender.send(Message::Job(JobUpdate::Progress(self.id, done, total)));
+    }
+
+    pub fn bytes(&self, done: u64, total: Option<u64>) {
+        let _ = self.sender.send(Message::Job(JobUpdate::Bytes(self.id, done, total)));
+    }
+
+    /// Run `tasks`, at most `concurrency` at a time, counting each finished one as a step.
+    /// Fails with how many of them failed. Cancelling the job aborts the tasks not done yet.
+    pub async fn run_all<Fut>(&self, concurrency: usize, tasks: Vec<Fut>) -> Result<(), String>
+    where
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        let total = tasks.len();
+        let semaphore = Arc::new(Semaphore::new(concurrency));
+        let mut running = JoinSet::new();
+        for task in tasks {
+            let semaphore = semaphore.clone();
+            running.spawn(async move {
+                let _permit = semaphore.acquire_owned().await.map_err(|e| e.to_string())?;
+                task.await
+            });
+        }
+
+        let (mut done, mut failed) = (0, 0);
+        while let Some(result) = running.join_next().await {
+            done += 1;
+            if !matches!(result, Ok(Ok(()))) {
+                failed += 1;
+            }
+            self.progress(done, Some(total));
+        }
+        match failed {
+            0 => Ok(()),
+            failed => Err(format!("{} of {} failed", failed, total)),
+        }
+    }
+}
+
+/// The background jobs of this session, in the order they were started
+#[derive(Debug)]
+pub struct Jobs {
+    jobs: Vec<Job>,
+    next_id: JobId,
+    /// Stops all running jobs when cancelled, on quit
+    shutdown: CancellationToken,
+}
+
+impl Jobs {
+    pub fn new(shutdown: CancellationToken) -> Self {
+        Self {
+            jobs: Vec::new(),
+            next_id: 0,
+            shutdown,
+        }
+    }
+
+    /// Spawn `work` as a job. It gets a reporter for its progress and its result marks
+    /// the job done or failed.
+    pub fn spawn<F, Fut>(&mut self, name: impl Into<String>, sender: UnboundedSender<Message>, work: F) -> JobId
+    where
+        F: FnOnce(JobReporter) -> Fut,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        let id = self.next_id;
+        self.next_id += 1;
+        let name = name.into();
+        info!("Starting job {}: {}", id, name);
+
+        let reporter = JobReporter { id, sender: sender.clone() };
+        let work = work(reporter);
+        let shutdown = self.shutdown.clone();
+        let task = tokio::spawn(async move {
+            if let Some(result) = shutdown.run_until_cancelled(work).await {
+                let _ = sender.send(Message::Job(JobUpdate::Finished(id, result)));
+            }
+        });
+
+        self.jobs.push(Job {
+            id,
+            name,
+            progress: (0, None),
+            in_bytes: false,
+            status: JobStatus::Running,
+            started: Instant::now(),
+            finished: None,
+            task: Some(task.abort_handle()),
+        });
+        id
+    }
+
+    pub fn update(&mut self, update: JobUpdate) {
+        match update {
+            JobUpdate::Progress(id, done, total) => {
+                if let Some(job) = self.get_mut(id) {
+                    job.progress = (done, total);
+                }
+            }
+            JobUpdate::Bytes(id, done, total) => {
+                if let Some(job) = self.get_mut(id) {
+                    job.progress = (done as usize, total.map(|total| total as usize));
+                    job.in_bytes = true;
+                }
+            }
+            JobUpdate::Finished(id, result) => {
+                let Some(job) = self.get_mut(id).filter(|job| job.status == JobStatus::Running) else {
+                    return;
+                };
+                job.status = match result {
+                    Ok(()) => JobStatus::Done,
+                    Err(e) => {
+                        warn!("Job {} failed: {}", job.name, e);
+                        JobStatus::Failed(e)
+                    }
+                };
+                job.finished = Some(Instant::now());
+                job.task = None;
+                self.forget_old();
+            }
+        }
+    }
+
+    /// Abort a running job. Returns whether it was still running.
+    pub fn cancel(&mut self, id: JobId) -> bool {
+        let Some(job) = self.get_mut(id).filter(|job| job.status == JobStatus::Running) else {
+            return false;
+        };
+        info!("Cancelling job {}: {}", id, job.name);
+        if let Some(task) = job.task.take() {
+            task.abort();
+        }
+        job.status = JobStatus::Cancelled;
+        job.finished = Some(Instant::now());
+        self.forget_old();
+        true
+    }
+
+    pub fn list(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    /// The job with this ID while it is running
+    pub fn get_running(&self, id: JobId) -> Option<&Job> {
+        self.jobs.iter().find(|job| job.id == id && job.status == JobStatus::Running)
+    }
+
+    pub fn running(&self) -> usize {
+        self.jobs.iter().filter(|job| job.status == JobStatus::Running).count()
+    }
+
+    fn get_mut(&mut self, id: JobId) -> Option<&mut Job> {
+        self.jobs.iter_mut().find(|job| job.id == id)
+    }
+
+    fn forget_old(&mut self) {
+        let mut finished = self.jobs.iter().filter(|job| job.status != JobStatus::Running).count();
+        self.jobs.retain(|job| {
+            if job.status == JobStatus::Running || finished <= MAX_FINISHED {
+                return true;
+            }
+            finished -= 1;
+            false
+        });
+    }
+}
@@ -0,0 +1,196 @@
+//! Playback on the server's jukebox, the audio output of the server itself, with HighPass
+//! as its remote control. Commands are sent one after another from a background task,
+//! which also polls the jukebox and reports its position as player events.
+
+use crate::source::MusicSource;
+use crate::ui::player::PlayerEvent;
+use log::{debug, info, warn};
+use std::sync::Arc;
+use std::time::Duration;
+use subsonic::JukeboxStatus;
+use tokio::sync::mpsc;
+
+/// How often the jukebox is asked for its position
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+enum Command {
+    /// Make a song the only one in the jukebox playlist, starting `offset` seconds into it
+    Load { song_id: String, offset: u32, play: bool },
+    Play,
+    Pause,
+    Seek(u32),
+    /// Volume between 0 and 1
+    Gain(f32),
+    Stop,
+}
+
+/// Handle on the task controlling the jukebox. Dropping it stops the jukebox.
+#[derive(Debug)]
+pub struct Jukebox {
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+impl Jukebox {
+    /// Take over the server's jukebox. What it reports is handed to `on_event`, which
+    /// should bring it back to [`PlayerState::handle_event`](crate::ui::player::PlayerState::handle_event).
+    pub fn new<F>(client: Arc<dyn MusicSource>, on_event: F) -> Self
+    where
+        F: FnMut(PlayerEvent) -> bool + Send + 'static,
+    {
+        info!("Controlling the server's jukebox");
+        let (commands, receiver) = mpsc::unbounded_channel();
+        let remote = Remote {
+            client,
+            on_event,
+            playing: false,
+            start_at: None,
+        };
+        tokio::spawn(remote.run(receiver));
+        Self { commands }
+    }
+
+    /// Play a song from `offset` seconds, or only cue it there unless `play`
+    pub fn load(&self, song_id: &str, offset: f32, play: bool) {
+        self.send(Command::Load {
+            song_id: song_id.to_string(),
+            offset: offset.max(0.0) as u32,
+            play,
+        });
+    }
+
+    pub fn play(&self) {
+        self.send(Command::Play);
+    }
+
+    pub fn pause(&self) {
+        self.send(Command::Pause);
+    }
+
+    /// Jump to `position` seconds into the song
+    pub fn seek(&self, position: f32) {
+        self.send(Command::Seek(position.max(0.0) as u32));
+    }
+
+    /// Volume in percent
+    pub fn set_volume(&self, volume: f64) {
+        self.send(Command::Gain((volume / 100.0) as f32));
+    }
+
+    /// Stop playing and empty the jukebox playlist
+    pub fn stop(&self) {
+        self.send(Command::Stop);
+    }
+
+    fn send(&self, command: Command) {
+        let _ = self.commands.send(command);
+    }
+}
+
+/// The task's side of the jukebox
+struct Remote<F> {
+    client: Arc<dyn MusicSource>,
+    on_event: F,
+    /// The jukebox should be playing, so it stopping by itself is the end of the song
+    playing: bool,
+    /// Where to start a song that was loaded without playing it
+    start_at: Option<u32>,
+}
+
+impl<F> Remote<F>
+where
+    F: FnMut(PlayerEvent) -> bool + Send + 'static,
+{
+    async fn run(mut self, mut commands: mpsc::UnboundedReceiver<Command>) {
+        let mut poll = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            let status = tokio::select! {
+                command = commands.recv() => {
+                    let Some(command) = command else {
+                        break;
+                    };
+                    debug!("Jukebox command {:?}", command);
+                    self.execute(command).await
+                }
+                _ = poll.tick() => self.client.jukebox_status().await,
+            };
+            match status {
+                Ok(status) => {
+                    if !self.report(status) {
+                        return;
+                    }
+                }
+                Err(e) => warn!("Jukebox request failed: {}", e),
+            }
+        }
+
+        info!("Releasing the server's jukebox");
+        if let Err(e) = self.client.jukebox_stop().await {
+            warn!("Failed to stop the jukebox: {}", e);
+        }
+    }
+
+    async fn execute(&mut self, command: Command) -> subsonic::Result<JukeboxStatus> {
+        match command {
+            Command::Load { song_id, offset, play } => {
+                self.playing = false;
+                self.start_at = None;
+                let status = self.client.jukebox_set(&[song_id]).await?;
+                (self.on_event)(PlayerEvent::PlaybackStarted);
+                if play {
+                    self.start(offset).await
+                } else {
+                    self.start_at = Some(offset).filter(|offset| *offset > 0);
+                    Ok(status)
+                }
+            }
+            Command::Play => {
+                let offset = self.start_at.take().unwrap_or(0);
+                self.start(offset).await
+            }
+            Command::Pause => {
+                self.playing = false;
+                self.client.jukebox_stop().await
+            }
+            // Skipping starts playback on some servers, a paused song starts there later
+            Command::Seek(position) if !self.playing => {
+                self.start_at = Some(position);
+                self.client.jukebox_status().await
+            }
+            Command::Seek(position) => {
+                self.client.jukebox_skip(0, position).await?;
+                self.client.jukebox_start().await
+            }
+            Command::Gain(gain) => self.client.jukebox_set_gain(gain).await,
+            Command::Stop => {
+                self.playing = false;
+                self.start_at = None;
+                self.client.jukebox_stop().await?;
+                self.client.jukebox_set(&[]).await
+            }
+        }
+    }
+
+    async fn start(&mut self, offset: u32) -> subsonic::Result<JukeboxStatus> {
+        if offset > 0 {
+            self.client.jukebox_skip(0, offset).await?;
+        }
+        let status = self.client.jukebox_start().await?;
+        self.playing = true;
+        Ok(status)
+    }
+
+    /// Pass the jukebox's state on as player events. Returns false once nobody listens.
+    fn report(&mut self, status: JukeboxStatus) -> bool {
+        let mut events = Vec::new();
+        if let Some(position) = self.start_at.or(status.position) {
+            events.push(PlayerEvent::Progress(position as f32));
+        }
+        if self.playing && !status.playing {
+            debug!("Jukebox stopped by itself at song {}", status.current_index);
+            self.playing = false;
+            events.push(PlayerEvent::EndOfFile);
+        }
+        events.into_iter().all(|event| (self.on_event)(event))
+    }
+}
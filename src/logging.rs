@@ -0,0 +1,61 @@
+use crate::config::LogFormat;
+use log::kv::{Error, Key, Value, VisitSource};
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Log to `./highpass.log` with `--debug`, otherwise disable logging entirely
+pub fn init(debug: bool, format: LogFormat) -> std::io::Result<()> {
+    if !debug {
+        env_logger::Builder::new()
+            .filter_level(log::LevelFilter::Off)
+            .init();
+        return Ok(());
+    }
+
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open("./highpass.log")?;
+    let mut builder = env_logger::Builder::from_default_env();
+    builder
+        .filter_level(log::LevelFilter::Debug)
+        .target(env_logger::Target::Pipe(Box::new(file)));
+    if format == LogFormat::Json {
+        builder.format(|buf, record| {
+            let mut event = serde_json::Map::new();
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs_f64())
+                .unwrap_or(0.0);
+            event.insert("ts".to_string(), timestamp.into());
+            event.insert("level".to_string(), record.level().as_str().into());
+            event.insert("target".to_string(), record.target().into());
+            event.insert("msg".to_string(), record.args().to_string().into());
+            let _ = record.key_values().visit(&mut JsonFields(&mut event));
+            writeln!(buf, "{}", serde_json::Value::Object(event))
+        });
+    }
+    builder.init();
+    Ok(())
+}
+
+/// Copies the key-values attached to a log record into a JSON object
+struct JsonFields<'a>(&'a mut serde_json::Map<String, serde_json::Value>);
+
+impl<'kvs> VisitSource<'kvs> for JsonFields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), Error> {
+        let value = if let Some(n) = value.to_u64() {
+            n.into()
+        } else if let Some(n) = value.to_i64() {
+            n.into()
+        } else if let Some(n) = value.to_f64() {
+            n.into()
+        } else if let Some(b) = value.to_bool() {
+            b.into()
+        } else {
+            value.to_string().into()
+        };
+        self.0.insert(key.to_string(), value);
+        Ok(())
+    }
+}
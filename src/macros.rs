@@ -0,0 +1,108 @@
+//! Key sequences recorded into registers and played back, like `q` and `@` in vim.
+//! Typed keys are recorded as they are, prompts included, and played back through the
+//! same path as typed ones, one per pass of the main loop so that the answers of the
+//! server can come in between, and `Esc` can stop them.
+
+use crossterm::event::KeyEvent;
+use std::collections::{HashMap, VecDeque};
+
+/// Most keys one typed `@` plays back, counting those of the macros it plays in turn,
+/// so a macro that plays itself stops there
+const MAX_REPLAYED_KEYS: usize = 10_000;
+
+/// What the next key names the register for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MacroCommand {
+    Record,
+    Replay,
+}
+
+#[derive(Debug, Default)]
+pub struct Macros {
+    registers: HashMap<char, Vec<KeyEvent>>,
+    /// The register being recorded into, with the keys typed so far
+    recording: Option<(char, Vec<KeyEvent>)>,
+    /// `q` or `@` was pressed and waits for the register
+    pub pending: Option<MacroCommand>,
+    /// Keys of the macros being played back that are still to come
+    replaying: VecDeque<KeyEvent>,
+    /// Keys queued since the last typed key, checked against [`MAX_REPLAYED_KEYS`]
+    replayed: usize,
+    /// The register played back last, for `@@`
+    last_replayed: Option<char>,
+}
+
+impl Macros {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The register keys are being recorded into
+    pub fn recording(&self) -> Option<char> {
+        self.recording.as_ref().map(|(register, _)| *register)
+    }
+
+    /// Remember a typed key while recording. A typed key also starts a fresh count of
+    /// keys played back, for the `@` it may be part of.
+    pub fn record(&mut self, key: KeyEvent) {
+        self.replayed = 0;
+        if let Some((_, keys)) = &mut self.recording {
+            keys.push(key);
+        }
+    }
+
+    /// Start recording into `register`, any letter or digit. Returns whether it is one.
+    pub fn start_recording(&mut self, register: char) -> bool {
+        if !register.is_ascii_alphanumeric() {
+            return false;
+        }
+        self.recording = Some((register, Vec::new()));
+        true
+    }
+
+    /// Keep the keys recorded so far in their register, but for the last one, which
+    /// stopped the recording. Returns the register and how many keys it holds.
+    pub fn stop_recording(&mut self) -> Option<(char, usize)> {
+        let (register, mut keys) = self.recording.take()?;
+        keys.pop();
+        let count = keys.len();
+        self.registers.insert(register, keys);
+        Some((register, count))
+    }
+
+    /// Play back the keys of `register`, `@` for the one played back last, ahead of those
+    /// of a macro already playing. Returns the register, or why nothing is played back.
+    pub fn replay(&mut self, register: char) -> Result<char, String> {
+        let register = match register {
+            '@' => self.last_replayed.ok_or("Nothing played back yet")?,
+            register => register,
+        };
+        let Some(keys) = self.registers.get(&register).filter(|keys| !keys.is_empty()) else {
+            return Err(format!("Nothing recorded in @{}", register));
+        };
+        self.replayed += keys.len();
+        if self.replayed > MAX_REPLAYED_KEYS {
+            self.stop_replaying();
+            return Err(format!("Stopped @{} after {} keys", register, MAX_REPLAYED_KEYS));
+        }
+        for key in keys.iter().rev() {
+            self.replaying.push_front(*key);
+        }
+        self.last_replayed = Some(register);
+        Ok(register)
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        !self.replaying.is_empty()
+    }
+
+    /// Drop the keys still to be played back
+    pub fn stop_replaying(&mut self) {
+        self.replaying.clear();
+    }
+
+    /// The next key to handle as if it was typed
+    pub fn next_replayed(&mut self) -> Option<KeyEvent> {
+        self.replaying.pop_front()
+    }
+}
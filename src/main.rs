@@ -1,227 +1,2890 @@
+mod action;
+mod clipboard;
 mod config;
-mod subsonic;
+mod cover_cache;
+mod download;
+mod health;
+mod ipc;
+mod jobs;
+mod jukebox;
+mod logging;
+mod macros;
+mod profile;
+mod queue;
+mod scrobble;
+mod source;
+mod state;
+mod system;
+mod terminal;
 mod ui;
+mod undo;
 
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEventKind},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
     tty::IsTty,
 };
-use ratatui::prelude::*;
-use std::{error::Error, io, time::Duration};
-use subsonic::{SubsonicClient, Artist, Album, Song};
+use ratatui::{prelude::*, widgets::{Clear, Paragraph}};
+use std::{collections::{HashMap, HashSet}, error::Error, io, path::PathBuf, sync::Arc, time::{Duration, Instant}};
+use subsonic::Traffic;
+use subsonic::{SubsonicClient, AlbumListType, Album, Song, InternetRadioStation, MusicFolder, Playlist, PlayQueue, SearchCategory, Transcoding};
 use ui::{
-    tree::{TreeWidget, TreeState, TreeItemType},
-    player::{PlayerWidget, PlayerState},
+    tree::{Folder, LibraryUpdate, TreeWidget, TreeState, TreeItemType, TreeSection},
+    player::{NowPlayingUpdate, Output, PlayerEvent, PlayerWidget, PlayerState, SongLyrics},
+    form::{FormState, FormWidget},
+    input::LineInput,
+    jobs::JobsPanelWidget,
+    folder_picker::MusicFolderPickerWidget,
+    artist_info::{ArtistInfoPanel, ArtistInfoWidget},
+    file_info::FileInfoWidget,
+    popup::SelectList,
+    confirm::ConfirmWidget,
+    stats::{LibraryStats, StatsWidget},
+    search::{SearchPrompt, SearchPromptWidget, SearchUpdate},
+    finder::{FinderEntry, FinderState, FinderWidget},
+    picker::{PickedPlaylist, PlaylistPicker, PlaylistPickerWidget},
+    hud::{DebugHud, DebugHudWidget, HudStats},
+    marquee::Marquee,
+    thumbnail::{self, Thumbnail},
 };
-use tokio::sync::mpsc;
+use tokio::{sync::mpsc, task::JoinHandle};
+use unicode_width::UnicodeWidthStr;
+use tokio_util::sync::CancellationToken;
 use log::{info, warn, error, debug};
 use ui::player::SimpleMpv;
-use config::Config;
+use action::Action;
+use config::{BrowseMode, Config, ReplayGainMode};
+use cover_cache::CoverCache;
+use health::Connection;
+use ipc::RemoteCommand;
+use jobs::{JobId, JobUpdate, Jobs};
+use jukebox::Jukebox;
+use queue::{Queue, PageRequest, QueueUpdate, PAGE_SIZE};
+use scrobble::{ScrobbleUpdate, Scrobbler};
+use source::MusicSource;
+use source::local::{self, LocalLibrary};
+use source::merged::{self, MergedSource, Prefixed};
+#[cfg(feature = "jellyfin")]
+use source::jellyfin::JellyfinClient;
+use state::{unix_time, Session, State};
+use system::{IdleInhibitor, SystemEvent};
+use undo::{UndoAction, UndoStack};
+use macros::{MacroCommand, Macros};
 
+/// Albums whose songs are prefetched at the same time after expanding an artist
+const PREFETCH_CONCURRENCY: usize = 4;
+
+/// After this many seconds "previous" restarts the current track instead
+const RESTART_AFTER: f32 = 3.0;
+
+/// How long a notice stays on the bottom line
+const NOTICE_DURATION: Duration = Duration::from_secs(5);
+
+/// Albums fetched for each list of the Browse section
+const ALBUM_LIST_SIZE: u32 = 100;
+
+/// Similar artists asked for with an artist's biography
+const SIMILAR_ARTISTS: u32 = 10;
+
+/// Songs shown under an expanded genre
+const GENRE_SONGS_SIZE: u32 = 100;
+
+/// Shown in the player while playback waits for the screen to be unlocked
+const LOCKED_REASON: &str = "Paused while locked";
+
+/// How often the server is asked how far a library scan got
+const SCAN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Sent from background tasks back to the app. Results that belong to one part of the
+/// app come wrapped in that part's own update, which its reducer applies.
 #[derive(Debug, Clone)]
 pub enum Message {
-    LoadedArtists(Vec<Artist>),
-    LoadedArtistAlbums(String, Vec<Album>),
-    LoadedAlbumSongs(String, Vec<Song>),
-    LoadedCoverArt(Vec<u8>),
-    LoadedLyrics(String),
+    Library(LibraryUpdate),
+    Search(SearchUpdate),
+    Queue(QueueUpdate),
+    NowPlaying(NowPlayingUpdate),
+    Scrobble(ScrobbleUpdate),
+    /// Progress or the end of a background job
+    Job(JobUpdate),
+    Player(PlayerEvent),
+    /// The server started or stopped answering, or refusing the login
+    Connection(Connection),
+    /// The system is suspending or woke up
+    System(SystemEvent),
+    /// A command handed over by another highpass process
+    Remote(RemoteCommand),
+    /// A destructive action finished in the background and can be undone
+    Undoable(UndoAction),
+    /// A playlist was created, changed or deleted on the server
+    PlaylistsChanged,
+    /// The server finished rescanning the library, or failed to
+    LibraryScanned(Result<(), String>),
+    /// A request made in the background failed, with what was being done and why
+    Failed(String),
     Quit,
 }
 
+/// What to do with the values of the open form once it is submitted
+#[derive(Debug, Clone)]
+enum FormAction {
+    CreateRadioStation,
+    EditRadioStation(String),
+    /// Create a playlist holding these songs and albums
+    CreatePlaylist(Vec<TreeItemType>),
+}
+
+/// A destructive action waiting for the user to confirm it
+#[derive(Debug, Clone)]
+enum Confirmation {
+    ClearQueue,
+    Unstar(Vec<TreeItemType>),
+    DeleteRadioStation(InternetRadioStation),
+    DeletePlaylist(Playlist),
+}
+
+impl Confirmation {
+    fn question(&self) -> String {
+        match self {
+            Confirmation::ClearQueue => "Clear the play queue?".to_string(),
+            Confirmation::Unstar(items) => format!("Unstar {} items?", items.len()),
+            Confirmation::DeleteRadioStation(station) => format!("Delete the radio station \"{}\"?", station.name),
+            Confirmation::DeletePlaylist(playlist) => format!("Delete the playlist \"{}\"?", playlist.name),
+        }
+    }
+
+    /// Name of the `[confirm]` option that turns this question off
+    fn option(&self) -> &'static str {
+        match self {
+            Confirmation::ClearQueue => "clear_queue",
+            Confirmation::Unstar(_) => "unstar_many",
+            Confirmation::DeleteRadioStation(_) => "delete_radio_station",
+            Confirmation::DeletePlaylist(_) => "delete_playlist",
+        }
+    }
+}
+
 pub struct App {
-    subsonic_client: Option<SubsonicClient>,
+    config: Config,
+    source: Option<Arc<dyn MusicSource>>,
     tree_state: TreeState,
     player_state: PlayerState,
+    queue: Queue,
+    /// Queue revision last copied into the tree's Queue section
+    shown_queue_revision: u64,
+    undo: UndoStack,
+    /// Key sequences recorded with `q`, played back with `@`
+    macros: Macros,
+    state: State,
+    scrobbler: Scrobbler,
+    cover_cache: CoverCache,
+    /// Cover art IDs whose thumbnails were loaded or are on their way
+    requested_thumbnails: HashSet<String>,
+    /// Artists whose biography and similar artists have been requested
+    requested_artist_info: HashSet<String>,
+    last_autosave: Instant,
+    /// Upload of the queue to the server, awaited briefly on quit
+    play_queue_sync: Option<JoinHandle<()>>,
+    /// Start playing from the queue as soon as its first page arrives
+    start_queue_on_load: bool,
     should_quit: bool,
+    form: Option<(FormState, FormAction)>,
+    confirm: Option<Confirmation>,
+    /// Text of the `:` command line while it is open
+    command_line: Option<LineInput>,
+    search_prompt: Option<SearchPrompt>,
+    /// The search request currently in flight and the query it is for
+    search_task: Option<(String, JoinHandle<()>)>,
+    finder: Option<FinderState>,
+    playlist_picker: Option<PlaylistPicker>,
+    /// The biography and similar artists popup while it is open
+    artist_info_panel: Option<ArtistInfoPanel>,
+    /// The file of a song shown by `O`, while the popup is open
+    file_info: Option<PathBuf>,
+    /// The music folder picker while it is open
+    music_folder_picker: Option<SelectList>,
+    /// Name or ID of the music folder to switch to once the folders have loaded
+    pending_music_folder: Option<String>,
+    stats: Option<LibraryStats>,
+    hud: Option<DebugHud>,
+    jobs: Jobs,
+    /// The jobs panel while it is open
+    jobs_panel: Option<SelectList>,
+    /// The job loading the artist list, while it runs
+    artists_job: Option<JobId>,
+    /// The job following the last library scan
+    scan_job: Option<JobId>,
+    /// Held while playing, when enabled
+    idle_inhibitor: Option<IdleInhibitor>,
+    /// Cancelled on quit, every other token below is a child of it
+    shutdown: CancellationToken,
+    /// Cover art and lyrics requests of the song playing, cancelled when another starts
+    song_requests: CancellationToken,
+    /// Album and song requests of expanded artists and albums by their ID, cancelled
+    /// when the artist or album is collapsed before they arrive
+    expand_requests: HashMap<String, CancellationToken>,
+    /// Short message on the bottom line and when it was shown
+    notice: Option<(String, Instant)>,
+    /// Compact layout toggled by the user, `None` picks it by terminal height
+    compact: Option<bool>,
+    /// How songs are streamed now, as they are when `None`
+    transcoding: Option<Transcoding>,
     message_receiver: mpsc::UnboundedReceiver<Message>,
     message_sender: mpsc::UnboundedSender<Message>,
 }
 
 impl App {
-    pub fn new() -> Self {
+    /// Create the app. With `mock` it plays from a fake library, `traffic` records or
+    /// replays API responses. Neither mocking nor replaying touch the state file.
+    pub fn new(mock: bool, traffic: Option<Traffic>) -> Self {
         info!("Initializing HighPass application");
         let (message_sender, message_receiver) = mpsc::unbounded_channel();
+        let shutdown = CancellationToken::new();
+        let replay = matches!(traffic, Some(Traffic::Replay(_)));
+        let offline = mock || replay;
+
+        // Load configuration and initialize Subsonic client
+        // We already validated the config exists in main(), so this should not fail
+        let config = if offline {
+            Config::load().unwrap_or_default()
+        } else {
+            Config::load().expect("Configuration should be available")
+        };
         
         let mut app = Self {
-            subsonic_client: None,
+            config: config.clone(),
+            source: None,
             tree_state: TreeState::new(),
             player_state: PlayerState::new(),
+            queue: Queue::new(),
+            shown_queue_revision: 0,
+            undo: UndoStack::new(),
+            macros: Macros::new(),
+            state: if offline { State::ephemeral() } else { State::load() },
+            scrobbler: Scrobbler::new(config.playback.scrobble_percent, config.playback.scrobble_seconds),
+            cover_cache: CoverCache::new(),
+            requested_thumbnails: HashSet::new(),
+            requested_artist_info: HashSet::new(),
+            last_autosave: Instant::now(),
+            play_queue_sync: None,
+            start_queue_on_load: false,
             should_quit: false,
+            form: None,
+            confirm: None,
+            command_line: None,
+            search_prompt: None,
+            search_task: None,
+            finder: None,
+            playlist_picker: None,
+            artist_info_panel: None,
+            file_info: None,
+            music_folder_picker: None,
+            pending_music_folder: None,
+            stats: None,
+            hud: None,
+            jobs: Jobs::new(shutdown.child_token()),
+            jobs_panel: None,
+            artists_job: None,
+            scan_job: None,
+            idle_inhibitor: None,
+            song_requests: shutdown.child_token(),
+            expand_requests: HashMap::new(),
+            shutdown,
+            notice: None,
+            compact: None,
+            transcoding: None,
             message_receiver,
             message_sender,
         };
 
-        // Load configuration and initialize Subsonic client
-        // We already validated the config exists in main(), so this should not fail
-        let config = Config::load().expect("Configuration should be available");
-        
-        info!("Connecting to Subsonic server: {}", config.subsonic.server);
-        let client = SubsonicClient::new(
-            config.subsonic.server,
-            config.subsonic.username,
-            config.subsonic.password,
-        );
-        app.subsonic_client = Some(client);
-
-        // Load artists asynchronously
-        info!("Loading artists from Subsonic server");
-        let client = app.subsonic_client.as_ref().unwrap().clone();
         let sender = app.message_sender.clone();
-        tokio::spawn(async move {
-            match client.get_artists().await {
-                Ok(artists) => {
-                    info!("Successfully loaded {} artists", artists.len());
-                    let _ = sender.send(Message::LoadedArtists(artists));
+        app.player_state.watch_events(move |event| sender.send(Message::Player(event)).is_ok());
+
+        let backend = config.subsonic.backend;
+        let source: Arc<dyn MusicSource> = match backend {
+            config::Backend::Local if !mock => {
+                let dir = config.library.local_music_dir().unwrap_or_default();
+                info!("Playing local music from {}", dir.display());
+                Arc::new(LocalLibrary::new(dir))
+            }
+            #[cfg(feature = "jellyfin")]
+            config::Backend::Jellyfin if !mock => {
+                info!("Connecting to Jellyfin server: {}", config.subsonic.server);
+                if traffic.is_some() {
+                    warn!("API traffic of Jellyfin servers is neither recorded nor replayed");
                 }
-                Err(e) => {
-                    error!("Failed to load artists: {}", e);
+                Arc::new(
+                    JellyfinClient::new(config.subsonic.server, config.subsonic.username, config.subsonic.password)
+                        .with_client_name("highpass")
+                        .with_proxy(config.network.proxy()),
+                )
+            }
+            _ => {
+                let mut client = if mock {
+                    info!("Using the mock library");
+                    SubsonicClient::mock()
+                } else {
+                    info!("Connecting to Subsonic server: {}", config.subsonic.server);
+                    SubsonicClient::new(
+                        config.subsonic.server,
+                        config.subsonic.username,
+                        config.subsonic.password,
+                    )
+                    .with_auth_method(config.subsonic.auth)
+                    .with_rate_limit(config.subsonic.max_requests_per_second)
+                    .with_proxy(config.network.proxy())
+                }
+                .with_client_name("highpass");
+                if let Some(traffic) = traffic {
+                    info!("API traffic: {:?}", traffic);
+                    client = client.with_traffic(traffic);
                 }
+                Arc::new(client)
             }
-        });
+        };
+        let mut merged = MergedSource::new(source.clone());
+        let mut merged_names = Vec::new();
+        for server in config.servers.into_iter().filter(|_| !mock) {
+            info!("Merging server {}: {}", server.name, server.connection.server);
+            let prefix = merged::server_prefix(&server.name);
+            let connection = connect(server.connection, config.network.proxy());
+            merged = merged.with_source(prefix.clone(), Arc::new(Prefixed::new(prefix, connection)));
+            merged_names.push(server.name);
+        }
+        match config.library.local_music_dir() {
+            Some(dir) if !mock && backend != config::Backend::Local => {
+                info!("Merging local music from {}", dir.display());
+                merged = merged.with_source(local::ID_PREFIX, Arc::new(LocalLibrary::new(dir)));
+                merged_names.push(local::ID_PREFIX.trim_end_matches(':').to_string());
+            }
+            _ => {}
+        }
+        // Local files are always there, and a replay has nothing to answer the pings
+        if backend != config::Backend::Local && !replay {
+            health::watch(source.clone(), app.message_sender.clone(), app.shutdown.child_token());
+        }
+        app.source = Some(if merged_names.is_empty() { source } else { Arc::new(merged) });
+        app.tree_state.sources = merged_names;
+        app.transcoding = app.config.streaming.transcoding();
+        if app.transcoding.is_some() {
+            app.source = app.source.map(|source| source.with_transcoding(app.transcoding.clone()));
+        }
+        if app.config.playback.jukebox {
+            app.player_state.set_output(app.jukebox_output());
+        }
+
+        if let Some(proxy) = app.config.network.proxy() {
+            app.player_state.set_http_proxy(proxy);
+        }
+        app.player_state.title_marquee = Marquee::new(app.config.marquee.speed, app.config.marquee.pause);
+        app.tree_state.collate_artists = app.config.library.collate_artists;
+        if app.tree_state.set_browse_folders(app.config.library.browse == BrowseMode::Folders) {
+            app.load_folders();
+        }
+        app.player_state.scrobbling = app.config.playback.scrobble;
+
+        let recent_songs = app.state.history.iter().map(|entry| entry.song.clone()).collect();
+        app.tree_state.set_recent_songs(recent_songs);
+
+        // The volume comes back even when the rest of the session doesn't
+        if let Some(volume) = app.state.session.as_ref().and_then(|s| s.volume) {
+            app.player_state.set_volume(volume);
+        }
+        if app.config.session.restore {
+            if let Some(session) = app.state.session.clone() {
+                app.restore_session(session);
+            }
+        }
+        if app.config.session.sync {
+            app.load_play_queue();
+        }
+
+        let cover_cache = app.cover_cache.clone();
+        tokio::task::spawn_blocking(move || cover_cache.prune());
+
+        app.tree_state.show_thumbnails = app.config.cover_art.thumbnails && terminal::supports_graphics();
+        // The library waits for the configured music folder to be looked up
+        if app.config.library.music_folder.is_empty() {
+            app.load_artists();
+        } else {
+            app.pending_music_folder = Some(app.config.library.music_folder.clone());
+            app.tree_state.set_loading_artists(true);
+            app.load_music_folders();
+        }
 
         app
     }
 
+    /// Load the artist list asynchronously, handing it to the tree section by section
+    fn load_artists(&mut self) {
+        let Some(client) = self.source.clone() else {
+            return;
+        };
+        info!("Loading artists from Subsonic server");
+        let sender = self.message_sender.clone();
+        self.tree_state.set_loading_artists(true);
+        let job = self.jobs.spawn("Loading artists", self.message_sender.clone(), |reporter| async move {
+            let mut count = 0;
+            let result = client
+                .get_artists_progressive(&mut |index| {
+                    count += index.artist.len();
+                    reporter.progress(count, None);
+                    let _ = sender.send(Message::Library(LibraryUpdate::ArtistIndex(index.name, index.artist)));
+                })
+                .await;
+            match &result {
+                Ok(()) => info!("Successfully loaded {} artists", count),
+                Err(e) => error!("Failed to load artists: {}", e),
+            }
+            let _ = sender.send(Message::Library(LibraryUpdate::ArtistsDone));
+            result.map_err(|e| e.to_string())
+        });
+        self.artists_job = Some(job);
+    }
+
+    /// Load the artist list again, e.g. once the server found new music
+    fn reload_artists(&mut self) {
+        if let Some(job) = self.artists_job.take() {
+            self.jobs.cancel(job);
+        }
+        self.tree_state.clear_artists();
+        self.load_artists();
+    }
+
+    /// Show whether the server answers. The artists are loaded again once it does, when
+    /// they failed to load while it didn't.
+    fn update_connection(&mut self, connection: Connection) {
+        match &connection {
+            Connection::Online => {
+                if self.tree_state.connection != Connection::Unknown {
+                    self.notice = Some(("Connected to the server again".to_string(), Instant::now()));
+                }
+                if self.artists_job.is_none() && self.tree_state.artists.is_empty() && self.pending_music_folder.is_none() {
+                    self.load_artists();
+                }
+            }
+            Connection::Offline(e) => {
+                self.notice = Some((format!("Server unreachable: {}", e), Instant::now()));
+            }
+            Connection::Unauthorized(e) => {
+                self.notice = Some((format!("Login failed: {}", e), Instant::now()));
+            }
+            Connection::Unknown => {}
+        }
+        self.tree_state.connection = connection;
+    }
+
+    /// Have the server rescan the library and follow the scan as a job, counting the
+    /// files scanned so far
+    fn scan_library(&mut self) {
+        if self.scan_job.is_some_and(|job| self.jobs.get_running(job).is_some()) {
+            self.notice = Some(("The library is already being scanned".to_string(), Instant::now()));
+            return;
+        }
+        let Some(client) = self.source.clone() else {
+            return;
+        };
+        info!("Starting a library scan");
+        let sender = self.message_sender.clone();
+        let job = self.jobs.spawn("Scanning the library", self.message_sender.clone(), |reporter| async move {
+            let scan = async {
+                let mut status = client.start_scan().await?;
+                while status.scanning {
+                    reporter.progress(status.count.unwrap_or(0) as usize, None);
+                    tokio::time::sleep(SCAN_POLL_INTERVAL).await;
+                    status = client.get_scan_status().await?;
+                }
+                info!("Library scan done, {} files scanned", status.count.unwrap_or(0));
+                Ok::<_, subsonic::Error>(())
+            };
+            let result = scan.await.map_err(|e| e.to_string());
+            let _ = sender.send(Message::LibraryScanned(result.clone()));
+            result
+        });
+        self.scan_job = Some(job);
+    }
+
     pub async fn run<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> Result<(), Box<dyn Error>> {
         loop {
+            if let Some(hud) = &mut self.hud {
+                hud.queued_messages = self.message_receiver.len();
+            }
+
             // Handle async messages
             while let Ok(message) = self.message_receiver.try_recv() {
+                let started = Instant::now();
                 self.handle_message(message).await;
+                profile::record("message", started.elapsed());
             }
 
             if self.should_quit {
+                self.save_session();
+                if let Some(task) = self.play_queue_sync.take() {
+                    let _ = tokio::time::timeout(Duration::from_secs(2), task).await;
+                }
+                self.shutdown.cancel();
+                self.player_state.stop();
                 break;
             }
 
-            // Update player progress
-            self.player_state.update_progress();
+            if self.player_state.take_finished() {
+                self.next_track().await;
+            }
+            if let Some(inhibitor) = &self.idle_inhibitor {
+                inhibitor.set_playing(self.player_state.is_playing_here());
+            }
+            self.feed_queue();
+            if let Some(artist_id) = self.tree_state.hovered_artist().map(|artist| artist.id.clone()) {
+                self.load_artist_info(artist_id);
+            }
+            let radio = self.queue.radio_seed().map(|seed| seed.title.clone());
+            if self.queue.revision() != self.shown_queue_revision || radio != self.tree_state.queue_radio {
+                self.shown_queue_revision = self.queue.revision();
+                self.tree_state.set_queue_songs(self.queue.songs().cloned().collect(), radio);
+            }
+            self.update_scrobbles();
+            self.report_skipped_items();
+
+            if self.last_autosave.elapsed() >= Duration::from_secs(self.config.session.autosave_interval) {
+                self.save_session();
+            }
+
+            // Search as the user types, once they pause
+            if let Some(query) = self.search_prompt.as_mut().and_then(|p| p.take_debounced()) {
+                self.search(query, false);
+            }
 
+            let started = Instant::now();
             terminal.draw(|f| self.ui(f))?;
+            profile::record("draw", started.elapsed());
+            if let Some(hud) = &mut self.hud {
+                hud.record_frame();
+            }
+
+            // Don't wait for input while a macro plays back, but still look for Esc
+            let replaying = self.macros.is_replaying();
+            let timeout = if replaying { Duration::ZERO } else { Duration::from_millis(100) };
+            if event::poll(timeout)? {
+                match event::read()? {
+                    Event::Key(key) if key.kind == KeyEventKind::Press && replaying && key.code == KeyCode::Esc => {
+                        info!("Stopped playing back a macro");
+                        self.macros.stop_replaying();
+                        self.notice = Some(("Stopped the macro".to_string(), Instant::now()));
+                    }
+                    // Other keys would mix with the played back ones and are dropped
+                    Event::Key(_) if replaying => {}
+                    Event::Key(key) if key.kind == KeyEventKind::Press => {
+                        self.macros.record(key);
+                        self.handle_key_event(key).await;
+                    }
+                    Event::Paste(text) => self.handle_paste(&text),
+                    _ => {}
+                }
+            }
+            if let Some(key) = self.macros.next_replayed() {
+                self.handle_key_event(key).await;
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle_message(&mut self, message: Message) {
+        match message {
+            Message::Library(update) => self.update_library(update),
+            Message::Search(update) => self.update_search(update),
+            Message::Queue(update) => self.update_queue(update).await,
+            Message::NowPlaying(update) => self.update_now_playing(update),
+            Message::Scrobble(update) => self.update_scrobbler(update),
+            Message::Job(update) => self.jobs.update(update),
+            Message::Player(PlayerEvent::AudioDeviceLost(reason)) => {
+                warn!("Audio device lost: {}", reason);
+                if self.config.playback.pause_on_device_loss {
+                    self.player_state.pause_for(reason);
+                }
+            }
+            Message::Player(event) => {
+                let started = Instant::now();
+                self.player_state.handle_event(event);
+                if let Some(hud) = &mut self.hud {
+                    hud.mpv_lag = started.elapsed();
+                }
+            }
+            Message::Connection(connection) => {
+                self.update_connection(connection);
+            }
+            Message::System(SystemEvent::Suspending) => {
+                if self.player_state.pause_for("Paused for suspend") {
+                    self.save_session();
+                }
+            }
+            Message::System(SystemEvent::Resumed) => {}
+            Message::System(SystemEvent::Locked) => {
+                self.player_state.pause_for(LOCKED_REASON);
+            }
+            Message::System(SystemEvent::Unlocked) => {
+                self.player_state.resume_paused_for(LOCKED_REASON);
+            }
+            Message::Remote(command) => {
+                info!("Received remote command: {:?}", command);
+                match command {
+                    RemoteCommand::EnqueueAlbum(album_id) => self.queue_album(album_id, false).await,
+                    RemoteCommand::PlayAlbum(album_id) => self.queue_album(album_id, true).await,
+                    RemoteCommand::PlayPlaylist(name) => self.queue_playlist(name, true),
+                }
+            }
+            Message::PlaylistsChanged => {
+                self.playlists_changed();
+            }
+            Message::LibraryScanned(result) => {
+                self.scan_job = None;
+                match result {
+                    Ok(()) => {
+                        self.notice = Some(("Library scan done".to_string(), Instant::now()));
+                        self.reload_artists();
+                    }
+                    Err(e) => {
+                        self.notice = Some((format!("Library scan failed: {}", e), Instant::now()));
+                    }
+                }
+            }
+            Message::Failed(text) => {
+                self.notice = Some((text, Instant::now()));
+            }
+            Message::Undoable(action) => {
+                if matches!(action, UndoAction::PlaylistAdd { .. } | UndoAction::PlaylistRemove { .. }) {
+                    self.playlists_changed();
+                }
+                self.undo.push(action);
+            }
+            Message::Quit => {
+                self.should_quit = true;
+            }
+        }
+    }
+
+    /// Settle the requests behind library contents and prefetch what they lead to,
+    /// before the tree takes them in
+    fn update_library(&mut self, update: LibraryUpdate) {
+        let music_folders = matches!(update, LibraryUpdate::MusicFolders(_));
+        match &update {
+            LibraryUpdate::ArtistsDone => {
+                self.artists_job = None;
+            }
+            LibraryUpdate::ArtistAlbums(artist_id, albums) => {
+                self.expand_requests.remove(artist_id);
+                self.prefetch_album_songs(albums);
+                self.load_thumbnails(albums);
+            }
+            LibraryUpdate::AlbumSongs(id, _) | LibraryUpdate::FolderContents(id, ..) => {
+                self.expand_requests.remove(id);
+            }
+            LibraryUpdate::AlbumList(_, albums) => {
+                self.load_thumbnails(albums);
+            }
+            _ => {}
+        }
+        self.tree_state.update(update);
+        if music_folders {
+            self.switch_to_pending_music_folder();
+        }
+    }
+
+    fn update_search(&mut self, update: SearchUpdate) {
+        match update {
+            SearchUpdate::Results(query, results) => {
+                // Drop results of queries that have been superseded while in flight
+                if self.search_task.as_ref().is_some_and(|(latest, _)| *latest != query) {
+                    debug!("Discarding stale search results for '{}'", query);
+                    return;
+                }
+                self.search_task = None;
+                info!(
+                    "Search '{}' found {} artists, {} albums, {} songs",
+                    query,
+                    results.artist.len(),
+                    results.album.len(),
+                    results.song.len()
+                );
+                self.load_thumbnails(&results.album);
+                self.tree_state.set_search_results(query, results);
+            }
+            SearchUpdate::More(query, category, page) => {
+                self.load_thumbnails(&page.album);
+                self.tree_state.append_search_results(&query, category, page);
+            }
+        }
+    }
+
+    async fn update_queue(&mut self, update: QueueUpdate) {
+        match update {
+            QueueUpdate::Page(request, songs) => {
+                self.queue.add_page(&request, songs);
+                if self.start_queue_on_load && !self.queue.is_empty() {
+                    self.start_queue_on_load = false;
+                    self.play_next().await;
+                }
+            }
+            QueueUpdate::PageFailed => {
+                self.queue.page_failed();
+            }
+            QueueUpdate::Album(album_id, songs, replace) => {
+                self.tree_state.set_album_songs(album_id, songs.clone());
+                self.queue_songs(songs, replace).await;
+            }
+            QueueUpdate::Playlist(songs, replace) => {
+                self.queue_songs(songs, replace).await;
+            }
+            QueueUpdate::Synced(play_queue) => {
+                self.apply_play_queue(play_queue);
+            }
+        }
+    }
+
+    fn update_now_playing(&mut self, update: NowPlayingUpdate) {
+        // Results for a song that is no longer playing, arriving after those of the current one
+        if !self.is_current_song(update.song_id()) {
+            debug!("Dropping cover art or lyrics of song {}, it is no longer playing", update.song_id());
+            return;
+        }
+        match update {
+            NowPlayingUpdate::CoverArt(_, cover_art) => {
+                if self.config.theme.accent_from_cover {
+                    self.player_state.accent = ui::accent::dominant_color(&cover_art);
+                    debug!("Accent color from cover: {:?}", self.player_state.accent);
+                }
+                self.player_state.set_cover_art(cover_art);
+            }
+            NowPlayingUpdate::Lyrics(_, lyrics) => {
+                self.player_state.set_lyrics(lyrics);
+            }
+        }
+    }
+
+    fn update_scrobbler(&mut self, update: ScrobbleUpdate) {
+        match update {
+            ScrobbleUpdate::Submitted => {
+                let count = self.scrobbler.submitted();
+                info!("Submitted {} scrobbles", count);
+                self.state.pending_scrobbles.drain(..count.min(self.state.pending_scrobbles.len()));
+                self.state.save();
+            }
+            ScrobbleUpdate::Failed => {
+                self.scrobbler.failed();
+            }
+        }
+    }
+
+    async fn handle_key_event(&mut self, event: KeyEvent) {
+        let key = event.code;
+        debug!(target: "highpass::ui", key:? = key; "Key pressed: {:?}", key);
+
+        // The debug overlay can be toggled from anywhere, including open prompts
+        if key == KeyCode::F(12) {
+            self.hud = match self.hud {
+                Some(_) => None,
+                None => Some(DebugHud::new()),
+            };
+            return;
+        }
+
+        if let Some(command) = self.macros.pending.take() {
+            self.handle_macro_key(key, command);
+            return;
+        }
+
+        if let Some(confirmation) = self.confirm.take() {
+            self.handle_confirm_key(key, confirmation);
+            return;
+        }
+
+        if self.form.is_some() {
+            self.handle_form_key(event);
+            return;
+        }
+
+        if self.command_line.is_some() {
+            self.handle_command_key(event);
+            return;
+        }
+
+        if self.search_prompt.is_some() {
+            self.handle_search_key(event);
+            return;
+        }
+
+        if self.finder.is_some() {
+            self.handle_finder_key(event).await;
+            return;
+        }
+
+        if self.playlist_picker.is_some() {
+            self.handle_picker_key(key);
+            return;
+        }
+
+        if self.artist_info_panel.is_some() {
+            self.handle_artist_info_key(key).await;
+            return;
+        }
+
+        if let Some(path) = self.file_info.take() {
+            match key {
+                KeyCode::Enter => match download::open_folder(&path) {
+                    Ok(()) => info!("Opened the folder of {}", path.display()),
+                    Err(e) => {
+                        warn!("Cannot open the folder of {}: {}", path.display(), e);
+                        self.notice = Some((format!("Cannot open the folder: {}", e), Instant::now()));
+                    }
+                },
+                KeyCode::Char('y') => {
+                    self.copy_to_clipboard("path", &path.display().to_string());
+                    self.file_info = Some(path);
+                }
+                KeyCode::Esc | KeyCode::Char('O') => {}
+                _ => self.file_info = Some(path),
+            }
+            return;
+        }
+
+        if self.music_folder_picker.is_some() {
+            self.handle_music_folder_key(key);
+            return;
+        }
+
+        if self.jobs_panel.is_some() {
+            self.handle_jobs_key(key);
+            return;
+        }
+
+        if self.tree_state.jump_pending {
+            self.tree_state.jump_pending = false;
+            if let KeyCode::Char(c) = key {
+                if !self.tree_state.jump_to_index(c) {
+                    debug!("No artists under '{}'", c);
+                }
+            }
+            return;
+        }
+
+        if self.stats.is_some() {
+            // Any key dismisses the statistics popup
+            self.stats = None;
+            return;
+        }
+
+        if let Some(action) = action::keymap(key) {
+            self.dispatch(action).await;
+        }
+    }
+
+    /// Carry out an action of the main view
+    async fn dispatch(&mut self, action: Action) {
+        match action {
+            Action::Quit => {
+                self.should_quit = true;
+            }
+            Action::SelectPrevious => {
+                self.tree_state.previous();
+            }
+            Action::SelectNext => {
+                self.tree_state.next();
+            }
+            Action::Activate => {
+                self.activate_selected().await;
+            }
+            Action::Collapse => {
+                self.collapse_selected();
+            }
+            Action::TogglePlayPause => {
+                match self.player_state.current_song.clone() {
+                    // The file was unloaded, start the song over
+                    Some(song) if self.player_state.stopped => self.select_song(song).await,
+                    None if self.player_state.stopped => {
+                        if let Some(station) = self.player_state.station.clone() {
+                            self.play_station(station);
+                        }
+                    }
+                    _ => self.player_state.toggle_play_pause(),
+                }
+            }
+            Action::Stop => {
+                self.player_state.stop();
+                self.save_session();
+            }
+            Action::NextTrack => {
+                self.next_track().await;
+            }
+            Action::PreviousTrack => {
+                self.previous_track().await;
+            }
+            Action::ToggleStarredFilter => {
+                self.toggle_starred_filter();
+            }
+            Action::QueueSelected => {
+                match self.tree_state.get_selected_item().map(|i| i.item_type.clone()) {
+                    Some(TreeItemType::Playlist(playlist)) if self.tree_state.marked.is_empty() => {
+                        self.queue_playlist_by_id(playlist.id, false).await;
+                    }
+                    _ => self.queue_marked().await,
+                }
+            }
+            Action::ToggleMark => {
+                self.tree_state.toggle_mark();
+            }
+            Action::MarkRange => {
+                self.tree_state.mark_range();
+            }
+            Action::RevealPlaying(album) => {
+                self.reveal_current_song(album).await;
+            }
+            Action::RevealSelected => {
+                self.reveal_selected().await;
+            }
+            Action::ShowArtistInfo => {
+                if let Some(TreeItemType::Artist(artist)) = self.tree_state.get_selected_item().map(|i| i.item_type.clone()) {
+                    self.load_artist_info(artist.id.clone());
+                    self.artist_info_panel = Some(ArtistInfoPanel::new(artist));
+                }
+            }
+            Action::ChangeVolume(step) => {
+                self.player_state.change_volume(step);
+            }
+            Action::ToggleMute => {
+                self.player_state.toggle_mute();
+            }
+            Action::ChangeSpeed(step) => {
+                self.player_state.change_speed(step);
+            }
+            Action::ResetSpeed => {
+                self.player_state.set_speed(1.0);
+            }
+            Action::Seek(seconds) => {
+                self.player_state.seek_relative(seconds);
+            }
+            Action::SeekPercent(percent) => {
+                self.player_state.seek_percent(percent);
+            }
+            Action::ChangeSongVolume(step) => {
+                self.adjust_song_volume(step);
+            }
+            Action::JumpToIndex => {
+                self.tree_state.jump_pending = true;
+            }
+            Action::OpenCommandLine => {
+                self.command_line = Some(LineInput::new());
+            }
+            Action::ToggleCompact => {
+                let height = crossterm::terminal::size().map_or(u16::MAX, |(_, height)| height);
+                self.compact = Some(!self.is_compact(height));
+            }
+            Action::OpenSearch => {
+                self.search_prompt = Some(SearchPrompt::new());
+            }
+            Action::OpenFinder => {
+                self.finder = Some(FinderState::new(&self.tree_state));
+            }
+            Action::PlaySelected(shuffle) => {
+                match self.tree_state.get_selected_item().map(|i| i.item_type.clone()) {
+                    Some(TreeItemType::Genre(genre)) => {
+                        self.undo.push(UndoAction::Queue(self.queue.clone()));
+                        self.queue.play_genre(&genre.value, genre.song_count.unwrap_or(0), shuffle);
+                        self.start_queue_on_load = true;
+                        self.feed_queue();
+                    }
+                    Some(TreeItemType::Playlist(playlist)) if !shuffle => {
+                        self.queue_playlist_by_id(playlist.id, true).await;
+                    }
+                    _ => {}
+                }
+            }
+            Action::NewRadioStation => {
+                if let Some(item) = self.tree_state.get_selected_item() {
+                    if matches!(item.item_type, TreeItemType::Section(TreeSection::Radio) | TreeItemType::RadioStation(_)) {
+                        let form = FormState::new("New Radio Station", &["Name", "Stream URL", "Homepage URL"]);
+                        self.form = Some((form, FormAction::CreateRadioStation));
+                    }
+                }
+            }
+            Action::AddToPlaylist => {
+                let targets = self.tree_state.take_marked();
+                if !targets.is_empty() {
+                    if self.tree_state.playlists.is_none() {
+                        self.load_playlists();
+                    }
+                    self.playlist_picker = Some(PlaylistPicker::new(targets));
+                }
+            }
+            Action::EditSelected => {
+                if let Some(TreeItemType::RadioStation(station)) = self.tree_state.get_selected_item().map(|i| &i.item_type) {
+                    let form = FormState::new("Edit Radio Station", &["Name", "Stream URL", "Homepage URL"])
+                        .with_values(&[
+                            &station.name,
+                            &station.stream_url,
+                            station.home_page_url.as_deref().unwrap_or(""),
+                        ]);
+                    self.form = Some((form, FormAction::EditRadioStation(station.id.clone())));
+                }
+            }
+            Action::DeleteSelected => {
+                if let Some(index) = self.tree_state.selected_queue_index() {
+                    self.undo.push(UndoAction::Queue(self.queue.clone()));
+                    self.queue.remove(index);
+                } else if let (Some((playlist, index)), Some(TreeItemType::Song(song))) = (
+                    self.tree_state.selected_playlist_entry(),
+                    self.tree_state.get_selected_item().map(|i| &i.item_type),
+                ) {
+                    let entries = vec![(index, song.id.clone())];
+                    self.remove_from_playlist(playlist.id.clone(), playlist.name.clone(), entries, true);
+                } else {
+                    match self.tree_state.get_selected_item().map(|i| i.item_type.clone()) {
+                        Some(TreeItemType::RadioStation(station)) => {
+                            if self.config.confirm.delete_radio_station {
+                                self.confirm = Some(Confirmation::DeleteRadioStation(station));
+                            } else {
+                                self.delete_radio_station(station);
+                            }
+                        }
+                        Some(TreeItemType::Playlist(playlist)) => {
+                            if self.config.confirm.delete_playlist {
+                                self.confirm = Some(Confirmation::DeletePlaylist(playlist));
+                            } else {
+                                self.delete_playlist(playlist);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Action::SaveToQuickPlaylist => {
+                self.save_to_quick_playlist();
+            }
+            Action::Download => self.download_marked(),
+            Action::ShowFile => self.show_file(),
+            Action::ToggleScrobbling => {
+                self.toggle_scrobbling();
+            }
+            Action::ToggleSongScrobbling => {
+                self.toggle_song_scrobbling();
+            }
+            Action::ShowJobs => {
+                self.jobs_panel = Some(SelectList::first(self.jobs.list().len()));
+            }
+            Action::ScanLibrary => {
+                self.scan_library();
+            }
+            Action::StartRadio => {
+                self.start_radio().await;
+            }
+            Action::TogglePublic => {
+                if let Some(TreeItemType::Playlist(playlist)) = self.tree_state.get_selected_item().map(|i| &i.item_type) {
+                    self.set_playlist_public(playlist.clone(), !playlist.public.unwrap_or(false));
+                }
+            }
+            Action::Undo => {
+                self.undo();
+            }
+            Action::RecordMacro => match self.macros.stop_recording() {
+                Some((register, count)) => {
+                    info!("Recorded {} keys into register {}", count, register);
+                    self.notice = Some((format!("Recorded {} keys into @{}", count, register), Instant::now()));
+                }
+                None => {
+                    // `q` used to quit, so say what it does now for those typing it out of habit
+                    let text = "Record into which register? (Esc cancels, Esc again or :q quits)";
+                    self.notice = Some((text.to_string(), Instant::now()));
+                    self.macros.pending = Some(MacroCommand::Record);
+                }
+            },
+            Action::ReplayMacro => {
+                self.macros.pending = Some(MacroCommand::Replay);
+            }
+            Action::CopyId => {
+                let id = self.tree_state.get_selected_item().and_then(|item| item.item_type.id().map(str::to_string));
+                if let Some(id) = id {
+                    self.copy_to_clipboard("ID", &id);
+                }
+            }
+            Action::CopyUrl => {
+                let url = match self.tree_state.get_selected_item().map(|item| &item.item_type) {
+                    Some(TreeItemType::RadioStation(station)) => Some(station.stream_url.clone()),
+                    Some(TreeItemType::Song(song)) => self.source.as_ref().map(|source| source.get_stream_url(&song.id)),
+                    _ => None,
+                };
+                if let Some(url) = url {
+                    self.copy_to_clipboard("stream URL", &url);
+                }
+            }
+        }
+    }
+
+    /// Copy `text` through the terminal and say so, `what` names it in the notice
+    fn copy_to_clipboard(&mut self, what: &str, text: &str) {
+        let notice = match clipboard::copy(text) {
+            Ok(()) => {
+                info!("Copied the {} to the clipboard", what);
+                format!("Copied the {} to the clipboard", what)
+            }
+            Err(e) => {
+                warn!("Failed to copy the {}: {}", what, e);
+                format!("Failed to copy the {}: {}", what, e)
+            }
+        };
+        self.notice = Some((notice, Instant::now()));
+    }
+
+    /// Start recording into or play back the register named by `key`
+    fn handle_macro_key(&mut self, key: KeyCode, command: MacroCommand) {
+        let KeyCode::Char(register) = key else {
+            return;
+        };
+        match command {
+            MacroCommand::Record => {
+                if self.macros.start_recording(register) {
+                    info!("Recording keys into register {}", register);
+                    self.notice = None;
+                }
+            }
+            MacroCommand::Replay => match self.macros.replay(register) {
+                Ok(register) => debug!("Playing back register {}", register),
+                Err(e) => {
+                    warn!("Not playing back @{}: {}", register, e);
+                    self.notice = Some((e, Instant::now()));
+                }
+            },
+        }
+    }
+
+    /// Expand or collapse the selected node, loading what it holds the first time,
+    /// or play or queue what it stands for
+    async fn activate_selected(&mut self) {
+        if let Some(item) = self.tree_state.get_selected_item().cloned() {
+            match &item.item_type {
+                TreeItemType::Section(section) => {
+                    let should_load = self.tree_state.toggle_section(*section);
+                    if should_load {
+                        match section {
+                            TreeSection::SearchResults
+                            | TreeSection::Queue
+                            | TreeSection::RecentlyPlayed
+                            | TreeSection::Browse => {}
+                            TreeSection::Playlists => self.load_playlists(),
+                            TreeSection::Genres => self.load_genres(),
+                            TreeSection::Radio => self.load_radio_stations(),
+                        }
+                    }
+                }
+                TreeItemType::Artist(artist) => {
+                    let should_load = self.tree_state.toggle_artist(&artist.id);
+                    if should_load {
+                        self.load_artist_albums(artist.id.clone()).await;
+                    } else {
+                        self.cancel_expand_request(&artist.id);
+                    }
+                }
+                TreeItemType::Album(album) => {
+                    let should_load = self.tree_state.toggle_album(&album.id);
+                    if should_load {
+                        self.load_album_songs(album.id.clone()).await;
+                    } else {
+                        self.cancel_expand_request(&album.id);
+                    }
+                }
+                TreeItemType::Folder(folder) => {
+                    if self.tree_state.toggle_folder(&folder.id) {
+                        self.load_folder_contents(folder.id.clone());
+                    } else {
+                        self.cancel_expand_request(&folder.id);
+                    }
+                }
+                TreeItemType::Song(song) => {
+                    self.select_song(song.clone()).await;
+                }
+                TreeItemType::LoadMore(category) => {
+                    self.load_more_search_results(*category);
+                }
+                TreeItemType::Playlist(playlist) => {
+                    if self.tree_state.toggle_playlist(&playlist.id) {
+                        self.load_playlist_songs(playlist.id.clone(), None);
+                    }
+                }
+                TreeItemType::AlbumList(list) => {
+                    if self.tree_state.toggle_album_list(*list) {
+                        self.load_album_list(*list);
+                    }
+                }
+                TreeItemType::Genre(genre) => {
+                    if self.tree_state.toggle_genre(&genre.value) {
+                        self.load_genre_songs(genre.value.clone());
+                    }
+                }
+                TreeItemType::RadioStation(station) => {
+                    self.play_station(station.clone());
+                }
+            }
+        }
+    }
+
+    /// Collapse the selected node, cancelling its contents if they are still loading
+    fn collapse_selected(&mut self) {
+        if let Some(item) = self.tree_state.get_selected_item().cloned() {
+            match &item.item_type {
+                TreeItemType::Section(section) => {
+                    self.tree_state.toggle_section(*section);
+                }
+                TreeItemType::Artist(artist) => {
+                    self.tree_state.toggle_artist(&artist.id);
+                    self.cancel_expand_request(&artist.id);
+                }
+                TreeItemType::Album(album) => {
+                    self.tree_state.toggle_album(&album.id);
+                    self.cancel_expand_request(&album.id);
+                }
+                TreeItemType::Folder(folder) => {
+                    self.tree_state.toggle_folder(&folder.id);
+                    self.cancel_expand_request(&folder.id);
+                }
+                TreeItemType::AlbumList(list) => {
+                    self.tree_state.toggle_album_list(*list);
+                }
+                TreeItemType::Genre(genre) => {
+                    self.tree_state.toggle_genre(&genre.value);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Text pasted into the terminal goes to the open prompt, if any
+    fn handle_paste(&mut self, text: &str) {
+        if let Some((form, _)) = &mut self.form {
+            if let Some(input) = form.focused_input() {
+                input.insert_str(text);
+            }
+        } else if let Some(command) = &mut self.command_line {
+            command.insert_str(text);
+        } else if let Some(prompt) = &mut self.search_prompt {
+            prompt.paste(text);
+        } else if let Some(finder) = &mut self.finder {
+            finder.paste(text);
+        }
+    }
+
+    fn handle_form_key(&mut self, event: KeyEvent) {
+        let Some((form, _)) = &mut self.form else {
+            return;
+        };
+
+        match event.code {
+            KeyCode::Esc => {
+                self.form = None;
+            }
+            KeyCode::Tab | KeyCode::Down => form.next_field(),
+            KeyCode::BackTab | KeyCode::Up => form.previous_field(),
+            KeyCode::Enter => {
+                if let Some((form, action)) = self.form.take() {
+                    self.submit_form(form, action);
+                }
+            }
+            _ => {
+                if let Some(input) = form.focused_input() {
+                    input.handle_key(event);
+                }
+            }
+        }
+    }
+
+    fn handle_command_key(&mut self, event: KeyEvent) {
+        let Some(command) = &mut self.command_line else {
+            return;
+        };
+
+        match event.code {
+            KeyCode::Esc => {
+                self.command_line = None;
+            }
+            KeyCode::Backspace if command.is_empty() => {
+                self.command_line = None;
+            }
+            KeyCode::Enter => {
+                if let Some(command) = self.command_line.take() {
+                    self.execute_command(command.text());
+                }
+            }
+            _ => {
+                command.handle_key(event);
+            }
+        }
+    }
+
+    fn handle_search_key(&mut self, event: KeyEvent) {
+        let Some(prompt) = &mut self.search_prompt else {
+            return;
+        };
+        let history = &self.state.search_history;
+
+        match event.code {
+            KeyCode::Esc => {
+                self.search_prompt = None;
+            }
+            KeyCode::Up => prompt.history_previous(history),
+            KeyCode::Down => prompt.history_next(history),
+            KeyCode::Tab => prompt.accept_suggestion(history),
+            // At the end of the input → takes the suggestion, elsewhere it moves the cursor
+            KeyCode::Right if prompt.input.at_end() && event.modifiers.is_empty() => prompt.accept_suggestion(history),
+            KeyCode::Enter => {
+                if let Some(prompt) = self.search_prompt.take() {
+                    self.search(prompt.input.text().trim().to_string(), true);
+                }
+            }
+            _ => {
+                prompt.handle_key(event);
+            }
+        }
+    }
+
+    async fn handle_finder_key(&mut self, event: KeyEvent) {
+        let Some(finder) = &mut self.finder else {
+            return;
+        };
+
+        let key = event.code;
+        match key {
+            KeyCode::Esc => {
+                self.finder = None;
+            }
+            KeyCode::Up => finder.previous(),
+            KeyCode::Down => finder.next(),
+            // → moves the cursor inside the query and only goes to the item from its end
+            KeyCode::Right if !finder.query.at_end() || !event.modifiers.is_empty() => {
+                finder.handle_key(event);
+            }
+            KeyCode::Enter | KeyCode::Tab | KeyCode::Right => {
+                let Some(entry) = finder.selected().cloned() else {
+                    return;
+                };
+                self.finder = None;
+
+                match (key, entry) {
+                    (KeyCode::Enter, FinderEntry::Song(song)) => self.select_song(song).await,
+                    (KeyCode::Enter, FinderEntry::Album(album)) => self.queue_album(album.id, true).await,
+                    (KeyCode::Tab, FinderEntry::Song(song)) => self.queue.push(song),
+                    (KeyCode::Tab, FinderEntry::Album(album)) => self.queue_album(album.id, false).await,
+                    (_, FinderEntry::Artist(artist)) => self.go_to(&artist.id, None, None).await,
+                    (_, FinderEntry::Album(album)) => {
+                        if let Some(artist_id) = &album.artist_id {
+                            self.go_to(artist_id, Some(&album.id), None).await;
+                        }
+                    }
+                    (_, FinderEntry::Song(song)) => {
+                        if let Some(artist_id) = &song.artist_id {
+                            self.go_to(artist_id, song.album_id.as_deref(), Some(&song.id)).await;
+                        }
+                    }
+                }
+            }
+            _ => {
+                finder.handle_key(event);
+            }
+        }
+    }
+
+    fn handle_picker_key(&mut self, key: KeyCode) {
+        let Some(picker) = &mut self.playlist_picker else {
+            return;
+        };
+        let playlists = self.tree_state.playlists.as_deref().unwrap_or_default();
+
+        match key {
+            KeyCode::Esc => {
+                self.playlist_picker = None;
+            }
+            KeyCode::Up => picker.previous(playlists),
+            KeyCode::Down => picker.next(playlists),
+            KeyCode::Enter => {
+                let picked = picker.selected(playlists);
+                let Some(picker) = self.playlist_picker.take() else {
+                    return;
+                };
+                match picked {
+                    PickedPlaylist::Existing(playlist) => {
+                        self.add_to_playlist(playlist.name, Some(playlist.id), picker.targets);
+                    }
+                    PickedPlaylist::New => {
+                        let form = FormState::new("New Playlist", &["Name"]);
+                        self.form = Some((form, FormAction::CreatePlaylist(picker.targets)));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    async fn handle_artist_info_key(&mut self, key: KeyCode) {
+        let Some(panel) = &mut self.artist_info_panel else {
+            return;
+        };
+        let info = self.tree_state.artist_info.get(&panel.artist.id);
+        let similar = info.map_or(0, |info| info.similar_artist.len());
+
+        match key {
+            KeyCode::Esc | KeyCode::Char('i') => {
+                self.artist_info_panel = None;
+            }
+            KeyCode::Up => panel.list.previous(similar),
+            KeyCode::Down => panel.list.next(similar),
+            KeyCode::Enter => {
+                let Some(artist_id) = panel.selected(info).map(|artist| artist.id.clone()) else {
+                    return;
+                };
+                self.artist_info_panel = None;
+                self.go_to(&artist_id, None, None).await;
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_music_folder_key(&mut self, key: KeyCode) {
+        let Some(list) = &mut self.music_folder_picker else {
+            return;
+        };
+        // "All folders" comes first
+        let folders = self.tree_state.music_folders.as_deref().unwrap_or_default();
+
+        match key {
+            KeyCode::Esc => {
+                self.music_folder_picker = None;
+            }
+            KeyCode::Up => list.previous(folders.len() + 1),
+            KeyCode::Down => list.next(folders.len() + 1),
+            KeyCode::Enter => {
+                let picked = match list.selected() {
+                    Some(0) | None => None,
+                    Some(i) => folders.get(i - 1).cloned(),
+                };
+                self.music_folder_picker = None;
+                if picked != self.tree_state.music_folder {
+                    self.select_music_folder(picked);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_jobs_key(&mut self, key: KeyCode) {
+        let Some(list) = &mut self.jobs_panel else {
+            return;
+        };
+        let jobs = self.jobs.list();
+
+        match key {
+            KeyCode::Esc | KeyCode::Char('J') => {
+                self.jobs_panel = None;
+            }
+            KeyCode::Up => list.previous(jobs.len()),
+            KeyCode::Down => list.next(jobs.len()),
+            KeyCode::Char('c') => {
+                let Some(id) = list.selected().and_then(|i| jobs.get(i)).map(|job| job.id) else {
+                    return;
+                };
+                if self.jobs.cancel(id) && self.artists_job == Some(id) {
+                    // The artists loaded so far stay, the rest is not coming
+                    self.artists_job = None;
+                    self.tree_state.set_loading_artists(false);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Search the server, replacing any search still in flight.
+    /// Submitted searches are `remember`ed in the history, live ones are not.
+    fn search(&mut self, query: String, remember: bool) {
+        if query.is_empty() {
+            return;
+        }
+
+        if remember {
+            self.state.record_search(&query);
+            self.state.save();
+        }
+
+        if let Some((previous, task)) = self.search_task.take() {
+            if previous == query && !task.is_finished() {
+                self.search_task = Some((previous, task));
+                return;
+            }
+            task.abort();
+        }
+
+        if let Some(client) = &self.source {
+            let client = client.clone();
+            let sender = self.message_sender.clone();
+            let task_query = query.clone();
+            let task = tokio::spawn(async move {
+                let query = task_query;
+                match client.search(&query, None).await {
+                    Ok(results) => {
+                        let _ = sender.send(Message::Search(SearchUpdate::Results(query, results)));
+                    }
+                    Err(e) => {
+                        Self::report_failure(&sender, &format!("Search for '{}' failed", query), e);
+                    }
+                }
+            });
+            self.search_task = Some((query, task));
+        }
+    }
+
+    /// Fetch the next page of one category of the current search results
+    fn load_more_search_results(&self, category: SearchCategory) {
+        let Some(search) = &self.tree_state.search_results else {
+            return;
+        };
+        let Some(client) = self.source.clone() else {
+            return;
+        };
+
+        let query = search.query.clone();
+        let offset = search.count(category) as u32;
+        let sender = self.message_sender.clone();
+        tokio::spawn(async move {
+            match client.search(&query, Some((category, offset))).await {
+                Ok(page) => {
+                    let _ = sender.send(Message::Search(SearchUpdate::More(query, category, page)));
+                }
+                Err(e) => {
+                    Self::report_failure(&sender, &format!("Failed to load more {:?} for '{}'", category, query), e);
+                }
+            }
+        });
+    }
+
+    fn execute_command(&mut self, command: &str) {
+        debug!("Executing command: {}", command);
+        let command = command.trim();
+        if let Some(name) = command.strip_prefix("playlist ") {
+            self.add_marked_to_playlist(name.trim().to_string());
+            return;
+        }
+        if let Some(options) = command.strip_prefix("transcode ") {
+            self.transcode(options.trim());
+            return;
+        }
+        if let Some(name) = command.strip_prefix("folder ") {
+            self.switch_music_folder(name.trim().to_string());
+            return;
+        }
+        match command {
+            "stats" => {
+                self.stats = Some(LibraryStats::from_tree(&self.tree_state));
+            }
+            "star" => self.star_marked(true),
+            "unstar" => self.star_marked(false),
+            "unmark" => self.tree_state.clear_marks(),
+            "jukebox" => self.toggle_jukebox(),
+            "transcode" => self.toggle_transcoding(),
+            "browse" => self.browse_folders(!self.tree_state.browse_folders),
+            "browse folders" => self.browse_folders(true),
+            "browse tags" => self.browse_folders(false),
+            "folder" => self.open_music_folder_picker(),
+            "clear" => {
+                if self.config.confirm.clear_queue {
+                    self.confirm = Some(Confirmation::ClearQueue);
+                } else {
+                    self.clear_queue();
+                }
+            }
+            "q" | "quit" => {
+                self.should_quit = true;
+            }
+            "" => {}
+            other => {
+                warn!("Unknown command: {}", other);
+            }
+        }
+    }
+
+    fn handle_confirm_key(&mut self, key: KeyCode, confirmation: Confirmation) {
+        match key {
+            KeyCode::Char('y') | KeyCode::Enter => self.perform(confirmation),
+            KeyCode::Char('a') => {
+                match confirmation {
+                    Confirmation::ClearQueue => self.config.confirm.clear_queue = false,
+                    Confirmation::Unstar(_) => self.config.confirm.unstar_many = false,
+                    Confirmation::DeleteRadioStation(_) => self.config.confirm.delete_radio_station = false,
+                    Confirmation::DeletePlaylist(_) => self.config.confirm.delete_playlist = false,
+                }
+                if let Err(e) = Config::disable_confirmation(confirmation.option()) {
+                    warn!("Could not save the confirmation setting: {}", e);
+                }
+                self.perform(confirmation);
+            }
+            KeyCode::Char('n') | KeyCode::Esc => {}
+            // Keep asking until the question is answered
+            _ => self.confirm = Some(confirmation),
+        }
+    }
+
+    fn perform(&mut self, confirmation: Confirmation) {
+        match confirmation {
+            Confirmation::ClearQueue => self.clear_queue(),
+            Confirmation::Unstar(targets) => self.set_starred(targets, false),
+            Confirmation::DeleteRadioStation(station) => self.delete_radio_station(station),
+            Confirmation::DeletePlaylist(playlist) => self.delete_playlist(playlist),
+        }
+    }
+
+    fn clear_queue(&mut self) {
+        self.undo.push(UndoAction::Queue(self.queue.clone()));
+        self.queue.clear();
+    }
+
+    fn submit_form(&mut self, form: FormState, action: FormAction) {
+        match action {
+            FormAction::CreatePlaylist(targets) => {
+                let name = form.value(0).trim().to_string();
+                if name.is_empty() {
+                    warn!("Playlist needs a name");
+                    self.form = Some((form, FormAction::CreatePlaylist(targets)));
+                    return;
+                }
+                self.create_playlist(name, targets);
+            }
+            FormAction::CreateRadioStation | FormAction::EditRadioStation(_) => self.save_radio_station(form, action),
+        }
+    }
+
+    fn save_radio_station(&mut self, form: FormState, action: FormAction) {
+        let Some(client) = self.source.clone() else {
+            return;
+        };
+
+        let name = form.value(0).to_string();
+        let stream_url = form.value(1).to_string();
+        let home_page_url = Some(form.value(2).to_string()).filter(|u| !u.is_empty());
+        if name.is_empty() || stream_url.is_empty() {
+            warn!("Radio station needs a name and a stream URL");
+            self.form = Some((form, action));
+            return;
+        }
+
+        let id = match action {
+            FormAction::EditRadioStation(id) => Some(id),
+            _ => None,
+        };
+        let sender = self.message_sender.clone();
+        tokio::spawn(async move {
+            let result = match &id {
+                None => {
+                    info!("Creating radio station: {}", name);
+                    client.create_internet_radio_station(&name, &stream_url, home_page_url.as_deref()).await
+                }
+                Some(id) => {
+                    info!("Updating radio station {}: {}", id, name);
+                    client.update_internet_radio_station(id, &name, &stream_url, home_page_url.as_deref()).await
+                }
+            }
+            .map_err(|e| e.to_string());
+
+            match result {
+                Ok(_) => Self::reload_radio_stations(client.as_ref(), &sender).await,
+                Err(e) => Self::report_failure(&sender, "Failed to save radio station", e),
+            }
+        });
+    }
+
+    fn delete_radio_station(&self, station: InternetRadioStation) {
+        if let Some(client) = &self.source {
+            let client = client.clone();
+            let sender = self.message_sender.clone();
+            tokio::spawn(async move {
+                info!("Deleting radio station: {}", station.name);
+                match client.delete_internet_radio_station(&station.id).await.map_err(|e| e.to_string()) {
+                    Ok(_) => {
+                        let _ = sender.send(Message::Undoable(UndoAction::RadioStation(station)));
+                        Self::reload_radio_stations(client.as_ref(), &sender).await;
+                    }
+                    Err(e) => Self::report_failure(&sender, "Failed to delete radio station", e),
+                }
+            });
+        }
+    }
+
+    /// Expand and select the artist or album of the song that is currently playing
+    async fn reveal_current_song(&mut self, album: bool) {
+        let Some(song) = self.player_state.current_song.clone() else {
+            return;
+        };
+        let Some(artist_id) = song.artist_id.clone() else {
+            warn!("Current song has no artist ID, cannot reveal it in the tree");
+            return;
+        };
+        let album_id = song.album_id.clone().filter(|_| album);
+        if album && album_id.is_none() {
+            warn!("Current song has no album ID, revealing its artist instead");
+        }
+
+        self.go_to(&artist_id, album_id.as_deref(), None).await;
+    }
+
+    /// Show the artist, album or song under the cursor in the library, e.g. a search result
+    async fn reveal_selected(&mut self) {
+        let Some(item) = self.tree_state.get_selected_item().cloned() else {
+            return;
+        };
+        match item.item_type {
+            TreeItemType::Artist(artist) => self.go_to(&artist.id, None, None).await,
+            TreeItemType::Album(album) => match album.artist_id {
+                Some(artist_id) => self.go_to(&artist_id, Some(&album.id), None).await,
+                None => warn!("Album {} has no artist ID, cannot reveal it in the tree", album.name),
+            },
+            TreeItemType::Song(song) => match (song.artist_id, song.album_id) {
+                (Some(artist_id), Some(album_id)) => self.go_to(&artist_id, Some(&album_id), Some(&song.id)).await,
+                _ => warn!("Song {} has no artist or album ID, cannot reveal it in the tree", song.title),
+            },
+            _ => {}
+        }
+    }
+
+    /// Reveal an artist, album or song in the tree, loading whatever is missing on the way
+    async fn go_to(&mut self, artist_id: &str, album_id: Option<&str>, song_id: Option<&str>) {
+        // Artists and albums are only listed while browsing by tags
+        self.browse_folders(false);
+        self.tree_state.reveal(artist_id, album_id, song_id);
+        if !self.tree_state.artist_albums.contains_key(artist_id) {
+            self.load_artist_albums(artist_id.to_string()).await;
+        }
+        if let Some(album_id) = album_id {
+            if !self.tree_state.album_songs.contains_key(album_id) {
+                self.load_album_songs(album_id.to_string()).await;
+            }
+        }
+    }
+
+    /// Replace or extend the queue with an album, fetching its songs if they are not cached
+    async fn queue_album(&mut self, album_id: String, replace: bool) {
+        if let Some(songs) = self.tree_state.album_songs.get(&album_id).cloned() {
+            self.queue_songs(songs, replace).await;
+            return;
+        }
+
+        if let Some(client) = &self.source {
+            let client = client.clone();
+            let sender = self.message_sender.clone();
+            tokio::spawn(async move {
+                match client.get_album(&album_id).await {
+                    Ok(album_detail) => {
+                        let _ = sender.send(Message::Queue(QueueUpdate::Album(album_id, album_detail.song, replace)));
+                    }
+                    Err(e) => {
+                        Self::report_failure(&sender, &format!("Failed to load album {}", album_id), e);
+                    }
+                }
+            });
+        }
+    }
+
+    /// Revert the most recent destructive action
+    fn undo(&mut self) {
+        let Some(action) = self.undo.pop() else {
+            info!("Nothing to undo");
+            return;
+        };
+        match action {
+            UndoAction::Queue(queue) => self.queue.restore(queue),
+            UndoAction::RadioStation(station) => self.restore_radio_station(station),
+            UndoAction::PlaylistAdd { playlist_id, name, first, song_ids } => {
+                let entries = song_ids.into_iter().enumerate().map(|(i, id)| (first + i, id)).collect();
+                self.remove_from_playlist(playlist_id, name, entries, false);
+            }
+            UndoAction::PlaylistRemove { playlist_id, name, entries } => {
+                self.restore_playlist_entries(playlist_id, name, entries);
+            }
+            UndoAction::Playlist { name, song_ids } => self.restore_playlist(name, song_ids),
+        }
+    }
+
+    /// Create a deleted radio station again
+    fn restore_radio_station(&self, station: InternetRadioStation) {
+        if let Some(client) = &self.source {
+            let client = client.clone();
+            let sender = self.message_sender.clone();
+            tokio::spawn(async move {
+                info!("Restoring radio station: {}", station.name);
+                let result = client
+                    .create_internet_radio_station(&station.name, &station.stream_url, station.home_page_url.as_deref())
+                    .await;
+                match result {
+                    Ok(()) => Self::reload_radio_stations(client.as_ref(), &sender).await,
+                    Err(e) => Self::report_failure(&sender, "Failed to restore radio station", e),
+                }
+            });
+        }
+    }
+
+    /// Create a deleted playlist again
+    fn restore_playlist(&self, name: String, song_ids: Vec<String>) {
+        if let Some(client) = &self.source {
+            let client = client.clone();
+            let sender = self.message_sender.clone();
+            tokio::spawn(async move {
+                info!("Restoring playlist: {}", name);
+                match client.create_playlist(&name, &song_ids).await {
+                    Ok(()) => {
+                        let _ = sender.send(Message::PlaylistsChanged);
+                    }
+                    Err(e) => Self::report_failure(&sender, &format!("Failed to restore playlist {}", name), e),
+                }
+            });
+        }
+    }
+
+    /// Remove the songs at these positions, given with their IDs. Nothing is removed if the
+    /// playlist changed since and a position holds another song.
+    fn remove_from_playlist(&self, playlist_id: String, name: String, entries: Vec<(usize, String)>, undoable: bool) {
+        if let Some(client) = &self.source {
+            let client = client.clone();
+            let sender = self.message_sender.clone();
+            tokio::spawn(async move {
+                let result = async {
+                    let songs = client.get_playlist(&playlist_id).await.map_err(|e| e.to_string())?.entry;
+                    let moved = entries.iter().any(|(index, id)| songs.get(*index).is_none_or(|song| song.id != *id));
+                    if moved {
+                        return Err("the playlist changed since".to_string());
+                    }
+                    let indexes: Vec<usize> = entries.iter().map(|(index, _)| *index).collect();
+                    client.remove_from_playlist(&playlist_id, &indexes).await.map_err(|e| e.to_string())
+                }
+                .await;
+                match result {
+                    Ok(()) => {
+                        info!("Removed {} songs from playlist {}", entries.len(), name);
+                        let message = if undoable {
+                            Message::Undoable(UndoAction::PlaylistRemove { playlist_id, name, entries })
+                        } else {
+                            Message::PlaylistsChanged
+                        };
+                        let _ = sender.send(message);
+                    }
+                    Err(e) => {
+                        let what = format!("Failed to remove songs from playlist {}", name);
+                        Self::report_failure(&sender, &what, e);
+                    }
+                }
+            });
+        }
+    }
+
+    /// Put removed songs back at their positions. Songs can only be appended, so the
+    /// playlist is cut before the first of them and the rest appended again.
+    fn restore_playlist_entries(&self, playlist_id: String, name: String, mut entries: Vec<(usize, String)>) {
+        let Some(client) = self.source.clone() else {
+            return;
+        };
+        let sender = self.message_sender.clone();
+        entries.sort_by_key(|(index, _)| *index);
+        tokio::spawn(async move {
+            let result = async {
+                let songs = client.get_playlist(&playlist_id).await?.entry;
+                let mut song_ids: Vec<String> = songs.into_iter().map(|song| song.id).collect();
+                let len = song_ids.len();
+                let first = entries.first().map_or(len, |(index, _)| (*index).min(len));
+                for (index, id) in entries {
+                    song_ids.insert(index.min(song_ids.len()), id);
+                }
+                client.remove_from_playlist(&playlist_id, &(first..len).collect::<Vec<_>>()).await?;
+                client.add_to_playlist(&playlist_id, &song_ids[first..]).await
+            }
+            .await;
+            match result {
+                Ok(()) => {
+                    info!("Restored songs of playlist {}", name);
+                    let _ = sender.send(Message::PlaylistsChanged);
+                }
+                Err(e) => Self::report_failure(&sender, &format!("Failed to restore songs of playlist {}", name), e),
+            }
+        });
+    }
+
+    /// Queue the marked songs and albums, or the one under the cursor
+    async fn queue_marked(&mut self) {
+        for item_type in self.tree_state.take_marked() {
+            match item_type {
+                TreeItemType::Song(song) => self.queue.push(song),
+                TreeItemType::Album(album) => self.queue_album(album.id, false).await,
+                _ => {}
+            }
+        }
+    }
+
+    /// Star or unstar the marked songs and albums, or the one under the cursor
+    fn star_marked(&mut self, star: bool) {
+        let targets = self.tree_state.take_marked();
+        if !star && targets.len() > 1 && self.config.confirm.unstar_many {
+            self.confirm = Some(Confirmation::Unstar(targets));
+        } else {
+            self.set_starred(targets, star);
+        }
+    }
+
+    fn set_starred(&self, targets: Vec<TreeItemType>, star: bool) {
+        let Some(client) = self.source.clone() else {
+            return;
+        };
+        let sender = self.message_sender.clone();
+        let mut song_ids = Vec::new();
+        let mut album_ids = Vec::new();
+        for item_type in targets {
+            match item_type {
+                TreeItemType::Song(song) => song_ids.push(song.id),
+                TreeItemType::Album(album) => album_ids.push(album.id),
+                _ => {}
+            }
+        }
+
+        tokio::spawn(async move {
+            let result = if star {
+                client.star(&song_ids, &album_ids).await
+            } else {
+                client.unstar(&song_ids, &album_ids).await
+            };
+            let (action, done) = if star { ("star", "Starred") } else { ("unstar", "Unstarred") };
+            match result {
+                Ok(()) => info!("{} {} songs and {} albums", done, song_ids.len(), album_ids.len()),
+                Err(e) => {
+                    let what = format!("Failed to {} {} songs and {} albums", action, song_ids.len(), album_ids.len());
+                    Self::report_failure(&sender, &what, e);
+                }
+            }
+        });
+    }
+
+    /// Append the marked songs and the songs of the marked albums to the playlist with the given name
+    fn add_marked_to_playlist(&mut self, name: String) {
+        let targets = self.tree_state.take_marked();
+        self.add_to_playlist(name, None, targets);
+    }
+
+    /// Append songs and the songs of albums to a playlist, looked up by `name` unless its ID is known
+    fn add_to_playlist(&self, name: String, playlist_id: Option<String>, targets: Vec<TreeItemType>) {
+        let Some(client) = self.source.clone() else {
+            return;
+        };
+        let sender = self.message_sender.clone();
+
+        tokio::spawn(async move {
+            let result = async {
+                let playlist_id = match playlist_id {
+                    Some(id) => id,
+                    None => {
+                        let playlists = client.get_playlists().await.map_err(|e| e.to_string())?;
+                        playlists
+                            .into_iter()
+                            .find(|p| p.name.eq_ignore_ascii_case(&name))
+                            .ok_or_else(|| format!("no playlist named '{}'", name))?
+                            .id
+                    }
+                };
+
+                let song_ids = Self::song_ids(client.as_ref(), targets).await?;
+                // Remember where the songs went, to remove them again on undo
+                let first = client.get_playlist(&playlist_id).await.map_err(|e| e.to_string())?.entry.len();
+                client.add_to_playlist(&playlist_id, &song_ids).await.map_err(|e| e.to_string())?;
+                Ok::<_, String>((playlist_id, first, song_ids))
+            }
+            .await;
+
+            match result {
+                Ok((playlist_id, first, song_ids)) => {
+                    info!("Added {} songs to playlist {}", song_ids.len(), name);
+                    let action = UndoAction::PlaylistAdd { playlist_id, name, first, song_ids };
+                    let _ = sender.send(Message::Undoable(action));
+                }
+                Err(e) => Self::report_failure(&sender, &format!("Failed to add songs to playlist {}", name), e),
+            }
+        });
+    }
+
+    /// Append the playing song to the quick playlist, creating the playlist on first use
+    fn save_to_quick_playlist(&mut self) {
+        let (Some(client), Some(song)) = (self.source.clone(), self.player_state.current_song.clone()) else {
+            return;
+        };
+        let name = self.config.library.quick_playlist.clone();
+        self.notice = Some((format!("Saving \"{}\" to {}", song.title, name), Instant::now()));
+        let sender = self.message_sender.clone();
+
+        tokio::spawn(async move {
+            let song_ids = [song.id.clone()];
+            let result = async {
+                let playlists = client.get_playlists().await?;
+                match playlists.into_iter().find(|p| p.name.eq_ignore_ascii_case(&name)) {
+                    Some(playlist) => {
+                        let first = client.get_playlist(&playlist.id).await?.entry.len();
+                        client.add_to_playlist(&playlist.id, &song_ids).await?;
+                        Ok(Some((playlist.id, first)))
+                    }
+                    None => client.create_playlist(&name, &song_ids).await.map(|_| None),
+                }
+            }
+            .await;
+
+            match result {
+                Ok(Some((playlist_id, first))) => {
+                    info!("Added {} to playlist {}", song.title, name);
+                    let song_ids = vec![song.id];
+                    let action = UndoAction::PlaylistAdd { playlist_id, name, first, song_ids };
+                    let _ = sender.send(Message::Undoable(action));
+                }
+                Ok(None) => {
+                    info!("Created playlist {} with {}", name, song.title);
+                    let _ = sender.send(Message::PlaylistsChanged);
+                }
+                Err(e) => {
+                    let what = format!("Failed to add {} to playlist {}", song.title, name);
+                    Self::report_failure(&sender, &what, e);
+                }
+            }
+        });
+    }
+
+    fn set_playlist_public(&self, playlist: Playlist, public: bool) {
+        let Some(client) = self.source.clone() else {
+            return;
+        };
+        let sender = self.message_sender.clone();
+        tokio::spawn(async move {
+            match client.set_playlist_public(&playlist.id, public).await {
+                Ok(()) => {
+                    info!("Made playlist {} {}", playlist.name, if public { "public" } else { "private" });
+                    let _ = sender.send(Message::PlaylistsChanged);
+                }
+                Err(e) => {
+                    let what = format!("Failed to change the visibility of playlist {}", playlist.name);
+                    Self::report_failure(&sender, &what, e);
+                }
+            }
+        });
+    }
+
+    fn create_playlist(&self, name: String, targets: Vec<TreeItemType>) {
+        let Some(client) = self.source.clone() else {
+            return;
+        };
+        let sender = self.message_sender.clone();
+
+        tokio::spawn(async move {
+            let result = async {
+                let song_ids = Self::song_ids(client.as_ref(), targets).await?;
+                client.create_playlist(&name, &song_ids).await.map_err(|e| e.to_string())?;
+                Ok::<_, String>(song_ids.len())
+            }
+            .await;
+
+            match result {
+                Ok(count) => {
+                    info!("Created playlist {} with {} songs", name, count);
+                    let _ = sender.send(Message::PlaylistsChanged);
+                }
+                Err(e) => Self::report_failure(&sender, &format!("Failed to create playlist {}", name), e),
+            }
+        });
+    }
+
+    /// Delete a playlist, keeping its songs to create it again on undo
+    fn delete_playlist(&mut self, playlist: Playlist) {
+        let Some(client) = self.source.clone() else {
+            return;
+        };
+        self.tree_state.expanded_playlists.remove(&playlist.id);
+        let sender = self.message_sender.clone();
+
+        tokio::spawn(async move {
+            info!("Deleting playlist: {}", playlist.name);
+            let result = async {
+                let detail = client.get_playlist(&playlist.id).await?;
+                client.delete_playlist(&playlist.id).await?;
+                Ok::<_, subsonic::Error>(detail.entry.into_iter().map(|song| song.id).collect())
+            }
+            .await;
+
+            match result {
+                Ok(song_ids) => {
+                    let _ = sender.send(Message::Undoable(UndoAction::Playlist { name: playlist.name, song_ids }));
+                    let _ = sender.send(Message::PlaylistsChanged);
+                }
+                Err(e) => Self::report_failure(&sender, &format!("Failed to delete playlist {}", playlist.name), e),
+            }
+        });
+    }
+
+    /// The songs among `targets`, with albums expanded into their songs
+    async fn songs(client: &dyn MusicSource, targets: Vec<TreeItemType>) -> Result<Vec<Song>, String> {
+        let mut songs = Vec::new();
+        for item_type in targets {
+            match item_type {
+                TreeItemType::Song(song) => songs.push(song),
+                TreeItemType::Album(album) => {
+                    let album = client.get_album(&album.id).await.map_err(|e| e.to_string())?;
+                    songs.extend(album.song);
+                }
+                _ => {}
+            }
+        }
+        Ok(songs)
+    }
+
+    /// The IDs of the songs among `targets`, with albums expanded into their songs
+    async fn song_ids(client: &dyn MusicSource, targets: Vec<TreeItemType>) -> Result<Vec<String>, String> {
+        let songs = Self::songs(client, targets).await?;
+        Ok(songs.into_iter().map(|song| song.id).collect())
+    }
+
+    /// Show where the file of the selected song, or else the playing one, is: among the
+    /// local music or downloaded
+    fn show_file(&mut self) {
+        let song = match self.tree_state.get_selected_item().map(|item| &item.item_type) {
+            Some(TreeItemType::Song(song)) => Some(song),
+            _ => self.player_state.current_song.as_ref(),
+        };
+        let Some(song) = song else {
+            return;
+        };
+        let local = self.source.as_ref().and_then(|client| client.local_path(&song.id));
+        let dir = self.config.library.download_dir();
+        match local.or_else(|| download::downloaded(song, &dir)) {
+            Some(path) => self.file_info = Some(path),
+            None => {
+                let text = format!("\"{}\" is not downloaded, D saves it to {}", song.title, dir.display());
+                self.notice = Some((text, Instant::now()));
+            }
+        }
+    }
+
+    /// Save the marked songs and albums, or the selected one, to the download directory
+    fn download_marked(&mut self) {
+        let targets = self.tree_state.take_marked();
+        let Some(client) = self.source.clone().filter(|_| !targets.is_empty()) else {
+            return;
+        };
+        let name = match targets.as_slice() {
+            [TreeItemType::Song(song)] => format!("Downloading {}", song.title),
+            [TreeItemType::Album(album)] => format!("Downloading {}", album.name),
+            targets => format!("Downloading {} items", targets.len()),
+        };
+        let dir = self.config.library.download_dir();
+        self.notice = Some((format!("{} to {}", name, dir.display()), Instant::now()));
+        self.jobs.spawn(name, self.message_sender.clone(), |reporter| async move {
+            let songs = Self::songs(client.as_ref(), targets).await?;
+            download::save(client.as_ref(), songs, &dir, &reporter).await
+        });
+    }
+
+    /// Replace or extend the queue with a playlist, fetching its songs if they are not loaded
+    async fn queue_playlist_by_id(&mut self, playlist_id: String, replace: bool) {
+        match self.tree_state.playlist_songs.get(&playlist_id) {
+            Some(songs) => self.queue_songs(songs.clone(), replace).await,
+            None => self.load_playlist_songs(playlist_id, Some(replace)),
+        }
+    }
+
+    /// Queue the playlist with the given name, falling back to matching its ID
+    fn queue_playlist(&self, name: String, replace: bool) {
+        if let Some(client) = &self.source {
+            let client = client.clone();
+            let sender = self.message_sender.clone();
+            tokio::spawn(async move {
+                let result = async {
+                    let playlists = client.get_playlists().await.map_err(|e| e.to_string())?;
+                    let playlist = playlists
+                        .iter()
+                        .find(|p| p.name.eq_ignore_ascii_case(&name))
+                        .or_else(|| playlists.iter().find(|p| p.id == name))
+                        .ok_or_else(|| format!("no playlist named '{}'", name))?;
+                    client.get_playlist(&playlist.id).await.map_err(|e| e.to_string())
+                }
+                .await;
+
+                match result {
+                    Ok(playlist) => {
+                        let _ = sender.send(Message::Queue(QueueUpdate::Playlist(playlist.entry, replace)));
+                    }
+                    Err(e) => {
+                        Self::report_failure(&sender, &format!("Failed to load playlist {}", name), e);
+                    }
+                }
+            });
+        }
+    }
+
+    async fn queue_songs(&mut self, songs: Vec<Song>, replace: bool) {
+        if replace {
+            self.undo.push(UndoAction::Queue(self.queue.clone()));
+            self.queue.play_songs(songs);
+            self.play_next().await;
+        } else {
+            self.queue.extend(songs);
+        }
+    }
+
+    fn toggle_starred_filter(&mut self) {
+        if self.tree_state.starred_filter.is_some() {
+            info!("Showing all items");
+            self.tree_state.set_starred_filter(None);
+            return;
+        }
+
+        if let Some(client) = &self.source {
+            let client = client.clone();
+            let sender = self.message_sender.clone();
+            tokio::spawn(async move {
+                match client.get_starred().await {
+                    Ok(starred) => {
+                        let _ = sender.send(Message::Library(LibraryUpdate::Starred(starred)));
+                    }
+                    Err(e) => {
+                        Self::report_failure(&sender, "Failed to load starred items", e);
+                    }
+                }
+            });
+        }
+    }
+
+    /// Play the next queued song, if any
+    async fn play_next(&mut self) {
+        if let Some(song) = self.queue.pop_next() {
+            debug!("Advancing queue ({} songs left)", self.queue.len());
+            self.select_song(song).await;
+        }
+    }
+
+    /// Skip to the next song of the queue, or of the current album when the queue is empty
+    async fn next_track(&mut self) {
+        if !self.queue.is_empty() {
+            self.play_next().await;
+        } else if let Some(song) = self.album_neighbour(1) {
+            self.select_song(song).await;
+        }
+    }
+
+    /// Go back to the previously played song, however it was picked, or without any
+    /// history to the previous one of the current album. A few seconds into the track
+    /// it restarts instead.
+    async fn previous_track(&mut self) {
+        if self.player_state.progress > RESTART_AFTER {
+            self.player_state.restart();
+        } else if let Some(song) = self.queue.pop_played(self.player_state.current_song.clone()) {
+            self.start_song(song).await;
+        } else if let Some(song) = self.album_neighbour(-1) {
+            self.start_song(song).await;
+        } else {
+            self.player_state.restart();
+        }
+    }
+
+    /// The song `offset` tracks away from the current one on its album, if the album is loaded
+    fn album_neighbour(&self, offset: isize) -> Option<Song> {
+        let current = self.player_state.current_song.as_ref()?;
+        let songs = self.tree_state.album_songs.get(current.album_id.as_ref()?)?;
+        let index = songs.iter().position(|s| s.id == current.id)?;
+        songs.get(index.checked_add_signed(offset)?).cloned()
+    }
+
+    /// Fetch the next page of the queue's source when it is running low
+    fn feed_queue(&mut self) {
+        let Some(request) = self.queue.next_page_request() else {
+            return;
+        };
+        let Some(client) = self.source.clone() else {
+            return;
+        };
+
+        let sender = self.message_sender.clone();
+        tokio::spawn(async move {
+            let result = match &request {
+                PageRequest::Genre { genre, offset } => client.get_songs_by_genre(genre, PAGE_SIZE, *offset).await,
+                PageRequest::Similar { artist_id, .. } => client.get_similar_songs(artist_id, PAGE_SIZE).await,
+            };
+            match result {
+                Ok(songs) => {
+                    let _ = sender.send(Message::Queue(QueueUpdate::Page(request, songs)));
+                }
+                Err(e) => {
+                    error!("Failed to load queue page {:?}: {}", request, e);
+                    let _ = sender.send(Message::Queue(QueueUpdate::PageFailed));
+                }
+            }
+        });
+    }
+
+    fn load_genres(&self) {
+        if let Some(client) = &self.source {
+            let client = client.clone();
+            let sender = self.message_sender.clone();
+            tokio::spawn(async move {
+                match client.get_genres().await {
+                    Ok(genres) => {
+                        info!("Loaded {} genres", genres.len());
+                        let _ = sender.send(Message::Library(LibraryUpdate::Genres(genres)));
+                    }
+                    Err(e) => {
+                        Self::report_failure(&sender, "Failed to load genres", e);
+                    }
+                }
+            });
+        }
+    }
+
+    /// List the library by folder or by artist
+    fn browse_folders(&mut self, folders: bool) {
+        if self.tree_state.set_browse_folders(folders) {
+            self.load_folders();
+        }
+    }
+
+    fn load_folders(&self) {
+        if let Some(client) = &self.source {
+            let client = client.clone();
+            let sender = self.message_sender.clone();
+            tokio::spawn(async move {
+                match client.get_indexes().await {
+                    Ok(indexes) => {
+                        let folders: Vec<Folder> = indexes
+                            .index
+                            .into_iter()
+                            .flat_map(|index| index.artist)
+                            .map(|entry| Folder { id: entry.id, name: entry.name })
+                            .collect();
+                        info!("Loaded {} top directories", folders.len());
+                        let _ = sender.send(Message::Library(LibraryUpdate::Folders(folders, indexes.child)));
+                    }
+                    Err(e) => {
+                        Self::report_failure(&sender, "Failed to load music folders", e);
+                    }
+                }
+            });
+        }
+    }
+
+    /// Fetch the biography and similar artists of an artist, once
+    fn load_artist_info(&mut self, artist_id: String) {
+        let Some(client) = self.source.clone() else {
+            return;
+        };
+        if !self.requested_artist_info.insert(artist_id.clone()) {
+            return;
+        }
+        let sender = self.message_sender.clone();
+        tokio::spawn(async move {
+            let info = match client.get_artist_info(&artist_id, SIMILAR_ARTISTS).await {
+                Ok(info) => info,
+                Err(e) => {
+                    // Shown without biography rather than loading forever
+                    warn!("Failed to load info of artist {}: {}", artist_id, e);
+                    Default::default()
+                }
+            };
+            let _ = sender.send(Message::Library(LibraryUpdate::ArtistInfo(artist_id, info)));
+        });
+    }
+
+    fn load_music_folders(&self) {
+        if let Some(client) = &self.source {
+            let client = client.clone();
+            let sender = self.message_sender.clone();
+            tokio::spawn(async move {
+                let folders = match client.get_music_folders().await {
+                    Ok(folders) => {
+                        info!("Loaded {} music folders", folders.len());
+                        folders
+                    }
+                    Err(e) => {
+                        // Still answered, a folder waiting to be switched to falls back to all of them
+                        error!("Failed to load music folders: {}", e);
+                        Vec::new()
+                    }
+                };
+                let _ = sender.send(Message::Library(LibraryUpdate::MusicFolders(folders)));
+            });
+        }
+    }
+
+    fn open_music_folder_picker(&mut self) {
+        if self.tree_state.music_folders.as_ref().is_none_or(|folders| folders.is_empty()) {
+            self.load_music_folders();
+        }
+        let folders = self.tree_state.music_folders.as_deref().unwrap_or_default();
+        let current = self.tree_state.music_folder.as_ref().and_then(|current| folders.iter().position(|folder| folder == current));
+        let mut list = SelectList::first(folders.len() + 1);
+        list.state.select(Some(current.map_or(0, |i| i + 1)));
+        self.music_folder_picker = Some(list);
+    }
+
+    /// Switch to the music folder with this name or ID, "all" lists all of them
+    fn switch_music_folder(&mut self, wanted: String) {
+        if wanted.eq_ignore_ascii_case("all") {
+            if self.tree_state.music_folder.is_some() {
+                self.select_music_folder(None);
+            }
+            return;
+        }
+        self.pending_music_folder = Some(wanted);
+        if self.tree_state.music_folders.as_ref().is_some_and(|folders| !folders.is_empty()) {
+            self.switch_to_pending_music_folder();
+        } else {
+            self.load_music_folders();
+        }
+    }
+
+    /// Look up the folder waiting in `pending_music_folder` among the loaded ones
+    fn switch_to_pending_music_folder(&mut self) {
+        let Some(wanted) = self.pending_music_folder.take() else {
+            return;
+        };
+        let folders = self.tree_state.music_folders.as_deref().unwrap_or_default();
+        let found = folders
+            .iter()
+            .find(|folder| folder.id == wanted || folder.name.as_deref().is_some_and(|name| name.eq_ignore_ascii_case(&wanted)))
+            .cloned();
+        match found {
+            Some(folder) if self.tree_state.music_folder.as_ref() == Some(&folder) => {}
+            Some(folder) => self.select_music_folder(Some(folder)),
+            None => {
+                warn!("No music folder named {}", wanted);
+                self.notice = Some((format!("No music folder \"{}\"", wanted), Instant::now()));
+                // Nothing is listed yet while the configured folder was looked up
+                if self.artists_job.is_none() && self.tree_state.artists.is_empty() {
+                    self.load_artists();
+                }
+            }
+        }
+    }
+
+    /// Stream the songs started from now on re-encoded like this, or as they are
+    fn set_transcoding(&mut self, transcoding: Option<Transcoding>) {
+        let Some(client) = self.source.take() else {
+            return;
+        };
+        let text = match &transcoding {
+            Some(transcoding) => format!("Streaming at {}", transcoding),
+            None => "Streaming the original files".to_string(),
+        };
+        info!("{}", text);
+        self.notice = Some((text, Instant::now()));
+        self.source = Some(client.with_transcoding(transcoding.clone()));
+        self.transcoding = transcoding;
+    }
+
+    /// Switch between the configured transcoding and the original files
+    fn toggle_transcoding(&mut self) {
+        if self.transcoding.is_some() {
+            self.set_transcoding(None);
+            return;
+        }
+        match self.config.streaming.transcoding() {
+            Some(transcoding) => self.set_transcoding(Some(transcoding)),
+            None => {
+                self.notice = Some(("No transcoding configured, try :transcode 128 opus".to_string(), Instant::now()));
+            }
+        }
+    }
+
+    /// `:transcode` with a bitrate in kbit/s, a format, both, or `off`
+    fn transcode(&mut self, options: &str) {
+        if options == "off" {
+            self.set_transcoding(None);
+            return;
+        }
+        let mut transcoding = Transcoding::default();
+        for option in options.split_whitespace() {
+            match option.trim_end_matches("kbps").parse() {
+                Ok(rate) => transcoding.max_bit_rate = rate,
+                Err(_) => transcoding.format = Some(option.to_lowercase()),
+            }
+        }
+        self.set_transcoding(Some(transcoding));
+    }
+
+    /// List the library from another music folder, or from all of them with `None`,
+    /// loading what is shown of it again
+    fn select_music_folder(&mut self, folder: Option<MusicFolder>) {
+        let Some(client) = self.source.take() else {
+            return;
+        };
+        info!("Listing music folder {}", folder.as_ref().map_or("(all)", |folder| folder.label()));
+        self.source = Some(client.with_music_folder(folder.as_ref().map(|folder| folder.id.clone())));
+
+        for (_, request) in self.expand_requests.drain() {
+            request.cancel();
+        }
+        if let Some(job) = self.artists_job.take() {
+            self.jobs.cancel(job);
+        }
+        if let Some((_, task)) = self.search_task.take() {
+            task.abort();
+        }
+        let starred = self.tree_state.starred_filter.is_some();
+        self.tree_state.set_music_folder(folder);
+
+        self.load_artists();
+        if self.tree_state.browse_folders {
+            self.load_folders();
+        }
+        if starred {
+            self.toggle_starred_filter();
+        }
+    }
+
+    fn load_folder_contents(&mut self, folder_id: String) {
+        if let Some(client) = &self.source {
+            let client = client.clone();
+            let sender = self.message_sender.clone();
+            let token = self.expand_request(&folder_id);
+            spawn_until_cancelled(token, async move {
+                match client.get_music_directory(&folder_id).await {
+                    Ok(directory) => {
+                        let (folders, songs): (Vec<_>, Vec<_>) = directory.child.into_iter().partition(|child| child.is_dir);
+                        let folders = folders
+                            .into_iter()
+                            .map(|child| Folder { id: child.song.id, name: child.song.title })
+                            .collect();
+                        let songs = songs.into_iter().map(|child| child.song).collect();
+                        let _ = sender.send(Message::Library(LibraryUpdate::FolderContents(folder_id, folders, songs)));
+                    }
+                    Err(e) => {
+                        Self::report_failure(&sender, &format!("Failed to load directory {}", folder_id), e);
+                    }
+                }
+            });
+        }
+    }
+
+    fn load_genre_songs(&self, genre: String) {
+        if let Some(client) = &self.source {
+            let client = client.clone();
+            let sender = self.message_sender.clone();
+            tokio::spawn(async move {
+                match client.get_songs_by_genre(&genre, GENRE_SONGS_SIZE, 0).await {
+                    Ok(songs) => {
+                        info!("Loaded {} songs of genre {}", songs.len(), genre);
+                        let _ = sender.send(Message::Library(LibraryUpdate::GenreSongs(genre, songs)));
+                    }
+                    Err(e) => {
+                        Self::report_failure(&sender, &format!("Failed to load songs of genre {}", genre), e);
+                    }
+                }
+            });
+        }
+    }
+
+    fn load_album_list(&self, list: AlbumListType) {
+        if let Some(client) = &self.source {
+            let client = client.clone();
+            let sender = self.message_sender.clone();
+            tokio::spawn(async move {
+                match client.get_album_list(list, ALBUM_LIST_SIZE, 0).await {
+                    Ok(albums) => {
+                        info!("Loaded {} albums ({})", albums.len(), list.param());
+                        let _ = sender.send(Message::Library(LibraryUpdate::AlbumList(list, albums)));
+                    }
+                    Err(e) => {
+                        Self::report_failure(&sender, &format!("Failed to load album list {}", list.param()), e);
+                    }
+                }
+            });
+        }
+    }
+
+    fn load_playlists(&self) {
+        if let Some(client) = &self.source {
+            let client = client.clone();
+            let sender = self.message_sender.clone();
+            tokio::spawn(async move {
+                match client.get_playlists().await {
+                    Ok(playlists) => {
+                        info!("Loaded {} playlists", playlists.len());
+                        let _ = sender.send(Message::Library(LibraryUpdate::Playlists(playlists)));
+                    }
+                    Err(e) => {
+                        Self::report_failure(&sender, "Failed to load playlists", e);
+                    }
+                }
+            });
+        }
+    }
+
+    /// Fetch the songs of a playlist for the tree, and with `queue` also replace
+    /// (`true`) or extend (`false`) the queue with them
+    fn load_playlist_songs(&self, playlist_id: String, queue: Option<bool>) {
+        if let Some(client) = &self.source {
+            let client = client.clone();
+            let sender = self.message_sender.clone();
+            tokio::spawn(async move {
+                match client.get_playlist(&playlist_id).await {
+                    Ok(playlist) => {
+                        if let Some(replace) = queue {
+                            let _ = sender.send(Message::Queue(QueueUpdate::Playlist(playlist.entry.clone(), replace)));
+                        }
+                        let _ = sender.send(Message::Library(LibraryUpdate::PlaylistSongs(playlist_id, playlist.entry)));
+                    }
+                    Err(e) => {
+                        Self::report_failure(&sender, &format!("Failed to load playlist {}", playlist_id), e);
+                    }
+                }
+            });
+        }
+    }
+
+    /// Tell the user about malformed items the client left out of responses, the log has the details
+    fn report_skipped_items(&mut self) {
+        let Some(client) = &self.source else {
+            return;
+        };
+        let text = match client.take_skipped().as_slice() {
+            [] => return,
+            [item] => format!("Skipped malformed {}", item),
+            items => format!("Skipped {} malformed items, see the log", items.len()),
+        };
+        self.notice = Some((text, Instant::now()));
+    }
+
+    /// Reload the playlists shown in the tree after one of them changed
+    fn playlists_changed(&mut self) {
+        if self.tree_state.invalidate_playlists() {
+            self.load_playlists();
+            // Expanded playlists would be left without songs otherwise
+            let expanded: Vec<String> = self
+                .tree_state
+                .expanded_playlists
+                .iter()
+                .filter(|(_, expanded)| **expanded)
+                .map(|(id, _)| id.clone())
+                .collect();
+            for playlist_id in expanded {
+                self.load_playlist_songs(playlist_id, None);
+            }
+        }
+    }
+
+    fn load_radio_stations(&self) {
+        if let Some(client) = &self.source {
+            let client = client.clone();
+            let sender = self.message_sender.clone();
+            tokio::spawn(async move {
+                Self::reload_radio_stations(client.as_ref(), &sender).await;
+            });
+        }
+    }
+
+    /// Log a failed request and show the server's reason as a notice
+    fn report_failure(sender: &mpsc::UnboundedSender<Message>, what: &str, e: impl std::fmt::Display) {
+        error!("{}: {}", what, e);
+        let _ = sender.send(Message::Failed(format!("{}: {}", what, e)));
+    }
+
+    async fn reload_radio_stations(client: &dyn MusicSource, sender: &mpsc::UnboundedSender<Message>) {
+        match client.get_internet_radio_stations().await {
+            Ok(stations) => {
+                info!("Loaded {} radio stations", stations.len());
+                let _ = sender.send(Message::Library(LibraryUpdate::RadioStations(stations)));
+            }
+            Err(e) => {
+                Self::report_failure(sender, "Failed to load radio stations", e);
+            }
+        }
+    }
+
+    async fn load_artist_albums(&mut self, artist_id: String) {
+        if let Some(client) = &self.source {
+            let client = client.clone();
+            let sender = self.message_sender.clone();
+            let token = self.expand_request(&artist_id);
+            spawn_until_cancelled(token, async move {
+                match client.get_artist(&artist_id).await {
+                    Ok(albums) => {
+                        let _ = sender.send(Message::Library(LibraryUpdate::ArtistAlbums(artist_id, albums)));
+                    }
+                    Err(e) => Self::report_failure(&sender, &format!("Failed to load artist {}", artist_id), e),
+                }
+            });
+        }
+    }
+
+    async fn load_album_songs(&mut self, album_id: String) {
+        if let Some(client) = &self.source {
+            let client = client.clone();
+            let sender = self.message_sender.clone();
+            let token = self.expand_request(&album_id);
+            spawn_until_cancelled(token, async move {
+                match client.get_album(&album_id).await {
+                    Ok(album_detail) => {
+                        let _ = sender.send(Message::Library(LibraryUpdate::AlbumSongs(album_id, album_detail.song)));
+                    }
+                    Err(e) => Self::report_failure(&sender, &format!("Failed to load album {}", album_id), e),
+                }
+            });
+        }
+    }
 
-            if event::poll(Duration::from_millis(100))? {
-                if let Event::Key(key) = event::read()? {
-                    if key.kind == KeyEventKind::Press {
-                        self.handle_key_event(key.code).await;
-                    }
-                }
-            }
+    /// Token for loading the contents of an artist or album, replacing an earlier one
+    fn expand_request(&mut self, id: &str) -> CancellationToken {
+        let token = self.shutdown.child_token();
+        if let Some(earlier) = self.expand_requests.insert(id.to_string(), token.clone()) {
+            earlier.cancel();
         }
-        Ok(())
+        token
     }
 
-    async fn handle_message(&mut self, message: Message) {
-        match message {
-            Message::LoadedArtists(artists) => {
-                self.tree_state.set_artists(artists);
-            }
-            Message::LoadedArtistAlbums(artist_id, albums) => {
-                self.tree_state.set_artist_albums(artist_id, albums);
-            }
-            Message::LoadedAlbumSongs(album_id, songs) => {
-                self.tree_state.set_album_songs(album_id, songs);
-            }
-            Message::LoadedCoverArt(cover_art) => {
-                self.player_state.set_cover_art(cover_art);
-            }
-            Message::LoadedLyrics(lyrics) => {
-                self.player_state.set_lyrics(lyrics);
-            }
-            Message::Quit => {
-                self.should_quit = true;
-            }
+    /// Drop the contents of a collapsed artist or album if they are still on their way
+    fn cancel_expand_request(&mut self, id: &str) {
+        if let Some(token) = self.expand_requests.remove(id) {
+            debug!("Cancelling request for collapsed {}", id);
+            token.cancel();
         }
     }
 
-    async fn handle_key_event(&mut self, key: KeyCode) {
-        match key {
-            KeyCode::Char('q') | KeyCode::Esc => {
-                self.should_quit = true;
-            }
-            KeyCode::Up => {
-                self.tree_state.previous();
-            }
-            KeyCode::Down => {
-                self.tree_state.next();
-            }
-            KeyCode::Enter | KeyCode::Right => {
-                if let Some(item) = self.tree_state.get_selected_item().cloned() {
-                    match &item.item_type {
-                        TreeItemType::Artist(artist) => {
-                            let should_load = self.tree_state.toggle_artist(&artist.id);
-                            if should_load {
-                                self.load_artist_albums(artist.id.clone()).await;
-                            }
-                        }
-                        TreeItemType::Album(album) => {
-                            let should_load = self.tree_state.toggle_album(&album.id);
-                            if should_load {
-                                self.load_album_songs(album.id.clone()).await;
-                            }
-                        }
-                        TreeItemType::Song(song) => {
-                            self.select_song(song.clone()).await;
-                        }
-                    }
-                }
-            }
-            KeyCode::Left => {
-                if let Some(item) = self.tree_state.get_selected_item().cloned() {
-                    match &item.item_type {
-                        TreeItemType::Artist(artist) => {
-                            self.tree_state.toggle_artist(&artist.id);
+    /// Fetch the songs of albums in the background, a few at a time, so expanding them is instant
+    fn prefetch_album_songs(&mut self, albums: &[Album]) {
+        let Some(client) = &self.source else {
+            return;
+        };
+        let tasks: Vec<_> = albums
+            .iter()
+            .filter(|a| !self.tree_state.album_songs.contains_key(&a.id))
+            .map(|album| {
+                let client = client.clone();
+                let sender = self.message_sender.clone();
+                let album_id = album.id.clone();
+                async move {
+                    match client.get_album(&album_id).await {
+                        Ok(album_detail) => {
+                            let _ = sender.send(Message::Library(LibraryUpdate::AlbumSongs(album_id, album_detail.song)));
+                            Ok(())
                         }
-                        TreeItemType::Album(album) => {
-                            self.tree_state.toggle_album(&album.id);
+                        Err(e) => {
+                            debug!("Failed to prefetch album {}: {}", album_id, e);
+                            Err(e.to_string())
                         }
-                        _ => {}
                     }
                 }
-            }
-            KeyCode::Char(' ') => {
-                self.player_state.toggle_play_pause();
-            }
-            _ => {}
+            })
+            .collect();
+        if tasks.is_empty() {
+            return;
         }
+
+        let name = format!("Prefetching {} albums", tasks.len());
+        self.jobs.spawn(name, self.message_sender.clone(), |reporter| async move {
+            reporter.run_all(PREFETCH_CONCURRENCY, tasks).await
+        });
     }
 
-    async fn load_artist_albums(&self, artist_id: String) {
-        if let Some(client) = &self.subsonic_client {
+    /// Fetch the thumbnails of albums that don't have one yet, when they are shown
+    fn load_thumbnails(&mut self, albums: &[Album]) {
+        if !self.tree_state.show_thumbnails {
+            return;
+        }
+        let Some(client) = &self.source else {
+            return;
+        };
+        let mut tasks = Vec::new();
+        for cover_art_id in albums.iter().filter_map(|a| a.cover_art.clone()) {
+            if !self.requested_thumbnails.insert(cover_art_id.clone()) {
+                continue;
+            }
             let client = client.clone();
             let sender = self.message_sender.clone();
-            tokio::spawn(async move {
-                if let Ok(albums) = client.get_artist(&artist_id).await {
-                    let _ = sender.send(Message::LoadedArtistAlbums(artist_id, albums));
+            let cover_cache = self.cover_cache.clone();
+            let size = Some(thumbnail::REQUEST_SIZE);
+            tasks.push(async move {
+                let cover_art = match cover_cache.get(&cover_art_id, size) {
+                    Some(cover_art) => cover_art,
+                    None => match client.get_cover_art(&cover_art_id, size).await {
+                        Ok(cover_art) => {
+                            cover_cache.put(&cover_art_id, size, &cover_art);
+                            cover_art
+                        }
+                        Err(e) => {
+                            debug!("Failed to load thumbnail {}: {}", cover_art_id, e);
+                            return Err(e.to_string());
+                        }
+                    },
+                };
+                if let Some(thumbnail) = Thumbnail::from_cover(&cover_art) {
+                    let _ = sender.send(Message::Library(LibraryUpdate::Thumbnail(cover_art_id, thumbnail)));
                 }
+                Ok(())
             });
         }
+        if tasks.is_empty() {
+            return;
+        }
+
+        let name = format!("Loading {} thumbnails", tasks.len());
+        self.jobs.spawn(name, self.message_sender.clone(), |reporter| async move {
+            reporter.run_all(PREFETCH_CONCURRENCY, tasks).await
+        });
     }
 
-    async fn load_album_songs(&self, album_id: String) {
-        if let Some(client) = &self.subsonic_client {
-            let client = client.clone();
-            let sender = self.message_sender.clone();
-            tokio::spawn(async move {
-                if let Ok(album_detail) = client.get_album(&album_id).await {
-                    let _ = sender.send(Message::LoadedAlbumSongs(album_id, album_detail.song));
-                }
-            });
+    /// Play the selected song, or the playing one, and fill the queue with similar songs as it drains
+    async fn start_radio(&mut self) {
+        let seed = match self.tree_state.get_selected_item().map(|i| &i.item_type) {
+            Some(TreeItemType::Song(song)) => Some(song.clone()),
+            _ => self.player_state.current_song.clone(),
+        };
+        let Some(seed) = seed else {
+            return;
+        };
+
+        let previous = self.queue.clone();
+        if !self.queue.play_similar(&seed) {
+            warn!("Song '{}' has no artist to start a radio from", seed.title);
+            self.notice = Some((format!("No artist to start a radio from \"{}\"", seed.title), Instant::now()));
+            return;
         }
+        self.undo.push(UndoAction::Queue(previous));
+        if self.player_state.current_song.as_ref().is_none_or(|current| current.id != seed.id) {
+            self.select_song(seed).await;
+        }
+        self.feed_queue();
     }
 
+    /// Play a song, remembering the one playing before for "previous"
     async fn select_song(&mut self, song: Song) {
+        if let Some(current) = self.player_state.current_song.clone().filter(|c| c.id != song.id) {
+            self.queue.push_played(current);
+        }
+        self.start_song(song).await;
+    }
+
+    async fn start_song(&mut self, song: Song) {
         info!("User selected song: {} by {}", 
                song.title, 
                song.artist.as_deref().unwrap_or("Unknown Artist"));
         
         self.player_state.set_current_song(song.clone());
+        self.cancel_song_requests();
+        self.apply_song_volume(&song);
+        self.apply_replay_gain(&song);
         self.tree_state.select_song(song.clone());
+        let unscrobbled = self.state.unscrobbled.contains(&song.id);
+        self.player_state.song_unscrobbled = unscrobbled;
+        if unscrobbled {
+            self.scrobbler.stop();
+        } else {
+            if self.player_state.scrobbling {
+                self.scrobbler.start(&song);
+                self.send_now_playing(&song);
+            }
+            self.state.record_played(&song);
+        }
+
+        self.save_session();
+        let recent_songs = self.state.history.iter().map(|entry| entry.song.clone()).collect();
+        self.tree_state.set_recent_songs(recent_songs);
 
-        if let Some(client) = &self.subsonic_client {
+        if let Some(client) = &self.source {
             // Start playing the song
             let stream_url = client.get_stream_url(&song.id);
-            info!("Generated stream URL: {}", stream_url);
+            info!("Generated stream URL for song {}", song.id);
             
             match self.player_state.play_url(&stream_url) {
                 Ok(_) => {
@@ -232,17 +2895,349 @@ impl App {
                 }
             }
 
+            self.load_song_metadata(&song);
+        } else {
+            error!("No Subsonic client available");
+        }
+    }
+
+    /// Stream an internet radio station. Stations aren't scrobbled or kept in the history.
+    fn play_station(&mut self, station: InternetRadioStation) {
+        if self.player_state.jukebox_active() {
+            self.notice = Some(("Internet radio can't play on the jukebox".to_string(), Instant::now()));
+            return;
+        }
+        // The stream URL may carry a login, so only the name is logged
+        info!("Tuning in to {}", station.name);
+        if let Some(current) = self.player_state.current_song.clone() {
+            self.queue.push_played(current);
+        }
+        self.scrobbler.stop();
+        self.player_state.set_current_station(station.clone());
+        self.cancel_song_requests();
+        self.tree_state.deselect_song();
+        self.player_state.set_volume_offset(0.0);
+        let playback = &self.config.playback;
+        self.player_state.set_replay_gain(None, playback.replaygain.mpv_value(), playback.replaygain_preamp);
+        if let Err(e) = self.player_state.play_url(&station.stream_url) {
+            error!("Failed to play station {}: {}", station.name, e);
+        }
+        self.save_session();
+    }
+
+    /// Take over the server's jukebox, reporting back like mpv does
+    fn jukebox_output(&self) -> Output {
+        let client = self.source.clone().expect("client is set up before playback");
+        let sender = self.message_sender.clone();
+        Output::Jukebox(Jukebox::new(client, move |event| sender.send(Message::Player(event)).is_ok()))
+    }
+
+    /// Move playback between this machine and the server's jukebox, continuing the
+    /// current song at the same position
+    fn toggle_jukebox(&mut self) {
+        if self.source.is_none() {
+            return;
+        }
+        let jukebox = !self.player_state.jukebox_active();
+        if jukebox && self.player_state.station.is_some() {
+            self.notice = Some(("Internet radio can't play on the jukebox".to_string(), Instant::now()));
+            return;
+        }
+
+        let (position, playing, stopped) = (self.player_state.progress, self.player_state.is_playing, self.player_state.stopped);
+        self.player_state.stop();
+        let output = if jukebox { self.jukebox_output() } else { Output::Local };
+        self.player_state.set_output(output);
+        let text = if jukebox { "Playing on the server's jukebox" } else { "Playing on this machine" };
+        info!("{}", text);
+        self.notice = Some((text.to_string(), Instant::now()));
+
+        let Some(song) = self.player_state.current_song.clone().filter(|_| !stopped) else {
+            return;
+        };
+        let Some(client) = &self.source else {
+            return;
+        };
+        let stream_url = client.get_stream_url(&song.id);
+        self.apply_replay_gain(&song);
+        if let Err(e) = self.player_state.resume_url(&stream_url, position) {
+            error!("Failed to continue {}: {}", song.title, e);
+            return;
+        }
+        if playing {
+            self.player_state.toggle_play_pause();
+        }
+    }
+
+    /// Use the volume adjustment remembered for `song`, or none
+    fn apply_song_volume(&mut self, song: &Song) {
+        let offset = self.state.song_volumes.get(&song.id).copied().unwrap_or(0.0);
+        self.player_state.set_volume_offset(offset);
+    }
+
+    /// Normalize `song` as configured, preferring the server's gain over the stream's tags
+    fn apply_replay_gain(&self, song: &Song) {
+        let playback = &self.config.playback;
+        let gain = match playback.replaygain {
+            ReplayGainMode::Off => None,
+            mode => song.replay_gain.as_ref().and_then(|r| r.gain(mode == ReplayGainMode::Album)),
+        };
+        if let Some(gain) = gain {
+            debug!("ReplayGain for {}: {:.2} dB", song.title, gain);
+        }
+        self.player_state.set_replay_gain(gain, playback.replaygain.mpv_value(), playback.replaygain_preamp);
+    }
+
+    /// Make the playing song louder or quieter by `delta` percent, remembered for the next time it plays
+    fn adjust_song_volume(&mut self, delta: f64) {
+        let Some(song) = &self.player_state.current_song else {
+            return;
+        };
+        let offset = (self.player_state.volume_offset + delta).clamp(-100.0, 100.0);
+        info!("Volume adjustment for {}: {:+.0}%", song.title, offset);
+        if offset == 0.0 {
+            self.state.song_volumes.remove(&song.id);
+        } else {
+            self.state.song_volumes.insert(song.id.clone(), offset);
+        }
+        self.state.save();
+        self.player_state.set_volume_offset(offset);
+    }
+
+    /// Switch scrobbling on or off for this session, e.g. for listening privately.
+    /// Switching it off mid-track means the track isn't recorded.
+    fn toggle_scrobbling(&mut self) {
+        self.player_state.scrobbling = !self.player_state.scrobbling;
+        info!("Scrobbling {}", if self.player_state.scrobbling { "on" } else { "off" });
+        match self.player_state.current_song.clone() {
+            Some(song) if self.player_state.scrobbling && !self.player_state.stopped && !self.player_state.song_unscrobbled => {
+                self.scrobbler.start(&song);
+                self.send_now_playing(&song);
+            }
+            _ => self.scrobbler.stop(),
+        }
+        let text = if self.player_state.scrobbling { "Scrobbling on" } else { "Scrobbling off, listening privately" };
+        self.notice = Some((text.to_string(), Instant::now()));
+    }
+
+    /// Mark the playing song to never be scrobbled nor kept in the history, or clear the mark.
+    /// Marking it drops its history entries and the scrobbles still waiting to be submitted.
+    fn toggle_song_scrobbling(&mut self) {
+        let Some(song) = self.player_state.current_song.clone() else {
+            return;
+        };
+        let unscrobbled = !self.state.unscrobbled.remove(&song.id);
+        if unscrobbled {
+            info!("Never scrobbling {}", song.title);
+            self.state.unscrobbled.insert(song.id.clone());
+            self.state.history.retain(|entry| entry.song.id != song.id);
+            self.state.pending_scrobbles.retain(|scrobble| scrobble.song_id != song.id);
+            self.scrobbler.stop();
+        } else {
+            info!("Scrobbling {} again", song.title);
+            if self.player_state.scrobbling && !self.player_state.stopped {
+                self.scrobbler.start(&song);
+                self.send_now_playing(&song);
+            }
+        }
+        self.player_state.song_unscrobbled = unscrobbled;
+        self.state.save();
+        let recent_songs = self.state.history.iter().map(|entry| entry.song.clone()).collect();
+        self.tree_state.set_recent_songs(recent_songs);
+
+        let text = if unscrobbled {
+            format!("\"{}\" won't be scrobbled or kept in the history", song.title)
+        } else {
+            format!("\"{}\" is scrobbled again", song.title)
+        };
+        self.notice = Some((text, Instant::now()));
+    }
+
+    fn send_now_playing(&self, song: &Song) {
+        let Some(client) = self.source.clone() else {
+            return;
+        };
+        let song_id = song.id.clone();
+        tokio::spawn(async move {
+            if let Err(e) = client.now_playing(&song_id).await {
+                warn!("Failed to send now playing for {}: {}", song_id, e);
+            }
+        });
+    }
+
+    /// Queue the playing track once it counts as played and submit queued scrobbles.
+    /// Scrobbles are kept in the state file until the server accepted them.
+    fn update_scrobbles(&mut self) {
+        if let Some(scrobble) = self.scrobbler.check(self.player_state.progress, self.player_state.duration) {
+            self.state.pending_scrobbles.push(scrobble);
+            self.state.save();
+        }
+
+        let Some(client) = &self.source else {
+            return;
+        };
+        let Some(batch) = self.scrobbler.next_batch(&self.state.pending_scrobbles) else {
+            return;
+        };
+
+        let client = client.clone();
+        let sender = self.message_sender.clone();
+        tokio::spawn(async move {
+            let plays: Vec<_> = batch.into_iter().map(|s| (s.song_id, s.time)).collect();
+            match client.scrobble(&plays).await.map_err(|e| e.to_string()) {
+                Ok(()) => {
+                    let _ = sender.send(Message::Scrobble(ScrobbleUpdate::Submitted));
+                }
+                Err(e) => {
+                    warn!("Failed to submit {} scrobbles: {}", plays.len(), e);
+                    let _ = sender.send(Message::Scrobble(ScrobbleUpdate::Failed));
+                }
+            }
+        });
+    }
+
+    /// Put the saved session back in place, with the current song loaded but paused
+    fn restore_session(&mut self, session: Session) {
+        info!("Restoring session with {} queued songs", session.queue.len());
+        self.queue.extend(session.queue);
+
+        let Some(song) = session.current_song else {
+            return;
+        };
+        let Some(client) = self.source.clone() else {
+            return;
+        };
+
+        self.player_state.set_current_song(song.clone());
+        self.apply_song_volume(&song);
+        self.apply_replay_gain(&song);
+        self.tree_state.select_song(song.clone());
+        let stream_url = client.get_stream_url(&song.id);
+        if let Err(e) = self.player_state.resume_url(&stream_url, session.position) {
+            error!("Failed to restore playback: {}", e);
+        }
+        self.load_song_metadata(&song);
+    }
+
+    /// Write the current song, position, queue and volume to the state file
+    fn save_session(&mut self) {
+        self.last_autosave = Instant::now();
+        self.state.session = Some(Session {
+            current_song: self.player_state.current_song.clone(),
+            position: self.player_state.progress,
+            queue: self.queue.songs().cloned().collect(),
+            volume: self.player_state.base_volume(),
+            saved_at: unix_time(),
+        });
+        self.state.save();
+
+        if self.config.session.sync {
+            self.save_play_queue();
+        }
+    }
+
+    /// Upload the current song and queue to the server's play queue bookmark
+    fn save_play_queue(&mut self) {
+        let Some(client) = &self.source else {
+            return;
+        };
+        let current = self.player_state.current_song.as_ref().map(|song| song.id.clone());
+        let song_ids: Vec<String> = current.iter().cloned().chain(self.queue.songs().map(|song| song.id.clone())).collect();
+        if song_ids.is_empty() {
+            return;
+        }
+        let position_ms = (self.player_state.progress * 1000.0) as u64;
+
+        if let Some(task) = self.play_queue_sync.take() {
+            task.abort();
+        }
+        let client = client.clone();
+        self.play_queue_sync = Some(tokio::spawn(async move {
+            match client.save_play_queue(&song_ids, current.as_deref(), position_ms).await.map_err(|e| e.to_string()) {
+                Ok(()) => debug!("Saved play queue of {} songs on the server", song_ids.len()),
+                Err(e) => warn!("Failed to save play queue on the server: {}", e),
+            }
+        }));
+    }
+
+    /// Fetch the server's play queue bookmark
+    fn load_play_queue(&self) {
+        if let Some(client) = &self.source {
+            let client = client.clone();
+            let sender = self.message_sender.clone();
+            tokio::spawn(async move {
+                match client.get_play_queue().await {
+                    Ok(Some(play_queue)) => {
+                        let _ = sender.send(Message::Queue(QueueUpdate::Synced(play_queue)));
+                    }
+                    Ok(None) => debug!("No play queue saved on the server"),
+                    Err(e) => warn!("Failed to load play queue from the server: {}", e),
+                }
+            });
+        }
+    }
+
+    /// Resume the server's play queue if it was saved after the local session
+    fn apply_play_queue(&mut self, play_queue: PlayQueue) {
+        let local_saved_at = self.state.session.as_ref().map_or(0, |session| session.saved_at);
+        let server_saved_at = play_queue.changed_at().unwrap_or(0);
+        if server_saved_at <= local_saved_at || play_queue.entry.is_empty() {
+            debug!("Keeping local session, the server's play queue is not newer");
+            return;
+        }
+        info!(
+            "Resuming play queue saved on the server by {}",
+            play_queue.changed_by.as_deref().unwrap_or("another client")
+        );
+
+        let current_id = play_queue.current_id();
+        let mut songs = play_queue.entry;
+        let current = current_id
+            .and_then(|id| songs.iter().position(|song| song.id == id))
+            .unwrap_or(0);
+        let upcoming = songs.split_off(current + 1);
+        self.queue.play_songs(Vec::new());
+        self.restore_session(Session {
+            current_song: songs.pop(),
+            position: play_queue.position.unwrap_or(0) as f32 / 1000.0,
+            queue: upcoming,
+            volume: None,
+            saved_at: server_saved_at,
+        });
+    }
+
+    fn is_current_song(&self, song_id: &str) -> bool {
+        self.player_state.current_song.as_ref().is_some_and(|song| song.id == song_id)
+    }
+
+    /// Drop the cover art and lyrics of the previous song if they are still on their way
+    fn cancel_song_requests(&mut self) {
+        self.song_requests.cancel();
+        self.song_requests = self.shutdown.child_token();
+    }
+
+    /// Fetch cover art and lyrics for a song in the background
+    fn load_song_metadata(&self, song: &Song) {
+        if let Some(client) = &self.source {
             // Load cover art
             if let Some(cover_art_id) = &song.cover_art {
                 debug!("Loading cover art with ID: {}", cover_art_id);
                 let client_clone = client.clone();
                 let cover_art_id = cover_art_id.clone();
                 let sender = self.message_sender.clone();
-                tokio::spawn(async move {
-                    match client_clone.get_cover_art(&cover_art_id, Some(200)).await {
+                let size = self.config.cover_art.request_size(terminal::supports_graphics());
+                let cover_cache = self.cover_cache.clone();
+                let song_id = song.id.clone();
+                spawn_until_cancelled(self.song_requests.clone(), async move {
+                    if let Some(cover_art) = cover_cache.get(&cover_art_id, size) {
+                        let _ = sender.send(Message::NowPlaying(NowPlayingUpdate::CoverArt(song_id, cover_art)));
+                        return;
+                    }
+                    match client_clone.get_cover_art(&cover_art_id, size).await {
                         Ok(cover_art) => {
                             debug!("Successfully loaded cover art ({} bytes)", cover_art.len());
-                            let _ = sender.send(Message::LoadedCoverArt(cover_art));
+                            cover_cache.put(&cover_art_id, size, &cover_art);
+                            let _ = sender.send(Message::NowPlaying(NowPlayingUpdate::CoverArt(song_id, cover_art)));
                         }
                         Err(e) => {
                             warn!("Failed to load cover art: {}", e);
@@ -253,46 +3248,159 @@ impl App {
                 debug!("No cover art ID available for this song");
             }
 
-            // Load lyrics
-            if let (Some(artist), title) = (&song.artist, &song.title) {
+            // Load lyrics, synced ones by song ID where the server has them
+            let client_clone = client.clone();
+            let artist = song.artist.clone();
+            let title = song.title.clone();
+            let sender = self.message_sender.clone();
+            let song_id = song.id.clone();
+            spawn_until_cancelled(self.song_requests.clone(), async move {
+                let structured = match client_clone.get_structured_lyrics(&song_id).await {
+                    Ok(versions) => SongLyrics::from_structured(versions),
+                    Err(e) => {
+                        debug!("No lyrics by song ID: {}", e);
+                        None
+                    }
+                };
+                if let Some(lyrics) = structured {
+                    debug!("Successfully loaded lyrics by song ID");
+                    let _ = sender.send(Message::NowPlaying(NowPlayingUpdate::Lyrics(song_id, lyrics)));
+                    return;
+                }
+
+                let Some(artist) = artist else {
+                    debug!("Missing artist or title for lyrics lookup");
+                    return;
+                };
                 debug!("Loading lyrics for: {} - {}", artist, title);
-                let client_clone = client.clone();
-                let artist = artist.clone();
-                let title = title.clone();
-                let sender = self.message_sender.clone();
-                tokio::spawn(async move {
-                    match client_clone.get_lyrics(&artist, &title).await {
-                        Ok(Some(lyrics)) => {
-                            debug!("Successfully loaded lyrics ({} chars)", lyrics.len());
-                            let _ = sender.send(Message::LoadedLyrics(lyrics));
-                        }
-                        Ok(None) => {
-                            debug!("No lyrics available for this song");
-                        }
-                        Err(e) => {
-                            warn!("Failed to load lyrics: {}", e);
-                        }
+                match client_clone.get_lyrics(&artist, &title).await {
+                    Ok(Some(lyrics)) => {
+                        debug!("Successfully loaded lyrics ({} chars)", lyrics.len());
+                        let _ = sender.send(Message::NowPlaying(NowPlayingUpdate::Lyrics(song_id, SongLyrics::Plain(lyrics))));
                     }
-                });
-            } else {
-                debug!("Missing artist or title for lyrics lookup");
-            }
+                    Ok(None) => {
+                        debug!("No lyrics available for this song");
+                    }
+                    Err(e) => {
+                        warn!("Failed to load lyrics: {}", e);
+                    }
+                }
+            });
         } else {
             error!("No Subsonic client available");
         }
     }
 
+    /// Whether the player is collapsed to a single line, either toggled or because the terminal is short
+    fn is_compact(&self, height: u16) -> bool {
+        self.compact.unwrap_or(height < self.config.layout.compact_below)
+    }
+
     fn ui(&mut self, f: &mut Frame) {
-        let layout = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
-            .split(f.area());
+        if self.is_compact(f.area().height) {
+            // Library on the whole screen with the player as a single line below
+            let layout = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(1), Constraint::Length(1)])
+                .split(f.area());
+            TreeWidget::render(&mut self.tree_state, self.player_state.accent, layout[0], f.buffer_mut());
+            PlayerWidget::render_compact(&self.player_state, layout[1], f.buffer_mut());
+        } else {
+            let layout = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+                .split(f.area());
+
+            // Tree view (left panel)
+            TreeWidget::render(&mut self.tree_state, self.player_state.accent, layout[0], f.buffer_mut());
+
+            // Player view (right panel)
+            PlayerWidget::render(&self.player_state, layout[1], f.buffer_mut());
+        }
+
+        if let Some(stats) = &self.stats {
+            StatsWidget::render(stats, f.area(), f.buffer_mut());
+        }
+
+        if let Some((form, _)) = &self.form {
+            FormWidget::render(form, f.area(), f.buffer_mut());
+        }
+
+        if let Some(confirmation) = &self.confirm {
+            ConfirmWidget::render(&confirmation.question(), f.area(), f.buffer_mut());
+        }
+
+        if let Some(prompt) = &self.search_prompt {
+            SearchPromptWidget::render(prompt, &self.state.search_history, f.area(), f.buffer_mut());
+        }
+
+        if let Some(finder) = &mut self.finder {
+            FinderWidget::render(finder, f.area(), f.buffer_mut());
+        }
+
+        if let Some(picker) = &mut self.playlist_picker {
+            PlaylistPickerWidget::render(picker, self.tree_state.playlists.as_deref(), f.area(), f.buffer_mut());
+        }
+
+        if let Some(panel) = &mut self.artist_info_panel {
+            let info = self.tree_state.artist_info.get(&panel.artist.id);
+            ArtistInfoWidget::render(panel, info, f.area(), f.buffer_mut());
+        }
+
+        if let Some(path) = &self.file_info {
+            FileInfoWidget::render(path, f.area(), f.buffer_mut());
+        }
+
+        if let Some(list) = &mut self.music_folder_picker {
+            let tree = &self.tree_state;
+            MusicFolderPickerWidget::render(tree.music_folders.as_deref(), tree.music_folder.as_ref(), list, f.area(), f.buffer_mut());
+        }
+
+        if let Some(list) = &mut self.jobs_panel {
+            JobsPanelWidget::render(&self.jobs, list, f.area(), f.buffer_mut());
+        }
+
+        if let Some(hud) = &self.hud {
+            let stats = HudStats {
+                tasks: tokio::runtime::Handle::current().metrics().num_alive_tasks(),
+                cache_bytes: ui::hud::estimate_cache_bytes(&self.tree_state),
+                cover_art_bytes: self.player_state.cover_art.as_ref().map_or(0, |art| art.len()),
+            };
+            DebugHudWidget::render(hud, &stats, f.area(), f.buffer_mut());
+        }
+
+        let area = f.area();
+        let line = Rect::new(area.x, area.bottom().saturating_sub(1), area.width, 1);
+        if let Some(command) = &self.command_line {
+            f.render_widget(Clear, line);
+            let mut spans = vec![Span::raw(":")];
+            spans.extend(command.spans(true));
+            f.render_widget(Paragraph::new(Line::from(spans)), line);
+        } else if let Some((notice, _)) = self.notice.as_ref().filter(|(_, shown)| shown.elapsed() < NOTICE_DURATION) {
+            f.render_widget(Clear, line);
+            f.render_widget(Paragraph::new(notice.as_str()).style(Style::default().fg(Color::Yellow)), line);
+        } else if let Some(register) = self.macros.recording() {
+            Self::render_indicator(f, line, format!(" recording @{} ", register), Color::Red);
+        } else if let Some(scan) = self.scan_job.and_then(|job| self.jobs.get_running(job)) {
+            Self::render_indicator(f, line, format!(" Scanning library: {} files ", scan.progress.0), Color::Cyan);
+        } else if let Some(summary) = self.tree_state.queue_summary.as_ref().filter(|_| !self.is_compact(area.height)) {
+            // Not in compact mode, the player line has no border to spare
+            Self::render_indicator(f, line, format!(" {} ", summary), Color::DarkGray);
+        }
+    }
 
-        // Tree view (left panel)
-        TreeWidget::render(&mut self.tree_state, layout[0], f.buffer_mut());
+    /// `text` at the right end of the bottom line, over the border of the player
+    fn render_indicator(f: &mut Frame, line: Rect, text: String, color: Color) {
+        let width = (text.width() as u16).min(line.width);
+        let indicator = Rect::new(line.right() - width, line.y, width, 1);
+        f.render_widget(Clear, indicator);
+        f.render_widget(Paragraph::new(text).style(Style::default().fg(color)), indicator);
+    }
+}
 
-        // Player view (right panel)
-        PlayerWidget::render(&self.player_state, layout[1], f.buffer_mut());
+impl Default for App {
+    fn default() -> Self {
+        Self::new(false, None)
     }
 }
 
@@ -369,6 +3477,77 @@ fn print_version_info() {
     }
 }
 
+/// Turn SIGTERM and SIGHUP into a regular quit, so the session is saved and the
+/// terminal restored when the terminal is closed or a service manager stops us
+#[cfg(unix)]
+fn listen_for_signals(sender: mpsc::UnboundedSender<Message>) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    tokio::spawn(async move {
+        let (mut terminate, mut hangup) = match (signal(SignalKind::terminate()), signal(SignalKind::hangup())) {
+            (Ok(terminate), Ok(hangup)) => (terminate, hangup),
+            (Err(e), _) | (_, Err(e)) => {
+                warn!("Failed to install signal handlers: {}", e);
+                return;
+            }
+        };
+
+        tokio::select! {
+            _ = terminate.recv() => info!("Received SIGTERM, shutting down"),
+            _ = hangup.recv() => info!("Received SIGHUP, shutting down"),
+        }
+        let _ = sender.send(Message::Quit);
+    });
+}
+
+#[cfg(not(unix))]
+fn listen_for_signals(sender: mpsc::UnboundedSender<Message>) {
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            info!("Received Ctrl-C, shutting down");
+            let _ = sender.send(Message::Quit);
+        }
+    });
+}
+
+/// Spawn `task`, dropping it as soon as `token` is cancelled so whatever it would
+/// have sent back never arrives
+fn spawn_until_cancelled<F>(token: CancellationToken, task: F)
+where
+    F: std::future::Future<Output = ()> + Send + 'static,
+{
+    tokio::spawn(async move {
+        token.run_until_cancelled(task).await;
+    });
+}
+
+/// The library of a server merged into that of `[subsonic]`
+fn connect(connection: config::SubsonicConfig, proxy: Option<&str>) -> Arc<dyn MusicSource> {
+    match connection.backend {
+        #[cfg(feature = "jellyfin")]
+        config::Backend::Jellyfin => Arc::new(
+            JellyfinClient::new(connection.server, connection.username, connection.password)
+                .with_client_name("highpass")
+                .with_proxy(proxy),
+        ),
+        _ => Arc::new(
+            SubsonicClient::new(connection.server, connection.username, connection.password)
+                .with_auth_method(connection.auth)
+                .with_rate_limit(connection.max_requests_per_second)
+                .with_proxy(proxy)
+                .with_client_name("highpass"),
+        ),
+    }
+}
+
+/// The value following `flag` on the command line
+fn arg_value(args: &[String], flag: &str) -> Option<String> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|i| args.get(i + 1))
+        .cloned()
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     // Check for command line arguments
@@ -380,37 +3559,46 @@ async fn main() -> Result<(), Box<dyn Error>> {
     
     let force_run = args.len() > 1 && args[1] == "--force-run";
     let debug_mode = args.iter().any(|arg| arg == "--debug");
-
-    // Initialize logger based on debug flag
-    if debug_mode {
-        // Log to file when --debug flag is present
-        env_logger::Builder::from_default_env()
-            .filter_level(log::LevelFilter::Debug)
-            .target(env_logger::Target::Pipe(Box::new(std::fs::OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open("./highpass.log")?)))
-            .init();
-    } else {
-        // Disable logging when --debug flag is not present
-        env_logger::Builder::new()
-            .filter_level(log::LevelFilter::Off)
-            .init();
+    let mock = args.iter().any(|arg| arg == "--mock");
+    let traffic = arg_value(&args, "--record")
+        .map(|dir| Traffic::Record(dir.into()))
+        .or_else(|| arg_value(&args, "--replay").map(|dir| Traffic::Replay(dir.into())));
+    let offline = mock || matches!(traffic, Some(Traffic::Replay(_)));
+    if args.iter().any(|arg| arg == "--profile") {
+        profile::enable();
     }
+
+    // Initialize logger based on debug flag, in the format chosen in the config
+    let log_format = Config::load().map(|config| config.log_format).unwrap_or_default();
+    logging::init(debug_mode, log_format)?;
     
     info!("Starting HighPass music player");
     
     // Check configuration early to fail fast before initializing anything
+    // (offline modes fall back to the default configuration)
     if let Err(e) = Config::load() {
-        // Always show configuration errors, regardless of logging settings
-        eprintln!("Configuration error: {}", e);
-        error!("Configuration error: {}", e);
-        return Err(e);
+        if !offline {
+            // Always show configuration errors, regardless of logging settings
+            eprintln!("Configuration error: {}", e);
+            error!("Configuration error: {}", e);
+            return Err(e);
+        }
+    }
+
+    // Hand the request over to an instance that is already running instead of starting a second player
+    let remote_command = RemoteCommand::from_args(&args);
+    if ipc::send(remote_command.as_ref()).await {
+        if remote_command.is_none() {
+            eprintln!("highpass is already running");
+            return Err("Already running".into());
+        }
+        info!("Handed command over to the running instance");
+        return Ok(());
     }
 
     // Create app first to test MPV initialization
     info!("Creating application instance");
-    let mut app = App::new();
+    let mut app = App::new(mock, traffic);
     
     // Give some time for async initialization
     tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
@@ -427,33 +3615,44 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     // Setup terminal
     info!("Setting up terminal");
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    info!("Enabled raw mode");
-    
-    execute!(stdout, EnterAlternateScreen)?;
-    info!("Entered alternate screen");
+    terminal::install_panic_hook();
+    let terminal_guard = terminal::TerminalGuard::new()?;
     
     // Skip mouse capture for now as it might be causing issues
     // execute!(stdout, EnableMouseCapture)?;
     info!("Skipping mouse capture for compatibility");
     
-    let backend = CrosstermBackend::new(stdout);
+    let backend = CrosstermBackend::new(io::stdout());
     let mut terminal = Terminal::new(backend)?;
     info!("Terminal setup complete");
 
+    listen_for_signals(app.message_sender.clone());
+    if app.config.playback.pause_on_suspend {
+        system::watch_suspend(app.message_sender.clone());
+    }
+    if app.config.playback.pause_on_lock {
+        system::watch_lock(app.message_sender.clone());
+    }
+    if app.config.playback.inhibit_idle {
+        app.idle_inhibitor = Some(IdleInhibitor::new());
+    }
+    let _server = ipc::serve(app.message_sender.clone());
+    if let Some(command) = remote_command {
+        let _ = app.message_sender.send(Message::Remote(command));
+    }
+
     // Run the app
     info!("Starting main application loop");
     let res = app.run(&mut terminal).await;
 
     // Restore terminal
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen
-        // DisableMouseCapture  // Skip since we didn't enable it
-    )?;
-    terminal.show_cursor()?;
+    drop(terminal_guard);
+
+    if profile::is_enabled() {
+        let summary = profile::summary();
+        info!("Profile summary:\n{}", summary);
+        eprintln!("{}", summary);
+    }
 
     if let Err(err) = res {
         println!("{err:?}");
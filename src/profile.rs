@@ -0,0 +1,57 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static SAMPLES: Mutex<BTreeMap<&'static str, Vec<Duration>>> = Mutex::new(BTreeMap::new());
+
+/// Start collecting timings, enabled by `--profile`
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Record how long one run of `name` took. Does nothing unless profiling is enabled.
+pub fn record(name: &'static str, duration: Duration) {
+    if !is_enabled() {
+        return;
+    }
+    if let Ok(mut samples) = SAMPLES.lock() {
+        samples.entry(name).or_default().push(duration);
+    }
+}
+
+/// A table with count, mean, 95th percentile and maximum of everything recorded so far
+pub fn summary() -> String {
+    let Ok(mut samples) = SAMPLES.lock() else {
+        return String::new();
+    };
+
+    let mut lines = vec![format!(
+        "{:<16} {:>8} {:>10} {:>10} {:>10}",
+        "measurement", "count", "mean ms", "p95 ms", "max ms"
+    )];
+    for (name, durations) in samples.iter_mut() {
+        if durations.is_empty() {
+            continue;
+        }
+        durations.sort();
+        let total: Duration = durations.iter().sum();
+        let mean = total / durations.len() as u32;
+        let p95 = durations[(durations.len() - 1) * 95 / 100];
+        let max = durations[durations.len() - 1];
+        lines.push(format!(
+            "{:<16} {:>8} {:>10.3} {:>10.3} {:>10.3}",
+            name,
+            durations.len(),
+            mean.as_secs_f64() * 1000.0,
+            p95.as_secs_f64() * 1000.0,
+            max.as_secs_f64() * 1000.0
+        ));
+    }
+    lines.join("\n")
+}
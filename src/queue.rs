@@ -0,0 +1,292 @@
+use subsonic::{PlayQueue, Song};
+use log::{debug, info};
+use rand::seq::SliceRandom;
+use std::collections::{HashSet, VecDeque};
+
+/// Number of songs requested per page when feeding the queue from the server
+pub const PAGE_SIZE: u32 = 50;
+
+/// Refill the queue from its source once fewer songs than this are left
+const REFILL_THRESHOLD: usize = 5;
+
+/// Number of songs remembered for going back with "previous"
+const MAX_PLAYED: usize = 100;
+
+/// Where additional songs come from once the queued ones run out
+#[derive(Debug, Clone)]
+pub enum QueueSource {
+    Genre {
+        genre: String,
+        /// Page offsets that have not been fetched yet, in playback order
+        offsets: VecDeque<u32>,
+        shuffle: bool,
+    },
+    /// Radio mode: songs similar to `seed`, topped up for as long as new ones come in
+    Similar {
+        seed: Box<Song>,
+        /// Artist the next songs are picked around, the one of the last song added
+        artist_id: String,
+        /// Songs added so far, which are not added again
+        seen: HashSet<String>,
+    },
+}
+
+/// A request for the next page of songs of a lazy queue source
+#[derive(Debug, Clone)]
+pub enum PageRequest {
+    Genre { genre: String, offset: u32 },
+    /// Songs similar to an artist, for the radio started from the song `seed_id`
+    Similar { seed_id: String, artist_id: String },
+}
+
+/// Songs for the queue loaded in the background, sent to the app as [`Message::Queue`](crate::Message::Queue)
+#[derive(Debug, Clone)]
+pub enum QueueUpdate {
+    Page(PageRequest, Vec<Song>),
+    PageFailed,
+    /// Songs of an album that should replace (`true`) or extend (`false`) the queue
+    Album(String, Vec<Song>, bool),
+    Playlist(Vec<Song>, bool),
+    /// The play queue saved on the server by another client
+    Synced(PlayQueue),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Queue {
+    songs: VecDeque<Song>,
+    /// Songs played before the current one, most recent last
+    played: Vec<Song>,
+    /// Bumped whenever the upcoming songs change, so views know when to refresh
+    revision: u64,
+    source: Option<QueueSource>,
+    fetching: bool,
+}
+
+impl Queue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the queue with every song of a genre, fetched page by page.
+    /// With `shuffle` the pages are visited in random order and each page is shuffled.
+    pub fn play_genre(&mut self, genre: &str, song_count: u32, shuffle: bool) {
+        let mut offsets: Vec<u32> = (0..song_count.max(1)).step_by(PAGE_SIZE as usize).collect();
+        if shuffle {
+            offsets.shuffle(&mut rand::thread_rng());
+        }
+        info!("Queueing genre '{}' ({} songs, {} pages, shuffle: {})", genre, song_count, offsets.len(), shuffle);
+
+        self.songs.clear();
+        self.revision += 1;
+        self.fetching = false;
+        self.source = Some(QueueSource::Genre {
+            genre: genre.to_string(),
+            offsets: offsets.into(),
+            shuffle,
+        });
+    }
+
+    /// Replace the queue with songs similar to `seed`, topped up from the server as it drains.
+    /// Returns false when the song has no artist to find similar songs for.
+    pub fn play_similar(&mut self, seed: &Song) -> bool {
+        let Some(artist_id) = seed.artist_id.clone() else {
+            return false;
+        };
+        info!("Starting radio from '{}'", seed.title);
+
+        self.songs.clear();
+        self.revision += 1;
+        self.fetching = false;
+        self.source = Some(QueueSource::Similar {
+            seed: Box::new(seed.clone()),
+            artist_id,
+            seen: HashSet::from([seed.id.clone()]),
+        });
+        true
+    }
+
+    /// The song radio mode was started from, while it is active
+    pub fn radio_seed(&self) -> Option<&Song> {
+        match &self.source {
+            Some(QueueSource::Similar { seed, .. }) => Some(seed),
+            _ => None,
+        }
+    }
+
+    /// Replace the queue with the given songs
+    pub fn play_songs(&mut self, songs: Vec<Song>) {
+        info!("Queueing {} songs", songs.len());
+        self.songs = songs.into();
+        self.revision += 1;
+        self.source = None;
+        self.fetching = false;
+    }
+
+    /// Append songs to the end of the queue
+    pub fn extend(&mut self, songs: Vec<Song>) {
+        info!("Enqueued {} songs", songs.len());
+        self.songs.extend(songs);
+        self.revision += 1;
+    }
+
+    /// Append a song to the end of the queue
+    pub fn push(&mut self, song: Song) {
+        info!("Enqueued: {}", song.title);
+        self.songs.push_back(song);
+        self.revision += 1;
+    }
+
+    pub fn songs(&self) -> impl Iterator<Item = &Song> {
+        self.songs.iter()
+    }
+
+    pub fn pop_next(&mut self) -> Option<Song> {
+        self.revision += 1;
+        self.songs.pop_front()
+    }
+
+    /// Remove every upcoming song and stop feeding the queue from its source
+    pub fn clear(&mut self) {
+        info!("Clearing the queue ({} songs)", self.songs.len());
+        self.songs.clear();
+        self.source = None;
+        self.fetching = false;
+        self.revision += 1;
+    }
+
+    /// Go back to an earlier copy of the queue, keeping the history of played songs
+    pub fn restore(&mut self, snapshot: Queue) {
+        info!("Restoring the queue ({} songs)", snapshot.songs.len());
+        self.songs = snapshot.songs;
+        self.source = snapshot.source;
+        // A page requested for the snapshot's source may have been dropped meanwhile
+        self.fetching = false;
+        self.revision += 1;
+    }
+
+    /// Drop the upcoming song at `index`
+    pub fn remove(&mut self, index: usize) -> Option<Song> {
+        let song = self.songs.remove(index)?;
+        info!("Removed from queue: {}", song.title);
+        self.revision += 1;
+        Some(song)
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Remember a song that was playing before moving on to another
+    pub fn push_played(&mut self, song: Song) {
+        self.played.push(song);
+        if self.played.len() > MAX_PLAYED {
+            self.played.remove(0);
+        }
+    }
+
+    /// Go back to the song played before `current`, which is put back at the front of the queue
+    pub fn pop_played(&mut self, current: Option<Song>) -> Option<Song> {
+        let song = self.played.pop()?;
+        if let Some(current) = current {
+            self.songs.push_front(current);
+            self.revision += 1;
+        }
+        Some(song)
+    }
+
+    pub fn len(&self) -> usize {
+        self.songs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.songs.is_empty()
+    }
+
+    /// Returns the next page to fetch if the queue is running low, marking it as in flight
+    pub fn next_page_request(&mut self) -> Option<PageRequest> {
+        if self.fetching || self.songs.len() >= REFILL_THRESHOLD {
+            return None;
+        }
+
+        let request = match self.source.as_mut()? {
+            QueueSource::Genre { genre, offsets, .. } => PageRequest::Genre {
+                genre: genre.clone(),
+                offset: offsets.pop_front()?,
+            },
+            QueueSource::Similar { seed, artist_id, .. } => PageRequest::Similar {
+                seed_id: seed.id.clone(),
+                artist_id: artist_id.clone(),
+            },
+        };
+        self.fetching = true;
+        Some(request)
+    }
+
+    /// Append a fetched page. Pages for a source that has since been replaced are dropped.
+    pub fn add_page(&mut self, request: &PageRequest, mut songs: Vec<Song>) {
+        let matches_source = match (&self.source, request) {
+            (Some(QueueSource::Genre { genre, .. }), PageRequest::Genre { genre: requested, .. }) => genre == requested,
+            (Some(QueueSource::Similar { seed, .. }), PageRequest::Similar { seed_id, .. }) => &seed.id == seed_id,
+            _ => false,
+        };
+        if !matches_source {
+            debug!("Dropping page for a stale queue source: {:?}", request);
+            return;
+        }
+
+        self.fetching = false;
+        if songs.is_empty() {
+            // The server ran out earlier than the song count suggested
+            self.source = None;
+            return;
+        }
+
+        match &mut self.source {
+            Some(QueueSource::Genre { shuffle: true, .. }) => songs.shuffle(&mut rand::thread_rng()),
+            Some(QueueSource::Similar { artist_id, seen, .. }) => {
+                songs.retain(|song| seen.insert(song.id.clone()));
+                songs.shuffle(&mut rand::thread_rng());
+                match songs.last().and_then(|song| song.artist_id.clone()) {
+                    // Wander on from the artist of the last new song
+                    Some(next) => *artist_id = next,
+                    None if songs.is_empty() => {
+                        info!("Radio ran out of new songs");
+                        self.source = None;
+                        return;
+                    }
+                    None => {}
+                }
+            }
+            _ => {}
+        }
+        debug!("Adding {} songs to the queue", songs.len());
+        self.songs.extend(songs);
+        self.revision += 1;
+    }
+
+    /// Mark an in-flight page as failed; the page is skipped and the next one fetched instead.
+    /// Radio mode has no next page to move on to and ends.
+    pub fn page_failed(&mut self) {
+        self.fetching = false;
+        if matches!(self.source, Some(QueueSource::Similar { .. })) {
+            self.source = None;
+        }
+    }
+}
+
+/// "12 tracks · 48 min remaining" for upcoming `songs`, `None` when there are none.
+/// Songs without a known duration only count as tracks.
+pub fn summary(songs: &[Song]) -> Option<String> {
+    if songs.is_empty() {
+        return None;
+    }
+    let tracks = if songs.len() == 1 { "1 track".to_string() } else { format!("{} tracks", songs.len()) };
+    let seconds: u64 = songs.iter().filter_map(|song| song.duration).map(u64::from).sum();
+    let minutes = seconds.div_ceil(60);
+    let time = if minutes >= 60 {
+        format!("{} h {} min", minutes / 60, minutes % 60)
+    } else {
+        format!("{} min", minutes)
+    };
+    Some(format!("{} · {} remaining", tracks, time))
+}
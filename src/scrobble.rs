@@ -0,0 +1,97 @@
+use crate::state::PendingScrobble;
+use subsonic::Song;
+use log::{debug, warn};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Most scrobbles submitted in one request
+const MAX_BATCH: usize = 50;
+
+/// Wait after the first failed submission, doubled on each further failure
+const INITIAL_BACKOFF: Duration = Duration::from_secs(30);
+const MAX_BACKOFF: Duration = Duration::from_secs(600);
+
+/// Outcome of submitting the queued scrobbles, sent to the app as [`Message::Scrobble`](crate::Message::Scrobble)
+#[derive(Debug, Clone, Copy)]
+pub enum ScrobbleUpdate {
+    Submitted,
+    Failed,
+}
+
+/// Decides when the playing track is scrobbled and paces submission of the queued
+/// scrobbles, backing off while the server is unreachable
+#[derive(Debug, Default)]
+pub struct Scrobbler {
+    /// A track counts as played after this percentage of its length or `after_seconds`,
+    /// whichever comes first
+    after_percent: f32,
+    after_seconds: f32,
+    /// Song ID and start time (ms since the epoch) of the playing track
+    current: Option<(String, u64)>,
+    /// The current track has already been queued
+    queued: bool,
+    /// Number of scrobbles in the request currently in flight
+    in_flight: Option<usize>,
+    retry_at: Option<Instant>,
+    backoff: Duration,
+}
+
+impl Scrobbler {
+    pub fn new(after_percent: f32, after_seconds: u32) -> Self {
+        Self {
+            after_percent: after_percent.clamp(0.0, 100.0),
+            after_seconds: after_seconds as f32,
+            ..Self::default()
+        }
+    }
+
+    /// A new track started playing
+    pub fn start(&mut self, song: &Song) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        self.current = Some((song.id.clone(), now));
+        self.queued = false;
+    }
+
+    /// Stop tracking the current track, it won't be scrobbled
+    pub fn stop(&mut self) {
+        self.current = None;
+    }
+
+    /// The scrobble for the current track, once it has played long enough
+    pub fn check(&mut self, progress: f32, duration: f32) -> Option<PendingScrobble> {
+        if self.queued || duration <= 0.0 || progress < self.after_seconds.min(duration * self.after_percent / 100.0) {
+            return None;
+        }
+        let (song_id, time) = self.current.clone()?;
+        self.queued = true;
+        debug!("Queueing scrobble for {}", song_id);
+        Some(PendingScrobble { song_id, time })
+    }
+
+    /// The next scrobbles to submit, unless a request is in flight or we are backing off
+    pub fn next_batch(&mut self, pending: &[PendingScrobble]) -> Option<Vec<PendingScrobble>> {
+        if pending.is_empty() || self.in_flight.is_some() || self.retry_at.is_some_and(|at| Instant::now() < at) {
+            return None;
+        }
+        let batch: Vec<_> = pending.iter().take(MAX_BATCH).cloned().collect();
+        self.in_flight = Some(batch.len());
+        Some(batch)
+    }
+
+    /// The batch in flight was accepted. Returns how many scrobbles to drop from the queue.
+    pub fn submitted(&mut self) -> usize {
+        self.retry_at = None;
+        self.backoff = Duration::ZERO;
+        self.in_flight.take().unwrap_or(0)
+    }
+
+    /// The batch in flight failed, keep it queued and retry later
+    pub fn failed(&mut self) {
+        self.in_flight = None;
+        self.backoff = if self.backoff.is_zero() { INITIAL_BACKOFF } else { (self.backoff * 2).min(MAX_BACKOFF) };
+        self.retry_at = Some(Instant::now() + self.backoff);
+        warn!("Scrobbling failed, retrying in {} seconds", self.backoff.as_secs());
+    }
+}
@@ -0,0 +1,372 @@
+//! Where the library comes from. The app only talks to a [`MusicSource`], which the
+//! Subsonic client implements; other backends like a local music directory, or fakes for
+//! testing, implement it too and answer with the same types, and several of them can be
+//! merged into one. Features only some servers have default to [`Error::Unsupported`].
+
+#[cfg(feature = "jellyfin")]
+pub mod jellyfin;
+pub mod local;
+pub mod merged;
+
+use async_trait::async_trait;
+use std::fmt::Debug;
+use std::path::PathBuf;
+use std::sync::Arc;
+use subsonic::{
+    Album, AlbumDetail, AlbumListType, ArtistIndex, ArtistInfo, Error, Genre, Indexes, InternetRadioStation, JukeboxStatus,
+    MusicDirectory, MusicFolder, OnChunk, PlayQueue, Playlist, PlaylistDetail, Result, ScanStatus, SearchCategory,
+    SearchResult, SkippedItem, Song, Starred, StructuredLyrics, SubsonicClient, Transcoding,
+};
+
+fn unsupported<T>(feature: &str) -> Result<T> {
+    Err(Error::Unsupported(format!("{} is not supported by this music source", feature)))
+}
+
+#[async_trait]
+pub trait MusicSource: Debug + Send + Sync {
+    /// The same source listing only the music folder with this ID, or all of them
+    fn with_music_folder(&self, folder_id: Option<String>) -> Arc<dyn MusicSource>;
+    /// The same source streaming songs re-encoded like this, or as they are with `None`
+    fn with_transcoding(&self, transcoding: Option<Transcoding>) -> Arc<dyn MusicSource>;
+
+    /// Hand each index section of the artist list to `on_index` as soon as it is there
+    async fn get_artists_progressive(&self, on_index: &mut (dyn FnMut(ArtistIndex) + Send)) -> Result<()>;
+    async fn get_artist(&self, artist_id: &str) -> Result<Vec<Album>>;
+    async fn get_album(&self, album_id: &str) -> Result<AlbumDetail>;
+    async fn get_album_list(&self, list: AlbumListType, size: u32, offset: u32) -> Result<Vec<Album>>;
+    /// The original image without a `size`
+    async fn get_cover_art(&self, cover_art_id: &str, size: Option<u32>) -> Result<Vec<u8>>;
+    /// What mpv plays for the song
+    fn get_stream_url(&self, song_id: &str) -> String;
+    async fn get_genres(&self) -> Result<Vec<Genre>>;
+    async fn get_songs_by_genre(&self, genre: &str, count: u32, offset: u32) -> Result<Vec<Song>>;
+    async fn search(&self, query: &str, more: Option<(SearchCategory, u32)>) -> Result<SearchResult>;
+    async fn get_starred(&self) -> Result<Starred>;
+
+    async fn get_playlists(&self) -> Result<Vec<Playlist>>;
+    async fn get_playlist(&self, playlist_id: &str) -> Result<PlaylistDetail>;
+    async fn create_playlist(&self, name: &str, song_ids: &[String]) -> Result<()>;
+    async fn add_to_playlist(&self, playlist_id: &str, song_ids: &[String]) -> Result<()>;
+    async fn remove_from_playlist(&self, playlist_id: &str, indexes: &[usize]) -> Result<()>;
+    async fn set_playlist_public(&self, playlist_id: &str, public: bool) -> Result<()>;
+    async fn delete_playlist(&self, playlist_id: &str) -> Result<()>;
+
+    /// List elements left out of responses since the last call because they could not be parsed
+    fn take_skipped(&self) -> Vec<SkippedItem> {
+        Vec::new()
+    }
+
+    /// Check that the source can be reached, sources without a server always can
+    async fn ping(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Biography and up to `count` similar artists in the library
+    async fn get_artist_info(&self, _artist_id: &str, _count: u32) -> Result<ArtistInfo> {
+        unsupported("Artist info")
+    }
+
+    /// Where the file of a song is on this machine, for sources that play files
+    fn local_path(&self, _song_id: &str) -> Option<PathBuf> {
+        None
+    }
+
+    /// Hand the original file of a song to `on_chunk` piece by piece, along with its size
+    /// when known
+    async fn download(&self, _song_id: &str, _on_chunk: &mut OnChunk<'_>) -> Result<()> {
+        unsupported("Downloading")
+    }
+
+    async fn get_music_folders(&self) -> Result<Vec<MusicFolder>> {
+        unsupported("Listing music folders")
+    }
+
+    async fn get_indexes(&self) -> Result<Indexes> {
+        unsupported("Browsing by folder")
+    }
+
+    async fn get_music_directory(&self, _directory_id: &str) -> Result<MusicDirectory> {
+        unsupported("Browsing by folder")
+    }
+
+    async fn get_lyrics(&self, _artist: &str, _title: &str) -> Result<Option<String>> {
+        unsupported("Lyrics")
+    }
+
+    /// Lyrics of a song, timed where the source has them, in every language it has
+    async fn get_structured_lyrics(&self, _song_id: &str) -> Result<Vec<StructuredLyrics>> {
+        unsupported("Synced lyrics")
+    }
+
+    async fn get_similar_songs(&self, _artist_id: &str, _count: u32) -> Result<Vec<Song>> {
+        unsupported("Radio from similar songs")
+    }
+
+    async fn star(&self, _song_ids: &[String], _album_ids: &[String]) -> Result<()> {
+        unsupported("Starring")
+    }
+
+    async fn unstar(&self, _song_ids: &[String], _album_ids: &[String]) -> Result<()> {
+        unsupported("Starring")
+    }
+
+    async fn get_play_queue(&self) -> Result<Option<PlayQueue>> {
+        unsupported("Syncing the play queue")
+    }
+
+    async fn save_play_queue(&self, _song_ids: &[String], _current: Option<&str>, _position_ms: u64) -> Result<()> {
+        unsupported("Syncing the play queue")
+    }
+
+    async fn now_playing(&self, _song_id: &str) -> Result<()> {
+        unsupported("Scrobbling")
+    }
+
+    /// Plays given as song ID and start time in milliseconds since the epoch
+    async fn scrobble(&self, _plays: &[(String, u64)]) -> Result<()> {
+        unsupported("Scrobbling")
+    }
+
+    /// Have the library rescanned for new and changed files
+    async fn start_scan(&self) -> Result<ScanStatus> {
+        unsupported("Scanning the library")
+    }
+
+    async fn get_scan_status(&self) -> Result<ScanStatus> {
+        unsupported("Scanning the library")
+    }
+
+    async fn get_internet_radio_stations(&self) -> Result<Vec<InternetRadioStation>> {
+        unsupported("Internet radio")
+    }
+
+    async fn create_internet_radio_station(&self, _name: &str, _stream_url: &str, _home_page_url: Option<&str>) -> Result<()> {
+        unsupported("Internet radio")
+    }
+
+    async fn update_internet_radio_station(&self, _id: &str, _name: &str, _stream_url: &str, _home_page_url: Option<&str>) -> Result<()> {
+        unsupported("Internet radio")
+    }
+
+    async fn delete_internet_radio_station(&self, _id: &str) -> Result<()> {
+        unsupported("Internet radio")
+    }
+
+    async fn jukebox_status(&self) -> Result<JukeboxStatus> {
+        unsupported("The jukebox")
+    }
+
+    /// Replace the jukebox playlist
+    async fn jukebox_set(&self, _song_ids: &[String]) -> Result<JukeboxStatus> {
+        unsupported("The jukebox")
+    }
+
+    async fn jukebox_start(&self) -> Result<JukeboxStatus> {
+        unsupported("The jukebox")
+    }
+
+    async fn jukebox_stop(&self) -> Result<JukeboxStatus> {
+        unsupported("The jukebox")
+    }
+
+    /// Jump to the song at `index` of the jukebox playlist, `offset` seconds into it
+    async fn jukebox_skip(&self, _index: usize, _offset: u32) -> Result<JukeboxStatus> {
+        unsupported("The jukebox")
+    }
+
+    /// Volume between 0 and 1
+    async fn jukebox_set_gain(&self, _gain: f32) -> Result<JukeboxStatus> {
+        unsupported("The jukebox")
+    }
+}
+
+#[async_trait]
+impl MusicSource for SubsonicClient {
+    fn with_music_folder(&self, folder_id: Option<String>) -> Arc<dyn MusicSource> {
+        Arc::new(self.clone().with_music_folder(folder_id))
+    }
+
+    fn with_transcoding(&self, transcoding: Option<Transcoding>) -> Arc<dyn MusicSource> {
+        Arc::new(self.clone().with_transcoding(transcoding))
+    }
+
+    async fn get_artists_progressive(&self, on_index: &mut (dyn FnMut(ArtistIndex) + Send)) -> Result<()> {
+        SubsonicClient::get_artists_progressive(self, on_index).await
+    }
+
+    async fn get_artist(&self, artist_id: &str) -> Result<Vec<Album>> {
+        SubsonicClient::get_artist(self, artist_id).await
+    }
+
+    async fn get_album(&self, album_id: &str) -> Result<AlbumDetail> {
+        SubsonicClient::get_album(self, album_id).await
+    }
+
+    async fn get_album_list(&self, list: AlbumListType, size: u32, offset: u32) -> Result<Vec<Album>> {
+        SubsonicClient::get_album_list(self, list, size, offset).await
+    }
+
+    async fn get_cover_art(&self, cover_art_id: &str, size: Option<u32>) -> Result<Vec<u8>> {
+        SubsonicClient::get_cover_art(self, cover_art_id, size).await
+    }
+
+    fn get_stream_url(&self, song_id: &str) -> String {
+        SubsonicClient::get_stream_url(self, song_id)
+    }
+
+    async fn get_genres(&self) -> Result<Vec<Genre>> {
+        SubsonicClient::get_genres(self).await
+    }
+
+    async fn get_songs_by_genre(&self, genre: &str, count: u32, offset: u32) -> Result<Vec<Song>> {
+        SubsonicClient::get_songs_by_genre(self, genre, count, offset).await
+    }
+
+    async fn search(&self, query: &str, more: Option<(SearchCategory, u32)>) -> Result<SearchResult> {
+        SubsonicClient::search(self, query, more).await
+    }
+
+    async fn get_starred(&self) -> Result<Starred> {
+        SubsonicClient::get_starred(self).await
+    }
+
+    async fn get_playlists(&self) -> Result<Vec<Playlist>> {
+        SubsonicClient::get_playlists(self).await
+    }
+
+    async fn get_playlist(&self, playlist_id: &str) -> Result<PlaylistDetail> {
+        SubsonicClient::get_playlist(self, playlist_id).await
+    }
+
+    async fn create_playlist(&self, name: &str, song_ids: &[String]) -> Result<()> {
+        SubsonicClient::create_playlist(self, name, song_ids).await
+    }
+
+    async fn add_to_playlist(&self, playlist_id: &str, song_ids: &[String]) -> Result<()> {
+        SubsonicClient::add_to_playlist(self, playlist_id, song_ids).await
+    }
+
+    async fn remove_from_playlist(&self, playlist_id: &str, indexes: &[usize]) -> Result<()> {
+        SubsonicClient::remove_from_playlist(self, playlist_id, indexes).await
+    }
+
+    async fn set_playlist_public(&self, playlist_id: &str, public: bool) -> Result<()> {
+        SubsonicClient::set_playlist_public(self, playlist_id, public).await
+    }
+
+    async fn delete_playlist(&self, playlist_id: &str) -> Result<()> {
+        SubsonicClient::delete_playlist(self, playlist_id).await
+    }
+
+    fn take_skipped(&self) -> Vec<SkippedItem> {
+        SubsonicClient::take_skipped(self)
+    }
+
+    async fn ping(&self) -> Result<()> {
+        SubsonicClient::ping(self).await
+    }
+
+    async fn get_artist_info(&self, artist_id: &str, count: u32) -> Result<ArtistInfo> {
+        SubsonicClient::get_artist_info(self, artist_id, count).await
+    }
+
+    async fn download(&self, song_id: &str, on_chunk: &mut OnChunk<'_>) -> Result<()> {
+        SubsonicClient::download(self, song_id, on_chunk).await
+    }
+
+    async fn get_music_folders(&self) -> Result<Vec<MusicFolder>> {
+        SubsonicClient::get_music_folders(self).await
+    }
+
+    async fn get_indexes(&self) -> Result<Indexes> {
+        SubsonicClient::get_indexes(self).await
+    }
+
+    async fn get_music_directory(&self, directory_id: &str) -> Result<MusicDirectory> {
+        SubsonicClient::get_music_directory(self, directory_id).await
+    }
+
+    async fn get_lyrics(&self, artist: &str, title: &str) -> Result<Option<String>> {
+        SubsonicClient::get_lyrics(self, artist, title).await
+    }
+
+    async fn get_structured_lyrics(&self, song_id: &str) -> Result<Vec<StructuredLyrics>> {
+        SubsonicClient::get_lyrics_by_song_id(self, song_id).await
+    }
+
+    async fn get_similar_songs(&self, artist_id: &str, count: u32) -> Result<Vec<Song>> {
+        SubsonicClient::get_similar_songs(self, artist_id, count).await
+    }
+
+    async fn star(&self, song_ids: &[String], album_ids: &[String]) -> Result<()> {
+        SubsonicClient::star(self, song_ids, album_ids).await
+    }
+
+    async fn unstar(&self, song_ids: &[String], album_ids: &[String]) -> Result<()> {
+        SubsonicClient::unstar(self, song_ids, album_ids).await
+    }
+
+    async fn get_play_queue(&self) -> Result<Option<PlayQueue>> {
+        SubsonicClient::get_play_queue(self).await
+    }
+
+    async fn save_play_queue(&self, song_ids: &[String], current: Option<&str>, position_ms: u64) -> Result<()> {
+        SubsonicClient::save_play_queue(self, song_ids, current, position_ms).await
+    }
+
+    async fn now_playing(&self, song_id: &str) -> Result<()> {
+        SubsonicClient::now_playing(self, song_id).await
+    }
+
+    async fn scrobble(&self, plays: &[(String, u64)]) -> Result<()> {
+        SubsonicClient::scrobble(self, plays).await
+    }
+
+    async fn start_scan(&self) -> Result<ScanStatus> {
+        SubsonicClient::start_scan(self).await
+    }
+
+    async fn get_scan_status(&self) -> Result<ScanStatus> {
+        SubsonicClient::get_scan_status(self).await
+    }
+
+    async fn get_internet_radio_stations(&self) -> Result<Vec<InternetRadioStation>> {
+        SubsonicClient::get_internet_radio_stations(self).await
+    }
+
+    async fn create_internet_radio_station(&self, name: &str, stream_url: &str, home_page_url: Option<&str>) -> Result<()> {
+        SubsonicClient::create_internet_radio_station(self, name, stream_url, home_page_url).await
+    }
+
+    async fn update_internet_radio_station(&self, id: &str, name: &str, stream_url: &str, home_page_url: Option<&str>) -> Result<()> {
+        SubsonicClient::update_internet_radio_station(self, id, name, stream_url, home_page_url).await
+    }
+
+    async fn delete_internet_radio_station(&self, id: &str) -> Result<()> {
+        SubsonicClient::delete_internet_radio_station(self, id).await
+    }
+
+    async fn jukebox_status(&self) -> Result<JukeboxStatus> {
+        SubsonicClient::jukebox_status(self).await
+    }
+
+    async fn jukebox_set(&self, song_ids: &[String]) -> Result<JukeboxStatus> {
+        SubsonicClient::jukebox_set(self, song_ids).await
+    }
+
+    async fn jukebox_start(&self) -> Result<JukeboxStatus> {
+        SubsonicClient::jukebox_start(self).await
+    }
+
+    async fn jukebox_stop(&self) -> Result<JukeboxStatus> {
+        SubsonicClient::jukebox_stop(self).await
+    }
+
+    async fn jukebox_skip(&self, index: usize, offset: u32) -> Result<JukeboxStatus> {
+        SubsonicClient::jukebox_skip(self, index, offset).await
+    }
+
+    async fn jukebox_set_gain(&self, gain: f32) -> Result<JukeboxStatus> {
+        SubsonicClient::jukebox_set_gain(self, gain).await
+    }
+}
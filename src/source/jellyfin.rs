@@ -0,0 +1,753 @@
+//! [Jellyfin](https://jellyfin.org) as a music source, built with the `jellyfin` feature.
+//! Jellyfin's items are translated to the Subsonic types the rest of the app works with:
+//! album artists are the artists, an album's or song's own ID doubles as its cover art ID,
+//! favorites are starred and music libraries are the music folders. Lyrics are looked up
+//! by song, browsing by folder, internet radio, the jukebox and syncing the play queue are
+//! not supported.
+
+use super::{unsupported, MusicSource};
+use async_trait::async_trait;
+use log::{debug, info, warn};
+use reqwest::{Client, Method, Response};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use subsonic::{
+    Album, AlbumDetail, AlbumListType, Artist, ArtistIndex, ArtistInfo, Genre, LyricsLine, MusicFolder, OnChunk, Playlist,
+    PlaylistDetail, ReplayGain, Result, SearchCategory, SearchResult, Song, Starred, StructuredLyrics, Transcoding,
+    SEARCH_PAGE_SIZE,
+};
+use tokio::sync::OnceCell;
+
+/// Fields asked for on top of Jellyfin's defaults, for what the Subsonic types carry
+const ITEM_FIELDS: &str = "Genres,DateCreated,Path,MediaSources,ChildCount,Overview,ItemCounts";
+
+/// Artists fetched per request, each page's complete index sections are handed on at once
+const ARTISTS_PAGE_SIZE: u32 = 500;
+
+/// Containers streamed as they are without a wanted format, anything else is transcoded
+/// to MP3 by the server
+const DIRECT_CONTAINERS: &str = "opus,mp3,aac,m4a,m4b,flac,alac,webma,webm,wav,ogg";
+
+/// Durations are in ticks of 100 nanoseconds
+const TICKS_PER_SECOND: u64 = 10_000_000;
+
+/// A user's connection to one Jellyfin server, logged in with the first request. Cheap to
+/// clone, clones share the HTTP connection pool and the login.
+#[derive(Debug, Clone)]
+pub struct JellyfinClient {
+    base_url: String,
+    username: String,
+    password: String,
+    /// Shown by Jellyfin in its list of devices
+    client_name: String,
+    client: Client,
+    session: Arc<OnceCell<Session>>,
+    /// ID of the music library the library is listed from, all of them when `None`
+    music_folder: Option<String>,
+    /// How songs are streamed, as they are when `None`
+    transcoding: Option<Transcoding>,
+}
+
+#[derive(Debug)]
+struct Session {
+    user_id: String,
+    token: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+struct QueryResult {
+    items: Vec<Item>,
+    total_record_count: u32,
+}
+
+/// The parts of Jellyfin's `BaseItemDto` that have a place in the Subsonic types
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+struct Item {
+    id: String,
+    name: String,
+    #[serde(rename = "Type")]
+    kind: Option<String>,
+    media_type: Option<String>,
+    collection_type: Option<String>,
+    album: Option<String>,
+    album_id: Option<String>,
+    album_artist: Option<String>,
+    album_artists: Vec<NameId>,
+    artist_items: Vec<NameId>,
+    index_number: Option<u32>,
+    production_year: Option<u32>,
+    genres: Vec<String>,
+    run_time_ticks: Option<u64>,
+    child_count: Option<u32>,
+    song_count: Option<u32>,
+    album_count: Option<u32>,
+    date_created: Option<String>,
+    overview: Option<String>,
+    path: Option<String>,
+    container: Option<String>,
+    image_tags: HashMap<String, String>,
+    album_primary_image_tag: Option<String>,
+    media_sources: Vec<MediaSource>,
+    user_data: Option<UserData>,
+    provider_ids: HashMap<String, String>,
+    /// Position of a song in a playlist, which removing it goes by
+    playlist_item_id: Option<String>,
+    /// Track gain in dB, from Jellyfin 10.9 on
+    normalization_gain: Option<f32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+struct NameId {
+    id: String,
+    name: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+struct MediaSource {
+    size: Option<u64>,
+    /// Bits per second
+    bitrate: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+struct UserData {
+    is_favorite: bool,
+}
+
+impl Item {
+    fn starred(&self) -> Option<String> {
+        // Jellyfin doesn't keep when an item became a favorite
+        self.user_data.as_ref().filter(|data| data.is_favorite).map(|_| String::new())
+    }
+
+    fn duration(&self) -> Option<u32> {
+        self.run_time_ticks.map(|ticks| (ticks / TICKS_PER_SECOND) as u32)
+    }
+
+    fn has_image(&self) -> bool {
+        self.image_tags.contains_key("Primary")
+    }
+
+    fn into_artist(self) -> Artist {
+        Artist {
+            album_count: self.album_count.or(self.child_count),
+            starred: self.starred(),
+            id: self.id,
+            name: self.name,
+        }
+    }
+
+    fn into_album(self) -> Album {
+        Album {
+            artist: self.album_artist.clone(),
+            artist_id: self.album_artists.first().map(|artist| artist.id.clone()),
+            year: self.production_year,
+            song_count: self.child_count.or(self.song_count),
+            duration: self.duration(),
+            cover_art: self.has_image().then(|| self.id.clone()),
+            genre: self.genres.first().cloned(),
+            starred: self.starred(),
+            created: self.date_created,
+            id: self.id,
+            name: self.name,
+        }
+    }
+
+    fn into_song(self) -> Song {
+        let artists: Vec<_> = self.artist_items.iter().map(|artist| artist.name.as_str()).collect();
+        let source = self.media_sources.first();
+        Song {
+            artist: if artists.is_empty() { self.album_artist.clone() } else { Some(artists.join(", ")) },
+            artist_id: self.artist_items.first().or(self.album_artists.first()).map(|artist| artist.id.clone()),
+            track: self.index_number,
+            year: self.production_year,
+            genre: self.genres.first().cloned(),
+            cover_art: if self.has_image() {
+                Some(self.id.clone())
+            } else {
+                self.album_primary_image_tag.as_ref().and(self.album_id.clone())
+            },
+            size: source.and_then(|source| source.size),
+            content_type: None,
+            duration: self.duration(),
+            bit_rate: source.and_then(|source| source.bitrate).map(|bitrate| bitrate / 1000),
+            starred: self.starred(),
+            kind: Some(if self.kind.as_deref() == Some("AudioBook") { "audiobook" } else { "music" }.to_string()),
+            replay_gain: self.normalization_gain.map(|gain| ReplayGain {
+                track_gain: Some(gain),
+                ..Default::default()
+            }),
+            suffix: self.container,
+            path: self.path,
+            album: self.album,
+            album_id: self.album_id,
+            id: self.id,
+            title: self.name,
+        }
+    }
+}
+
+impl JellyfinClient {
+    pub fn new(base_url: String, username: String, password: String) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            username,
+            password,
+            client_name: env!("CARGO_PKG_NAME").to_string(),
+            client: Client::new(),
+            session: Arc::default(),
+            music_folder: None,
+            transcoding: None,
+        }
+    }
+
+    /// Send every request through the HTTP or SOCKS proxy at this URL. `None` leaves it
+    /// to the environment variables, as does a URL that does not parse.
+    pub fn with_proxy(mut self, url: Option<&str>) -> Self {
+        let Some(url) = url else {
+            return self;
+        };
+        match reqwest::Proxy::all(url).and_then(|proxy| Client::builder().proxy(proxy).build()) {
+            Ok(client) => self.client = client,
+            // The URL is left out, it may carry the proxy login
+            Err(e) => warn!("Ignoring the proxy setting: {}", e),
+        }
+        self
+    }
+
+    /// Name the application to the server, defaults to the name of this crate
+    pub fn with_client_name(mut self, name: impl Into<String>) -> Self {
+        self.client_name = name.into();
+        self
+    }
+
+    /// The value of the `Authorization` header, with the token once logged in
+    fn authorization(&self, token: Option<&str>) -> String {
+        let mut header = format!(
+            r#"MediaBrowser Client="{}", Device="{}", DeviceId="{}", Version="{}""#,
+            self.client_name,
+            self.client_name,
+            self.device_id(),
+            env!("CARGO_PKG_VERSION"),
+        );
+        if let Some(token) = token {
+            header.push_str(&format!(r#", Token="{}""#, token));
+        }
+        header
+    }
+
+    /// Stays the same between runs, so Jellyfin lists one device per user rather than one per login
+    fn device_id(&self) -> String {
+        format!("{}-{}", self.client_name, self.username)
+    }
+
+    async fn session(&self) -> Result<&Session> {
+        self.session.get_or_try_init(|| self.login()).await
+    }
+
+    async fn login(&self) -> Result<Session> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct AuthenticationResult {
+            access_token: String,
+            user: NameId,
+        }
+
+        let response = self
+            .client
+            .post(format!("{}/Users/AuthenticateByName", self.base_url))
+            .header("Authorization", self.authorization(None))
+            .json(&json!({ "Username": self.username, "Pw": self.password }))
+            .send()
+            .await?
+            .error_for_status()?;
+        let result: AuthenticationResult = response.json().await?;
+        info!("Logged in to Jellyfin as {}", result.user.name);
+        Ok(Session {
+            user_id: result.user.id,
+            token: result.access_token,
+        })
+    }
+
+    async fn send(&self, method: Method, path: &str, query: &[(&str, &str)], body: Option<Value>) -> Result<Response> {
+        let session = self.session().await?;
+        debug!("Jellyfin request: {} {}", method, path);
+        let mut request = self
+            .client
+            .request(method, format!("{}{}", self.base_url, path))
+            .header("Authorization", self.authorization(Some(&session.token)))
+            .query(query);
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        Ok(request.send().await?.error_for_status()?)
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, path: &str, query: &[(&str, &str)]) -> Result<T> {
+        let body = self.send(Method::GET, path, query, None).await?.bytes().await?;
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    async fn user_id(&self) -> Result<&str> {
+        Ok(&self.session().await?.user_id)
+    }
+
+    /// The user's items matching `query`, searched through all libraries
+    async fn items(&self, query: &[(&str, &str)]) -> Result<QueryResult> {
+        let path = format!("/Users/{}/Items", self.user_id().await?);
+        let mut query = query.to_vec();
+        query.extend([("Recursive", "true"), ("Fields", ITEM_FIELDS)]);
+        self.get_json(&path, &query).await
+    }
+
+    async fn item(&self, item_id: &str) -> Result<Item> {
+        let path = format!("/Users/{}/Items/{}", self.user_id().await?, item_id);
+        self.get_json(&path, &[("Fields", ITEM_FIELDS)]).await
+    }
+
+    /// Album artists, the ones with albums of their own
+    async fn album_artists(&self, query: &[(&str, &str)]) -> Result<QueryResult> {
+        let user_id = self.user_id().await?;
+        let mut query = query.to_vec();
+        query.extend([("UserId", user_id), ("SortBy", "SortName"), ("Fields", ITEM_FIELDS)]);
+        self.get_json("/Artists/AlbumArtists", &self.in_music_folder(&query)).await
+    }
+
+    /// Add the music library the library is listed from to the query
+    fn in_music_folder<'a>(&'a self, query: &[(&'a str, &'a str)]) -> Vec<(&'a str, &'a str)> {
+        let mut query = query.to_vec();
+        if let Some(folder) = &self.music_folder {
+            query.push(("ParentId", folder));
+        }
+        query
+    }
+
+    /// Songs of the playlist, with the entry IDs removing them goes by
+    async fn playlist_items(&self, playlist_id: &str) -> Result<Vec<Item>> {
+        let user_id = self.user_id().await?;
+        let path = format!("/Playlists/{}/Items", playlist_id);
+        let result: QueryResult = self.get_json(&path, &[("UserId", user_id), ("Fields", ITEM_FIELDS)]).await?;
+        Ok(result.items)
+    }
+
+    async fn set_favorite(&self, item_ids: impl Iterator<Item = &String>, favorite: bool) -> Result<()> {
+        let user_id = self.user_id().await?;
+        let method = if favorite { Method::POST } else { Method::DELETE };
+        for item_id in item_ids {
+            let path = format!("/Users/{}/FavoriteItems/{}", user_id, item_id);
+            self.send(method.clone(), &path, &[], None).await?;
+        }
+        Ok(())
+    }
+}
+
+/// The index section an artist is listed in, like Subsonic servers do it
+fn index_name(name: &str) -> String {
+    match name.chars().next() {
+        Some(c) if c.is_alphabetic() => c.to_uppercase().collect(),
+        _ => "#".to_string(),
+    }
+}
+
+#[async_trait]
+impl MusicSource for JellyfinClient {
+    fn with_music_folder(&self, folder_id: Option<String>) -> Arc<dyn MusicSource> {
+        Arc::new(Self {
+            music_folder: folder_id,
+            ..self.clone()
+        })
+    }
+
+    fn with_transcoding(&self, transcoding: Option<Transcoding>) -> Arc<dyn MusicSource> {
+        Arc::new(Self {
+            transcoding,
+            ..self.clone()
+        })
+    }
+
+    async fn get_artists_progressive(&self, on_index: &mut (dyn FnMut(ArtistIndex) + Send)) -> Result<()> {
+        let limit = ARTISTS_PAGE_SIZE.to_string();
+        let mut section: Option<ArtistIndex> = None;
+        let mut offset = 0;
+        loop {
+            let start = offset.to_string();
+            let page = self.album_artists(&[("Limit", &limit), ("StartIndex", &start)]).await?;
+            let count = page.items.len() as u32;
+            for artist in page.items {
+                let name = index_name(&artist.name);
+                match &mut section {
+                    Some(current) if current.name == name => current.artist.push(artist.into_artist()),
+                    _ => {
+                        let next = ArtistIndex {
+                            name,
+                            artist: vec![artist.into_artist()],
+                        };
+                        if let Some(done) = section.replace(next) {
+                            on_index(done);
+                        }
+                    }
+                }
+            }
+            offset += count;
+            if count == 0 || offset >= page.total_record_count {
+                break;
+            }
+        }
+        if let Some(last) = section {
+            on_index(last);
+        }
+        Ok(())
+    }
+
+    async fn get_artist(&self, artist_id: &str) -> Result<Vec<Album>> {
+        let result = self
+            .items(&[
+                ("IncludeItemTypes", "MusicAlbum"),
+                ("AlbumArtistIds", artist_id),
+                ("SortBy", "ProductionYear,SortName"),
+            ])
+            .await?;
+        Ok(result.items.into_iter().map(Item::into_album).collect())
+    }
+
+    async fn get_album(&self, album_id: &str) -> Result<AlbumDetail> {
+        let album = self.item(album_id).await?;
+        let songs = self
+            .items(&[
+                ("ParentId", album_id),
+                ("IncludeItemTypes", "Audio"),
+                ("SortBy", "ParentIndexNumber,IndexNumber,SortName"),
+            ])
+            .await?;
+        Ok(AlbumDetail {
+            artist: album.album_artist.clone(),
+            artist_id: album.album_artists.first().map(|artist| artist.id.clone()),
+            cover_art: album.has_image().then(|| album.id.clone()),
+            song_count: album.child_count,
+            duration: album.duration(),
+            year: album.production_year,
+            id: album.id,
+            name: album.name,
+            song: songs.items.into_iter().map(Item::into_song).collect(),
+        })
+    }
+
+    async fn get_album_list(&self, list: AlbumListType, size: u32, offset: u32) -> Result<Vec<Album>> {
+        let (sort_by, sort_order, filters) = match list {
+            AlbumListType::Newest => ("DateCreated", "Descending", None),
+            AlbumListType::Recent => ("DatePlayed", "Descending", Some("IsPlayed")),
+            AlbumListType::Frequent => ("PlayCount", "Descending", Some("IsPlayed")),
+            AlbumListType::Random => ("Random", "Ascending", None),
+            AlbumListType::ByYear => ("ProductionYear,SortName", "Descending,Ascending", None),
+        };
+        let (size, offset) = (size.to_string(), offset.to_string());
+        let mut query = vec![
+            ("IncludeItemTypes", "MusicAlbum"),
+            ("SortBy", sort_by),
+            ("SortOrder", sort_order),
+            ("Limit", size.as_str()),
+            ("StartIndex", offset.as_str()),
+        ];
+        if let Some(filters) = filters {
+            query.push(("Filters", filters));
+        }
+        let result = self.items(&self.in_music_folder(&query)).await?;
+        Ok(result.items.into_iter().map(Item::into_album).collect())
+    }
+
+    async fn get_cover_art(&self, cover_art_id: &str, size: Option<u32>) -> Result<Vec<u8>> {
+        let path = format!("/Items/{}/Images/Primary", cover_art_id);
+        let size = size.map(|size| size.to_string());
+        let query: Vec<_> = match &size {
+            Some(size) => vec![("maxWidth", size.as_str()), ("maxHeight", size.as_str())],
+            None => Vec::new(),
+        };
+        let body = self.send(Method::GET, &path, &query, None).await?.bytes().await?;
+        Ok(body.to_vec())
+    }
+
+    fn get_stream_url(&self, song_id: &str) -> String {
+        // Songs are only played after listing them, which logged in
+        let Some(session) = self.session.get() else {
+            warn!("Streaming {} from Jellyfin before logging in", song_id);
+            return format!("{}/Audio/{}/stream?static=true", self.base_url, song_id);
+        };
+        let transcoding = self.transcoding.clone().unwrap_or_default();
+        let format = transcoding.format.as_deref();
+        if format == Some("raw") {
+            return format!("{}/Audio/{}/stream?static=true&api_key={}", self.base_url, song_id, session.token);
+        }
+        // A wanted format is the only one streamed as it is, anything else is transcoded to it
+        let (containers, codec) = format.map_or((DIRECT_CONTAINERS, "mp3"), |format| (format, format));
+        let container = if codec == "opus" { "ogg" } else { codec };
+        let mut url = format!(
+            "{}/Audio/{}/universal?UserId={}&DeviceId={}&api_key={}&Container={}&AudioCodec={}&TranscodingContainer={}&TranscodingProtocol=http",
+            self.base_url,
+            song_id,
+            session.user_id,
+            urlencoding::encode(&self.device_id()),
+            session.token,
+            urlencoding::encode(containers),
+            urlencoding::encode(codec),
+            urlencoding::encode(container),
+        );
+        if transcoding.max_bit_rate > 0 {
+            url.push_str(&format!("&MaxStreamingBitrate={}", transcoding.max_bit_rate * 1000));
+        }
+        url
+    }
+
+    async fn get_genres(&self) -> Result<Vec<Genre>> {
+        let user_id = self.user_id().await?;
+        let query = [("UserId", user_id), ("SortBy", "SortName"), ("Fields", ITEM_FIELDS)];
+        let result: QueryResult = self.get_json("/MusicGenres", &self.in_music_folder(&query)).await?;
+        Ok(result
+            .items
+            .into_iter()
+            .map(|genre| Genre {
+                value: genre.name,
+                song_count: genre.song_count,
+                album_count: genre.album_count,
+                starred: None,
+            })
+            .collect())
+    }
+
+    async fn get_songs_by_genre(&self, genre: &str, count: u32, offset: u32) -> Result<Vec<Song>> {
+        let (count, offset) = (count.to_string(), offset.to_string());
+        let query = [
+            ("IncludeItemTypes", "Audio"),
+            ("Genres", genre),
+            ("SortBy", "AlbumArtist,Album,ParentIndexNumber,IndexNumber"),
+            ("Limit", count.as_str()),
+            ("StartIndex", offset.as_str()),
+        ];
+        let result = self.items(&self.in_music_folder(&query)).await?;
+        Ok(result.items.into_iter().map(Item::into_song).collect())
+    }
+
+    async fn search(&self, query: &str, more: Option<(SearchCategory, u32)>) -> Result<SearchResult> {
+        let wanted = |category| more.is_none_or(|(c, _)| c == category);
+        let page_size = SEARCH_PAGE_SIZE.to_string();
+        let offset = more.map_or(0, |(_, offset)| offset).to_string();
+        let page = [("SearchTerm", query), ("Limit", page_size.as_str()), ("StartIndex", offset.as_str())];
+        let of_type = |kind| {
+            let mut query = self.in_music_folder(&page);
+            query.push(("IncludeItemTypes", kind));
+            query
+        };
+
+        let mut result = SearchResult::default();
+        if wanted(SearchCategory::Artists) {
+            result.artist = self.album_artists(&page).await?.items.into_iter().map(Item::into_artist).collect();
+        }
+        if wanted(SearchCategory::Albums) {
+            result.album = self.items(&of_type("MusicAlbum")).await?.items.into_iter().map(Item::into_album).collect();
+        }
+        if wanted(SearchCategory::Songs) {
+            result.song = self.items(&of_type("Audio")).await?.items.into_iter().map(Item::into_song).collect();
+        }
+        Ok(result)
+    }
+
+    async fn get_starred(&self) -> Result<Starred> {
+        let favorites = |kind| self.in_music_folder(&[("IncludeItemTypes", kind), ("Filters", "IsFavorite")]);
+        let artists = self.album_artists(&[("IsFavorite", "true")]).await?;
+        let albums = self.items(&favorites("MusicAlbum")).await?;
+        let songs = self.items(&favorites("Audio")).await?;
+        Ok(Starred {
+            artist: artists.items.into_iter().map(Item::into_artist).collect(),
+            album: albums.items.into_iter().map(Item::into_album).collect(),
+            song: songs.items.into_iter().map(Item::into_song).collect(),
+        })
+    }
+
+    async fn get_playlists(&self) -> Result<Vec<Playlist>> {
+        let result = self.items(&[("IncludeItemTypes", "Playlist"), ("SortBy", "SortName")]).await?;
+        Ok(result
+            .items
+            .into_iter()
+            .filter(|playlist| playlist.media_type.as_deref() != Some("Video"))
+            .map(|playlist| Playlist {
+                song_count: playlist.child_count,
+                duration: playlist.duration(),
+                owner: None,
+                comment: playlist.overview,
+                public: None,
+                created: playlist.date_created,
+                changed: None,
+                id: playlist.id,
+                name: playlist.name,
+            })
+            .collect())
+    }
+
+    async fn get_playlist(&self, playlist_id: &str) -> Result<PlaylistDetail> {
+        let playlist = self.item(playlist_id).await?;
+        let entries = self.playlist_items(playlist_id).await?;
+        Ok(PlaylistDetail {
+            id: playlist.id,
+            name: playlist.name,
+            entry: entries.into_iter().map(Item::into_song).collect(),
+        })
+    }
+
+    async fn create_playlist(&self, name: &str, song_ids: &[String]) -> Result<()> {
+        let user_id = self.user_id().await?;
+        let body = json!({ "Name": name, "Ids": song_ids, "UserId": user_id, "MediaType": "Audio" });
+        self.send(Method::POST, "/Playlists", &[], Some(body)).await?;
+        Ok(())
+    }
+
+    async fn add_to_playlist(&self, playlist_id: &str, song_ids: &[String]) -> Result<()> {
+        let user_id = self.user_id().await?;
+        let path = format!("/Playlists/{}/Items", playlist_id);
+        let ids = song_ids.join(",");
+        self.send(Method::POST, &path, &[("Ids", &ids), ("UserId", user_id)], None).await?;
+        Ok(())
+    }
+
+    async fn remove_from_playlist(&self, playlist_id: &str, indexes: &[usize]) -> Result<()> {
+        let entries = self.playlist_items(playlist_id).await?;
+        let entry_ids: Vec<_> = indexes
+            .iter()
+            .filter_map(|index| entries.get(*index)?.playlist_item_id.as_deref())
+            .collect();
+        let path = format!("/Playlists/{}/Items", playlist_id);
+        self.send(Method::DELETE, &path, &[("EntryIds", &entry_ids.join(","))], None).await?;
+        Ok(())
+    }
+
+    async fn set_playlist_public(&self, _playlist_id: &str, _public: bool) -> Result<()> {
+        unsupported("Sharing playlists")
+    }
+
+    async fn delete_playlist(&self, playlist_id: &str) -> Result<()> {
+        self.send(Method::DELETE, &format!("/Items/{}", playlist_id), &[], None).await?;
+        Ok(())
+    }
+
+    async fn ping(&self) -> Result<()> {
+        // Logs in first if it hasn't yet
+        self.send(Method::GET, "/System/Ping", &[], None).await?;
+        Ok(())
+    }
+
+    async fn get_artist_info(&self, artist_id: &str, count: u32) -> Result<ArtistInfo> {
+        let artist = self.item(artist_id).await?;
+        let user_id = self.user_id().await?;
+        let path = format!("/Items/{}/Similar", artist_id);
+        let count = count.to_string();
+        let similar: QueryResult = self.get_json(&path, &[("UserId", user_id), ("Limit", &count)]).await?;
+        Ok(ArtistInfo {
+            biography: artist.overview,
+            music_brainz_id: artist.provider_ids.get("MusicBrainzArtist").cloned(),
+            similar_artist: similar
+                .items
+                .into_iter()
+                .filter(|item| item.kind.as_deref() == Some("MusicArtist"))
+                .map(Item::into_artist)
+                .collect(),
+            ..Default::default()
+        })
+    }
+
+    async fn get_music_folders(&self) -> Result<Vec<MusicFolder>> {
+        let path = format!("/Users/{}/Views", self.user_id().await?);
+        let result: QueryResult = self.get_json(&path, &[]).await?;
+        Ok(result
+            .items
+            .into_iter()
+            .filter(|view| view.collection_type.as_deref() == Some("music"))
+            .map(|view| MusicFolder {
+                id: view.id,
+                name: Some(view.name),
+            })
+            .collect())
+    }
+
+    async fn download(&self, song_id: &str, on_chunk: &mut OnChunk<'_>) -> Result<()> {
+        let mut response = self.send(Method::GET, &format!("/Items/{}/Download", song_id), &[], None).await?;
+        let size = response.content_length();
+        while let Some(chunk) = response.chunk().await? {
+            on_chunk(&chunk, size)?;
+        }
+        Ok(())
+    }
+
+    async fn get_structured_lyrics(&self, song_id: &str) -> Result<Vec<StructuredLyrics>> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct LyricDto {
+            #[serde(default)]
+            lyrics: Vec<LyricLine>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct LyricLine {
+            text: String,
+            /// In ticks, for synced lyrics
+            start: Option<u64>,
+        }
+
+        let lyrics: LyricDto = self.get_json(&format!("/Audio/{}/Lyrics", song_id), &[]).await?;
+        if lyrics.lyrics.is_empty() {
+            return Ok(Vec::new());
+        }
+        let line: Vec<_> = lyrics
+            .lyrics
+            .into_iter()
+            .map(|line| LyricsLine {
+                start: line.start.map(|ticks| ticks / (TICKS_PER_SECOND / 1000)),
+                value: line.text,
+            })
+            .collect();
+        Ok(vec![StructuredLyrics {
+            synced: line.iter().all(|line| line.start.is_some()),
+            line,
+            ..Default::default()
+        }])
+    }
+
+    async fn get_similar_songs(&self, artist_id: &str, count: u32) -> Result<Vec<Song>> {
+        let user_id = self.user_id().await?;
+        let path = format!("/Items/{}/InstantMix", artist_id);
+        let count = count.to_string();
+        let query = [("UserId", user_id), ("Limit", count.as_str()), ("Fields", ITEM_FIELDS)];
+        let result: QueryResult = self.get_json(&path, &query).await?;
+        Ok(result.items.into_iter().map(Item::into_song).collect())
+    }
+
+    async fn star(&self, song_ids: &[String], album_ids: &[String]) -> Result<()> {
+        self.set_favorite(song_ids.iter().chain(album_ids), true).await
+    }
+
+    async fn unstar(&self, song_ids: &[String], album_ids: &[String]) -> Result<()> {
+        self.set_favorite(song_ids.iter().chain(album_ids), false).await
+    }
+
+    async fn now_playing(&self, song_id: &str) -> Result<()> {
+        let body = json!({ "ItemId": song_id, "CanSeek": true });
+        self.send(Method::POST, "/Sessions/Playing", &[], Some(body)).await?;
+        Ok(())
+    }
+
+    async fn scrobble(&self, plays: &[(String, u64)]) -> Result<()> {
+        // Marked as played now, which also counts the play
+        let user_id = self.user_id().await?;
+        for (song_id, _) in plays {
+            let path = format!("/Users/{}/PlayedItems/{}", user_id, song_id);
+            self.send(Method::POST, &path, &[], None).await?;
+        }
+        Ok(())
+    }
+}
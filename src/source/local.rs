@@ -0,0 +1,561 @@
+//! A directory of music files as a music source. The tags of the files are read once, on
+//! the first request, into artists (by album artist), albums and songs, and mpv plays the
+//! files directly. [`MergedSource`](super::merged::MergedSource) lists them next to a
+//! server's library.
+//!
+//! IDs start with [`ID_PREFIX`], which is how the merged source tells local items from the
+//! server's. A song's ID is its path below the directory, an album's or song's cover art ID
+//! is the ID of a song whose file or directory has a cover.
+
+use super::{unsupported, MusicSource};
+use crate::ui::collate;
+use async_trait::async_trait;
+use lofty::picture::PictureType;
+use lofty::prelude::*;
+use lofty::tag::Tag;
+use log::{debug, info, warn};
+use rand::seq::SliceRandom;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use subsonic::{
+    Album, AlbumDetail, AlbumListType, Artist, ArtistIndex, Error, Genre, OnChunk, Playlist, PlaylistDetail, ReplayGain,
+    Result, SearchCategory, SearchResult, SkippedItem, Song, Starred, SubsonicError, Transcoding,
+    SEARCH_PAGE_SIZE,
+};
+use tokio::io::AsyncReadExt;
+use tokio::sync::OnceCell;
+
+/// What the IDs of local artists, albums and songs start with
+pub const ID_PREFIX: &str = "local:";
+
+/// Extensions of the files that are indexed
+const AUDIO_EXTENSIONS: [&str; 13] = [
+    "mp3", "flac", "ogg", "oga", "opus", "m4a", "aac", "wav", "wv", "ape", "mpc", "aif", "aiff",
+];
+
+/// Images next to the files used as the cover of songs without one in their tags
+const COVER_FILES: [&str; 4] = ["cover", "folder", "front", "album"];
+
+fn not_found(what: &str, id: &str) -> Error {
+    Error::Api {
+        error: SubsonicError::NotFound,
+        message: format!("{} not found: {}", what, id),
+    }
+}
+
+/// The music files below one directory. Cheap to clone, clones share the index.
+#[derive(Debug, Clone)]
+pub struct LocalLibrary {
+    root: PathBuf,
+    index: Arc<OnceCell<Index>>,
+    /// Files that could not be read, until taken by `take_skipped`
+    skipped: Arc<Mutex<Vec<SkippedItem>>>,
+}
+
+#[derive(Debug, Default)]
+struct Index {
+    /// Sorted the way the tree collates them
+    artists: Vec<Artist>,
+    albums: HashMap<String, Album>,
+    /// Album IDs of each artist, oldest first
+    artist_albums: HashMap<String, Vec<String>>,
+    /// Songs of each album in disc and track order
+    album_songs: HashMap<String, Vec<Song>>,
+    /// When the newest file of each album was modified, for the newest albums list
+    album_added: HashMap<String, SystemTime>,
+    genres: Vec<Genre>,
+}
+
+impl Index {
+    fn songs(&self) -> impl Iterator<Item = &Song> {
+        self.album_songs.values().flatten()
+    }
+}
+
+/// A song with what its album and artist are filed by
+struct ScannedSong {
+    song: Song,
+    disc: u32,
+    album_artist: String,
+    modified: SystemTime,
+}
+
+impl LocalLibrary {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            index: Arc::default(),
+            skipped: Arc::default(),
+        }
+    }
+
+    async fn index(&self) -> Result<&Index> {
+        self.index
+            .get_or_try_init(|| async {
+                let root = self.root.clone();
+                let (index, skipped) = tokio::task::spawn_blocking(move || scan(&root))
+                    .await
+                    .map_err(|e| Error::Unsupported(format!("Indexing local music failed: {}", e)))?;
+                self.skipped.lock().unwrap_or_else(|e| e.into_inner()).extend(skipped);
+                Ok(index)
+            })
+            .await
+    }
+
+    fn song_path(&self, song_id: &str) -> Option<PathBuf> {
+        let relative = song_id.strip_prefix(ID_PREFIX)?.strip_prefix("song/")?;
+        Some(self.root.join(relative))
+    }
+}
+
+fn albums_of(index: &Index, artist_id: &str) -> Vec<Album> {
+    let album_ids = index.artist_albums.get(artist_id).map(Vec::as_slice).unwrap_or_default();
+    album_ids.iter().filter_map(|id| index.albums.get(id).cloned()).collect()
+}
+
+/// One page of `items`
+fn page<T: Clone>(items: impl Iterator<Item = T>, offset: u32, size: u32) -> Vec<T> {
+    items.skip(offset as usize).take(size as usize).collect()
+}
+
+/// Read the tags of all music files below `root`
+fn scan(root: &Path) -> (Index, Vec<SkippedItem>) {
+    info!("Indexing local music in {}", root.display());
+    let mut files = Vec::new();
+    collect_files(root, &mut files, &mut HashSet::new());
+
+    let mut skipped = Vec::new();
+    let mut covers = HashMap::new();
+    let mut songs = Vec::new();
+    for path in files {
+        match read_song(root, &path, &mut covers) {
+            Ok(song) => songs.push(song),
+            Err(e) => {
+                debug!("Skipping {}: {}", path.display(), e);
+                skipped.push(SkippedItem {
+                    kind: "File",
+                    label: Some(path.display().to_string()),
+                    error: e.to_string(),
+                });
+            }
+        }
+    }
+    info!("Indexed {} local songs, {} files unreadable", songs.len(), skipped.len());
+    (build_index(songs), skipped)
+}
+
+/// The audio files below `dir`. Symlinked directories are followed, but each directory
+/// is only read once, `visited` holds the canonical paths of those read so far, so links
+/// to a parent don't loop.
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>, visited: &mut HashSet<PathBuf>) {
+    match fs::canonicalize(dir) {
+        Ok(canonical) => {
+            if !visited.insert(canonical) {
+                debug!("Skipping {}, already indexed", dir.display());
+                return;
+            }
+        }
+        Err(e) => {
+            warn!("Cannot resolve {}: {}", dir.display(), e);
+            return;
+        }
+    }
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Cannot read {}: {}", dir.display(), e);
+            return;
+        }
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            collect_files(&path, files, visited);
+        } else if extension(&path).is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.as_str())) {
+            files.push(path);
+        }
+    }
+}
+
+fn extension(path: &Path) -> Option<String> {
+    Some(path.extension()?.to_string_lossy().to_lowercase())
+}
+
+/// An image in `dir` named like a cover, looked up once per directory
+fn cover_file(dir: &Path) -> Option<PathBuf> {
+    fs::read_dir(dir).ok()?.flatten().map(|entry| entry.path()).find(|path| {
+        let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_lowercase());
+        let image = extension(path).is_some_and(|ext| matches!(ext.as_str(), "jpg" | "jpeg" | "png"));
+        image && stem.is_some_and(|stem| COVER_FILES.contains(&stem.as_str()))
+    })
+}
+
+/// A ReplayGain tag value like "-6.48 dB"
+fn parse_gain(tag: &Tag, key: &ItemKey) -> Option<f32> {
+    tag.get_string(key)?.trim().trim_end_matches(|c: char| c.is_alphabetic()).trim().parse().ok()
+}
+
+fn read_song(root: &Path, path: &Path, covers: &mut HashMap<PathBuf, bool>) -> lofty::error::Result<ScannedSong> {
+    let file = lofty::read_from_path(path)?;
+    let properties = file.properties();
+    let empty = Tag::new(file.primary_tag_type());
+    let tag = file.primary_tag().or_else(|| file.first_tag()).unwrap_or(&empty);
+    let dir = path.parent().unwrap_or(root);
+    let relative = path.strip_prefix(root).unwrap_or(path).to_string_lossy().replace('\\', "/");
+    let metadata = fs::metadata(path)?;
+
+    // Untagged files are filed by their directories, as in Artist/Album/01 Title.flac
+    let dir_name = |dir: &Path| Some(dir.strip_prefix(root).ok()?.file_name()?.to_string_lossy().to_string());
+    let artist = tag.artist().map(|artist| artist.to_string());
+    let album_artist = tag
+        .get_string(&ItemKey::AlbumArtist)
+        .map(str::to_string)
+        .or_else(|| artist.clone())
+        .or_else(|| dir.parent().and_then(dir_name))
+        .unwrap_or_else(|| "Unknown Artist".to_string());
+    let album = tag
+        .album()
+        .map(|album| album.to_string())
+        .or_else(|| dir_name(dir))
+        .unwrap_or_else(|| "Unknown Album".to_string());
+    let title = tag
+        .title()
+        .map(|title| title.to_string())
+        .or_else(|| path.file_stem().map(|stem| stem.to_string_lossy().to_string()))
+        .unwrap_or_default();
+    let id = format!("{}song/{}", ID_PREFIX, relative);
+    let has_cover = !tag.pictures().is_empty() || *covers.entry(dir.to_path_buf()).or_insert_with(|| cover_file(dir).is_some());
+    let replay_gain = ReplayGain {
+        track_gain: parse_gain(tag, &ItemKey::ReplayGainTrackGain),
+        album_gain: parse_gain(tag, &ItemKey::ReplayGainAlbumGain),
+        track_peak: parse_gain(tag, &ItemKey::ReplayGainTrackPeak),
+        album_peak: parse_gain(tag, &ItemKey::ReplayGainAlbumPeak),
+        ..Default::default()
+    };
+
+    let song = Song {
+        artist: artist.or_else(|| Some(album_artist.clone())),
+        album_id: Some(album_id(&album_artist, &album)),
+        artist_id: Some(artist_id(&album_artist)),
+        album: Some(album),
+        track: tag.track(),
+        year: tag.year(),
+        genre: tag.genre().map(|genre| genre.to_string()),
+        cover_art: has_cover.then(|| id.clone()),
+        size: Some(metadata.len()),
+        content_type: None,
+        suffix: extension(path),
+        duration: Some(properties.duration().as_secs() as u32),
+        bit_rate: properties.audio_bitrate(),
+        path: Some(relative),
+        starred: None,
+        kind: None,
+        replay_gain: (replay_gain.track_gain.is_some() || replay_gain.album_gain.is_some()).then_some(replay_gain),
+        id,
+        title,
+    };
+    Ok(ScannedSong {
+        song,
+        disc: tag.disk().unwrap_or(1),
+        album_artist,
+        modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+    })
+}
+
+fn artist_id(name: &str) -> String {
+    format!("{}artist/{}", ID_PREFIX, name)
+}
+
+fn album_id(artist: &str, album: &str) -> String {
+    format!("{}album/{}/{}", ID_PREFIX, artist, album)
+}
+
+fn build_index(mut songs: Vec<ScannedSong>) -> Index {
+    songs.sort_by(|a, b| (a.disc, a.song.track, &a.song.title).cmp(&(b.disc, b.song.track, &b.song.title)));
+    let mut index = Index::default();
+    let mut artist_names = HashMap::new();
+    let mut genres: HashMap<String, (u32, HashSet<String>)> = HashMap::new();
+
+    for scanned in songs {
+        let song = scanned.song;
+        let album_id = song.album_id.clone().unwrap_or_default();
+        let artist_id = artist_id(&scanned.album_artist);
+        artist_names.entry(artist_id.clone()).or_insert(scanned.album_artist.clone());
+        if let Some(genre) = &song.genre {
+            let (songs, albums) = genres.entry(genre.clone()).or_default();
+            *songs += 1;
+            albums.insert(album_id.clone());
+        }
+
+        let album = index.albums.entry(album_id.clone()).or_insert_with(|| {
+            index.artist_albums.entry(artist_id.clone()).or_default().push(album_id.clone());
+            Album {
+                id: album_id.clone(),
+                name: song.album.clone().unwrap_or_default(),
+                artist: Some(scanned.album_artist.clone()),
+                artist_id: Some(artist_id.clone()),
+                year: None,
+                song_count: Some(0),
+                duration: Some(0),
+                cover_art: None,
+                genre: None,
+                created: None,
+                starred: None,
+            }
+        });
+        album.song_count = album.song_count.map(|count| count + 1);
+        album.duration = album.duration.map(|duration| duration + song.duration.unwrap_or(0));
+        album.year = album.year.or(song.year);
+        album.genre = album.genre.clone().or(song.genre.clone());
+        album.cover_art = album.cover_art.clone().or(song.cover_art.clone());
+        let added = index.album_added.entry(album_id.clone()).or_insert(scanned.modified);
+        *added = (*added).max(scanned.modified);
+        index.album_songs.entry(album_id).or_default().push(song);
+    }
+
+    for album_ids in index.artist_albums.values_mut() {
+        album_ids.sort_by_cached_key(|id| {
+            let album = &index.albums[id];
+            (album.year, album.name.clone())
+        });
+    }
+    index.artists = artist_names
+        .into_iter()
+        .map(|(id, name)| Artist {
+            album_count: index.artist_albums.get(&id).map(|albums| albums.len() as u32),
+            starred: None,
+            id,
+            name,
+        })
+        .collect();
+    index.artists.sort_by_cached_key(|artist| (collate::sort_key(&artist.name), artist.name.clone()));
+    index.genres = genres
+        .into_iter()
+        .map(|(value, (songs, albums))| Genre {
+            value,
+            song_count: Some(songs),
+            album_count: Some(albums.len() as u32),
+            starred: None,
+        })
+        .collect();
+    index.genres.sort_by(|a, b| a.value.cmp(&b.value));
+    index
+}
+
+/// The cover in the tags of the file, or an image named like a cover next to it
+fn read_cover(path: &Path) -> Option<Vec<u8>> {
+    let embedded = lofty::read_from_path(path).ok().and_then(|file| {
+        let tag = file.primary_tag().or_else(|| file.first_tag())?;
+        let picture = tag.get_picture_type(PictureType::CoverFront).or_else(|| tag.pictures().first())?;
+        Some(picture.data().to_vec())
+    });
+    embedded.or_else(|| fs::read(cover_file(path.parent()?)?).ok())
+}
+
+fn read_lyrics(path: &Path) -> Option<String> {
+    let file = lofty::read_from_path(path).ok()?;
+    let tag = file.primary_tag().or_else(|| file.first_tag())?;
+    tag.get_string(&ItemKey::Lyrics).map(str::to_string)
+}
+
+#[async_trait]
+impl MusicSource for LocalLibrary {
+    fn with_music_folder(&self, _folder_id: Option<String>) -> Arc<dyn MusicSource> {
+        Arc::new(self.clone())
+    }
+
+    // The files are played as they are
+    fn with_transcoding(&self, _transcoding: Option<Transcoding>) -> Arc<dyn MusicSource> {
+        Arc::new(self.clone())
+    }
+
+    async fn get_artists_progressive(&self, on_index: &mut (dyn FnMut(ArtistIndex) + Send)) -> Result<()> {
+        let index = self.index().await?;
+        let mut section: Option<ArtistIndex> = None;
+        for artist in &index.artists {
+            let letter = collate::index_letter(&collate::sort_key(&artist.name));
+            match &mut section {
+                Some(current) if current.name == letter => current.artist.push(artist.clone()),
+                _ => {
+                    let next = ArtistIndex {
+                        name: letter,
+                        artist: vec![artist.clone()],
+                    };
+                    if let Some(done) = section.replace(next) {
+                        on_index(done);
+                    }
+                }
+            }
+        }
+        if let Some(last) = section {
+            on_index(last);
+        }
+        Ok(())
+    }
+
+    async fn get_artist(&self, artist_id: &str) -> Result<Vec<Album>> {
+        let index = self.index().await?;
+        if !index.artist_albums.contains_key(artist_id) {
+            return Err(not_found("Artist", artist_id));
+        }
+        Ok(albums_of(index, artist_id))
+    }
+
+    async fn get_album(&self, album_id: &str) -> Result<AlbumDetail> {
+        let index = self.index().await?;
+        let album = index.albums.get(album_id).ok_or_else(|| not_found("Album", album_id))?;
+        Ok(AlbumDetail {
+            id: album.id.clone(),
+            name: album.name.clone(),
+            artist: album.artist.clone(),
+            artist_id: album.artist_id.clone(),
+            cover_art: album.cover_art.clone(),
+            song_count: album.song_count,
+            duration: album.duration,
+            year: album.year,
+            song: index.album_songs.get(album_id).cloned().unwrap_or_default(),
+        })
+    }
+
+    async fn get_album_list(&self, list: AlbumListType, size: u32, offset: u32) -> Result<Vec<Album>> {
+        let index = self.index().await?;
+        let mut albums: Vec<&Album> = index.albums.values().collect();
+        match list {
+            AlbumListType::Newest => albums.sort_by_key(|album| std::cmp::Reverse(index.album_added.get(&album.id))),
+            AlbumListType::Random => albums.shuffle(&mut rand::thread_rng()),
+            AlbumListType::ByYear => albums.sort_by(|a, b| b.year.cmp(&a.year).then_with(|| a.name.cmp(&b.name))),
+            // Plays are not counted for local files
+            AlbumListType::Recent | AlbumListType::Frequent => return Ok(Vec::new()),
+        }
+        Ok(page(albums.into_iter().cloned(), offset, size))
+    }
+
+    async fn get_cover_art(&self, cover_art_id: &str, _size: Option<u32>) -> Result<Vec<u8>> {
+        let path = self.song_path(cover_art_id).ok_or_else(|| not_found("Cover art", cover_art_id))?;
+        tokio::task::spawn_blocking(move || read_cover(&path))
+            .await
+            .ok()
+            .flatten()
+            .ok_or_else(|| not_found("Cover art", cover_art_id))
+    }
+
+    fn get_stream_url(&self, song_id: &str) -> String {
+        self.song_path(song_id).unwrap_or_default().to_string_lossy().to_string()
+    }
+
+    async fn get_genres(&self) -> Result<Vec<Genre>> {
+        Ok(self.index().await?.genres.clone())
+    }
+
+    async fn get_songs_by_genre(&self, genre: &str, count: u32, offset: u32) -> Result<Vec<Song>> {
+        let index = self.index().await?;
+        let songs = index.songs().filter(|song| song.genre.as_deref() == Some(genre));
+        Ok(page(songs.cloned(), offset, count))
+    }
+
+    async fn search(&self, query: &str, more: Option<(SearchCategory, u32)>) -> Result<SearchResult> {
+        let index = self.index().await?;
+        let query = collate::fold(query);
+        let matches = |text: &str| collate::fold(text).contains(&query);
+        let offset = more.map_or(0, |(_, offset)| offset);
+        let wanted = |category| more.is_none_or(|(c, _)| c == category);
+
+        let mut result = SearchResult::default();
+        if wanted(SearchCategory::Artists) {
+            let artists = index.artists.iter().filter(|artist| matches(&artist.name));
+            result.artist = page(artists.cloned(), offset, SEARCH_PAGE_SIZE);
+        }
+        if wanted(SearchCategory::Albums) {
+            let mut albums: Vec<&Album> = index.albums.values().filter(|album| matches(&album.name)).collect();
+            albums.sort_by(|a, b| a.name.cmp(&b.name));
+            result.album = page(albums.into_iter().cloned(), offset, SEARCH_PAGE_SIZE);
+        }
+        if wanted(SearchCategory::Songs) {
+            let mut songs: Vec<&Song> = index.songs().filter(|song| matches(&song.title)).collect();
+            songs.sort_by(|a, b| a.title.cmp(&b.title));
+            result.song = page(songs.into_iter().cloned(), offset, SEARCH_PAGE_SIZE);
+        }
+        Ok(result)
+    }
+
+    async fn get_starred(&self) -> Result<Starred> {
+        Ok(Starred::default())
+    }
+
+    async fn get_playlists(&self) -> Result<Vec<Playlist>> {
+        Ok(Vec::new())
+    }
+
+    async fn get_playlist(&self, playlist_id: &str) -> Result<PlaylistDetail> {
+        Err(not_found("Playlist", playlist_id))
+    }
+
+    async fn create_playlist(&self, _name: &str, _song_ids: &[String]) -> Result<()> {
+        unsupported("Playlists of local files")
+    }
+
+    async fn add_to_playlist(&self, _playlist_id: &str, _song_ids: &[String]) -> Result<()> {
+        unsupported("Playlists of local files")
+    }
+
+    async fn remove_from_playlist(&self, _playlist_id: &str, _indexes: &[usize]) -> Result<()> {
+        unsupported("Playlists of local files")
+    }
+
+    async fn set_playlist_public(&self, _playlist_id: &str, _public: bool) -> Result<()> {
+        unsupported("Playlists of local files")
+    }
+
+    async fn delete_playlist(&self, _playlist_id: &str) -> Result<()> {
+        unsupported("Playlists of local files")
+    }
+
+    fn take_skipped(&self) -> Vec<SkippedItem> {
+        std::mem::take(&mut *self.skipped.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    fn local_path(&self, song_id: &str) -> Option<PathBuf> {
+        self.song_path(song_id)
+    }
+
+    // Copies the file
+    async fn download(&self, song_id: &str, on_chunk: &mut OnChunk<'_>) -> Result<()> {
+        let path = self.song_path(song_id).ok_or_else(|| not_found("Song", song_id))?;
+        let mut file = tokio::fs::File::open(&path).await?;
+        let size = file.metadata().await?.len();
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let read = file.read(&mut buffer).await?;
+            if read == 0 {
+                return Ok(());
+            }
+            on_chunk(&buffer[..read], Some(size))?;
+        }
+    }
+
+    async fn get_lyrics(&self, artist: &str, title: &str) -> Result<Option<String>> {
+        let index = self.index().await?;
+        let song = index
+            .songs()
+            .find(|song| song.title.eq_ignore_ascii_case(title) && song.artist.as_deref().is_some_and(|a| a.eq_ignore_ascii_case(artist)));
+        let Some(path) = song.and_then(|song| self.song_path(&song.id)) else {
+            return Ok(None);
+        };
+        Ok(tokio::task::spawn_blocking(move || read_lyrics(&path)).await.ok().flatten())
+    }
+
+    // Plays of local files are only kept in the history
+    async fn now_playing(&self, _song_id: &str) -> Result<()> {
+        Ok(())
+    }
+
+    async fn scrobble(&self, _plays: &[(String, u64)]) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,640 @@
+//! Several libraries listed as one. [`MergedSource`] puts the artists of a primary source
+//! and any number of others into one list and sends every request for an item to the
+//! source its ID comes from, so the queue can mix songs of all of them.
+//!
+//! IDs of the other sources start with a prefix of their own: local files bring theirs,
+//! other servers get their name put in front of their IDs by [`Prefixed`].
+
+use super::{unsupported, MusicSource};
+use crate::ui::collate;
+use async_trait::async_trait;
+use log::warn;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use subsonic::{
+    Album, AlbumDetail, AlbumListType, Artist, ArtistIndex, ArtistInfo, Genre, Indexes, InternetRadioStation,
+    JukeboxStatus, MusicDirectory, MusicFolder, OnChunk, PlayQueue, Playlist, PlaylistDetail, Result, ScanStatus,
+    SearchCategory, SearchResult, SkippedItem, Song, Starred, StructuredLyrics, Transcoding,
+};
+
+/// The prefix the IDs of the server with this name get
+pub fn server_prefix(name: &str) -> String {
+    format!("{}:", name)
+}
+
+/// Artists listed so far, to find the albums other sources have of them
+#[derive(Debug, Default)]
+struct ArtistNames {
+    /// Folded name of each artist by ID
+    names: HashMap<String, String>,
+    /// IDs of the artists of each folded name, in the order of the sources
+    ids: HashMap<String, Vec<String>>,
+}
+
+/// The primary source's library with those of others merged in. Artists of the same name
+/// are listed once, with the albums of all sources. Album lists, genres, the play queue,
+/// internet radio, the jukebox and library scans are the primary's.
+#[derive(Debug, Clone)]
+pub struct MergedSource {
+    primary: Arc<dyn MusicSource>,
+    /// The other sources with what their IDs start with
+    others: Vec<(String, Arc<dyn MusicSource>)>,
+    artists: Arc<Mutex<ArtistNames>>,
+}
+
+impl MergedSource {
+    pub fn new(primary: Arc<dyn MusicSource>) -> Self {
+        Self {
+            primary,
+            others: Vec::new(),
+            artists: Arc::default(),
+        }
+    }
+
+    /// Merge in a source all IDs of which start with `prefix`
+    pub fn with_source(mut self, prefix: impl Into<String>, source: Arc<dyn MusicSource>) -> Self {
+        self.others.push((prefix.into(), source));
+        self
+    }
+
+    /// The source an item comes from
+    fn route(&self, id: &str) -> &dyn MusicSource {
+        let other = self.others.iter().find(|(prefix, _)| id.starts_with(prefix.as_str()));
+        other.map_or(self.primary.as_ref(), |(_, source)| source.as_ref())
+    }
+
+    fn is_primary(&self, id: &str) -> bool {
+        !self.others.iter().any(|(prefix, _)| id.starts_with(prefix.as_str()))
+    }
+
+    fn sources(&self) -> impl Iterator<Item = &dyn MusicSource> {
+        std::iter::once(self.primary.as_ref()).chain(self.others.iter().map(|(_, source)| source.as_ref()))
+    }
+
+    /// `items` split by the source their IDs come from, primary first, leaving out
+    /// sources none of them come from
+    fn split<T: Clone>(&self, items: &[T], id: impl Fn(&T) -> &str) -> Vec<(&dyn MusicSource, Vec<T>)> {
+        let mut groups: Vec<(&dyn MusicSource, Vec<T>)> = self.sources().map(|source| (source, Vec::new())).collect();
+        for item in items {
+            let index = self
+                .others
+                .iter()
+                .position(|(prefix, _)| id(item).starts_with(prefix.as_str()))
+                .map_or(0, |index| index + 1);
+            groups[index].1.push(item.clone());
+        }
+        groups.retain(|(_, items)| !items.is_empty());
+        groups
+    }
+
+    /// The one source all `ids` come from, failing for a mix
+    fn owner(&self, ids: &[String], feature: &str) -> Result<&dyn MusicSource> {
+        let mut groups = self.split(ids, |id| id.as_str());
+        match groups.len() {
+            0 => Ok(self.primary.as_ref()),
+            1 => Ok(groups.remove(0).0),
+            _ => unsupported(feature),
+        }
+    }
+}
+
+#[async_trait]
+impl MusicSource for MergedSource {
+    fn with_music_folder(&self, folder_id: Option<String>) -> Arc<dyn MusicSource> {
+        Arc::new(Self {
+            primary: self.primary.with_music_folder(folder_id),
+            ..self.clone()
+        })
+    }
+
+    fn with_transcoding(&self, transcoding: Option<Transcoding>) -> Arc<dyn MusicSource> {
+        let others = self.others.iter();
+        Arc::new(Self {
+            primary: self.primary.with_transcoding(transcoding.clone()),
+            others: others.map(|(prefix, source)| (prefix.clone(), source.with_transcoding(transcoding.clone()))).collect(),
+            artists: self.artists.clone(),
+        })
+    }
+
+    async fn get_artists_progressive(&self, on_index: &mut (dyn FnMut(ArtistIndex) + Send)) -> Result<()> {
+        let mut listed = HashSet::new();
+        for (position, source) in self.sources().enumerate() {
+            let listing = source
+                .get_artists_progressive(&mut |mut index| {
+                    let mut artists = self.artists.lock().unwrap_or_else(|e| e.into_inner());
+                    for artist in &index.artist {
+                        let name = collate::fold(&artist.name);
+                        artists.names.insert(artist.id.clone(), name.clone());
+                        let ids = artists.ids.entry(name).or_default();
+                        if !ids.contains(&artist.id) {
+                            ids.push(artist.id.clone());
+                        }
+                    }
+                    drop(artists);
+                    index.artist.retain(|artist| listed.insert(collate::fold(&artist.name)));
+                    if !index.artist.is_empty() {
+                        on_index(index);
+                    }
+                })
+                .await;
+            match listing {
+                Err(e) if position > 0 => warn!("Listing the artists of a merged source failed: {}", e),
+                result => result?,
+            }
+        }
+        Ok(())
+    }
+
+    async fn get_artist(&self, artist_id: &str) -> Result<Vec<Album>> {
+        let mut albums = self.route(artist_id).get_artist(artist_id).await?;
+        let namesakes = {
+            let artists = self.artists.lock().unwrap_or_else(|e| e.into_inner());
+            let name = artists.names.get(artist_id);
+            name.and_then(|name| artists.ids.get(name)).cloned().unwrap_or_default()
+        };
+        for id in namesakes.iter().filter(|id| *id != artist_id) {
+            match self.route(id).get_artist(id).await {
+                Ok(more) => albums.extend(more),
+                Err(e) => warn!("Loading the albums of {} failed: {}", id, e),
+            }
+        }
+        Ok(albums)
+    }
+
+    async fn get_album(&self, album_id: &str) -> Result<AlbumDetail> {
+        self.route(album_id).get_album(album_id).await
+    }
+
+    async fn get_album_list(&self, list: AlbumListType, size: u32, offset: u32) -> Result<Vec<Album>> {
+        self.primary.get_album_list(list, size, offset).await
+    }
+
+    async fn get_cover_art(&self, cover_art_id: &str, size: Option<u32>) -> Result<Vec<u8>> {
+        self.route(cover_art_id).get_cover_art(cover_art_id, size).await
+    }
+
+    fn get_stream_url(&self, song_id: &str) -> String {
+        self.route(song_id).get_stream_url(song_id)
+    }
+
+    async fn get_genres(&self) -> Result<Vec<Genre>> {
+        self.primary.get_genres().await
+    }
+
+    async fn get_songs_by_genre(&self, genre: &str, count: u32, offset: u32) -> Result<Vec<Song>> {
+        self.primary.get_songs_by_genre(genre, count, offset).await
+    }
+
+    async fn search(&self, query: &str, more: Option<(SearchCategory, u32)>) -> Result<SearchResult> {
+        let mut result = self.primary.search(query, more).await?;
+        for (prefix, source) in &self.others {
+            match source.search(query, more).await {
+                Ok(other) => {
+                    result.artist.extend(other.artist);
+                    result.album.extend(other.album);
+                    result.song.extend(other.song);
+                }
+                Err(e) => warn!("Searching {} failed: {}", prefix, e),
+            }
+        }
+        Ok(result)
+    }
+
+    async fn get_starred(&self) -> Result<Starred> {
+        let mut starred = self.primary.get_starred().await?;
+        for (prefix, source) in &self.others {
+            match source.get_starred().await {
+                Ok(other) => {
+                    starred.artist.extend(other.artist);
+                    starred.album.extend(other.album);
+                    starred.song.extend(other.song);
+                }
+                Err(e) => warn!("Loading the starred items of {} failed: {}", prefix, e),
+            }
+        }
+        Ok(starred)
+    }
+
+    async fn get_playlists(&self) -> Result<Vec<Playlist>> {
+        let mut playlists = self.primary.get_playlists().await?;
+        for (prefix, source) in &self.others {
+            match source.get_playlists().await {
+                Ok(other) => playlists.extend(other),
+                Err(e) => warn!("Loading the playlists of {} failed: {}", prefix, e),
+            }
+        }
+        Ok(playlists)
+    }
+
+    async fn get_playlist(&self, playlist_id: &str) -> Result<PlaylistDetail> {
+        self.route(playlist_id).get_playlist(playlist_id).await
+    }
+
+    async fn create_playlist(&self, name: &str, song_ids: &[String]) -> Result<()> {
+        let source = self.owner(song_ids, "Playlists mixing sources")?;
+        source.create_playlist(name, song_ids).await
+    }
+
+    async fn add_to_playlist(&self, playlist_id: &str, song_ids: &[String]) -> Result<()> {
+        let mut ids = song_ids.to_vec();
+        ids.push(playlist_id.to_string());
+        self.owner(&ids, "Adding songs to playlists of other sources")?;
+        self.route(playlist_id).add_to_playlist(playlist_id, song_ids).await
+    }
+
+    async fn remove_from_playlist(&self, playlist_id: &str, indexes: &[usize]) -> Result<()> {
+        self.route(playlist_id).remove_from_playlist(playlist_id, indexes).await
+    }
+
+    async fn set_playlist_public(&self, playlist_id: &str, public: bool) -> Result<()> {
+        self.route(playlist_id).set_playlist_public(playlist_id, public).await
+    }
+
+    async fn delete_playlist(&self, playlist_id: &str) -> Result<()> {
+        self.route(playlist_id).delete_playlist(playlist_id).await
+    }
+
+    fn take_skipped(&self) -> Vec<SkippedItem> {
+        self.sources().flat_map(|source| source.take_skipped()).collect()
+    }
+
+    async fn ping(&self) -> Result<()> {
+        self.primary.ping().await
+    }
+
+    async fn get_artist_info(&self, artist_id: &str, count: u32) -> Result<ArtistInfo> {
+        self.route(artist_id).get_artist_info(artist_id, count).await
+    }
+
+    fn local_path(&self, song_id: &str) -> Option<PathBuf> {
+        self.route(song_id).local_path(song_id)
+    }
+
+    async fn download(&self, song_id: &str, on_chunk: &mut OnChunk<'_>) -> Result<()> {
+        self.route(song_id).download(song_id, on_chunk).await
+    }
+
+    async fn get_music_folders(&self) -> Result<Vec<MusicFolder>> {
+        self.primary.get_music_folders().await
+    }
+
+    async fn get_indexes(&self) -> Result<Indexes> {
+        self.primary.get_indexes().await
+    }
+
+    async fn get_music_directory(&self, directory_id: &str) -> Result<MusicDirectory> {
+        self.primary.get_music_directory(directory_id).await
+    }
+
+    // Without an ID to go by, the others are asked first: local files answer quickly
+    async fn get_lyrics(&self, artist: &str, title: &str) -> Result<Option<String>> {
+        for (_, source) in &self.others {
+            if let Ok(Some(lyrics)) = source.get_lyrics(artist, title).await {
+                return Ok(Some(lyrics));
+            }
+        }
+        self.primary.get_lyrics(artist, title).await
+    }
+
+    async fn get_structured_lyrics(&self, song_id: &str) -> Result<Vec<StructuredLyrics>> {
+        self.route(song_id).get_structured_lyrics(song_id).await
+    }
+
+    async fn get_similar_songs(&self, artist_id: &str, count: u32) -> Result<Vec<Song>> {
+        self.route(artist_id).get_similar_songs(artist_id, count).await
+    }
+
+    async fn star(&self, song_ids: &[String], album_ids: &[String]) -> Result<()> {
+        for (source, ids) in self.split(song_ids, |id| id.as_str()) {
+            source.star(&ids, &[]).await?;
+        }
+        for (source, ids) in self.split(album_ids, |id| id.as_str()) {
+            source.star(&[], &ids).await?;
+        }
+        Ok(())
+    }
+
+    async fn unstar(&self, song_ids: &[String], album_ids: &[String]) -> Result<()> {
+        for (source, ids) in self.split(song_ids, |id| id.as_str()) {
+            source.unstar(&ids, &[]).await?;
+        }
+        for (source, ids) in self.split(album_ids, |id| id.as_str()) {
+            source.unstar(&[], &ids).await?;
+        }
+        Ok(())
+    }
+
+    async fn get_play_queue(&self) -> Result<Option<PlayQueue>> {
+        self.primary.get_play_queue().await
+    }
+
+    /// Only the primary's songs are saved, the primary could not play the others
+    async fn save_play_queue(&self, song_ids: &[String], current: Option<&str>, position_ms: u64) -> Result<()> {
+        let song_ids: Vec<String> = song_ids.iter().filter(|id| self.is_primary(id)).cloned().collect();
+        let current = current.filter(|id| self.is_primary(id));
+        self.primary.save_play_queue(&song_ids, current, position_ms).await
+    }
+
+    async fn now_playing(&self, song_id: &str) -> Result<()> {
+        self.route(song_id).now_playing(song_id).await
+    }
+
+    async fn scrobble(&self, plays: &[(String, u64)]) -> Result<()> {
+        for (source, plays) in self.split(plays, |(id, _)| id.as_str()) {
+            source.scrobble(&plays).await?;
+        }
+        Ok(())
+    }
+
+    async fn start_scan(&self) -> Result<ScanStatus> {
+        self.primary.start_scan().await
+    }
+
+    async fn get_scan_status(&self) -> Result<ScanStatus> {
+        self.primary.get_scan_status().await
+    }
+
+    async fn get_internet_radio_stations(&self) -> Result<Vec<InternetRadioStation>> {
+        self.primary.get_internet_radio_stations().await
+    }
+
+    async fn create_internet_radio_station(&self, name: &str, stream_url: &str, home_page_url: Option<&str>) -> Result<()> {
+        self.primary.create_internet_radio_station(name, stream_url, home_page_url).await
+    }
+
+    async fn update_internet_radio_station(&self, id: &str, name: &str, stream_url: &str, home_page_url: Option<&str>) -> Result<()> {
+        self.primary.update_internet_radio_station(id, name, stream_url, home_page_url).await
+    }
+
+    async fn delete_internet_radio_station(&self, id: &str) -> Result<()> {
+        self.primary.delete_internet_radio_station(id).await
+    }
+
+    async fn jukebox_status(&self) -> Result<JukeboxStatus> {
+        self.primary.jukebox_status().await
+    }
+
+    async fn jukebox_set(&self, song_ids: &[String]) -> Result<JukeboxStatus> {
+        if !song_ids.iter().all(|id| self.is_primary(id)) {
+            return unsupported("Playing songs of other sources on the jukebox");
+        }
+        self.primary.jukebox_set(song_ids).await
+    }
+
+    async fn jukebox_start(&self) -> Result<JukeboxStatus> {
+        self.primary.jukebox_start().await
+    }
+
+    async fn jukebox_stop(&self) -> Result<JukeboxStatus> {
+        self.primary.jukebox_stop().await
+    }
+
+    async fn jukebox_skip(&self, index: usize, offset: u32) -> Result<JukeboxStatus> {
+        self.primary.jukebox_skip(index, offset).await
+    }
+
+    async fn jukebox_set_gain(&self, gain: f32) -> Result<JukeboxStatus> {
+        self.primary.jukebox_set_gain(gain).await
+    }
+}
+
+/// Put `prefix` in front of the IDs of an item and the items it holds
+trait Namespace {
+    fn prefix(&mut self, prefix: &str);
+}
+
+fn prefix_id(id: &mut String, prefix: &str) {
+    id.insert_str(0, prefix);
+}
+
+fn prefix_optional(id: &mut Option<String>, prefix: &str) {
+    if let Some(id) = id {
+        prefix_id(id, prefix);
+    }
+}
+
+impl<T: Namespace> Namespace for Vec<T> {
+    fn prefix(&mut self, prefix: &str) {
+        self.iter_mut().for_each(|item| item.prefix(prefix));
+    }
+}
+
+impl Namespace for Artist {
+    fn prefix(&mut self, prefix: &str) {
+        prefix_id(&mut self.id, prefix);
+    }
+}
+
+impl Namespace for Album {
+    fn prefix(&mut self, prefix: &str) {
+        prefix_id(&mut self.id, prefix);
+        prefix_optional(&mut self.artist_id, prefix);
+        prefix_optional(&mut self.cover_art, prefix);
+    }
+}
+
+impl Namespace for Song {
+    fn prefix(&mut self, prefix: &str) {
+        prefix_id(&mut self.id, prefix);
+        prefix_optional(&mut self.album_id, prefix);
+        prefix_optional(&mut self.artist_id, prefix);
+        prefix_optional(&mut self.cover_art, prefix);
+    }
+}
+
+impl Namespace for AlbumDetail {
+    fn prefix(&mut self, prefix: &str) {
+        prefix_id(&mut self.id, prefix);
+        prefix_optional(&mut self.artist_id, prefix);
+        prefix_optional(&mut self.cover_art, prefix);
+        self.song.prefix(prefix);
+    }
+}
+
+impl Namespace for Playlist {
+    fn prefix(&mut self, prefix: &str) {
+        prefix_id(&mut self.id, prefix);
+    }
+}
+
+impl Namespace for PlaylistDetail {
+    fn prefix(&mut self, prefix: &str) {
+        prefix_id(&mut self.id, prefix);
+        self.entry.prefix(prefix);
+    }
+}
+
+impl Namespace for SearchResult {
+    fn prefix(&mut self, prefix: &str) {
+        self.artist.prefix(prefix);
+        self.album.prefix(prefix);
+        self.song.prefix(prefix);
+    }
+}
+
+impl Namespace for Starred {
+    fn prefix(&mut self, prefix: &str) {
+        self.artist.prefix(prefix);
+        self.album.prefix(prefix);
+        self.song.prefix(prefix);
+    }
+}
+
+/// Another server merged into the library, with its name in front of all its IDs so they
+/// cannot be mistaken for those of the primary server
+#[derive(Debug, Clone)]
+pub struct Prefixed {
+    prefix: String,
+    inner: Arc<dyn MusicSource>,
+}
+
+impl Prefixed {
+    pub fn new(prefix: impl Into<String>, inner: Arc<dyn MusicSource>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            inner,
+        }
+    }
+
+    /// The ID the server knows the item by
+    fn strip<'a>(&self, id: &'a str) -> &'a str {
+        id.strip_prefix(self.prefix.as_str()).unwrap_or(id)
+    }
+
+    fn strip_all(&self, ids: &[String]) -> Vec<String> {
+        ids.iter().map(|id| self.strip(id).to_string()).collect()
+    }
+
+    fn prefixed<T: Namespace>(&self, mut item: T) -> T {
+        item.prefix(&self.prefix);
+        item
+    }
+}
+
+#[async_trait]
+impl MusicSource for Prefixed {
+    fn with_music_folder(&self, folder_id: Option<String>) -> Arc<dyn MusicSource> {
+        Arc::new(Self::new(self.prefix.clone(), self.inner.with_music_folder(folder_id)))
+    }
+
+    fn with_transcoding(&self, transcoding: Option<Transcoding>) -> Arc<dyn MusicSource> {
+        Arc::new(Self::new(self.prefix.clone(), self.inner.with_transcoding(transcoding)))
+    }
+
+    async fn get_artists_progressive(&self, on_index: &mut (dyn FnMut(ArtistIndex) + Send)) -> Result<()> {
+        self.inner
+            .get_artists_progressive(&mut |mut index| {
+                index.artist.prefix(&self.prefix);
+                on_index(index);
+            })
+            .await
+    }
+
+    async fn get_artist(&self, artist_id: &str) -> Result<Vec<Album>> {
+        Ok(self.prefixed(self.inner.get_artist(self.strip(artist_id)).await?))
+    }
+
+    async fn get_album(&self, album_id: &str) -> Result<AlbumDetail> {
+        Ok(self.prefixed(self.inner.get_album(self.strip(album_id)).await?))
+    }
+
+    async fn get_album_list(&self, list: AlbumListType, size: u32, offset: u32) -> Result<Vec<Album>> {
+        Ok(self.prefixed(self.inner.get_album_list(list, size, offset).await?))
+    }
+
+    async fn get_cover_art(&self, cover_art_id: &str, size: Option<u32>) -> Result<Vec<u8>> {
+        self.inner.get_cover_art(self.strip(cover_art_id), size).await
+    }
+
+    fn get_stream_url(&self, song_id: &str) -> String {
+        self.inner.get_stream_url(self.strip(song_id))
+    }
+
+    async fn get_genres(&self) -> Result<Vec<Genre>> {
+        self.inner.get_genres().await
+    }
+
+    async fn get_songs_by_genre(&self, genre: &str, count: u32, offset: u32) -> Result<Vec<Song>> {
+        Ok(self.prefixed(self.inner.get_songs_by_genre(genre, count, offset).await?))
+    }
+
+    async fn search(&self, query: &str, more: Option<(SearchCategory, u32)>) -> Result<SearchResult> {
+        Ok(self.prefixed(self.inner.search(query, more).await?))
+    }
+
+    async fn get_starred(&self) -> Result<Starred> {
+        Ok(self.prefixed(self.inner.get_starred().await?))
+    }
+
+    async fn get_playlists(&self) -> Result<Vec<Playlist>> {
+        Ok(self.prefixed(self.inner.get_playlists().await?))
+    }
+
+    async fn get_playlist(&self, playlist_id: &str) -> Result<PlaylistDetail> {
+        Ok(self.prefixed(self.inner.get_playlist(self.strip(playlist_id)).await?))
+    }
+
+    async fn create_playlist(&self, name: &str, song_ids: &[String]) -> Result<()> {
+        self.inner.create_playlist(name, &self.strip_all(song_ids)).await
+    }
+
+    async fn add_to_playlist(&self, playlist_id: &str, song_ids: &[String]) -> Result<()> {
+        self.inner.add_to_playlist(self.strip(playlist_id), &self.strip_all(song_ids)).await
+    }
+
+    async fn remove_from_playlist(&self, playlist_id: &str, indexes: &[usize]) -> Result<()> {
+        self.inner.remove_from_playlist(self.strip(playlist_id), indexes).await
+    }
+
+    async fn set_playlist_public(&self, playlist_id: &str, public: bool) -> Result<()> {
+        self.inner.set_playlist_public(self.strip(playlist_id), public).await
+    }
+
+    async fn delete_playlist(&self, playlist_id: &str) -> Result<()> {
+        self.inner.delete_playlist(self.strip(playlist_id)).await
+    }
+
+    fn take_skipped(&self) -> Vec<SkippedItem> {
+        self.inner.take_skipped()
+    }
+
+    async fn get_artist_info(&self, artist_id: &str, count: u32) -> Result<ArtistInfo> {
+        let mut info = self.inner.get_artist_info(self.strip(artist_id), count).await?;
+        info.similar_artist.prefix(&self.prefix);
+        Ok(info)
+    }
+
+    async fn download(&self, song_id: &str, on_chunk: &mut OnChunk<'_>) -> Result<()> {
+        self.inner.download(self.strip(song_id), on_chunk).await
+    }
+
+    async fn get_lyrics(&self, artist: &str, title: &str) -> Result<Option<String>> {
+        self.inner.get_lyrics(artist, title).await
+    }
+
+    async fn get_structured_lyrics(&self, song_id: &str) -> Result<Vec<StructuredLyrics>> {
+        self.inner.get_structured_lyrics(self.strip(song_id)).await
+    }
+
+    async fn get_similar_songs(&self, artist_id: &str, count: u32) -> Result<Vec<Song>> {
+        Ok(self.prefixed(self.inner.get_similar_songs(self.strip(artist_id), count).await?))
+    }
+
+    async fn star(&self, song_ids: &[String], album_ids: &[String]) -> Result<()> {
+        self.inner.star(&self.strip_all(song_ids), &self.strip_all(album_ids)).await
+    }
+
+    async fn unstar(&self, song_ids: &[String], album_ids: &[String]) -> Result<()> {
+        self.inner.unstar(&self.strip_all(song_ids), &self.strip_all(album_ids)).await
+    }
+
+    async fn now_playing(&self, song_id: &str) -> Result<()> {
+        self.inner.now_playing(self.strip(song_id)).await
+    }
+
+    async fn scrobble(&self, plays: &[(String, u64)]) -> Result<()> {
+        let plays: Vec<_> = plays.iter().map(|(id, time)| (self.strip(id).to_string(), *time)).collect();
+        self.inner.scrobble(&plays).await
+    }
+}
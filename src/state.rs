@@ -0,0 +1,232 @@
+use subsonic::Song;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Maximum number of entries kept in the local play history
+const MAX_HISTORY: usize = 200;
+
+/// Maximum number of remembered search queries
+const MAX_SEARCH_HISTORY: usize = 100;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HistoryEntry {
+    pub song: Song,
+    /// Unix timestamp of when playback started
+    pub played_at: u64,
+}
+
+/// The current Unix time in seconds
+pub fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// A play that has not been submitted to the server yet
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PendingScrobble {
+    pub song_id: String,
+    /// Unix time in milliseconds when playback started
+    pub time: u64,
+}
+
+/// The playback session, saved periodically so it can be resumed after a restart
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Session {
+    pub current_song: Option<Song>,
+    /// Playback position in seconds
+    pub position: f32,
+    /// Upcoming songs, in order
+    pub queue: Vec<Song>,
+    pub volume: Option<f64>,
+    /// Unix time in seconds when the session was saved
+    #[serde(default)]
+    pub saved_at: u64,
+}
+
+/// Version of the state file format written by this build. Bump it whenever the
+/// format changes incompatibly and add a step to `State::migrate`.
+const STATE_VERSION: u32 = 1;
+
+/// Local application state that survives restarts.
+///
+/// Stored as JSON with a top-level `version` field. Files written before the
+/// field existed are treated as version 0.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct State {
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub history: Vec<HistoryEntry>,
+    /// Previous search queries, most recent first
+    #[serde(default)]
+    pub search_history: Vec<String>,
+    #[serde(default)]
+    pub session: Option<Session>,
+    /// Scrobbles waiting for the server to become reachable, oldest first
+    #[serde(default)]
+    pub pending_scrobbles: Vec<PendingScrobble>,
+    /// Volume adjustments in percent for songs that were made louder or quieter, by song ID
+    #[serde(default)]
+    pub song_volumes: HashMap<String, f64>,
+    /// Songs that are never scrobbled nor added to the history, like sleep sounds, by song ID
+    #[serde(default)]
+    pub unscrobbled: HashSet<String>,
+    /// The state must not be written back, because the file was written by a
+    /// newer highpass or the state is a throwaway one for `--mock`
+    #[serde(skip)]
+    read_only: bool,
+}
+
+impl State {
+    /// Load the state file, falling back to an empty state if it is missing or unreadable
+    pub fn load() -> Self {
+        let Some(path) = Self::get_state_path() else {
+            return Self::default();
+        };
+
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) => {
+                debug!("No state file at {}: {}", path.display(), e);
+                return Self::default();
+            }
+        };
+
+        match Self::parse(&content) {
+            Ok(state) => {
+                info!("Loaded state (version {}) from: {}", state.version, path.display());
+                state
+            }
+            Err(e) => {
+                // Keep the unreadable file around instead of silently overwriting it
+                let backup = path.with_extension("json.bak");
+                warn!("Ignoring unreadable state file {} ({}), moving it to {}", path.display(), e, backup.display());
+                if let Err(e) = fs::rename(&path, &backup) {
+                    warn!("Failed to back up state file: {}", e);
+                }
+                Self::default()
+            }
+        }
+    }
+
+    /// Parse a state file of any known version, migrating it to the current one
+    fn parse(content: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut value: serde_json::Value = serde_json::from_str(content)?;
+        let version = value.get("version").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+
+        if version > STATE_VERSION {
+            warn!(
+                "State file has version {} but this build only knows version {}, it will not be modified",
+                version, STATE_VERSION
+            );
+            let mut state: Self = serde_json::from_value(value).unwrap_or_default();
+            state.read_only = true;
+            return Ok(state);
+        }
+
+        for from in version..STATE_VERSION {
+            info!("Migrating state file from version {} to {}", from, from + 1);
+            Self::migrate(from, &mut value)?;
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Upgrade the raw JSON of a state file from version `from` to `from + 1`
+    fn migrate(from: u32, value: &mut serde_json::Value) -> Result<(), Box<dyn std::error::Error>> {
+        let object = value.as_object_mut().ok_or("state file is not a JSON object")?;
+        match from {
+            // Unversioned files only lacked the version field
+            0 => {}
+            _ => return Err(format!("no migration from state version {}", from).into()),
+        }
+        object.insert("version".to_string(), (from + 1).into());
+        Ok(())
+    }
+
+    /// An empty state that is never saved
+    pub fn ephemeral() -> Self {
+        Self {
+            read_only: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn save(&self) {
+        if self.read_only {
+            debug!("Not saving read-only state");
+            return;
+        }
+        let Some(path) = Self::get_state_path() else {
+            return;
+        };
+
+        // Write to a temporary file first so a crash never leaves a truncated state file
+        let temp_path = path.with_extension("json.tmp");
+        let result = path
+            .parent()
+            .map(fs::create_dir_all)
+            .unwrap_or(Ok(()))
+            .and_then(|_| {
+                let state = Self {
+                    version: STATE_VERSION,
+                    ..self.clone()
+                };
+                let content = serde_json::to_string(&state)?;
+                fs::write(&temp_path, content)?;
+                fs::rename(&temp_path, &path)
+            });
+        if let Err(e) = result {
+            warn!("Failed to save state to {}: {}", path.display(), e);
+        }
+    }
+
+    /// Record that a song started playing, most recent first
+    pub fn record_played(&mut self, song: &Song) {
+        let played_at = unix_time();
+
+        // Replaying a song moves it to the top instead of listing it twice
+        self.history.retain(|entry| entry.song.id != song.id);
+        self.history.insert(0, HistoryEntry {
+            song: song.clone(),
+            played_at,
+        });
+        self.history.truncate(MAX_HISTORY);
+    }
+
+    /// Remember a search query, most recent first
+    pub fn record_search(&mut self, query: &str) {
+        self.search_history.retain(|q| q != query);
+        self.search_history.insert(0, query.to_string());
+        self.search_history.truncate(MAX_SEARCH_HISTORY);
+    }
+
+    /// Get the state file path (~/.local/state/highpass/state.json on Unix)
+    fn get_state_path() -> Option<PathBuf> {
+        #[cfg(unix)]
+        {
+            std::env::var("XDG_STATE_HOME")
+                .map(PathBuf::from)
+                .or_else(|_| std::env::var("HOME").map(|home| PathBuf::from(home).join(".local").join("state")))
+                .ok()
+                .map(|dir| dir.join("highpass").join("state.json"))
+        }
+
+        #[cfg(windows)]
+        {
+            std::env::var("LOCALAPPDATA")
+                .ok()
+                .map(|dir| PathBuf::from(dir).join("highpass").join("state.json"))
+        }
+
+        #[cfg(not(any(unix, windows)))]
+        {
+            None
+        }
+    }
+}
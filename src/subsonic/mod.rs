@@ -1,5 +0,0 @@
-pub mod client;
-pub mod types;
-
-pub use client::SubsonicClient;
-pub use types::*;
\ No newline at end of file
@@ -0,0 +1,196 @@
+//! What the machine around the player is doing. On Linux, logind tells over D-Bus when the
+//! system is about to suspend, with a delay lock giving playback a moment to pause first,
+//! and when the screen of the session is locked. It also keeps the system from suspending
+//! for idleness while music plays. Elsewhere nothing is watched or held.
+
+use crate::Message;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::watch;
+
+/// Sent to the app as [`Message::System`]
+#[derive(Debug, Clone)]
+pub enum SystemEvent {
+    /// The system is about to suspend or hibernate
+    Suspending,
+    /// The system woke up again
+    Resumed,
+    /// The screen was locked
+    Locked,
+    Unlocked,
+}
+
+/// Pass suspend and resume to the app for as long as it runs
+#[cfg(target_os = "linux")]
+pub fn watch_suspend(sender: UnboundedSender<Message>) {
+    tokio::spawn(async move {
+        if let Err(e) = logind::watch_sleep(&sender).await {
+            log::warn!("Not watching for suspend: {}", e);
+        }
+    });
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn watch_suspend(_sender: UnboundedSender<Message>) {}
+
+/// Pass locking and unlocking the screen to the app for as long as it runs
+#[cfg(target_os = "linux")]
+pub fn watch_lock(sender: UnboundedSender<Message>) {
+    tokio::spawn(async move {
+        if let Err(e) = logind::watch_lock(&sender).await {
+            log::warn!("Not watching for the screen lock: {}", e);
+        }
+    });
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn watch_lock(_sender: UnboundedSender<Message>) {}
+
+/// Holds an idle inhibitor while playing, so a long album isn't cut short by the system
+/// suspending because nobody touched the keyboard. Released when playback pauses or stops.
+pub struct IdleInhibitor {
+    playing: watch::Sender<bool>,
+}
+
+impl IdleInhibitor {
+    pub fn new() -> Self {
+        let (playing, _changes) = watch::channel(false);
+        #[cfg(target_os = "linux")]
+        tokio::spawn(async move {
+            if let Err(e) = logind::inhibit_idle(_changes).await {
+                log::warn!("Not keeping the system awake while playing: {}", e);
+            }
+        });
+        Self { playing }
+    }
+
+    /// Take the inhibitor when playback starts and release it when it ends
+    pub fn set_playing(&self, playing: bool) {
+        self.playing.send_if_modified(|held| std::mem::replace(held, playing) != playing);
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod logind {
+    use super::SystemEvent;
+    use crate::Message;
+    use futures_util::StreamExt;
+    use log::{debug, info};
+    use std::time::Duration;
+    use tokio::sync::mpsc::UnboundedSender;
+    use tokio::sync::watch;
+    use zbus::zvariant::{OwnedFd, OwnedObjectPath};
+    use zbus::Connection;
+
+    /// How long the delay lock is held once suspend is announced, for the app to pause
+    const SLEEP_GRACE: Duration = Duration::from_millis(500);
+
+    #[zbus::proxy(
+        interface = "org.freedesktop.login1.Manager",
+        default_service = "org.freedesktop.login1",
+        default_path = "/org/freedesktop/login1"
+    )]
+    trait Manager {
+        /// Hold off `what` for as long as the returned file descriptor is open
+        fn inhibit(&self, what: &str, who: &str, why: &str, mode: &str) -> zbus::Result<OwnedFd>;
+
+        /// The session with this ID, "auto" for the one we run in or else the user's
+        /// graphical one
+        fn get_session(&self, session_id: &str) -> zbus::Result<OwnedObjectPath>;
+
+        #[zbus(signal)]
+        fn prepare_for_sleep(&self, start: bool) -> zbus::Result<()>;
+    }
+
+    #[zbus::proxy(interface = "org.freedesktop.login1.Session", default_service = "org.freedesktop.login1")]
+    trait Session {
+        /// Set by the desktop's screen locker while the screen is locked
+        #[zbus(property)]
+        fn locked_hint(&self) -> zbus::Result<bool>;
+    }
+
+    /// A lock that lets suspend wait until playback is paused, `None` if logind refuses
+    async fn delay_lock(manager: &ManagerProxy<'_>) -> Option<OwnedFd> {
+        match manager.inhibit("sleep", "highpass", "Pausing playback", "delay").await {
+            Ok(lock) => Some(lock),
+            Err(e) => {
+                debug!("No delay lock for suspend: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Block idle actions for as long as `playing` is true, until the sender is dropped
+    pub async fn inhibit_idle(mut playing: watch::Receiver<bool>) -> zbus::Result<()> {
+        let connection = Connection::system().await?;
+        let manager = ManagerProxy::new(&connection).await?;
+        loop {
+            let _lock = if *playing.borrow_and_update() {
+                match manager.inhibit("idle", "highpass", "Playing music", "block").await {
+                    Ok(lock) => {
+                        debug!("Holding the idle inhibitor");
+                        Some(lock)
+                    }
+                    Err(e) => {
+                        debug!("No idle inhibitor: {}", e);
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+            if playing.changed().await.is_err() {
+                return Ok(());
+            }
+        }
+    }
+
+    pub async fn watch_sleep(sender: &UnboundedSender<Message>) -> zbus::Result<()> {
+        let connection = Connection::system().await?;
+        let manager = ManagerProxy::new(&connection).await?;
+        let mut signals = manager.receive_prepare_for_sleep().await?;
+        let mut lock = delay_lock(&manager).await;
+        info!("Watching for suspend");
+        while let Some(signal) = signals.next().await {
+            let event = if signal.args()?.start {
+                SystemEvent::Suspending
+            } else {
+                SystemEvent::Resumed
+            };
+            info!("System event: {:?}", event);
+            if sender.send(Message::System(event.clone())).is_err() {
+                break;
+            }
+            match event {
+                SystemEvent::Suspending => {
+                    tokio::time::sleep(SLEEP_GRACE).await;
+                    lock = None;
+                }
+                SystemEvent::Resumed if lock.is_none() => lock = delay_lock(&manager).await,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn watch_lock(sender: &UnboundedSender<Message>) -> zbus::Result<()> {
+        let connection = Connection::system().await?;
+        let manager = ManagerProxy::new(&connection).await?;
+        let path = manager.get_session("auto").await?;
+        let session = SessionProxy::builder(&connection).path(path)?.build().await?;
+        let mut changes = session.receive_locked_hint_changed().await;
+        let mut locked = session.locked_hint().await?;
+        info!("Watching for the screen lock");
+        while let Some(change) = changes.next().await {
+            if change.get().await? == locked {
+                continue;
+            }
+            locked = !locked;
+            let event = if locked { SystemEvent::Locked } else { SystemEvent::Unlocked };
+            info!("System event: {:?}", event);
+            if sender.send(Message::System(event)).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,65 @@
+use crossterm::{
+    cursor,
+    event::{DisableBracketedPaste, EnableBracketedPaste},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use log::{error, info};
+use std::io;
+
+/// Puts the terminal into raw mode on the alternate screen and restores it when
+/// dropped, so early returns and errors never leave the user's terminal broken.
+pub struct TerminalGuard;
+
+impl TerminalGuard {
+    pub fn new() -> io::Result<Self> {
+        enable_raw_mode()?;
+        info!("Enabled raw mode");
+
+        // Construct the guard before entering the alternate screen so a failure there
+        // still disables raw mode again
+        let guard = Self;
+        execute!(io::stdout(), EnterAlternateScreen, EnableBracketedPaste)?;
+        info!("Entered alternate screen");
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore();
+        info!("Terminal restored");
+    }
+}
+
+/// Leave the alternate screen and raw mode. Safe to call more than once.
+pub fn restore() {
+    let _ = disable_raw_mode();
+    let _ = execute!(io::stdout(), DisableBracketedPaste, LeaveAlternateScreen, cursor::Show);
+}
+
+/// Restore the terminal before the default panic message is printed, so it is
+/// readable and the shell keeps working after a crash.
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |panic_info| {
+        error!("Panic: {}", panic_info);
+
+        // Panics in background tasks are caught by tokio and the UI keeps running,
+        // only a panic on the main thread takes the application down
+        if std::thread::current().name() == Some("main") {
+            restore();
+        }
+        default_hook(panic_info);
+    }));
+}
+
+/// Whether the terminal can display images (kitty graphics, iTerm2 or sixel capable
+/// terminals that identify themselves through the environment)
+pub fn supports_graphics() -> bool {
+    let var = |name| std::env::var(name).unwrap_or_default();
+    std::env::var_os("KITTY_WINDOW_ID").is_some()
+        || var("TERM") == "xterm-kitty"
+        || matches!(var("TERM_PROGRAM").as_str(), "WezTerm" | "iTerm.app" | "ghostty")
+        || var("TERM").contains("sixel")
+}
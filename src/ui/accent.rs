@@ -0,0 +1,42 @@
+use ratatui::style::Color;
+use std::collections::HashMap;
+
+/// Covers are scaled down to this many pixels per side before counting colors
+const SAMPLE_SIZE: u32 = 24;
+
+/// The accents are brightened to at least this luma, so they stay visible on dark terminals
+const MIN_LUMA: f32 = 110.0;
+
+/// The dominant color of an encoded cover image, preferring saturated colors over greys
+pub fn dominant_color(cover_art: &[u8]) -> Option<Color> {
+    let image = image::load_from_memory(cover_art).ok()?;
+    let pixels = image.thumbnail(SAMPLE_SIZE, SAMPLE_SIZE).to_rgb8();
+
+    // Bucket by the top 4 bits of each channel, summing the exact colors for averaging
+    let mut buckets: HashMap<(u8, u8, u8), (f32, [f32; 3])> = HashMap::new();
+    for pixel in pixels.pixels() {
+        let [r, g, b] = pixel.0;
+        let max = r.max(g).max(b) as f32;
+        let min = r.min(g).min(b) as f32;
+        let saturation = if max > 0.0 { (max - min) / max } else { 0.0 };
+        let weight = 0.1 + saturation;
+
+        let bucket = buckets.entry((r >> 4, g >> 4, b >> 4)).or_default();
+        bucket.0 += weight;
+        bucket.1[0] += r as f32 * weight;
+        bucket.1[1] += g as f32 * weight;
+        bucket.1[2] += b as f32 * weight;
+    }
+
+    let (weight, sum) = buckets.into_values().max_by(|a, b| a.0.total_cmp(&b.0))?;
+    let [mut r, mut g, mut b] = sum.map(|channel| channel / weight);
+
+    let luma = 0.299 * r + 0.587 * g + 0.114 * b;
+    if luma < MIN_LUMA {
+        let lift = MIN_LUMA - luma;
+        r += lift;
+        g += lift;
+        b += lift;
+    }
+    Some(Color::Rgb(r.min(255.0) as u8, g.min(255.0) as u8, b.min(255.0) as u8))
+}
@@ -0,0 +1,75 @@
+use crate::ui::popup::{Popup, SelectList};
+use crate::ui::text::strip_html;
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, ListItem, Paragraph, Wrap},
+};
+use subsonic::{Artist, ArtistInfo};
+
+/// Popup with the biography of an artist and the similar artists, the selected one of
+/// which can be gone to in the tree
+#[derive(Debug, Clone)]
+pub struct ArtistInfoPanel {
+    pub artist: Artist,
+    pub list: SelectList,
+}
+
+impl ArtistInfoPanel {
+    pub fn new(artist: Artist) -> Self {
+        Self {
+            artist,
+            list: SelectList::default(),
+        }
+    }
+
+    /// The similar artist under the cursor
+    pub fn selected<'a>(&self, info: Option<&'a ArtistInfo>) -> Option<&'a Artist> {
+        info?.similar_artist.get(self.list.selected()?)
+    }
+}
+
+pub struct ArtistInfoWidget;
+
+impl ArtistInfoWidget {
+    /// `info` is `None` while it is still loading
+    pub fn render(panel: &mut ArtistInfoPanel, info: Option<&ArtistInfo>, area: Rect, buf: &mut Buffer) {
+        let inner = Popup::new(panel.artist.name.as_str(), 70, area.height.saturating_sub(4).min(24))
+            .hint("Enter: Go to  Esc: Close")
+            .render(area, buf);
+        let Some(info) = info else {
+            Line::from("Loading…")
+                .style(Style::default().fg(Color::DarkGray))
+                .centered()
+                .render(inner, buf);
+            return;
+        };
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(inner);
+        let biography = info.biography.as_deref().map(strip_html).filter(|b| !b.is_empty());
+        let mut text = Text::from(biography.unwrap_or_else(|| "No biography".to_string()));
+        if let Some(url) = &info.last_fm_url {
+            text.push_line("");
+            text.push_line(Line::from(url.as_str()).style(Style::default().fg(Color::DarkGray)));
+        }
+        Paragraph::new(text).wrap(Wrap { trim: true }).render(layout[0], buf);
+
+        let similar = &info.similar_artist;
+        if panel.list.selected().is_none() {
+            panel.list.reset(similar.len());
+        }
+        let block = Block::default().title("Similar artists").borders(Borders::TOP);
+        if similar.is_empty() {
+            let empty = block.inner(layout[1]);
+            block.render(layout[1], buf);
+            Line::from("None in the library")
+                .style(Style::default().fg(Color::DarkGray))
+                .render(empty, buf);
+            return;
+        }
+        let items = similar.iter().map(|artist| ListItem::new(artist.name.as_str()));
+        panel.list.render(items, Some(block), layout[1], buf);
+    }
+}
@@ -0,0 +1,26 @@
+//! Comparing names the way people read them rather than by code point: "Björk" sorts
+//! and matches like "bjork", and "The Beatles" is found under B.
+
+use deunicode::deunicode;
+
+/// Lowercase ASCII transliteration of `text`, for matching typed queries
+pub fn fold(text: &str) -> String {
+    deunicode(text).to_lowercase()
+}
+
+/// What to sort an artist name by: folded, and without a leading "The "
+pub fn sort_key(name: &str) -> String {
+    let folded = fold(name);
+    match folded.strip_prefix("the ") {
+        Some(rest) if !rest.trim().is_empty() => rest.trim_start().to_string(),
+        _ => folded,
+    }
+}
+
+/// Index section a sort key belongs to: its first letter, or "#" for anything else
+pub fn index_letter(key: &str) -> String {
+    match key.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => c.to_ascii_uppercase().to_string(),
+        _ => "#".to_string(),
+    }
+}
@@ -0,0 +1,20 @@
+use crate::ui::popup::Popup;
+use ratatui::{
+    prelude::*,
+    widgets::{Paragraph, Wrap},
+};
+
+/// A yes/no popup in front of a destructive action
+pub struct ConfirmWidget;
+
+impl ConfirmWidget {
+    pub fn render(question: &str, area: Rect, buf: &mut Buffer) {
+        let inner = Popup::new("Confirm", 50, 6)
+            .hint("y: Yes  n: No  a: Yes, don't ask again")
+            .render(area, buf);
+        Paragraph::new(question)
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true })
+            .render(inner, buf);
+    }
+}
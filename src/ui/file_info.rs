@@ -0,0 +1,21 @@
+use crate::ui::popup::Popup;
+use ratatui::{
+    prelude::*,
+    widgets::{Paragraph, Wrap},
+};
+use std::path::Path;
+
+/// Where the file of a downloaded or local song is, to copy it elsewhere
+pub struct FileInfoWidget;
+
+impl FileInfoWidget {
+    pub fn render(path: &Path, area: Rect, buf: &mut Buffer) {
+        let inner = Popup::new("File", 70, 6)
+            .hint("Enter: Open folder  y: Copy  Esc: Close")
+            .render(area, buf);
+        Paragraph::new(path.display().to_string())
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true })
+            .render(inner, buf);
+    }
+}
@@ -0,0 +1,255 @@
+use subsonic::{Album, Artist, Song};
+use crate::ui::collate;
+use crate::ui::popup::{Popup, SelectList};
+use crate::ui::input::{InputKey, LineInput};
+use crate::ui::tree::TreeState;
+use crossterm::event::KeyEvent;
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, ListItem, Paragraph, Wrap},
+};
+
+/// Upper bound on rendered matches, the list is only a few screens high anyway
+const MAX_MATCHES: usize = 200;
+
+#[derive(Debug, Clone)]
+pub enum FinderEntry {
+    Artist(Artist),
+    Album(Album),
+    Song(Song),
+}
+
+impl FinderEntry {
+    fn label(&self) -> String {
+        match self {
+            FinderEntry::Artist(artist) => format!("👤 {}", artist.name),
+            FinderEntry::Album(album) => format!(
+                "💿 {} - {}",
+                album.name,
+                album.artist.as_deref().unwrap_or("Unknown Artist")
+            ),
+            FinderEntry::Song(song) => format!(
+                "♪ {} - {}",
+                song.title,
+                song.artist.as_deref().unwrap_or("Unknown Artist")
+            ),
+        }
+    }
+}
+
+/// Fuzzy finder over everything the tree has cached
+#[derive(Debug, Clone)]
+pub struct FinderState {
+    pub query: LineInput,
+    /// Each entry with its label and the text queries are matched against
+    entries: Vec<(FinderEntry, String, String)>,
+    /// Match ignoring accents, like the tree sorts artists
+    fold: bool,
+    /// Indices into `entries`, best match first
+    matches: Vec<usize>,
+    pub list: SelectList,
+}
+
+impl FinderState {
+    pub fn new(tree: &TreeState) -> Self {
+        let filter = tree.starred_filter.as_ref();
+        let mut entries = Vec::new();
+
+        for artist in &tree.artists {
+            if filter.is_none_or(|f| f.is_artist_starred(artist)) {
+                entries.push(FinderEntry::Artist(artist.clone()));
+            }
+        }
+        for album in tree.artist_albums.values().flatten() {
+            if filter.is_none_or(|f| f.is_album_starred(album)) {
+                entries.push(FinderEntry::Album(album.clone()));
+            }
+        }
+        for song in tree.album_songs.values().flatten() {
+            if filter.is_none_or(|f| f.is_song_starred(song)) {
+                entries.push(FinderEntry::Song(song.clone()));
+            }
+        }
+
+        let mut state = Self {
+            query: LineInput::new(),
+            entries: entries.into_iter().map(|e| {
+                let label = e.label();
+                let text = if tree.collate_artists { collate::fold(&label) } else { label.clone() };
+                (e, label, text)
+            }).collect(),
+            fold: tree.collate_artists,
+            matches: Vec::new(),
+            list: SelectList::default(),
+        };
+        state.update_matches();
+        state
+    }
+
+    /// Apply an editing key, returns whether it was one
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        match self.query.handle_key(key) {
+            InputKey::Edited => self.update_matches(),
+            InputKey::Moved => {}
+            InputKey::Unhandled => return false,
+        }
+        true
+    }
+
+    pub fn paste(&mut self, text: &str) {
+        self.query.insert_str(text);
+        self.update_matches();
+    }
+
+    pub fn next(&mut self) {
+        self.list.next(self.matches.len());
+    }
+
+    pub fn previous(&mut self) {
+        self.list.previous(self.matches.len());
+    }
+
+    pub fn selected(&self) -> Option<&FinderEntry> {
+        self.list
+            .selected()
+            .and_then(|i| self.matches.get(i))
+            .map(|&i| &self.entries[i].0)
+    }
+
+    fn update_matches(&mut self) {
+        let query = self.query.text();
+        let query = if self.fold { collate::fold(query) } else { query.to_string() };
+        let mut scored: Vec<(i64, usize)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (_, _, text))| fuzzy_score(&query, text).map(|score| (score, i)))
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        self.matches = scored.into_iter().take(MAX_MATCHES).map(|(_, i)| i).collect();
+        self.list.reset(self.matches.len());
+    }
+}
+
+/// Score `text` against `pattern` as a case-insensitive subsequence match.
+/// Consecutive characters and matches at word starts score higher; `None` means no match.
+pub fn fuzzy_score(pattern: &str, text: &str) -> Option<i64> {
+    let mut score = 0;
+    let mut text_chars = text.chars().flat_map(char::to_lowercase).enumerate().peekable();
+    let mut previous_match: Option<usize> = None;
+    let mut previous_char = ' ';
+
+    for p in pattern.chars().flat_map(char::to_lowercase) {
+        if p == ' ' {
+            continue;
+        }
+        loop {
+            let (i, c) = text_chars.next()?;
+            let at_word_start = !previous_char.is_alphanumeric();
+            previous_char = c;
+            if c == p {
+                score += 1;
+                if previous_match.is_some_and(|m| m + 1 == i) {
+                    score += 5;
+                }
+                if at_word_start {
+                    score += 3;
+                }
+                previous_match = Some(i);
+                break;
+            }
+        }
+    }
+
+    // Prefer shorter labels when the match quality is otherwise equal
+    Some(score * 100 - text.len() as i64)
+}
+
+pub struct FinderWidget;
+
+impl FinderWidget {
+    pub fn render(state: &mut FinderState, area: Rect, buf: &mut Buffer) {
+        let inner = Popup::new("Find", 80, area.height * 7 / 10)
+            .hint("Enter: Play  Tab: Enqueue  →: Go to  Esc: Close")
+            .style(Style::default())
+            .render(area, buf);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(1)])
+            .split(inner);
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(rows[1]);
+
+        let mut spans = vec![Span::raw("> ")];
+        spans.extend(state.query.spans(true));
+        spans.push(Span::raw(format!("  ({} matches)", state.matches.len())));
+        Paragraph::new(Line::from(spans))
+            .style(Style::default().fg(Color::Yellow))
+            .render(rows[0], buf);
+
+        let items: Vec<ListItem> = state
+            .matches
+            .iter()
+            .map(|&i| ListItem::new(state.entries[i].1.clone()))
+            .collect();
+        let block = Block::default().borders(Borders::TOP | Borders::RIGHT);
+        state.list.render(items, Some(block), columns[0], buf);
+
+        Paragraph::new(state.selected().map(Self::preview).unwrap_or_default())
+            .block(Block::default().title("Preview").borders(Borders::TOP))
+            .wrap(Wrap { trim: true })
+            .render(columns[1], buf);
+    }
+
+    fn preview(entry: &FinderEntry) -> String {
+        let mut lines = Vec::new();
+        match entry {
+            FinderEntry::Artist(artist) => {
+                lines.push(format!("Artist: {}", artist.name));
+                if let Some(count) = artist.album_count {
+                    lines.push(format!("Albums: {}", count));
+                }
+            }
+            FinderEntry::Album(album) => {
+                lines.push(format!("Album:  {}", album.name));
+                lines.push(format!("Artist: {}", album.artist.as_deref().unwrap_or("Unknown Artist")));
+                if let Some(year) = album.year {
+                    lines.push(format!("Year:   {}", year));
+                }
+                if let Some(genre) = &album.genre {
+                    lines.push(format!("Genre:  {}", genre));
+                }
+                if let Some(count) = album.song_count {
+                    lines.push(format!("Songs:  {}", count));
+                }
+                if let Some(duration) = album.duration {
+                    lines.push(format!("Length: {}:{:02}", duration / 60, duration % 60));
+                }
+            }
+            FinderEntry::Song(song) => {
+                lines.push(format!("Title:  {}", song.title));
+                lines.push(format!("Artist: {}", song.artist.as_deref().unwrap_or("Unknown Artist")));
+                lines.push(format!("Album:  {}", song.album.as_deref().unwrap_or("Unknown Album")));
+                if let Some(track) = song.track {
+                    lines.push(format!("Track:  {}", track));
+                }
+                if let Some(year) = song.year {
+                    lines.push(format!("Year:   {}", year));
+                }
+                if let Some(genre) = &song.genre {
+                    lines.push(format!("Genre:  {}", genre));
+                }
+                if let Some(duration) = song.duration {
+                    lines.push(format!("Length: {}:{:02}", duration / 60, duration % 60));
+                }
+                if let Some(bit_rate) = song.bit_rate {
+                    lines.push(format!("Bitrate: {} kbps", bit_rate));
+                }
+            }
+        }
+        lines.join("\n")
+    }
+}
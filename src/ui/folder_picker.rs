@@ -0,0 +1,25 @@
+use crate::ui::popup::{Popup, SelectList};
+use ratatui::{prelude::*, widgets::ListItem};
+use subsonic::MusicFolder;
+
+pub struct MusicFolderPickerWidget;
+
+impl MusicFolderPickerWidget {
+    /// List "All folders" followed by the server's music folders, the one the library is
+    /// listed from marked. `folders` is `None` while they are still loading.
+    pub fn render(folders: Option<&[MusicFolder]>, current: Option<&MusicFolder>, list: &mut SelectList, area: Rect, buf: &mut Buffer) {
+        let folders = folders.unwrap_or_default();
+        let height = (folders.len() as u16 + 1).min(area.height / 2) + 4;
+        let inner = Popup::new("Music folder", 40, height)
+            .hint("Enter: Switch  Esc: Cancel")
+            .render(area, buf);
+
+        let item = |label: &str, selected: bool| {
+            let marker = if selected { "● " } else { "  " };
+            ListItem::new(format!("{}{}", marker, label))
+        };
+        let items = std::iter::once(item("All folders", current.is_none()))
+            .chain(folders.iter().map(|folder| item(folder.label(), current == Some(folder))));
+        list.render(items, None, inner, buf);
+    }
+}
@@ -0,0 +1,95 @@
+use crate::ui::input::LineInput;
+use crate::ui::popup::Popup;
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Paragraph},
+};
+
+#[derive(Debug, Clone)]
+pub struct FormField {
+    pub label: String,
+    pub input: LineInput,
+}
+
+/// A small modal form with labelled single-line text fields
+#[derive(Debug, Clone)]
+pub struct FormState {
+    pub title: String,
+    pub fields: Vec<FormField>,
+    pub focused: usize,
+}
+
+impl FormState {
+    pub fn new(title: &str, labels: &[&str]) -> Self {
+        Self {
+            title: title.to_string(),
+            fields: labels
+                .iter()
+                .map(|label| FormField {
+                    label: label.to_string(),
+                    input: LineInput::new(),
+                })
+                .collect(),
+            focused: 0,
+        }
+    }
+
+    pub fn with_values(mut self, values: &[&str]) -> Self {
+        for (field, value) in self.fields.iter_mut().zip(values) {
+            field.input.set_text(value);
+        }
+        self
+    }
+
+    pub fn value(&self, index: usize) -> &str {
+        self.fields.get(index).map(|f| f.input.text().trim()).unwrap_or("")
+    }
+
+    pub fn next_field(&mut self) {
+        if !self.fields.is_empty() {
+            self.focused = (self.focused + 1) % self.fields.len();
+        }
+    }
+
+    pub fn previous_field(&mut self) {
+        if !self.fields.is_empty() {
+            self.focused = if self.focused == 0 {
+                self.fields.len() - 1
+            } else {
+                self.focused - 1
+            };
+        }
+    }
+
+    pub fn focused_input(&mut self) -> Option<&mut LineInput> {
+        self.fields.get_mut(self.focused).map(|field| &mut field.input)
+    }
+}
+
+pub struct FormWidget;
+
+impl FormWidget {
+    pub fn render(state: &FormState, area: Rect, buf: &mut Buffer) {
+        let height = state.fields.len() as u16 * 3 + 3;
+        let inner = Popup::new(state.title.as_str(), 60, height)
+            .hint("Tab: Next field  Enter: Save  Esc: Cancel")
+            .render(area, buf);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(state.fields.iter().map(|_| Constraint::Length(3)))
+            .split(inner);
+
+        for (i, field) in state.fields.iter().enumerate() {
+            let style = if i == state.focused {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default().fg(Color::Gray)
+            };
+            Paragraph::new(Line::from(field.input.spans(i == state.focused)))
+                .block(Block::default().title(field.label.as_str()).borders(Borders::ALL))
+                .style(style)
+                .render(rows[i], buf);
+        }
+    }
+}
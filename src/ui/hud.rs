@@ -0,0 +1,100 @@
+use subsonic::{Album, Artist, Song};
+use crate::ui::tree::TreeState;
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+use std::collections::VecDeque;
+use std::mem::size_of;
+use std::time::{Duration, Instant};
+
+/// Frame timestamps older than this are not counted towards the FPS
+const FPS_WINDOW: Duration = Duration::from_secs(1);
+
+/// Developer overlay with runtime numbers that help diagnose sluggishness
+#[derive(Debug, Clone, Default)]
+pub struct DebugHud {
+    frames: VecDeque<Instant>,
+    /// How long handling the last mpv event took
+    pub mpv_lag: Duration,
+    /// Messages waiting in the channel at the start of the last frame
+    pub queued_messages: usize,
+}
+
+/// The numbers shown by the overlay, gathered by the app each frame
+#[derive(Debug, Clone, Default)]
+pub struct HudStats {
+    pub tasks: usize,
+    /// Rough size of the cached artists, albums and songs in bytes
+    pub cache_bytes: usize,
+    pub cover_art_bytes: usize,
+}
+
+impl DebugHud {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_frame(&mut self) {
+        let now = Instant::now();
+        self.frames.push_back(now);
+        while self.frames.front().is_some_and(|t| now.duration_since(*t) > FPS_WINDOW) {
+            self.frames.pop_front();
+        }
+    }
+
+    pub fn fps(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+/// Estimate the memory held by the tree's metadata cache. Counts the structs and
+/// their most common strings, so it is a lower bound rather than an exact figure.
+pub fn estimate_cache_bytes(tree: &TreeState) -> usize {
+    let artists: usize = tree
+        .artists
+        .iter()
+        .map(|a| size_of::<Artist>() + a.id.len() + a.name.len())
+        .sum();
+    let albums: usize = tree
+        .artist_albums
+        .values()
+        .flatten()
+        .map(|a| size_of::<Album>() + a.id.len() + a.name.len())
+        .sum();
+    let songs: usize = tree
+        .album_songs
+        .values()
+        .flatten()
+        .map(|s| size_of::<Song>() + s.id.len() + s.title.len())
+        .sum();
+    artists + albums + songs
+}
+
+pub struct DebugHudWidget;
+
+impl DebugHudWidget {
+    /// Render the overlay in the top right corner of `area`
+    pub fn render(hud: &DebugHud, stats: &HudStats, area: Rect, buf: &mut Buffer) {
+        let width = 40.min(area.width);
+        let height = 7.min(area.height);
+        let popup = Rect::new(area.right().saturating_sub(width), area.y, width, height);
+        Clear.render(popup, buf);
+
+        let lines = [
+            format!("FPS:         {}", hud.fps()),
+            format!("Tasks:       {}", stats.tasks),
+            format!("Queued msgs: {}", hud.queued_messages),
+            format!("mpv lag:     {:.1} ms", hud.mpv_lag.as_secs_f64() * 1000.0),
+            format!(
+                "Cache:       {} KiB (+{} KiB art)",
+                stats.cache_bytes / 1024,
+                stats.cover_art_bytes / 1024
+            ),
+        ];
+        Paragraph::new(lines.join("\n"))
+            .block(Block::default().title("Debug").borders(Borders::ALL))
+            .style(Style::default().fg(Color::Yellow))
+            .render(popup, buf);
+    }
+}
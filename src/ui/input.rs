@@ -0,0 +1,174 @@
+//! Single-line text input shared by the prompts, with a cursor and the usual readline
+//! keys: word-wise movement, killing and yanking text, and pasting.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::prelude::*;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// What a key did to the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputKey {
+    /// The text changed
+    Edited,
+    /// Only the cursor moved
+    Moved,
+    /// Not an editing key, left to the prompt
+    Unhandled,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LineInput {
+    text: String,
+    /// Byte offset of the cursor in `text`
+    cursor: usize,
+    /// Text removed by the last kill, inserted again by yank
+    killed: String,
+}
+
+impl LineInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    pub fn at_end(&self) -> bool {
+        self.cursor == self.text.len()
+    }
+
+    /// Replace the text, with the cursor at its end
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = self.text.len();
+    }
+
+    pub fn clear(&mut self) {
+        self.set_text("");
+    }
+
+    /// Insert `text` at the cursor. Line breaks and other control characters of pasted
+    /// text become spaces, the input only has one line.
+    pub fn insert_str(&mut self, text: &str) {
+        let text: String = text
+            .trim_end_matches(['\r', '\n'])
+            .chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .collect();
+        self.text.insert_str(self.cursor, &text);
+        self.cursor += text.len();
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    /// Apply an editing key
+    pub fn handle_key(&mut self, key: KeyEvent) -> InputKey {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        let before = self.text.clone();
+
+        match key.code {
+            KeyCode::Char(c) if !ctrl && !alt => self.insert_char(c),
+            KeyCode::Char('a') if ctrl => self.cursor = 0,
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::Char('e') if ctrl => self.cursor = self.text.len(),
+            KeyCode::End => self.cursor = self.text.len(),
+            KeyCode::Char('b') if alt => self.cursor = self.word_start(),
+            KeyCode::Left if ctrl || alt => self.cursor = self.word_start(),
+            KeyCode::Char('b') if ctrl => self.cursor = self.previous_boundary(),
+            KeyCode::Left => self.cursor = self.previous_boundary(),
+            KeyCode::Char('f') if alt => self.cursor = self.word_end(),
+            KeyCode::Right if ctrl || alt => self.cursor = self.word_end(),
+            KeyCode::Char('f') if ctrl => self.cursor = self.next_boundary(),
+            KeyCode::Right => self.cursor = self.next_boundary(),
+            KeyCode::Backspace if alt || ctrl => self.kill(self.word_start(), self.cursor),
+            KeyCode::Char('w') if ctrl => self.kill(self.whitespace_word_start(), self.cursor),
+            KeyCode::Char('h') if ctrl => self.remove(self.previous_boundary(), self.cursor),
+            KeyCode::Backspace => self.remove(self.previous_boundary(), self.cursor),
+            KeyCode::Char('d') if ctrl => self.remove(self.cursor, self.next_boundary()),
+            KeyCode::Delete => self.remove(self.cursor, self.next_boundary()),
+            KeyCode::Char('d') if alt => self.kill(self.cursor, self.word_end()),
+            KeyCode::Char('k') if ctrl => self.kill(self.cursor, self.text.len()),
+            KeyCode::Char('u') if ctrl => self.kill(0, self.cursor),
+            KeyCode::Char('y') if ctrl => {
+                let killed = self.killed.clone();
+                self.insert_str(&killed);
+            }
+            _ => return InputKey::Unhandled,
+        }
+
+        if self.text != before {
+            InputKey::Edited
+        } else {
+            InputKey::Moved
+        }
+    }
+
+    /// Remove the text between the byte offsets `start` and `end`
+    fn remove(&mut self, start: usize, end: usize) {
+        self.text.replace_range(start..end, "");
+        self.cursor = start;
+    }
+
+    /// Remove the text between `start` and `end`, keeping it for yanking
+    fn kill(&mut self, start: usize, end: usize) {
+        if start < end {
+            self.killed = self.text[start..end].to_string();
+            self.remove(start, end);
+        }
+    }
+
+    fn previous_boundary(&self) -> usize {
+        self.text[..self.cursor].grapheme_indices(true).next_back().map_or(0, |(i, _)| i)
+    }
+
+    fn next_boundary(&self) -> usize {
+        self.text[self.cursor..]
+            .graphemes(true)
+            .next()
+            .map_or(self.cursor, |grapheme| self.cursor + grapheme.len())
+    }
+
+    /// Start of the word before the cursor, words being runs of letters and digits
+    fn word_start(&self) -> usize {
+        let before = &self.text[..self.cursor];
+        let end = before.trim_end_matches(|c: char| !c.is_alphanumeric()).len();
+        before[..end].trim_end_matches(char::is_alphanumeric).len()
+    }
+
+    /// End of the word after the cursor
+    fn word_end(&self) -> usize {
+        let after = &self.text[self.cursor..];
+        let skipped = after.len() - after.trim_start_matches(|c: char| !c.is_alphanumeric()).len();
+        let word = &after[skipped..];
+        self.cursor + skipped + (word.len() - word.trim_start_matches(char::is_alphanumeric).len())
+    }
+
+    /// Start of the whitespace-delimited word before the cursor, what Ctrl-W removes
+    fn whitespace_word_start(&self) -> usize {
+        self.text[..self.cursor].trim_end().trim_end_matches(|c: char| !c.is_whitespace()).len()
+    }
+
+    /// The text with the cursor drawn on it, reversing the grapheme under it or a space
+    /// at the end. Without `focused` the text is shown plain.
+    pub fn spans(&self, focused: bool) -> Vec<Span<'static>> {
+        if !focused {
+            return vec![Span::raw(self.text.clone())];
+        }
+        let next = self.next_boundary();
+        let under = if next == self.cursor { " " } else { &self.text[self.cursor..next] };
+        vec![
+            Span::raw(self.text[..self.cursor].to_string()),
+            Span::styled(under.to_string(), Style::default().add_modifier(Modifier::REVERSED)),
+            Span::raw(self.text[next..].to_string()),
+        ]
+    }
+}
@@ -0,0 +1,57 @@
+use crate::jobs::{Job, JobStatus, Jobs};
+use crate::ui::popup::{Popup, SelectList};
+use crate::ui::stats::format_size;
+use ratatui::{prelude::*, widgets::ListItem};
+
+pub struct JobsPanelWidget;
+
+impl JobsPanelWidget {
+    /// List the background jobs with their progress, the selected one can be cancelled
+    pub fn render(jobs: &Jobs, list: &mut SelectList, area: Rect, buf: &mut Buffer) {
+        let title = match jobs.running() {
+            0 => "Jobs".to_string(),
+            n => format!("Jobs ({} running)", n),
+        };
+        let jobs = jobs.list();
+        let height = (jobs.len().max(1) as u16).min(area.height / 2) + 3;
+        let inner = Popup::new(title, 70, height)
+            .hint("c: Cancel  Esc: Close")
+            .render(area, buf);
+
+        if jobs.is_empty() {
+            Line::from("No background jobs yet")
+                .style(Style::default().fg(Color::DarkGray))
+                .centered()
+                .render(inner, buf);
+            return;
+        }
+        list.render(jobs.iter().map(Self::item), None, inner, buf);
+    }
+
+    fn item(job: &Job) -> ListItem<'static> {
+        let (symbol, color) = match job.status {
+            JobStatus::Running => ("⟳", Color::Yellow),
+            JobStatus::Done => ("✓", Color::Green),
+            JobStatus::Failed(_) => ("✗", Color::Red),
+            JobStatus::Cancelled => ("⊘", Color::DarkGray),
+        };
+        let count = |count: usize| match job.in_bytes {
+            true => format_size(count as u64),
+            false => count.to_string(),
+        };
+        let progress = match job.progress {
+            (0, None) => String::new(),
+            (done, None) => format!("  {}", count(done)),
+            (done, Some(total)) => format!("  {}/{}", count(done), count(total)),
+        };
+        let detail = match &job.status {
+            JobStatus::Failed(error) => format!("  {}", error),
+            _ => format!("  {}s", job.elapsed().as_secs()),
+        };
+        ListItem::new(Line::from(vec![
+            Span::styled(format!("{} ", symbol), Style::default().fg(color)),
+            Span::raw(format!("{}{}", job.name, progress)),
+            Span::styled(detail, Style::default().fg(Color::DarkGray)),
+        ]))
+    }
+}
@@ -0,0 +1,58 @@
+use crate::ui::text::{display_width, truncate, window};
+use std::time::Instant;
+
+/// Scrolls text that is too long for its area back and forth, pausing at both ends
+#[derive(Debug, Clone)]
+pub struct Marquee {
+    /// Columns per second, 0 truncates instead of scrolling
+    pub speed: f32,
+    /// Seconds to rest at either end
+    pub pause: f32,
+    started: Instant,
+}
+
+impl Default for Marquee {
+    fn default() -> Self {
+        Self::new(8.0, 2.0)
+    }
+}
+
+impl Marquee {
+    pub fn new(speed: f32, pause: f32) -> Self {
+        Self {
+            speed,
+            pause,
+            started: Instant::now(),
+        }
+    }
+
+    /// Start again from the beginning, e.g. when the text changes
+    pub fn restart(&mut self) {
+        self.started = Instant::now();
+    }
+
+    /// The part of `text` to show in `width` columns right now
+    pub fn window(&self, text: &str, width: usize) -> String {
+        let overflow = display_width(text).saturating_sub(width);
+        if overflow == 0 || self.speed <= 0.0 {
+            return truncate(text, width);
+        }
+
+        // One cycle: rest, scroll to the end, rest, scroll back
+        let scroll = overflow as f32 / self.speed;
+        let cycle = 2.0 * (self.pause + scroll);
+        let t = self.started.elapsed().as_secs_f32() % cycle;
+        let offset = if t < self.pause {
+            0.0
+        } else if t < self.pause + scroll {
+            (t - self.pause) * self.speed
+        } else if t < 2.0 * self.pause + scroll {
+            overflow as f32
+        } else {
+            overflow as f32 - (t - 2.0 * self.pause - scroll) * self.speed
+        };
+
+        let offset = (offset as usize).min(overflow);
+        window(text, offset, width)
+    }
+}
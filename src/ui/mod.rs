@@ -1,2 +1,20 @@
 pub mod tree;
-pub mod player;
\ No newline at end of file
+pub mod player;
+pub mod form;
+pub mod stats;
+pub mod search;
+pub mod finder;
+pub mod hud;
+pub mod accent;
+pub mod marquee;
+pub mod confirm;
+pub mod thumbnail;
+pub mod picker;
+pub mod text;
+pub mod collate;
+pub mod input;
+pub mod popup;
+pub mod jobs;
+pub mod folder_picker;
+pub mod artist_info;
+pub mod file_info;
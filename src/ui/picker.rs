@@ -0,0 +1,69 @@
+use subsonic::Playlist;
+use crate::ui::popup::{Popup, SelectList};
+use crate::ui::tree::TreeItemType;
+use ratatui::{prelude::*, widgets::ListItem};
+
+/// Popup choosing the playlist to add songs and albums to.
+/// The playlists are listed first, followed by an entry creating a new one.
+#[derive(Debug, Clone)]
+pub struct PlaylistPicker {
+    /// The songs and albums to add
+    pub targets: Vec<TreeItemType>,
+    pub list: SelectList,
+}
+
+/// What the user picked
+#[derive(Debug, Clone)]
+pub enum PickedPlaylist {
+    Existing(Playlist),
+    New,
+}
+
+impl PlaylistPicker {
+    pub fn new(targets: Vec<TreeItemType>) -> Self {
+        Self {
+            targets,
+            list: SelectList::first(1),
+        }
+    }
+
+    pub fn next(&mut self, playlists: &[Playlist]) {
+        self.list.next(playlists.len() + 1);
+    }
+
+    pub fn previous(&mut self, playlists: &[Playlist]) {
+        self.list.previous(playlists.len() + 1);
+    }
+
+    pub fn selected(&self, playlists: &[Playlist]) -> PickedPlaylist {
+        match self.list.selected().and_then(|i| playlists.get(i)) {
+            Some(playlist) => PickedPlaylist::Existing(playlist.clone()),
+            None => PickedPlaylist::New,
+        }
+    }
+}
+
+pub struct PlaylistPickerWidget;
+
+impl PlaylistPickerWidget {
+    /// `playlists` is `None` while they are still loading
+    pub fn render(picker: &mut PlaylistPicker, playlists: Option<&[Playlist]>, area: Rect, buf: &mut Buffer) {
+        let playlists = playlists.unwrap_or_default();
+        let height = (playlists.len() as u16 + 1).min(area.height / 2) + 4;
+        let title = match picker.targets.len() {
+            1 => "Add to playlist".to_string(),
+            n => format!("Add {} items to playlist", n),
+        };
+        let inner = Popup::new(title, 50, height)
+            .hint("Enter: Add  Esc: Cancel")
+            .render(area, buf);
+
+        let items = playlists
+            .iter()
+            .map(|playlist| ListItem::new(format!("≡ {} ({})", playlist.name, playlist.song_count.unwrap_or(0))))
+            .chain(std::iter::once(
+                ListItem::new("+ New playlist…").style(Style::default().fg(Color::Green)),
+            ));
+        picker.list.render(items, None, inner, buf);
+    }
+}
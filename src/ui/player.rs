@@ -1,14 +1,147 @@
-use crate::subsonic::Song;
+use subsonic::{InternetRadioStation, Song, StructuredLyrics};
 use ratatui::{
     prelude::*,
-    widgets::{Block, Borders, Gauge, Paragraph},
+    widgets::{Block, Borders, Paragraph},
 };
 use std::ffi::{CString, CStr};
+use std::os::raw::{c_char, c_int, c_void};
 use std::ptr;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::thread::JoinHandle;
+use unicode_width::UnicodeWidthStr;
+
+/// Something mpv reported on its event thread
+#[derive(Debug, Clone)]
+pub enum PlayerEvent {
+    /// A file was loaded and playback begins
+    PlaybackStarted,
+    /// The current file played to the end
+    EndOfFile,
+    /// A seek finished at this position, in seconds
+    Seeked(f32),
+    PauseChanged(bool),
+    Progress(f32),
+    Duration(f32),
+    /// Position up to which the stream is cached, in seconds
+    Buffered(f32),
+    /// Percentage of the cache filled while playback waits for data, `None` once it resumes
+    Buffering(Option<u8>),
+    /// Volume in percent
+    Volume(f64),
+    Muted(bool),
+    /// The audio output failed or one of the audio devices went away, e.g. headphones
+    /// were unplugged, and this is why
+    AudioDeviceLost(String),
+}
+
+/// Details of a song fetched once it starts, sent to the app as [`Message::NowPlaying`](crate::Message::NowPlaying)
+#[derive(Debug, Clone)]
+pub enum NowPlayingUpdate {
+    /// Cover art for the song ID
+    CoverArt(String, Vec<u8>),
+    /// Lyrics for the song ID
+    Lyrics(String, SongLyrics),
+}
+
+impl NowPlayingUpdate {
+    pub fn song_id(&self) -> &str {
+        match self {
+            NowPlayingUpdate::CoverArt(song_id, _) | NowPlayingUpdate::Lyrics(song_id, _) => song_id,
+        }
+    }
+}
+
+/// Lyrics of the playing song
+#[derive(Debug, Clone)]
+pub enum SongLyrics {
+    Plain(String),
+    /// Lines with the second they start at, in order
+    Synced(Vec<(f32, String)>),
+}
+
+impl SongLyrics {
+    /// The version worth showing of what `getLyricsBySongId` returned: synced when there
+    /// is one, `None` when there are no lines at all
+    pub fn from_structured(versions: Vec<StructuredLyrics>) -> Option<Self> {
+        let synced = versions.iter().position(|lyrics| lyrics.synced && !lyrics.line.is_empty());
+        let lyrics = match synced {
+            Some(index) => versions.into_iter().nth(index)?,
+            None => versions.into_iter().find(|lyrics| !lyrics.line.is_empty())?,
+        };
+        if !lyrics.synced {
+            return Some(SongLyrics::Plain(lyrics.text()));
+        }
+        let offset = lyrics.offset as f32 / 1000.0;
+        let mut lines: Vec<_> = lyrics
+            .line
+            .into_iter()
+            .map(|line| (line.start.unwrap_or(0) as f32 / 1000.0 - offset, line.value))
+            .collect();
+        lines.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Some(SongLyrics::Synced(lines))
+    }
+
+    /// Index of the synced line sung at `position` seconds, `None` before the first one
+    pub fn current_line(&self, position: f32) -> Option<usize> {
+        let SongLyrics::Synced(lines) = self else {
+            return None;
+        };
+        lines.partition_point(|(start, _)| *start <= position).checked_sub(1)
+    }
+}
+
+// Reply ids of the observed properties
+const OBSERVE_PAUSE: u64 = 1;
+const OBSERVE_TIME_POS: u64 = 2;
+const OBSERVE_DURATION: u64 = 3;
+const OBSERVE_CACHE_TIME: u64 = 4;
+const OBSERVE_PAUSED_FOR_CACHE: u64 = 5;
+const OBSERVE_BUFFERING_STATE: u64 = 6;
+const OBSERVE_VOLUME: u64 = 7;
+const OBSERVE_MUTE: u64 = 8;
+const OBSERVE_AUDIO_DEVICES: u64 = 9;
+
+/// Percent the volume keys change the volume by
+pub const VOLUME_STEP: f64 = 5.0;
+
+/// How much the speed keys change the playback speed by
+pub const SPEED_STEP: f64 = 0.25;
+
+/// Slowest and fastest playback speed
+const SPEED_RANGE: (f64, f64) = (0.5, 3.0);
+
+/// Cells of the volume gauge in the controls panel
+const VOLUME_GAUGE_WIDTH: usize = 8;
+
+/// Shown in the player while the current song isn't scrobbled
+const PRIVATE_LABEL: &str = "🕶 private ";
+
+/// Shown in the player while the audio plays on the server's jukebox
+const JUKEBOX_LABEL: &str = "📡 jukebox ";
+
+/// Smallest change in playback position worth a message, in seconds
+const PROGRESS_STEP: f64 = 0.25;
+
+/// The mpv handle as seen from the event thread
+struct EventHandle(*mut libmpv_sys::mpv_handle);
+
+// mpv_wait_event may be called from any thread, as long as it is only one
+unsafe impl Send for EventHandle {}
+
+impl EventHandle {
+    fn get(&self) -> *mut libmpv_sys::mpv_handle {
+        self.0
+    }
+}
 
 // Simple MPV wrapper using libmpv-sys directly
 pub struct SimpleMpv {
     handle: *mut libmpv_sys::mpv_handle,
+    /// Thread waiting for mpv events and the flag telling it to stop
+    events: Option<(JoinHandle<()>, Arc<AtomicBool>)>,
 }
 
 impl SimpleMpv {
@@ -26,11 +159,12 @@ impl SimpleMpv {
                 return Err(format!("Failed to initialize MPV: {}", ret).into());
             }
             
-            Ok(SimpleMpv { handle })
+            Ok(SimpleMpv { handle, events: None })
         }
     }
     
     pub fn set_property(&self, name: &str, value: &str) -> Result<(), Box<dyn std::error::Error>> {
+        debug!(target: "highpass::mpv", property = name, value; "Setting mpv property {} = {}", name, value);
         unsafe {
             let name_c = CString::new(name)?;
             let value_c = CString::new(value)?;
@@ -64,6 +198,7 @@ impl SimpleMpv {
     }
     
     pub fn command(&self, command: &str, args: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
+        debug!(target: "highpass::mpv", command; "Sending mpv command {} {:?}", command, args);
         unsafe {
             let mut c_args: Vec<CString> = Vec::new();
             c_args.push(CString::new(command)?);
@@ -81,10 +216,185 @@ impl SimpleMpv {
             Ok(())
         }
     }
+
+    fn observe_property(&self, id: u64, name: &str, format: libmpv_sys::mpv_format) -> Result<(), Box<dyn std::error::Error>> {
+        unsafe {
+            let name_c = CString::new(name)?;
+            let ret = libmpv_sys::mpv_observe_property(self.handle, id, name_c.as_ptr(), format);
+            if ret < 0 {
+                return Err(format!("Failed to observe property {}: {}", name, ret).into());
+            }
+            Ok(())
+        }
+    }
+
+    /// Observe the playback properties and pass mpv's events to `on_event` from a
+    /// background thread, until `on_event` returns false or mpv shuts down
+    pub fn watch_events<F>(&mut self, on_event: F) -> Result<(), Box<dyn std::error::Error>>
+    where
+        F: FnMut(PlayerEvent) -> bool + Send + 'static,
+    {
+        use libmpv_sys::{
+            mpv_format_MPV_FORMAT_DOUBLE as DOUBLE, mpv_format_MPV_FORMAT_FLAG as FLAG, mpv_format_MPV_FORMAT_INT64 as INT64,
+            mpv_format_MPV_FORMAT_STRING as STRING,
+        };
+        self.observe_property(OBSERVE_PAUSE, "pause", FLAG)?;
+        self.observe_property(OBSERVE_TIME_POS, "time-pos", DOUBLE)?;
+        self.observe_property(OBSERVE_DURATION, "duration", DOUBLE)?;
+        self.observe_property(OBSERVE_CACHE_TIME, "demuxer-cache-time", DOUBLE)?;
+        self.observe_property(OBSERVE_PAUSED_FOR_CACHE, "paused-for-cache", FLAG)?;
+        self.observe_property(OBSERVE_BUFFERING_STATE, "cache-buffering-state", INT64)?;
+        self.observe_property(OBSERVE_VOLUME, "volume", DOUBLE)?;
+        self.observe_property(OBSERVE_MUTE, "mute", FLAG)?;
+        self.observe_property(OBSERVE_AUDIO_DEVICES, "audio-device-list", STRING)?;
+        // Errors of the audio output arrive as log messages
+        let ret = unsafe { libmpv_sys::mpv_request_log_messages(self.handle, c"error".as_ptr()) };
+        if ret < 0 {
+            return Err(format!("Failed to request log messages: {}", ret).into());
+        }
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = EventHandle(self.handle);
+        let thread = std::thread::Builder::new()
+            .name("mpv-events".to_string())
+            .spawn({
+                let stop = stop.clone();
+                move || event_loop(handle, &stop, on_event)
+            })?;
+        self.events = Some((thread, stop));
+        Ok(())
+    }
+}
+
+/// Translate mpv events into [`PlayerEvent`]s until told to stop
+// libmpv's enum values are bound as lowercase-prefixed constants
+#[allow(non_upper_case_globals)]
+fn event_loop<F>(handle: EventHandle, stop: &AtomicBool, mut on_event: F)
+where
+    F: FnMut(PlayerEvent) -> bool,
+{
+    use libmpv_sys::*;
+
+    let mut loaded = false;
+    let mut last_progress = f64::NEG_INFINITY;
+    let mut last_cached = f64::NEG_INFINITY;
+    let mut paused_for_cache = false;
+    // Names of the audio devices, to notice one going away
+    let mut devices: Option<Vec<String>> = None;
+    while !stop.load(Ordering::Relaxed) {
+        let event = unsafe { &*mpv_wait_event(handle.get(), -1.0) };
+        let forwarded = match event.event_id {
+            mpv_event_id_MPV_EVENT_SHUTDOWN => break,
+            mpv_event_id_MPV_EVENT_FILE_LOADED => {
+                loaded = true;
+                last_progress = f64::NEG_INFINITY;
+                Some(PlayerEvent::PlaybackStarted)
+            }
+            // mpv resumes after loading a file the same way it does after a seek
+            mpv_event_id_MPV_EVENT_PLAYBACK_RESTART if std::mem::take(&mut loaded) => None,
+            mpv_event_id_MPV_EVENT_PLAYBACK_RESTART => {
+                let mut position = 0.0f64;
+                let name = c"time-pos";
+                let ret = unsafe {
+                    mpv_get_property(handle.get(), name.as_ptr(), mpv_format_MPV_FORMAT_DOUBLE, &mut position as *mut f64 as *mut c_void)
+                };
+                (ret >= 0).then_some(PlayerEvent::Seeked(position as f32))
+            }
+            mpv_event_id_MPV_EVENT_END_FILE => {
+                let end = unsafe { &*(event.data as *const mpv_event_end_file) };
+                match end.reason as mpv_end_file_reason {
+                    mpv_end_file_reason_MPV_END_FILE_REASON_EOF => Some(PlayerEvent::EndOfFile),
+                    mpv_end_file_reason_MPV_END_FILE_REASON_ERROR => {
+                        let reason = unsafe { CStr::from_ptr(mpv_error_string(end.error)) };
+                        warn!(target: "highpass::mpv", event = "end-file"; "Playback failed: {}", reason.to_string_lossy());
+                        None
+                    }
+                    // Stopped or replaced by another file
+                    _ => None,
+                }
+            }
+            mpv_event_id_MPV_EVENT_PROPERTY_CHANGE => {
+                let property = unsafe { &*(event.data as *const mpv_event_property) };
+                // Unavailable properties, e.g. the position while nothing is loaded
+                if property.format == mpv_format_MPV_FORMAT_NONE {
+                    None
+                } else {
+                    let flag = || unsafe { *(property.data as *const c_int) != 0 };
+                    let double = || unsafe { *(property.data as *const f64) };
+                    match event.reply_userdata {
+                        OBSERVE_PAUSE => Some(PlayerEvent::PauseChanged(flag())),
+                        OBSERVE_TIME_POS => {
+                            let position = double();
+                            ((position - last_progress).abs() >= PROGRESS_STEP).then(|| {
+                                last_progress = position;
+                                PlayerEvent::Progress(position as f32)
+                            })
+                        }
+                        OBSERVE_DURATION => Some(PlayerEvent::Duration(double() as f32)),
+                        OBSERVE_CACHE_TIME => {
+                            let cached = double();
+                            ((cached - last_cached).abs() >= PROGRESS_STEP).then(|| {
+                                last_cached = cached;
+                                PlayerEvent::Buffered(cached as f32)
+                            })
+                        }
+                        OBSERVE_PAUSED_FOR_CACHE => {
+                            paused_for_cache = flag();
+                            (!paused_for_cache).then_some(PlayerEvent::Buffering(None))
+                        }
+                        OBSERVE_BUFFERING_STATE if paused_for_cache => {
+                            let percent = unsafe { *(property.data as *const i64) };
+                            Some(PlayerEvent::Buffering(Some(percent.clamp(0, 100) as u8)))
+                        }
+                        OBSERVE_VOLUME => Some(PlayerEvent::Volume(double())),
+                        OBSERVE_MUTE => Some(PlayerEvent::Muted(flag())),
+                        OBSERVE_AUDIO_DEVICES => {
+                            let list = unsafe { CStr::from_ptr(*(property.data as *const *const c_char)) };
+                            let now = audio_devices(&list.to_string_lossy());
+                            let gone = devices.replace(now.clone()).unwrap_or_default().into_iter().find(|device| !now.contains(device));
+                            gone.map(|device| PlayerEvent::AudioDeviceLost(format!("{} went away", device)))
+                        }
+                        _ => None,
+                    }
+                }
+            }
+            mpv_event_id_MPV_EVENT_LOG_MESSAGE => {
+                let message = unsafe { &*(event.data as *const mpv_event_log_message) };
+                let prefix = unsafe { CStr::from_ptr(message.prefix) }.to_string_lossy();
+                let text = unsafe { CStr::from_ptr(message.text) }.to_string_lossy().trim().to_string();
+                warn!(target: "highpass::mpv", "[{}] {}", prefix, text);
+                prefix.starts_with("ao").then(|| PlayerEvent::AudioDeviceLost(format!("Audio output failed: {}", text)))
+            }
+            _ => None,
+        };
+        if let Some(forwarded) = forwarded {
+            if !on_event(forwarded) {
+                break;
+            }
+        }
+    }
+    debug!(target: "highpass::mpv", "mpv event thread finished");
+}
+
+/// Descriptions of the devices in mpv's `audio-device-list`, which reads as JSON
+fn audio_devices(list: &str) -> Vec<String> {
+    #[derive(serde::Deserialize)]
+    struct Device {
+        name: String,
+        description: Option<String>,
+    }
+    let devices: Vec<Device> = serde_json::from_str(list).unwrap_or_default();
+    devices.into_iter().map(|device| device.description.unwrap_or(device.name)).collect()
 }
 
 impl Drop for SimpleMpv {
     fn drop(&mut self) {
+        // The event thread has to let go of the handle before it is destroyed
+        if let Some((thread, stop)) = self.events.take() {
+            stop.store(true, Ordering::Relaxed);
+            unsafe { libmpv_sys::mpv_wakeup(self.handle) };
+            let _ = thread.join();
+        }
         unsafe {
             if !self.handle.is_null() {
                 libmpv_sys::mpv_destroy(self.handle);
@@ -98,30 +408,91 @@ unsafe impl Send for SimpleMpv {}
 
 type Mpv = SimpleMpv;
 use log::{info, warn, error, debug};
+use crate::jukebox::Jukebox;
+use crate::ui::marquee::Marquee;
+use crate::ui::text::display_width;
 
 // SimpleMpv is already defined above and exported via the module
 
 
+/// Where the audio plays
+#[derive(Debug)]
+pub enum Output {
+    /// Through mpv on this machine
+    Local,
+    /// On the server's jukebox, with this machine as the remote control
+    Jukebox(Jukebox),
+}
+
 pub struct PlayerState {
     pub current_song: Option<Song>,
+    /// Internet radio station playing instead of a song
+    pub station: Option<InternetRadioStation>,
     pub is_playing: bool,
+    /// Playback was stopped and the file unloaded, the current song stays selected
+    pub stopped: bool,
     pub progress: f32,
     pub duration: f32,
+    /// Position up to which mpv has the stream cached, in seconds
+    pub buffered: f32,
+    /// Percentage of the cache filled while mpv waits for data before resuming
+    pub buffering: Option<u8>,
     pub cover_art: Option<Vec<u8>>,
-    pub lyrics: Option<String>,
+    /// Dominant color of the cover, used to tint the UI when enabled in the theme
+    pub accent: Option<Color>,
+    pub lyrics: Option<SongLyrics>,
+    /// Volume in percent as reported by mpv, `None` until it did
+    pub volume: Option<f64>,
+    pub muted: bool,
+    /// Part of the volume that is the current song's remembered adjustment
+    pub volume_offset: f64,
+    /// Playback speed, the pitch is kept when it isn't 1
+    pub speed: f64,
+    /// Plays are reported to the server, off while listening privately
+    pub scrobbling: bool,
+    /// The current song is marked to never be scrobbled
+    pub song_unscrobbled: bool,
+    /// Why playback was paused when it wasn't the user, until it resumes
+    pub pause_reason: Option<String>,
+    /// Scrolls the now playing line when it does not fit
+    pub title_marquee: Marquee,
     pub mpv: Option<Mpv>,
+    output: Output,
+    /// mpv started playing the current file
+    track_started: bool,
+    /// The current file played to the end and mpv went idle
+    track_finished: bool,
+    /// Position to seek to once the current file has loaded
+    pending_seek: Option<f32>,
 }
 
 impl Default for PlayerState {
     fn default() -> Self {
         Self {
             current_song: None,
+            station: None,
             is_playing: false,
+            stopped: false,
             progress: 0.0,
             duration: 100.0,
+            buffered: 0.0,
+            buffering: None,
             cover_art: None,
+            accent: None,
             lyrics: None,
+            volume: None,
+            muted: false,
+            volume_offset: 0.0,
+            speed: 1.0,
+            scrobbling: true,
+            song_unscrobbled: false,
+            pause_reason: None,
+            title_marquee: Marquee::default(),
             mpv: None,
+            output: Output::Local,
+            track_started: false,
+            track_finished: false,
+            pending_seek: None,
         }
     }
 }
@@ -165,13 +536,61 @@ impl PlayerState {
         state
     }
 
+    /// Switch where the audio plays. The volume and mute state carry over, playback
+    /// should be stopped first.
+    pub fn set_output(&mut self, output: Output) {
+        // The jukebox plays at its own speed
+        if matches!(output, Output::Jukebox(_)) {
+            self.set_speed(1.0);
+        }
+        self.output = output;
+        if let Some(volume) = self.volume {
+            self.set_volume(volume);
+        }
+        if let Some(mpv) = self.local_mpv() {
+            if let Err(e) = mpv.set_property("mute", if self.muted { "yes" } else { "no" }) {
+                warn!("Failed to restore mute: {}", e);
+            }
+        }
+    }
+
+    pub fn jukebox_active(&self) -> bool {
+        matches!(self.output, Output::Jukebox(_))
+    }
+
+    /// mpv, unless the audio plays on the jukebox
+    fn local_mpv(&self) -> Option<&Mpv> {
+        match self.output {
+            Output::Local => self.mpv.as_ref(),
+            Output::Jukebox(_) => None,
+        }
+    }
+
+    /// The song to play on the jukebox, which only plays songs of the library
+    fn jukebox_song(&self) -> Result<&str, Box<dyn std::error::Error>> {
+        match &self.current_song {
+            Some(song) if self.station.is_none() => Ok(&song.id),
+            _ => Err("only songs of the library can play on the jukebox".into()),
+        }
+    }
+
     pub fn set_current_song(&mut self, song: Song) {
         info!("Setting current song: {} by {}", 
                song.title, 
                song.artist.as_deref().unwrap_or("Unknown Artist"));
         
+        // A faster pace is for listening to talk, music starts over at its own
+        if !song.is_spoken_word() {
+            self.set_speed(1.0);
+        }
         self.current_song = Some(song);
+        self.station = None;
+        self.pause_reason = None;
+        self.lyrics = None;
         self.progress = 0.0;
+        self.title_marquee.restart();
+        self.buffered = 0.0;
+        self.buffering = None;
         
         if let Some(duration) = &self.current_song.as_ref().unwrap().duration {
             self.duration = *duration as f32;
@@ -179,52 +598,305 @@ impl PlayerState {
         }
     }
 
+    /// Switch to a radio station, a live stream without a length, cover or lyrics
+    pub fn set_current_station(&mut self, station: InternetRadioStation) {
+        info!("Setting current station: {}", station.name);
+        self.set_speed(1.0);
+        self.station = Some(station);
+        self.current_song = None;
+        self.pause_reason = None;
+        self.progress = 0.0;
+        self.duration = 0.0;
+        self.title_marquee.restart();
+        self.buffered = 0.0;
+        self.buffering = None;
+        self.cover_art = None;
+        self.accent = None;
+        self.lyrics = None;
+    }
+
     pub fn play_url(&mut self, url: &str) -> Result<(), Box<dyn std::error::Error>> {
-        info!("Attempting to play URL: {}", url);
-        
+        // The query of a stream URL carries the credentials, maybe the password itself
+        info!("Attempting to play URL: {}", url.split('?').next().unwrap_or(url));
+
+        if let Output::Jukebox(jukebox) = &self.output {
+            jukebox.load(self.jukebox_song()?, 0.0, true);
+            self.is_playing = true;
+            self.stopped = false;
+            self.track_started = false;
+            self.track_finished = false;
+            return Ok(());
+        }
         if let Some(mpv) = &self.mpv {
             debug!("Sending loadfile command to MPV");
             match mpv.command("loadfile", &[url]) {
                 Ok(_) => {
                     info!("Successfully sent loadfile command to MPV");
                     self.is_playing = true;
-                    
-                    // Try to get some info about the loaded file
-                    std::thread::sleep(std::time::Duration::from_millis(500));
-                    
-                    match mpv.get_property::<String>("media-title") {
-                        Ok(title) => info!("MPV media title: {}", title),
-                        Err(e) => debug!("Could not get media title: {}", e),
-                    }
-                    
-                    match mpv.get_property::<f64>("duration") {
-                        Ok(duration) => info!("MPV duration: {} seconds", duration),
-                        Err(e) => debug!("Could not get duration: {}", e),
-                    }
+                    self.stopped = false;
+                    self.track_started = false;
+                    self.track_finished = false;
                 }
                 Err(e) => {
                     error!("Failed to send loadfile command to MPV: {}", e);
-                    return Err(e.into());
+                    return Err(e);
                 }
             }
         } else {
             warn!("MPV not available - simulating playback for UI testing");
-            warn!("Would play: {}", url);
+            warn!("Would play: {}", url.split('?').next().unwrap_or(url));
             self.is_playing = true; // Simulate playback state for UI
+            self.stopped = false;
         }
         
         Ok(())
     }
 
+    /// Load a URL paused at `position`, used to resume a saved session
+    pub fn resume_url(&mut self, url: &str, position: f32) -> Result<(), Box<dyn std::error::Error>> {
+        if let Output::Jukebox(jukebox) = &self.output {
+            jukebox.load(self.jukebox_song()?, position, false);
+            self.is_playing = false;
+            self.stopped = false;
+            self.track_started = false;
+            self.track_finished = false;
+            self.progress = position;
+            return Ok(());
+        }
+        if let Some(mpv) = &self.mpv {
+            mpv.set_property("pause", "yes")?;
+        }
+        self.play_url(url)?;
+        self.is_playing = false;
+        self.progress = position;
+        self.pending_seek = Some(position).filter(|p| *p > 0.0);
+        Ok(())
+    }
+
+    /// Stop playback and unload the current file
+    pub fn stop(&mut self) {
+        match &self.output {
+            Output::Jukebox(jukebox) => jukebox.stop(),
+            Output::Local => {
+                if let Some(mpv) = &self.mpv {
+                    if let Err(e) = mpv.command("stop", &[]) {
+                        warn!("Failed to stop playback: {}", e);
+                    }
+                }
+            }
+        }
+        self.is_playing = false;
+        self.stopped = true;
+        // mpv going idle now is not the end of the track, so don't advance the queue
+        self.track_started = false;
+        self.track_finished = false;
+        self.pending_seek = None;
+        self.progress = 0.0;
+        self.buffered = 0.0;
+        self.buffering = None;
+    }
+
+    /// Seek back to the start of the current track
+    pub fn restart(&mut self) {
+        self.seek(0.0);
+    }
+
+    /// Jump to `position` seconds into the current track. The progress shows the new
+    /// position right away, mpv confirms it once the seek is done.
+    pub fn seek(&mut self, position: f32) {
+        if self.stopped || self.current_song.is_none() {
+            return;
+        }
+        let position = position.clamp(0.0, self.duration.max(0.0));
+        if let Output::Jukebox(jukebox) = &self.output {
+            jukebox.seek(position);
+        }
+        if let Some(mpv) = self.local_mpv() {
+            if let Err(e) = mpv.command("seek", &[&position.to_string(), "absolute"]) {
+                warn!("Failed to seek to {:.1}s: {}", position, e);
+                return;
+            }
+        }
+        self.progress = position;
+    }
+
+    /// Seek `delta` seconds forward, or back if negative
+    pub fn seek_relative(&mut self, delta: f32) {
+        self.seek(self.progress + delta);
+    }
+
+    /// Seek to `percent` of the current track
+    pub fn seek_percent(&mut self, percent: f32) {
+        self.seek(self.duration * percent / 100.0);
+    }
+
+    pub fn set_volume(&mut self, volume: f64) {
+        if let Output::Jukebox(jukebox) = &self.output {
+            jukebox.set_volume(if self.muted { 0.0 } else { volume });
+        }
+        if let Some(mpv) = self.local_mpv() {
+            if let Err(e) = mpv.set_property("volume", &volume.to_string()) {
+                warn!("Failed to set volume: {}", e);
+                return;
+            }
+        }
+        // Don't wait for mpv to confirm, the next change builds on this one
+        self.volume = Some(volume);
+    }
+
+    /// Play at `speed` times the normal speed. mpv inserts its scaletempo filter so
+    /// voices keep their pitch.
+    pub fn set_speed(&mut self, speed: f64) {
+        if speed == self.speed {
+            return;
+        }
+        if let Some(mpv) = self.local_mpv() {
+            let result = mpv
+                .set_property("audio-pitch-correction", "yes")
+                .and_then(|_| mpv.set_property("speed", &speed.to_string()));
+            if let Err(e) = result {
+                warn!("Failed to set speed: {}", e);
+                return;
+            }
+        } else if self.jukebox_active() && speed != 1.0 {
+            warn!("The jukebox cannot change its playback speed");
+            return;
+        }
+        info!("Speed {}x", speed);
+        self.speed = speed;
+    }
+
+    /// Speed up by `delta`, or slow down if negative, within the speed range
+    pub fn change_speed(&mut self, delta: f64) {
+        self.set_speed((self.speed + delta).clamp(SPEED_RANGE.0, SPEED_RANGE.1));
+    }
+
+    /// The volume without the current song's adjustment
+    pub fn base_volume(&self) -> Option<f64> {
+        self.volume.map(|volume| volume - self.volume_offset)
+    }
+
+    /// Swap the current song's volume adjustment for `offset`
+    pub fn set_volume_offset(&mut self, offset: f64) {
+        if offset == self.volume_offset {
+            return;
+        }
+        let base = self.base_volume().unwrap_or(100.0);
+        self.volume_offset = offset;
+        self.set_volume((base + offset).clamp(0.0, 100.0));
+    }
+
+    /// Normalize the next file with a `gain` in dB computed by the server. Without one,
+    /// mpv reads the ReplayGain tags of the stream in `tag_mode` ("track", "album" or "no").
+    /// The jukebox plays without normalization.
+    pub fn set_replay_gain(&self, gain: Option<f32>, tag_mode: &str, preamp: f32) {
+        let Some(mpv) = self.local_mpv() else {
+            return;
+        };
+        let (filter, tag_mode) = match gain {
+            // The tags would be applied on top of the server's gain
+            Some(gain) => (format!("@replaygain:lavfi=[volume={:.2}dB]", gain + preamp), "no"),
+            None => (String::new(), tag_mode),
+        };
+        let result = mpv
+            .set_property("af", &filter)
+            .and_then(|_| mpv.set_property("replaygain", tag_mode))
+            .and_then(|_| mpv.set_property("replaygain-preamp", &preamp.to_string()));
+        if let Err(e) = result {
+            warn!("Failed to set ReplayGain: {}", e);
+        }
+    }
+
+    /// Raise or lower the volume by `delta` percent, within 0 to 100
+    pub fn change_volume(&mut self, delta: f64) {
+        let volume = (self.volume.unwrap_or(100.0) + delta).clamp(0.0, 100.0);
+        info!("Volume {:.0}%", volume);
+        self.set_volume(volume);
+        if self.muted {
+            self.toggle_mute();
+        }
+    }
+
+    /// The jukebox has no mute of its own, it is muted by turning its volume down
+    pub fn toggle_mute(&mut self) {
+        if let Output::Jukebox(jukebox) = &self.output {
+            self.muted = !self.muted;
+            jukebox.set_volume(if self.muted { 0.0 } else { self.volume.unwrap_or(100.0) });
+        }
+        if let Some(mpv) = self.local_mpv() {
+            let mute = if self.muted { "no" } else { "yes" };
+            if let Err(e) = mpv.set_property("mute", mute) {
+                warn!("Failed to toggle mute: {}", e);
+            }
+        }
+    }
+
     pub fn set_cover_art(&mut self, cover_art: Vec<u8>) {
         self.cover_art = Some(cover_art);
     }
 
-    pub fn set_lyrics(&mut self, lyrics: String) {
+    pub fn set_lyrics(&mut self, lyrics: SongLyrics) {
         self.lyrics = Some(lyrics);
     }
 
+    /// Stream through the proxy at `url`. mpv only speaks HTTP proxies, with a SOCKS proxy
+    /// streams go out directly.
+    pub fn set_http_proxy(&self, url: &str) {
+        let Some(mpv) = &self.mpv else {
+            return;
+        };
+        if !url.starts_with("http://") {
+            let scheme = url.split("://").next().unwrap_or_default();
+            warn!("mpv cannot stream through a {} proxy, only through http:// proxies", scheme);
+            return;
+        }
+        if let Err(e) = mpv.set_property("http-proxy", url) {
+            warn!("Failed to set the HTTP proxy: {}", e);
+        }
+    }
+
+    /// Whether music is coming out of this machine, not paused and not on the jukebox
+    pub fn is_playing_here(&self) -> bool {
+        self.is_playing && self.local_mpv().is_some()
+    }
+
+    /// Pause what plays on this machine for `reason`, which the player shows until
+    /// playback resumes. Returns whether it was playing.
+    pub fn pause_for(&mut self, reason: impl Into<String>) -> bool {
+        if !self.is_playing || self.local_mpv().is_none() {
+            return false;
+        }
+        let reason = reason.into();
+        info!("Pausing playback: {}", reason);
+        self.toggle_play_pause();
+        if self.is_playing {
+            return false;
+        }
+        self.pause_reason = Some(reason);
+        true
+    }
+
+    /// Resume playback if it was paused for `reason`. Returns whether it was.
+    pub fn resume_paused_for(&mut self, reason: &str) -> bool {
+        if self.is_playing || self.pause_reason.as_deref() != Some(reason) {
+            return false;
+        }
+        info!("Resuming playback paused for: {}", reason);
+        self.toggle_play_pause();
+        self.pause_reason = None;
+        self.is_playing
+    }
+
     pub fn toggle_play_pause(&mut self) {
+        if let Output::Jukebox(jukebox) = &self.output {
+            if self.is_playing {
+                jukebox.pause();
+            } else {
+                jukebox.play();
+            }
+            self.is_playing = !self.is_playing;
+            return;
+        }
         if let Some(mpv) = &self.mpv {
             if self.is_playing {
                 info!("Pausing playback");
@@ -249,45 +921,91 @@ impl PlayerState {
     }
 
 
-    pub fn update_progress(&mut self) {
-        if let Some(mpv) = &self.mpv {
-            match mpv.get_property::<f64>("time-pos") {
-                Ok(time_pos) => {
-                    let new_progress = time_pos as f32;
-                    if (new_progress - self.progress).abs() > 1.0 { // Only log every second
-                        debug!("Progress: {:.1}s", new_progress);
+    /// Watch mpv's events on a background thread, each one is handed to `on_event`
+    /// and should come back through [`PlayerState::handle_event`]
+    pub fn watch_events<F>(&mut self, on_event: F)
+    where
+        F: FnMut(PlayerEvent) -> bool + Send + 'static,
+    {
+        if let Some(mpv) = &mut self.mpv {
+            if let Err(e) = mpv.watch_events(on_event) {
+                error!("Failed to watch mpv events: {}", e);
+            }
+        }
+    }
+
+    pub fn handle_event(&mut self, event: PlayerEvent) {
+        debug!(target: "highpass::mpv", event:? = event; "mpv event {:?}", event);
+        // Events still in flight from before playback was stopped
+        if self.stopped && !matches!(event, PlayerEvent::Volume(_) | PlayerEvent::Muted(_)) {
+            return;
+        }
+        match event {
+            PlayerEvent::PlaybackStarted => {
+                self.track_started = true;
+                if let Some(mpv) = &self.mpv {
+                    match mpv.get_property::<String>("media-title") {
+                        Ok(title) => info!("MPV media title: {}", title),
+                        Err(e) => debug!("Could not get media title: {}", e),
                     }
-                    self.progress = new_progress;
-                }
-                Err(e) => {
-                    if e.to_string() != "property unavailable" {
-                        debug!("Could not get time-pos: {}", e);
+                    if let Some(position) = self.pending_seek.take() {
+                        debug!("Seeking to restored position {:.1}s", position);
+                        if let Err(e) = mpv.command("seek", &[&position.to_string(), "absolute"]) {
+                            warn!("Failed to seek to restored position: {}", e);
+                        }
+                        self.progress = position;
                     }
                 }
             }
-            
-            if let Ok(duration) = mpv.get_property::<f64>("duration") {
-                let new_duration = duration as f32;
-                if (new_duration - self.duration).abs() > 0.1 {
-                    debug!("Duration updated: {:.1}s", new_duration);
-                    self.duration = new_duration;
+            PlayerEvent::EndOfFile => {
+                // An end from before the current file was loaded doesn't count
+                if self.track_started {
+                    info!(target: "highpass::mpv", event = "end-file"; "Track finished");
+                    self.is_playing = false;
+                    self.track_started = false;
+                    self.track_finished = true;
                 }
             }
-            
-            match mpv.get_property::<bool>("pause") {
-                Ok(pause) => {
-                    let was_playing = self.is_playing;
+            PlayerEvent::Seeked(position) => {
+                debug!("Seeked to {:.1}s", position);
+                self.progress = position;
+            }
+            PlayerEvent::PauseChanged(pause) => {
+                if !pause {
+                    self.pause_reason = None;
+                }
+                if (self.current_song.is_some() || self.station.is_some()) && self.is_playing == pause {
                     self.is_playing = !pause;
-                    if was_playing != self.is_playing {
-                        info!("Playback state changed: {}", if self.is_playing { "playing" } else { "paused" });
-                    }
+                    info!("Playback state changed: {}", if self.is_playing { "playing" } else { "paused" });
                 }
-                Err(e) => {
-                    debug!("Could not get pause state: {}", e);
+            }
+            PlayerEvent::Progress(position) => {
+                // A restored position is applied once the file has loaded
+                if self.pending_seek.is_none() {
+                    self.progress = position;
                 }
             }
+            // Live streams keep the elapsed-only display even if mpv guesses a length
+            PlayerEvent::Duration(_) if self.station.is_some() => {}
+            PlayerEvent::Duration(duration) => {
+                if (duration - self.duration).abs() > 0.1 {
+                    debug!("Duration updated: {:.1}s", duration);
+                    self.duration = duration;
+                }
+            }
+            PlayerEvent::Buffered(buffered) => self.buffered = buffered,
+            PlayerEvent::Buffering(buffering) => self.buffering = buffering,
+            PlayerEvent::Volume(volume) => self.volume = Some(volume),
+            PlayerEvent::Muted(muted) => self.muted = muted,
+            // Up to the app whether to pause for it
+            PlayerEvent::AudioDeviceLost(_) => {}
         }
     }
+
+    /// Returns true once after the current track played to the end
+    pub fn take_finished(&mut self) -> bool {
+        std::mem::take(&mut self.track_finished)
+    }
 }
 
 pub struct PlayerWidget;
@@ -322,7 +1040,9 @@ impl PlayerWidget {
     }
 
     fn render_track_info(state: &PlayerState, area: Rect, buf: &mut Buffer) {
-        let text = if let Some(song) = &state.current_song {
+        let text = if let Some(station) = &state.station {
+            format!("📻 {}", station.name)
+        } else if let Some(song) = &state.current_song {
             format!(
                 "♪ {} - {} ({})",
                 song.title,
@@ -333,8 +1053,11 @@ impl PlayerWidget {
             "No track selected".to_string()
         };
 
+        let title = if state.stopped { "Stopped" } else { "Now Playing" };
+        let block = Block::default().title(title).borders(Borders::ALL);
+        let text = state.title_marquee.window(&text, block.inner(area).width as usize);
         let paragraph = Paragraph::new(text)
-            .block(Block::default().title("Now Playing").borders(Borders::ALL))
+            .block(block)
             .style(Style::default().fg(Color::White))
             .alignment(Alignment::Center);
 
@@ -374,49 +1097,191 @@ impl PlayerWidget {
     }
 
     fn render_lyrics(state: &PlayerState, area: Rect, buf: &mut Buffer) {
-        let lyrics_text = if let Some(lyrics) = &state.lyrics {
-            lyrics.lines().take(10).collect::<Vec<_>>().join("\n")
-        } else {
-            "No lyrics available".to_string()
+        let block = Block::default().title("Lyrics").borders(Borders::ALL);
+        let (lyrics_text, scroll) = match &state.lyrics {
+            Some(SongLyrics::Plain(lyrics)) => (Text::from(lyrics.lines().take(10).collect::<Vec<_>>().join("\n")), 0),
+            Some(lyrics @ SongLyrics::Synced(lines)) => {
+                let current = lyrics.current_line(state.progress);
+                let text: Vec<Line> = lines
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (_, line))| match Some(i) == current {
+                        true => Line::from(line.as_str()).style(Style::default().add_modifier(Modifier::BOLD)),
+                        false => Line::from(line.as_str()).style(Style::default().add_modifier(Modifier::DIM)),
+                    })
+                    .collect();
+                // Keep the current line a third down the panel, counting wrapped rows
+                let inner = block.inner(area);
+                let rows = |line: &str| (line.width() as u16).div_ceil(inner.width.max(1)).max(1);
+                let above: u16 = lines.iter().take(current.unwrap_or(0)).map(|(_, line)| rows(line)).sum();
+                (Text::from(text), above.saturating_sub(inner.height / 3))
+            }
+            None => (Text::from("No lyrics available"), 0),
         };
 
         let paragraph = Paragraph::new(lyrics_text)
-            .block(Block::default().title("Lyrics").borders(Borders::ALL))
+            .block(block)
             .style(Style::default().fg(Color::Yellow))
-            .wrap(ratatui::widgets::Wrap { trim: true });
+            .wrap(ratatui::widgets::Wrap { trim: true })
+            .scroll((scroll, 0));
 
         paragraph.render(area, buf);
     }
 
     fn render_progress_bar(state: &PlayerState, area: Rect, buf: &mut Buffer) {
+        let block = Block::default().borders(Borders::ALL);
+        let inner = block.inner(area);
+        block.render(area, buf);
+        Self::render_progress(state, inner, buf);
+    }
+
+    /// Fill `area` as a progress bar with the elapsed and total time centered on it
+    fn render_progress(state: &PlayerState, area: Rect, buf: &mut Buffer) {
+        if area.is_empty() {
+            return;
+        }
+
         let progress_ratio = if state.duration > 0.0 {
             (state.progress / state.duration).min(1.0)
         } else {
             0.0
         };
 
+        let buffered_ratio = if state.duration > 0.0 {
+            (state.buffered / state.duration).clamp(progress_ratio, 1.0)
+        } else {
+            progress_ratio
+        };
+
         let current_time = Self::format_time(state.progress);
         let total_time = Self::format_time(state.duration);
+        let label = match state.buffering {
+            _ if state.stopped => "Stopped".to_string(),
+            // A live stream has no end to show progress towards, only how long it has been on
+            Some(percent) if state.station.is_some() => format!("● LIVE  {}  (buffering {}%)", current_time, percent),
+            None if state.station.is_some() => format!("● LIVE  {}", current_time),
+            Some(percent) => format!("{} / {}  (buffering {}%)", current_time, total_time, percent),
+            None => format!("{} / {}", current_time, total_time),
+        };
+
+        // Played part in green, the part that is cached ahead of it dimmed
+        let played_width = (area.width as f32 * progress_ratio) as u16;
+        let buffered_width = (area.width as f32 * buffered_ratio) as u16;
+        for y in area.top()..area.bottom() {
+            for x in 0..area.width {
+                let color = if x < played_width {
+                    state.accent.unwrap_or(Color::Green)
+                } else if x < buffered_width {
+                    Color::DarkGray
+                } else {
+                    continue;
+                };
+                buf[(area.x + x, y)].set_bg(color);
+            }
+        }
+
+        let label_width = (display_width(&label) as u16).min(area.width);
+        buf.set_stringn(
+            area.x + (area.width - label_width) / 2,
+            area.y + (area.height - 1) / 2,
+            &label,
+            label_width as usize,
+            Style::default().fg(Color::White),
+        );
+    }
+
+    /// Render the player as a single line: play state and title on the left, progress on the right
+    pub fn render_compact(state: &PlayerState, area: Rect, buf: &mut Buffer) {
+        let line = Rect::new(area.x, area.y, area.width, area.height.min(1));
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(line);
+
+        let symbol = Self::state_symbol(state);
+        let title = match (&state.station, &state.current_song) {
+            (Some(station), _) => format!("📻 {}", station.name),
+            (None, Some(song)) => format!("{} - {}", song.title, song.artist.as_deref().unwrap_or("Unknown Artist")),
+            (None, None) => "No track selected".to_string(),
+        };
+        let labels = Self::labels(state);
+        let width = columns[0].width.saturating_sub(3 + display_width(&labels) as u16) as usize;
+        Paragraph::new(format!("{} {}{} ", symbol, labels, state.title_marquee.window(&title, width)))
+            .style(Style::default().fg(Color::White))
+            .render(columns[0], buf);
+
+        Self::render_progress(state, columns[1], buf);
+    }
 
-        let gauge = Gauge::default()
-            .block(Block::default().borders(Borders::ALL))
-            .gauge_style(Style::default().fg(Color::Green))
-            .percent((progress_ratio * 100.0) as u16)
-            .label(format!("{} / {}", current_time, total_time));
+    /// Where the audio plays when it isn't this machine, the speed when it isn't normal,
+    /// whether the current song is scrobbled when it isn't, for the session or for good,
+    /// and why playback was paused if the user didn't pause it
+    fn labels(state: &PlayerState) -> String {
+        let output = if state.jukebox_active() { JUKEBOX_LABEL } else { "" };
+        let speed = if state.speed == 1.0 { String::new() } else { format!("{}x ", state.speed) };
+        let private = if state.scrobbling && !state.song_unscrobbled { "" } else { PRIVATE_LABEL };
+        let paused = state.pause_reason.as_ref().map_or(String::new(), |reason| format!("⏸ {} ", reason));
+        format!("{}{}{}{}", output, speed, private, paused)
+    }
 
-        gauge.render(area, buf);
+    /// ▶ while playing, ⏸ while paused and ⏹ once stopped
+    fn state_symbol(state: &PlayerState) -> &'static str {
+        if state.stopped {
+            "⏹"
+        } else if state.is_playing {
+            "▶"
+        } else {
+            "⏸"
+        }
     }
 
     fn render_controls(state: &PlayerState, area: Rect, buf: &mut Buffer) {
         let play_pause_symbol = if state.is_playing { "⏸" } else { "▶" };
-        let controls_text = format!("⏮  {}  ⏭  [Space: Play/Pause, ←/→: Prev/Next]", play_pause_symbol);
+        let controls_text = format!("⏮  {}  ⏭  [Space: Play/Pause, s: Stop, p/n: Prev/Next, +/-: Volume]", play_pause_symbol);
 
-        let paragraph = Paragraph::new(controls_text)
-            .block(Block::default().title("Controls").borders(Borders::ALL))
+        let block = Block::default().title("Controls").borders(Borders::ALL);
+        let inner = block.inner(area);
+        block.style(Style::default().fg(Color::Magenta)).render(area, buf);
+
+        let volume = Self::volume_text(state);
+        let labels = Self::labels(state);
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Length(display_width(&labels) as u16),
+                Constraint::Min(0),
+                Constraint::Length(display_width(&volume) as u16 + 1),
+            ])
+            .split(inner);
+        Paragraph::new(labels)
+            .style(Style::default().fg(Color::DarkGray))
+            .render(columns[0], buf);
+        Paragraph::new(controls_text)
             .style(Style::default().fg(Color::Magenta))
-            .alignment(Alignment::Center);
+            .alignment(Alignment::Center)
+            .render(columns[1], buf);
+        Paragraph::new(volume)
+            .style(Style::default().fg(if state.muted { Color::DarkGray } else { Color::Magenta }))
+            .alignment(Alignment::Right)
+            .render(columns[2], buf);
+    }
 
-        paragraph.render(area, buf);
+    /// A small gauge and the volume in percent, e.g. "🔊 ██████░░ 75%", followed by the
+    /// current song's adjustment if it has one
+    fn volume_text(state: &PlayerState) -> String {
+        let Some(volume) = state.volume else {
+            return String::new();
+        };
+        if state.muted {
+            return "🔇 muted".to_string();
+        }
+        let filled = ((volume / 100.0) * VOLUME_GAUGE_WIDTH as f64).round().clamp(0.0, VOLUME_GAUGE_WIDTH as f64) as usize;
+        let gauge = format!("🔊 {}{} {:>3.0}%", "█".repeat(filled), "░".repeat(VOLUME_GAUGE_WIDTH - filled), volume);
+        if state.volume_offset == 0.0 {
+            gauge
+        } else {
+            format!("{} ({:+.0})", gauge, state.volume_offset)
+        }
     }
 
     fn format_time(seconds: f32) -> String {
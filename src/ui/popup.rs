@@ -0,0 +1,132 @@
+//! Building blocks shared by the modal popups: the framed popup itself with the keys it
+//! takes listed along its bottom, and a list whose selection wraps around.
+
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+};
+
+/// Highlight of the selected row in popup lists
+pub const SELECTED: Style = Style::new().bg(Color::Blue).fg(Color::White);
+
+/// A framed popup centered on the screen, cleared of whatever is drawn below it
+pub struct Popup<'a> {
+    title: Line<'a>,
+    /// Width in percent of the screen
+    width: u16,
+    /// Height in rows, including the frame
+    height: u16,
+    /// Keys the popup takes, e.g. "Enter: Add  Esc: Cancel"
+    hint: Option<&'a str>,
+    style: Style,
+}
+
+impl<'a> Popup<'a> {
+    pub fn new(title: impl Into<Line<'a>>, width: u16, height: u16) -> Self {
+        Self {
+            title: title.into(),
+            width,
+            height,
+            hint: None,
+            style: Style::default().fg(Color::White),
+        }
+    }
+
+    pub fn hint(mut self, hint: &'a str) -> Self {
+        self.hint = Some(hint);
+        self
+    }
+
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Draw the frame and hint, returns the area left for the content
+    pub fn render(self, area: Rect, buf: &mut Buffer) -> Rect {
+        let popup = centered_rect(self.width, self.height, area);
+        Clear.render(popup, buf);
+
+        let block = Block::default()
+            .title(self.title)
+            .borders(Borders::ALL)
+            .style(self.style);
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        let Some(hint) = self.hint else {
+            return inner;
+        };
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(inner);
+        Paragraph::new(hint)
+            .style(Style::default().fg(Color::DarkGray))
+            .alignment(Alignment::Center)
+            .render(rows[1], buf);
+        rows[0]
+    }
+}
+
+/// Selection in a list of rows, moving past either end wraps around to the other
+#[derive(Debug, Clone, Default)]
+pub struct SelectList {
+    pub state: ListState,
+}
+
+impl SelectList {
+    /// Select the first of the `len` rows, if there are any
+    pub fn first(len: usize) -> Self {
+        let mut list = Self::default();
+        list.reset(len);
+        list
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.state.selected()
+    }
+
+    /// Back to the first row after the rows changed
+    pub fn reset(&mut self, len: usize) {
+        self.state.select((len > 0).then_some(0));
+    }
+
+    pub fn next(&mut self, len: usize) {
+        if len > 0 {
+            let i = self.state.selected().map_or(0, |i| (i + 1) % len);
+            self.state.select(Some(i));
+        }
+    }
+
+    pub fn previous(&mut self, len: usize) {
+        if len > 0 {
+            let i = match self.state.selected() {
+                Some(0) | None => len - 1,
+                Some(i) => i - 1,
+            };
+            self.state.select(Some(i));
+        }
+    }
+
+    /// Render `items` with the selected one highlighted and scrolled into view
+    pub fn render<'i>(&mut self, items: impl IntoIterator<Item = ListItem<'i>>, block: Option<Block<'i>>, area: Rect, buf: &mut Buffer) {
+        let mut list = List::new(items).highlight_style(SELECTED);
+        if let Some(block) = block {
+            list = list.block(block);
+        }
+        StatefulWidget::render(list, area, buf, &mut self.state);
+    }
+}
+
+/// Center a rectangle of the given width percentage and fixed height inside `area`
+pub fn centered_rect(percent_x: u16, height: u16, area: Rect) -> Rect {
+    let width = area.width * percent_x / 100;
+    let height = height.min(area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
@@ -0,0 +1,137 @@
+use crate::ui::input::{InputKey, LineInput};
+use crossterm::event::KeyEvent;
+use ratatui::{
+    prelude::*,
+    widgets::{Clear, Paragraph},
+};
+use std::time::{Duration, Instant};
+use subsonic::{SearchCategory, SearchResult};
+
+/// How long typing has to pause before a live search is sent
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Shorter queries are only searched when submitted with Enter
+const MIN_LIVE_QUERY: usize = 2;
+
+/// Search results arriving in the background, sent to the app as [`Message::Search`](crate::Message::Search)
+#[derive(Debug, Clone)]
+pub enum SearchUpdate {
+    /// First results of a query
+    Results(String, SearchResult),
+    /// The next page of one category of a query's results
+    More(String, SearchCategory, SearchResult),
+}
+
+/// The `/` search prompt with history navigation and inline suggestions
+#[derive(Debug, Clone, Default)]
+pub struct SearchPrompt {
+    pub input: LineInput,
+    /// Position in the search history while cycling with Up/Down
+    history_index: Option<usize>,
+    /// When the input last changed without being searched yet
+    edited_at: Option<Instant>,
+}
+
+impl SearchPrompt {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply an editing key, returns whether it was one
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        match self.input.handle_key(key) {
+            InputKey::Edited => self.edited(),
+            InputKey::Moved => {}
+            InputKey::Unhandled => return false,
+        }
+        true
+    }
+
+    pub fn paste(&mut self, text: &str) {
+        self.input.insert_str(text);
+        self.edited();
+    }
+
+    fn edited(&mut self) {
+        self.history_index = None;
+        self.edited_at = Some(Instant::now());
+    }
+
+    /// Returns the query to search for once typing has paused long enough
+    pub fn take_debounced(&mut self) -> Option<String> {
+        let edited_at = self.edited_at?;
+        if edited_at.elapsed() < DEBOUNCE {
+            return None;
+        }
+        self.edited_at = None;
+
+        let query = self.input.text().trim();
+        (query.chars().count() >= MIN_LIVE_QUERY).then(|| query.to_string())
+    }
+
+    /// Step to the next older query in `history` (most recent first)
+    pub fn history_previous(&mut self, history: &[String]) {
+        let index = match self.history_index {
+            Some(i) if i + 1 < history.len() => i + 1,
+            Some(i) => i,
+            None if !history.is_empty() => 0,
+            None => return,
+        };
+        self.history_index = Some(index);
+        self.input.set_text(&history[index]);
+        self.edited_at = Some(Instant::now());
+    }
+
+    /// Step to the next newer query, clearing the input past the newest one
+    pub fn history_next(&mut self, history: &[String]) {
+        match self.history_index {
+            Some(0) | None => {
+                self.history_index = None;
+                self.input.clear();
+            }
+            Some(i) => {
+                self.history_index = Some(i - 1);
+                self.input.set_text(&history[i - 1]);
+            }
+        }
+        self.edited_at = Some(Instant::now());
+    }
+
+    /// The rest of the most recent history entry that starts with the current input
+    pub fn suggestion(&self, history: &[String]) -> Option<String> {
+        let text = self.input.text();
+        if text.is_empty() || !self.input.at_end() {
+            return None;
+        }
+
+        let input = text.to_lowercase();
+        history
+            .iter()
+            .find(|query| query.len() > text.len() && query.to_lowercase().starts_with(&input))
+            .map(|query| query.chars().skip(text.chars().count()).collect())
+    }
+
+    pub fn accept_suggestion(&mut self, history: &[String]) {
+        if let Some(rest) = self.suggestion(history) {
+            self.input.insert_str(&rest);
+            self.edited();
+        }
+    }
+}
+
+pub struct SearchPromptWidget;
+
+impl SearchPromptWidget {
+    /// Render the prompt on the bottom line of `area`, with the suggestion dimmed after the cursor
+    pub fn render(prompt: &SearchPrompt, history: &[String], area: Rect, buf: &mut Buffer) {
+        let line_area = Rect::new(area.x, area.bottom().saturating_sub(1), area.width, 1);
+        Clear.render(line_area, buf);
+
+        let suggestion = prompt.suggestion(history).unwrap_or_default();
+        let mut spans = vec![Span::raw("/")];
+        spans.extend(prompt.input.spans(true));
+        spans.push(Span::styled(suggestion, Style::default().fg(Color::DarkGray)));
+        let line = Line::from(spans);
+        Paragraph::new(line).render(line_area, buf);
+    }
+}
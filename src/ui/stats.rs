@@ -0,0 +1,131 @@
+use crate::ui::popup::Popup;
+use crate::ui::tree::TreeState;
+use ratatui::{
+    prelude::*,
+    widgets::Paragraph,
+};
+use std::collections::HashMap;
+
+/// How many genres and new albums the overview lists
+const TOP_ENTRIES: usize = 5;
+
+/// Library totals computed from the metadata that has been loaded so far
+#[derive(Debug, Clone, Default)]
+pub struct LibraryStats {
+    pub artists: usize,
+    /// Artists whose albums are in the local cache
+    pub cached_artists: usize,
+    pub albums: u64,
+    pub songs: u64,
+    pub duration: u64,
+    pub size: u64,
+    pub genres: Vec<(String, u32)>,
+    /// (created date, album, artist) of the most recently added albums
+    pub newest: Vec<(String, String, String)>,
+}
+
+impl LibraryStats {
+    pub fn from_tree(tree: &TreeState) -> Self {
+        let mut stats = Self {
+            artists: tree.artists.len(),
+            cached_artists: tree.artist_albums.len(),
+            albums: tree.artists.iter().map(|a| a.album_count.unwrap_or(0) as u64).sum(),
+            ..Self::default()
+        };
+
+        let albums: Vec<_> = tree.artist_albums.values().flatten().collect();
+        stats.songs = albums.iter().map(|a| a.song_count.unwrap_or(0) as u64).sum();
+        stats.duration = albums.iter().map(|a| a.duration.unwrap_or(0) as u64).sum();
+        stats.size = tree.album_songs.values().flatten().filter_map(|s| s.size).sum();
+
+        // Prefer the server's genre counts, fall back to counting cached songs
+        let mut genres: Vec<(String, u32)> = match &tree.genres {
+            Some(genres) => genres.iter().map(|g| (g.value.clone(), g.song_count.unwrap_or(0))).collect(),
+            None => {
+                let mut counts: HashMap<String, u32> = HashMap::new();
+                for song in tree.album_songs.values().flatten() {
+                    if let Some(genre) = &song.genre {
+                        *counts.entry(genre.clone()).or_default() += 1;
+                    }
+                }
+                counts.into_iter().collect()
+            }
+        };
+        genres.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        genres.truncate(TOP_ENTRIES);
+        stats.genres = genres;
+
+        let mut newest: Vec<_> = albums
+            .iter()
+            .filter_map(|a| {
+                a.created.as_ref().map(|created| {
+                    (
+                        created.chars().take(10).collect(),
+                        a.name.clone(),
+                        a.artist.clone().unwrap_or_else(|| "Unknown Artist".to_string()),
+                    )
+                })
+            })
+            .collect();
+        newest.sort_by(|a: &(String, String, String), b| b.0.cmp(&a.0));
+        newest.truncate(TOP_ENTRIES);
+        stats.newest = newest;
+
+        stats
+    }
+}
+
+pub struct StatsWidget;
+
+impl StatsWidget {
+    pub fn render(stats: &LibraryStats, area: Rect, buf: &mut Buffer) {
+        let mut lines = vec![
+            format!("Artists:  {}", stats.artists),
+            format!("Albums:   {}", stats.albums),
+            format!("Songs:    {} (in {} cached artists)", stats.songs, stats.cached_artists),
+            format!("Duration: {}", format_duration(stats.duration)),
+            format!("Size:     {}", format_size(stats.size)),
+            String::new(),
+            "Top genres:".to_string(),
+        ];
+        if stats.genres.is_empty() {
+            lines.push("  (none cached)".to_string());
+        }
+        for (genre, count) in &stats.genres {
+            lines.push(format!("  {} ({} songs)", genre, count));
+        }
+
+        lines.push(String::new());
+        lines.push("Newest additions:".to_string());
+        if stats.newest.is_empty() {
+            lines.push("  (none cached)".to_string());
+        }
+        for (created, album, artist) in &stats.newest {
+            lines.push(format!("  {}  {} - {}", created, artist, album));
+        }
+
+        let inner = Popup::new("Library Statistics", 60, lines.len() as u16 + 2).render(area, buf);
+        Paragraph::new(lines.join("\n")).render(inner, buf);
+    }
+}
+
+fn format_duration(seconds: u64) -> String {
+    let hours = seconds / 3600;
+    let minutes = (seconds % 3600) / 60;
+    if hours >= 24 {
+        format!("{}d {}h {}m", hours / 24, hours % 24, minutes)
+    } else {
+        format!("{}h {}m", hours, minutes)
+    }
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
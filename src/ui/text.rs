@@ -0,0 +1,83 @@
+//! Text measured in terminal columns rather than bytes or characters: CJK characters
+//! and most emoji take two columns, and text is only ever cut between graphemes.
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Columns `text` takes up in the terminal
+pub fn display_width(text: &str) -> usize {
+    text.width()
+}
+
+/// `text` cut down to at most `width` columns, ending in "…" when anything was cut off
+pub fn truncate(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+
+    let mut result = String::new();
+    let mut used = 0;
+    for grapheme in text.graphemes(true) {
+        used += grapheme.width();
+        if used > width - 1 {
+            break;
+        }
+        result.push_str(grapheme);
+    }
+    result.push('…');
+    result
+}
+
+/// The `width` columns of `text` starting `offset` columns in. A wide character cut in
+/// half at either edge is replaced by a space, so the result is always exactly `width`
+/// columns wide as long as the text reaches that far.
+pub fn window(text: &str, offset: usize, width: usize) -> String {
+    let mut result = String::new();
+    let mut column = 0;
+    for grapheme in text.graphemes(true) {
+        let start = column;
+        column += grapheme.width();
+        if column <= offset {
+            continue;
+        }
+        if column > offset + width {
+            // Partly visible on the right
+            result.push_str(&" ".repeat((offset + width).saturating_sub(start.max(offset))));
+            break;
+        }
+        if start < offset {
+            // Partly visible on the left
+            result.push_str(&" ".repeat(column - offset));
+        } else {
+            result.push_str(grapheme);
+        }
+    }
+    result
+}
+
+/// `html` as plain text: tags are dropped, the common entities decoded and runs of
+/// whitespace collapsed, for biographies and comments servers pass on from the web
+pub fn strip_html(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    let text = text
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,39 @@
+use ratatui::prelude::*;
+
+/// Cells a thumbnail takes up next to an album name
+pub const WIDTH: usize = 3;
+
+/// Edge length in pixels of the covers requested for thumbnails
+pub const REQUEST_SIZE: u32 = 32;
+
+/// A cover shrunk to `WIDTH`×2 pixels, drawn as one row of upper half blocks
+/// with the top pixel in the foreground and the bottom one in the background
+#[derive(Debug, Clone, Copy)]
+pub struct Thumbnail {
+    top: [Color; WIDTH],
+    bottom: [Color; WIDTH],
+}
+
+impl Thumbnail {
+    pub fn from_cover(cover_art: &[u8]) -> Option<Self> {
+        let image = image::load_from_memory(cover_art).ok()?;
+        let pixels = image
+            .resize_exact(WIDTH as u32, 2, image::imageops::FilterType::Triangle)
+            .to_rgb8();
+        let color = |x: usize, y: u32| {
+            let [r, g, b] = pixels.get_pixel(x as u32, y).0;
+            Color::Rgb(r, g, b)
+        };
+        Some(Self {
+            top: std::array::from_fn(|x| color(x, 0)),
+            bottom: std::array::from_fn(|x| color(x, 1)),
+        })
+    }
+
+    pub fn spans(&self) -> impl Iterator<Item = Span<'static>> + '_ {
+        self.top
+            .iter()
+            .zip(&self.bottom)
+            .map(|(top, bottom)| Span::styled("▀", Style::default().fg(*top).bg(*bottom)))
+    }
+}
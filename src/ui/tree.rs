@@ -1,21 +1,95 @@
-use crate::subsonic::{Artist, Album, Song};
+use subsonic::{AlbumListType, Artist, ArtistInfo, Album, Song, Genre, InternetRadioStation, MusicFolder, Playlist, SearchCategory, SearchResult, Starred, SEARCH_PAGE_SIZE};
 use ratatui::{
     prelude::*,
-    widgets::{Block, Borders, List, ListItem, ListState},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
 };
-use std::collections::HashMap;
+use crate::health::Connection;
+use crate::ui::thumbnail::{self, Thumbnail};
+use crate::ui::collate;
+use crate::ui::text::{display_width, strip_html, truncate};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use log::info;
 
+/// Number of locally played songs listed in the "Recently Played" section
+const RECENT_SONGS: usize = 25;
 
-#[derive(Debug, Clone)]
+/// How long the cursor has to rest on a song before its details are shown
+const PREVIEW_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Default)]
 pub struct TreeState {
     pub artists: Vec<Artist>,
+    /// Index sections of the artist list ("A", "B", …) and where in `artists` each starts
+    pub artist_index: Vec<(String, usize)>,
+    /// The next key picks an index section to jump to
+    pub jump_pending: bool,
+    /// Sort artists by `collate::sort_key` and index them by its first letter, instead of
+    /// keeping the server's order and index
+    pub collate_artists: bool,
+    /// The artist list is still downloading, `artists` holds the sections received so far
+    pub loading_artists: bool,
     pub expanded_artists: HashMap<String, bool>,
     pub expanded_albums: HashMap<String, bool>,
     pub artist_albums: HashMap<String, Vec<Album>>,
+    /// Biographies and similar artists by artist ID
+    pub artist_info: HashMap<String, ArtistInfo>,
     pub album_songs: HashMap<String, Vec<Song>>,
+    /// List the directories of the music folders below the sections instead of the artists
+    pub browse_folders: bool,
+    /// Top directories of the music folders, once loaded
+    pub folders: Option<Vec<Folder>>,
+    /// Songs lying directly in a music folder, listed after the top directories
+    pub folder_songs: Vec<Song>,
+    pub expanded_folders: HashMap<String, bool>,
+    /// Subdirectories and songs of the loaded directories
+    pub folder_contents: HashMap<String, (Vec<Folder>, Vec<Song>)>,
+    /// The server's music folders, once loaded
+    pub music_folders: Option<Vec<MusicFolder>>,
+    /// The music folder the library is listed from, all of them when `None`
+    pub music_folder: Option<MusicFolder>,
+    pub expanded_sections: HashMap<TreeSection, bool>,
+    pub radio_stations: Option<Vec<InternetRadioStation>>,
+    pub playlists: Option<Vec<Playlist>>,
+    pub expanded_playlists: HashMap<String, bool>,
+    pub playlist_songs: HashMap<String, Vec<Song>>,
+    /// Albums of the Browse section, by the order they are listed in
+    pub album_lists: HashMap<AlbumListType, Vec<Album>>,
+    pub expanded_album_lists: HashMap<AlbumListType, bool>,
+    pub genres: Option<Vec<Genre>>,
+    pub expanded_genres: HashMap<String, bool>,
+    /// First songs of each expanded genre, `P` and `S` play all of them
+    pub genre_songs: HashMap<String, Vec<Song>>,
+    pub recent_songs: Vec<Song>,
+    /// Upcoming songs of the play queue
+    pub queue_songs: Vec<Song>,
+    /// Title of the song radio mode was started from, while it is topping up the queue
+    pub queue_radio: Option<String>,
+    /// Number and length of the upcoming songs, `None` while the queue is empty
+    pub queue_summary: Option<String>,
+    pub search_results: Option<SearchResults>,
+    /// When set, only starred items (and what leads to them) are shown
+    pub starred_filter: Option<StarredFilter>,
+    /// Names of the sources merged into the library, whose items are badged with them
+    pub sources: Vec<String>,
+    /// Whether the server answers, shown in the title
+    pub connection: Connection,
+    /// The playing song, marked with ▶ in the tree
     pub selected_song: Option<Song>,
+    /// Item to select as soon as it shows up in the tree (e.g. after its parent loaded)
+    pub pending_reveal: Option<TreeTarget>,
     pub list_state: ListState,
     pub items: Vec<TreeItem>,
+    /// Cursor position and since when it is there, for the song preview
+    cursor_since: Option<(Option<usize>, Instant)>,
+    /// Songs and albums marked for a bulk action, in the order they were marked
+    pub marked: Vec<TreeItemType>,
+    /// Row of the last mark, where `mark_range` starts
+    mark_anchor: Option<usize>,
+    /// Draw cover thumbnails next to albums
+    pub show_thumbnails: bool,
+    /// Thumbnails by cover art ID
+    pub thumbnails: HashMap<String, Thumbnail>,
 }
 
 #[derive(Debug, Clone)]
@@ -24,42 +98,349 @@ pub struct TreeItem {
     pub id: String,
     pub display_text: String,
     pub item_type: TreeItemType,
-    #[allow(dead_code)]
     pub level: usize,
+    /// Top-level section the row belongs to, `None` for the artist library
+    pub section: Option<TreeSection>,
+}
+
+/// A directory of the music folders, for browsing by folder
+#[derive(Debug, Clone)]
+pub struct Folder {
+    pub id: String,
+    pub name: String,
 }
 
 #[derive(Debug, Clone)]
 pub enum TreeItemType {
+    Section(TreeSection),
     Artist(Artist),
     Album(Album),
+    Folder(Folder),
     Song(Song),
+    RadioStation(InternetRadioStation),
+    Genre(Genre),
+    Playlist(Playlist),
+    AlbumList(AlbumListType),
+    /// Fetch the next page of a search result category
+    LoadMore(SearchCategory),
+}
+
+impl TreeItemType {
+    /// Name of the item as shown in the breadcrumb
+    pub fn name(&self) -> &str {
+        match self {
+            TreeItemType::Section(section) => section.title(),
+            TreeItemType::Artist(artist) => &artist.name,
+            TreeItemType::Album(album) => &album.name,
+            TreeItemType::Folder(folder) => &folder.name,
+            TreeItemType::Song(song) => &song.title,
+            TreeItemType::RadioStation(station) => &station.name,
+            TreeItemType::Genre(genre) => &genre.value,
+            TreeItemType::Playlist(playlist) => &playlist.name,
+            TreeItemType::AlbumList(list) => album_list_title(*list),
+            TreeItemType::LoadMore(_) => "Load more",
+        }
+    }
+
+    /// ID of the item on the server, for the items that have one
+    pub fn id(&self) -> Option<&str> {
+        match self {
+            TreeItemType::Artist(artist) => Some(&artist.id),
+            TreeItemType::Album(album) => Some(&album.id),
+            TreeItemType::Folder(folder) => Some(&folder.id),
+            TreeItemType::Song(song) => Some(&song.id),
+            TreeItemType::RadioStation(station) => Some(&station.id),
+            TreeItemType::Playlist(playlist) => Some(&playlist.id),
+            _ => None,
+        }
+    }
+}
+
+pub fn album_list_title(list: AlbumListType) -> &'static str {
+    match list {
+        AlbumListType::Newest => "Recently added",
+        AlbumListType::Recent => "Recently played",
+        AlbumListType::Frequent => "Most played",
+        AlbumListType::Random => "Random",
+        AlbumListType::ByYear => "By year",
+    }
+}
+
+/// Query and results of the last search, with the categories that may have more pages
+#[derive(Debug, Clone)]
+pub struct SearchResults {
+    pub query: String,
+    pub results: SearchResult,
+    pub more_artists: bool,
+    pub more_albums: bool,
+    pub more_songs: bool,
+}
+
+impl SearchResults {
+    pub fn count(&self, category: SearchCategory) -> usize {
+        match category {
+            SearchCategory::Artists => self.results.artist.len(),
+            SearchCategory::Albums => self.results.album.len(),
+            SearchCategory::Songs => self.results.song.len(),
+        }
+    }
+}
+
+/// An artist or album that should be revealed in the tree
+#[derive(Debug, Clone, PartialEq)]
+pub enum TreeTarget {
+    Artist(String),
+    Album(String),
+    Song(String),
+}
+
+/// IDs of starred items and of the artists/albums that contain them
+#[derive(Debug, Clone, Default)]
+pub struct StarredFilter {
+    artists: HashSet<String>,
+    albums: HashSet<String>,
+    songs: HashSet<String>,
+    artists_with_starred: HashSet<String>,
+    albums_with_starred: HashSet<String>,
+}
+
+impl StarredFilter {
+    pub fn new(starred: &Starred) -> Self {
+        let mut filter = Self {
+            artists: starred.artist.iter().map(|a| a.id.clone()).collect(),
+            albums: starred.album.iter().map(|a| a.id.clone()).collect(),
+            songs: starred.song.iter().map(|s| s.id.clone()).collect(),
+            ..Self::default()
+        };
+
+        for album in &starred.album {
+            filter.artists_with_starred.extend(album.artist_id.clone());
+        }
+        for song in &starred.song {
+            filter.artists_with_starred.extend(song.artist_id.clone());
+            filter.albums_with_starred.extend(song.album_id.clone());
+        }
+        filter
+    }
+
+    pub fn is_artist_starred(&self, artist: &Artist) -> bool {
+        artist.starred.is_some() || self.artists.contains(&artist.id)
+    }
+
+    pub fn is_album_starred(&self, album: &Album) -> bool {
+        album.starred.is_some() || self.albums.contains(&album.id)
+    }
+
+    pub fn is_song_starred(&self, song: &Song) -> bool {
+        song.starred.is_some() || self.songs.contains(&song.id)
+    }
+
+    fn shows_artist(&self, artist: &Artist) -> bool {
+        self.is_artist_starred(artist) || self.artists_with_starred.contains(&artist.id)
+    }
+
+    /// Albums of a starred artist are all shown
+    fn shows_album(&self, album: &Album, artist_starred: bool) -> bool {
+        artist_starred || self.is_album_starred(album) || self.albums_with_starred.contains(&album.id)
+    }
+
+    /// Songs of a starred artist or album are all shown
+    fn shows_song(&self, song: &Song, parent_starred: bool) -> bool {
+        parent_starred || self.is_song_starred(song)
+    }
 }
 
-impl Default for TreeState {
-    fn default() -> Self {
-        Self {
-            artists: Vec::new(),
-            expanded_artists: HashMap::new(),
-            expanded_albums: HashMap::new(),
-            artist_albums: HashMap::new(),
-            album_songs: HashMap::new(),
-            selected_song: None,
-            list_state: ListState::default(),
-            items: Vec::new(),
+/// Top-level tree nodes shown above the artist list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TreeSection {
+    SearchResults,
+    Queue,
+    RecentlyPlayed,
+    /// Albums of the whole library in different orders
+    Browse,
+    Playlists,
+    Genres,
+    Radio,
+}
+
+impl TreeSection {
+    pub const ALL: [TreeSection; 7] = [
+        TreeSection::SearchResults,
+        TreeSection::Queue,
+        TreeSection::RecentlyPlayed,
+        TreeSection::Browse,
+        TreeSection::Playlists,
+        TreeSection::Genres,
+        TreeSection::Radio,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            TreeSection::SearchResults => "Search",
+            TreeSection::Queue => "Queue",
+            TreeSection::RecentlyPlayed => "Recently Played",
+            TreeSection::Browse => "Browse",
+            TreeSection::Playlists => "Playlists",
+            TreeSection::Genres => "Genres",
+            TreeSection::Radio => "Radio",
         }
     }
 }
 
+/// Library contents loaded in the background, sent to the app as
+/// [`Message::Library`](crate::Message::Library) and applied by [`TreeState::update`]
+#[derive(Debug, Clone)]
+pub enum LibraryUpdate {
+    /// One index section of the artist list, more may follow
+    ArtistIndex(String, Vec<Artist>),
+    /// The artist list finished downloading, or failed to
+    ArtistsDone,
+    ArtistAlbums(String, Vec<Album>),
+    ArtistInfo(String, ArtistInfo),
+    AlbumSongs(String, Vec<Song>),
+    /// Thumbnail for the cover art ID
+    Thumbnail(String, Thumbnail),
+    RadioStations(Vec<InternetRadioStation>),
+    Genres(Vec<Genre>),
+    GenreSongs(String, Vec<Song>),
+    /// Top directories of the music folders and the songs next to them
+    Folders(Vec<Folder>, Vec<Song>),
+    /// Subdirectories and songs of the directory ID
+    FolderContents(String, Vec<Folder>, Vec<Song>),
+    MusicFolders(Vec<MusicFolder>),
+    Playlists(Vec<Playlist>),
+    AlbumList(AlbumListType, Vec<Album>),
+    PlaylistSongs(String, Vec<Song>),
+    Starred(Starred),
+}
+
 impl TreeState {
     pub fn new() -> Self {
         Self::default()
     }
 
-    pub fn set_artists(&mut self, artists: Vec<Artist>) {
-        self.artists = artists;
+    pub fn update(&mut self, update: LibraryUpdate) {
+        match update {
+            LibraryUpdate::ArtistIndex(index, artists) => self.add_artists(index, artists),
+            LibraryUpdate::ArtistsDone => self.set_loading_artists(false),
+            LibraryUpdate::ArtistAlbums(artist_id, albums) => self.set_artist_albums(artist_id, albums),
+            LibraryUpdate::ArtistInfo(artist_id, info) => {
+                self.artist_info.insert(artist_id, info);
+            }
+            LibraryUpdate::AlbumSongs(album_id, songs) => self.set_album_songs(album_id, songs),
+            LibraryUpdate::Thumbnail(cover_art_id, thumbnail) => self.set_thumbnail(cover_art_id, thumbnail),
+            LibraryUpdate::RadioStations(stations) => self.set_radio_stations(stations),
+            LibraryUpdate::Genres(genres) => self.set_genres(genres),
+            LibraryUpdate::GenreSongs(genre, songs) => self.set_genre_songs(genre, songs),
+            LibraryUpdate::Folders(folders, songs) => self.set_folders(folders, songs),
+            LibraryUpdate::FolderContents(folder_id, folders, songs) => self.set_folder_contents(folder_id, folders, songs),
+            LibraryUpdate::MusicFolders(folders) => self.music_folders = Some(folders),
+            LibraryUpdate::Playlists(playlists) => self.set_playlists(playlists),
+            LibraryUpdate::AlbumList(list, albums) => self.set_album_list(list, albums),
+            LibraryUpdate::PlaylistSongs(playlist_id, songs) => self.set_playlist_songs(playlist_id, songs),
+            LibraryUpdate::Starred(starred) => {
+                info!(
+                    "Showing starred items only ({} artists, {} albums, {} songs)",
+                    starred.artist.len(),
+                    starred.album.len(),
+                    starred.song.len()
+                );
+                self.set_starred_filter(Some(StarredFilter::new(&starred)));
+            }
+        }
+    }
+
+    /// Append the artists of one index section
+    pub fn add_artists(&mut self, index: String, artists: Vec<Artist>) {
+        let start = self.artists.len();
+        self.artists.extend(artists);
+        if self.collate_artists {
+            self.collate_artist_index();
+        } else {
+            self.artist_index.push((index, start));
+        }
         self.rebuild_items();
     }
 
+    /// Sort all artists received so far and index them again
+    fn collate_artist_index(&mut self) {
+        self.artists.sort_by_cached_key(|artist| (collate::sort_key(&artist.name), artist.name.clone()));
+        self.artist_index.clear();
+        for (i, artist) in self.artists.iter().enumerate() {
+            let letter = collate::index_letter(&collate::sort_key(&artist.name));
+            if self.artist_index.last().is_none_or(|(last, _)| *last != letter) {
+                self.artist_index.push((letter, i));
+            }
+        }
+    }
+
+    pub fn set_loading_artists(&mut self, loading: bool) {
+        self.loading_artists = loading;
+    }
+
+    /// Move the cursor to the first shown artist of the index section starting with `key`,
+    /// or to the first top directory starting with it while browsing folders
+    pub fn jump_to_index(&mut self, key: char) -> bool {
+        if self.browse_folders {
+            let key = collate::fold(&key.to_string()).chars().next();
+            let row = self.items.iter().position(|item| {
+                item.section.is_none()
+                    && item.level == 0
+                    && matches!(&item.item_type, TreeItemType::Folder(folder)
+                        if collate::fold(&collate::index_letter(&collate::sort_key(&folder.name))).chars().next() == key)
+            });
+            if row.is_some() {
+                self.list_state.select(row);
+            }
+            return row.is_some();
+        }
+        let Some(i) = self
+            .artist_index
+            .iter()
+            .position(|(name, _)| collate::fold(name).chars().next() == collate::fold(&key.to_string()).chars().next())
+        else {
+            return false;
+        };
+        let start = self.artist_index[i].1;
+        let end = self.artist_index.get(i + 1).map_or(self.artists.len(), |(_, start)| *start);
+        let ids: HashSet<&str> = self.artists[start..end].iter().map(|a| a.id.as_str()).collect();
+        let row = self.items.iter().position(|item| {
+            item.section.is_none() && matches!(&item.item_type, TreeItemType::Artist(artist) if ids.contains(artist.id.as_str()))
+        });
+        if row.is_some() {
+            self.list_state.select(row);
+        }
+        row.is_some()
+    }
+
+    /// Names of the selected row and of the rows it is nested in, outermost first
+    pub fn breadcrumb(&self) -> Vec<&str> {
+        let Some(selected) = self.list_state.selected().filter(|i| *i < self.items.len()) else {
+            return Vec::new();
+        };
+        let item = &self.items[selected];
+        let mut crumbs = vec![item.item_type.name()];
+        let mut level = item.level;
+        for parent in self.items[..selected].iter().rev() {
+            if level == 0 {
+                break;
+            }
+            if parent.level < level {
+                crumbs.push(parent.item_type.name());
+                level = parent.level;
+            }
+        }
+        if item.section.is_none() {
+            crumbs.push(if self.browse_folders { "Folders" } else { "Library" });
+        }
+        crumbs.reverse();
+        crumbs
+    }
+
+    pub fn set_thumbnail(&mut self, cover_art_id: String, thumbnail: Thumbnail) {
+        self.thumbnails.insert(cover_art_id, thumbnail);
+    }
+
     pub fn toggle_artist(&mut self, artist_id: &str) -> bool {
         let expanded = !self.expanded_artists.get(artist_id).unwrap_or(&false);
         self.expanded_artists.insert(artist_id.to_string(), expanded);
@@ -74,6 +455,168 @@ impl TreeState {
         expanded && !self.album_songs.contains_key(album_id)
     }
 
+    pub fn toggle_folder(&mut self, folder_id: &str) -> bool {
+        let expanded = !self.expanded_folders.get(folder_id).unwrap_or(&false);
+        self.expanded_folders.insert(folder_id.to_string(), expanded);
+        self.rebuild_items();
+        expanded && !self.folder_contents.contains_key(folder_id)
+    }
+
+    pub fn toggle_playlist(&mut self, playlist_id: &str) -> bool {
+        let expanded = !self.expanded_playlists.get(playlist_id).unwrap_or(&false);
+        self.expanded_playlists.insert(playlist_id.to_string(), expanded);
+        self.rebuild_items();
+        expanded && !self.playlist_songs.contains_key(playlist_id)
+    }
+
+    pub fn toggle_genre(&mut self, genre: &str) -> bool {
+        let expanded = !self.expanded_genres.get(genre).unwrap_or(&false);
+        self.expanded_genres.insert(genre.to_string(), expanded);
+        self.rebuild_items();
+        expanded && !self.genre_songs.contains_key(genre)
+    }
+
+    /// Random albums are fetched anew every time the list is expanded
+    pub fn toggle_album_list(&mut self, list: AlbumListType) -> bool {
+        let expanded = !self.expanded_album_lists.get(&list).unwrap_or(&false);
+        self.expanded_album_lists.insert(list, expanded);
+        self.rebuild_items();
+        expanded && (list == AlbumListType::Random || !self.album_lists.contains_key(&list))
+    }
+
+    pub fn toggle_section(&mut self, section: TreeSection) -> bool {
+        let expanded = !self.expanded_sections.get(&section).unwrap_or(&false);
+        self.expanded_sections.insert(section, expanded);
+        self.rebuild_items();
+        match section {
+            TreeSection::SearchResults | TreeSection::Queue | TreeSection::RecentlyPlayed | TreeSection::Browse => false,
+            TreeSection::Playlists => expanded && self.playlists.is_none(),
+            TreeSection::Genres => expanded && self.genres.is_none(),
+            TreeSection::Radio => expanded && self.radio_stations.is_none(),
+        }
+    }
+
+    pub fn set_queue_songs(&mut self, songs: Vec<Song>, radio: Option<String>) {
+        self.queue_summary = crate::queue::summary(&songs);
+        self.queue_songs = songs;
+        self.queue_radio = radio;
+        self.rebuild_items();
+    }
+
+    /// Position in the queue of the song under the cursor, if it is in the Queue section
+    pub fn selected_queue_index(&self) -> Option<usize> {
+        let selected = self.list_state.selected()?;
+        let item = self.items.get(selected)?;
+        if item.section != Some(TreeSection::Queue) || !matches!(item.item_type, TreeItemType::Song(_)) {
+            return None;
+        }
+        let header = self.items[..selected]
+            .iter()
+            .rposition(|item| matches!(item.item_type, TreeItemType::Section(TreeSection::Queue)))?;
+        Some(selected - header - 1)
+    }
+
+    /// The playlist of the song under the cursor and the song's position in it,
+    /// if it is listed under a playlist
+    pub fn selected_playlist_entry(&self) -> Option<(&Playlist, usize)> {
+        let selected = self.list_state.selected()?;
+        if !matches!(self.items.get(selected)?.item_type, TreeItemType::Song(_)) {
+            return None;
+        }
+        let header = self.items[..selected]
+            .iter()
+            .rposition(|item| !matches!(item.item_type, TreeItemType::Song(_)))?;
+        match &self.items[header].item_type {
+            TreeItemType::Playlist(playlist) => Some((playlist, selected - header - 1)),
+            _ => None,
+        }
+    }
+
+    pub fn set_recent_songs(&mut self, songs: Vec<Song>) {
+        self.recent_songs = songs;
+        self.rebuild_items();
+    }
+
+    /// Show search results in their own expanded section and move the cursor onto it
+    pub fn set_search_results(&mut self, query: String, results: SearchResult) {
+        let full_page = |len: usize| len == SEARCH_PAGE_SIZE as usize;
+        self.search_results = Some(SearchResults {
+            query,
+            more_artists: full_page(results.artist.len()),
+            more_albums: full_page(results.album.len()),
+            more_songs: full_page(results.song.len()),
+            results,
+        });
+        self.expanded_sections.insert(TreeSection::SearchResults, true);
+        self.rebuild_items();
+        self.list_state.select(Some(0));
+    }
+
+    /// Append the next page of one result category, if it still belongs to the current search
+    pub fn append_search_results(&mut self, query: &str, category: SearchCategory, page: SearchResult) {
+        let Some(search) = self.search_results.as_mut().filter(|s| s.query == query) else {
+            return;
+        };
+
+        let full_page = |len: usize| len == SEARCH_PAGE_SIZE as usize;
+        match category {
+            SearchCategory::Artists => {
+                search.more_artists = full_page(page.artist.len());
+                search.results.artist.extend(page.artist);
+            }
+            SearchCategory::Albums => {
+                search.more_albums = full_page(page.album.len());
+                search.results.album.extend(page.album);
+            }
+            SearchCategory::Songs => {
+                search.more_songs = full_page(page.song.len());
+                search.results.song.extend(page.song);
+            }
+        }
+        self.rebuild_items();
+    }
+
+    pub fn set_starred_filter(&mut self, filter: Option<StarredFilter>) {
+        self.starred_filter = filter;
+        self.rebuild_items();
+    }
+
+    pub fn set_genres(&mut self, genres: Vec<Genre>) {
+        self.genres = Some(genres);
+        self.rebuild_items();
+    }
+
+    pub fn set_playlists(&mut self, playlists: Vec<Playlist>) {
+        self.playlists = Some(playlists);
+        self.rebuild_items();
+    }
+
+    pub fn set_album_list(&mut self, list: AlbumListType, albums: Vec<Album>) {
+        self.album_lists.insert(list, albums);
+        self.rebuild_items();
+    }
+
+    pub fn set_genre_songs(&mut self, genre: String, songs: Vec<Song>) {
+        self.genre_songs.insert(genre, songs);
+        self.rebuild_items();
+    }
+
+    pub fn set_playlist_songs(&mut self, playlist_id: String, songs: Vec<Song>) {
+        self.playlist_songs.insert(playlist_id, songs);
+        self.rebuild_items();
+    }
+
+    /// Forget the loaded playlists after one changed, returns whether they were shown
+    pub fn invalidate_playlists(&mut self) -> bool {
+        self.playlist_songs.clear();
+        self.playlists.take().is_some()
+    }
+
+    pub fn set_radio_stations(&mut self, stations: Vec<InternetRadioStation>) {
+        self.radio_stations = Some(stations);
+        self.rebuild_items();
+    }
+
     pub fn set_artist_albums(&mut self, artist_id: String, albums: Vec<Album>) {
         self.artist_albums.insert(artist_id, albums);
         self.rebuild_items();
@@ -84,8 +627,90 @@ impl TreeState {
         self.rebuild_items();
     }
 
+    /// List the library by folder or by artist, returns whether the folders still need loading
+    pub fn set_browse_folders(&mut self, browse_folders: bool) -> bool {
+        if browse_folders != self.browse_folders {
+            self.browse_folders = browse_folders;
+            self.list_state.select(Some(0));
+            self.rebuild_items();
+        }
+        browse_folders && self.folders.is_none()
+    }
+
+    pub fn set_folders(&mut self, folders: Vec<Folder>, songs: Vec<Song>) {
+        self.folders = Some(folders);
+        self.folder_songs = songs;
+        self.rebuild_items();
+    }
+
+    pub fn set_folder_contents(&mut self, folder_id: String, folders: Vec<Folder>, songs: Vec<Song>) {
+        self.folder_contents.insert(folder_id, (folders, songs));
+        self.rebuild_items();
+    }
+
+    /// Forget the artists and what was loaded of them, before the list is loaded again
+    pub fn clear_artists(&mut self) {
+        self.artists.clear();
+        self.artist_index.clear();
+        self.expanded_artists.clear();
+        self.expanded_albums.clear();
+        self.artist_albums.clear();
+        self.album_songs.clear();
+        self.rebuild_items();
+    }
+
+    /// List the library from another music folder, forgetting everything loaded from the
+    /// previous one. The artists and, while browsing them, the folders need loading again.
+    pub fn set_music_folder(&mut self, folder: Option<MusicFolder>) {
+        self.music_folder = folder;
+        self.artists.clear();
+        self.artist_index.clear();
+        self.expanded_artists.clear();
+        self.expanded_albums.clear();
+        self.artist_albums.clear();
+        self.album_songs.clear();
+        self.folders = None;
+        self.folder_songs.clear();
+        self.expanded_folders.clear();
+        self.folder_contents.clear();
+        self.album_lists.clear();
+        self.expanded_album_lists.clear();
+        self.genre_songs.clear();
+        self.expanded_genres.clear();
+        self.search_results = None;
+        self.starred_filter = None;
+        self.pending_reveal = None;
+        self.list_state.select(Some(0));
+        self.rebuild_items();
+    }
+
     pub fn select_song(&mut self, song: Song) {
         self.selected_song = Some(song);
+        self.rebuild_items();
+    }
+
+    /// Nothing from the library is playing, e.g. while a radio station is
+    pub fn deselect_song(&mut self) {
+        self.selected_song = None;
+        self.rebuild_items();
+    }
+
+    /// Expand an artist (and optionally one of its albums) and move the cursor onto
+    /// the deepest of them, or onto a song of that album. If the rows are not loaded yet,
+    /// they are selected once they arrive.
+    pub fn reveal(&mut self, artist_id: &str, album_id: Option<&str>, song_id: Option<&str>) {
+        self.expanded_artists.insert(artist_id.to_string(), true);
+        self.pending_reveal = Some(match album_id {
+            Some(album_id) => {
+                self.expanded_albums.insert(album_id.to_string(), true);
+                match song_id {
+                    Some(song_id) => TreeTarget::Song(song_id.to_string()),
+                    None => TreeTarget::Album(album_id.to_string()),
+                }
+            }
+            None => TreeTarget::Artist(artist_id.to_string()),
+        });
+        self.rebuild_items();
     }
 
     pub fn next(&mut self) {
@@ -114,71 +739,639 @@ impl TreeState {
         }
     }
 
+    /// ID of a song or album, the items that can be marked
+    fn markable_id(item_type: &TreeItemType) -> Option<&str> {
+        match item_type {
+            TreeItemType::Song(song) => Some(&song.id),
+            TreeItemType::Album(album) => Some(&album.id),
+            _ => None,
+        }
+    }
+
+    pub fn is_marked(&self, item_type: &TreeItemType) -> bool {
+        let Some(id) = Self::markable_id(item_type) else {
+            return false;
+        };
+        self.marked.iter().any(|marked| Self::markable_id(marked) == Some(id))
+    }
+
+    /// Mark or unmark the song or album under the cursor and move on to the next row
+    pub fn toggle_mark(&mut self) {
+        let Some(item_type) = self.get_selected_item().map(|item| item.item_type.clone()) else {
+            return;
+        };
+        let Some(id) = Self::markable_id(&item_type).map(str::to_string) else {
+            return;
+        };
+        if self.is_marked(&item_type) {
+            self.marked.retain(|marked| Self::markable_id(marked) != Some(&id));
+        } else {
+            self.marked.push(item_type);
+        }
+        self.mark_anchor = self.list_state.selected();
+        self.next();
+    }
+
+    /// Mark every song and album between the last mark and the cursor
+    pub fn mark_range(&mut self) {
+        let Some(cursor) = self.list_state.selected() else {
+            return;
+        };
+        let anchor = self.mark_anchor.unwrap_or(cursor).min(self.items.len().saturating_sub(1));
+        let range = anchor.min(cursor)..=anchor.max(cursor);
+        let items: Vec<_> = self.items[range].iter().map(|item| item.item_type.clone()).collect();
+        for item_type in items {
+            if Self::markable_id(&item_type).is_some() && !self.is_marked(&item_type) {
+                self.marked.push(item_type);
+            }
+        }
+        self.mark_anchor = Some(cursor);
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.marked.clear();
+        self.mark_anchor = None;
+    }
+
+    /// The marked songs and albums, or the one under the cursor when nothing is marked.
+    /// The marks are cleared.
+    pub fn take_marked(&mut self) -> Vec<TreeItemType> {
+        let marked = std::mem::take(&mut self.marked);
+        self.mark_anchor = None;
+        if !marked.is_empty() {
+            return marked;
+        }
+        self.get_selected_item()
+            .map(|item| item.item_type.clone())
+            .filter(|item_type| Self::markable_id(item_type).is_some())
+            .into_iter()
+            .collect()
+    }
+
     pub fn get_selected_item(&self) -> Option<&TreeItem> {
         self.list_state.selected().and_then(|i| self.items.get(i))
     }
 
     fn rebuild_items(&mut self) {
-        self.items.clear();
-        
-        for artist in &self.artists {
-            let is_expanded = self.expanded_artists.get(&artist.id).unwrap_or(&false);
-            let prefix = if *is_expanded { "▼" } else { "▶" };
-            
-            self.items.push(TreeItem {
-                id: artist.id.clone(),
-                display_text: format!("{} {}", prefix, artist.name),
-                item_type: TreeItemType::Artist(artist.clone()),
+        let started = Instant::now();
+        let mut items = Vec::new();
+
+        for section in TreeSection::ALL {
+            if section == TreeSection::SearchResults && self.search_results.is_none() {
+                continue;
+            }
+
+            let is_expanded = *self.expanded_sections.get(&section).unwrap_or(&false);
+            let prefix = if is_expanded { "▼" } else { "▶" };
+            let title = match (&section, &self.search_results) {
+                (TreeSection::SearchResults, Some(search)) => format!("Search: {}", search.query),
+                (TreeSection::Queue, _) => {
+                    let mut title = section.title().to_string();
+                    if let Some(summary) = &self.queue_summary {
+                        title = format!("{} · {}", title, summary);
+                    }
+                    if let Some(seed) = &self.queue_radio {
+                        title = format!("{} · radio from \"{}\"", title, seed);
+                    }
+                    title
+                }
+                _ => section.title().to_string(),
+            };
+
+            items.push(TreeItem {
+                id: section.title().to_string(),
+                display_text: format!("{} [{}]", prefix, title),
+                item_type: TreeItemType::Section(section),
                 level: 0,
+                section: Some(section),
             });
 
-            if *is_expanded {
-                if let Some(albums) = self.artist_albums.get(&artist.id) {
-                    for album in albums {
-                        let is_album_expanded = self.expanded_albums.get(&album.id).unwrap_or(&false);
-                        let prefix = if *is_album_expanded { "▼" } else { "▶" };
-                        
-                        self.items.push(TreeItem {
-                            id: album.id.clone(),
-                            display_text: format!("  {} {}", prefix, album.name),
-                            item_type: TreeItemType::Album(album.clone()),
+            if !is_expanded {
+                continue;
+            }
+
+            let first_child = items.len();
+            match section {
+                TreeSection::SearchResults => {
+                    if let Some(search) = &self.search_results {
+                        for artist in &search.results.artist {
+                            self.push_artist(&mut items, artist, 1);
+                        }
+                        if search.more_artists {
+                            items.push(Self::load_more_item(SearchCategory::Artists, "artists"));
+                        }
+                        for album in &search.results.album {
+                            self.push_album(&mut items, album, 1, false, false);
+                        }
+                        if search.more_albums {
+                            items.push(Self::load_more_item(SearchCategory::Albums, "albums"));
+                        }
+                        for song in &search.results.song {
+                            self.push_song(&mut items, song, 1, false);
+                        }
+                        if search.more_songs {
+                            items.push(Self::load_more_item(SearchCategory::Songs, "songs"));
+                        }
+                    }
+                }
+                TreeSection::Queue => {
+                    // Not filtered, rows have to line up with queue positions
+                    for song in &self.queue_songs {
+                        items.push(self.song_item(song, 1, true));
+                    }
+                }
+                TreeSection::RecentlyPlayed => {
+                    let recent_songs = self.recent_songs
+                        .iter()
+                        .filter(|song| self.starred_filter.as_ref().is_none_or(|f| f.is_song_starred(song)))
+                        .take(RECENT_SONGS);
+                    for song in recent_songs {
+                        self.push_song(&mut items, song, 1, false);
+                    }
+                }
+                TreeSection::Browse => {
+                    for list in AlbumListType::ALL {
+                        let is_expanded = *self.expanded_album_lists.get(&list).unwrap_or(&false);
+                        let prefix = if is_expanded { "▼" } else { "▶" };
+                        items.push(TreeItem {
+                            id: list.param().to_string(),
+                            display_text: format!("  {} {}", prefix, album_list_title(list)),
+                            item_type: TreeItemType::AlbumList(list),
                             level: 1,
+                            section: None,
                         });
-
-                        if *is_album_expanded {
-                            if let Some(songs) = self.album_songs.get(&album.id) {
-                                for song in songs {
-                                    self.items.push(TreeItem {
-                                        id: song.id.clone(),
-                                        display_text: format!("    ♪ {}", song.title),
-                                        item_type: TreeItemType::Song(song.clone()),
-                                        level: 2,
-                                    });
-                                }
+                        if is_expanded {
+                            for album in self.album_lists.get(&list).into_iter().flatten() {
+                                self.push_album(&mut items, album, 2, false, true);
                             }
                         }
                     }
                 }
+                TreeSection::Playlists => {
+                    // Not filtered, rows have to line up with playlist positions
+                    for playlist in self.playlists.iter().flatten() {
+                        let is_expanded = *self.expanded_playlists.get(&playlist.id).unwrap_or(&false);
+                        let prefix = if is_expanded { "▼" } else { "▶" };
+                        items.push(TreeItem {
+                            id: playlist.id.clone(),
+                            display_text: format!("  {} ≡ {} ({})", prefix, playlist.name, playlist.song_count.unwrap_or(0)),
+                            item_type: TreeItemType::Playlist(playlist.clone()),
+                            level: 1,
+                            section: None,
+                        });
+                        if is_expanded {
+                            for song in self.playlist_songs.get(&playlist.id).into_iter().flatten() {
+                                items.push(self.song_item(song, 2, true));
+                            }
+                        }
+                    }
+                }
+                TreeSection::Genres => {
+                    for genre in self.genres.iter().flatten() {
+                        let is_expanded = *self.expanded_genres.get(&genre.value).unwrap_or(&false);
+                        let prefix = if is_expanded { "▼" } else { "▶" };
+                        items.push(TreeItem {
+                            id: genre.value.clone(),
+                            display_text: format!("  {} ♫ {} ({})", prefix, genre.value, genre.song_count.unwrap_or(0)),
+                            item_type: TreeItemType::Genre(genre.clone()),
+                            level: 1,
+                            section: None,
+                        });
+                        if is_expanded {
+                            for song in self.genre_songs.get(&genre.value).into_iter().flatten() {
+                                self.push_song(&mut items, song, 2, false);
+                            }
+                        }
+                    }
+                }
+                TreeSection::Radio => {
+                    for station in self.radio_stations.iter().flatten() {
+                        items.push(TreeItem {
+                            id: station.id.clone(),
+                            display_text: format!("  📻 {}", station.name),
+                            item_type: TreeItemType::RadioStation(station.clone()),
+                            level: 1,
+                            section: None,
+                        });
+                    }
+                }
+            }
+            for item in &mut items[first_child..] {
+                item.section = Some(section);
             }
         }
+
+        if self.browse_folders {
+            for folder in self.folders.iter().flatten() {
+                self.push_folder(&mut items, folder, 0);
+            }
+            for song in &self.folder_songs {
+                self.push_song(&mut items, song, 0, false);
+            }
+        } else {
+            for artist in &self.artists {
+                self.push_artist(&mut items, artist, 0);
+            }
+        }
+        self.items = items;
+
+        // Keep the cursor inside the list when filtering removed rows
+        if let Some(selected) = self.list_state.selected() {
+            if selected >= self.items.len() {
+                self.list_state.select(self.items.len().checked_sub(1));
+            }
+        }
+
+        if let Some(target) = &self.pending_reveal {
+            let position = self.items.iter().position(|item| {
+                item.section.is_none()
+                    && match (&item.item_type, target) {
+                        (TreeItemType::Artist(artist), TreeTarget::Artist(id)) => &artist.id == id,
+                        (TreeItemType::Album(album), TreeTarget::Album(id)) => &album.id == id,
+                        (TreeItemType::Song(song), TreeTarget::Song(id)) => &song.id == id,
+                        _ => false,
+                    }
+            });
+            if let Some(position) = position {
+                self.list_state.select(Some(position));
+                self.pending_reveal = None;
+            }
+        }
+
+        crate::profile::record("rebuild_items", started.elapsed());
+    }
+
+    fn load_more_item(category: SearchCategory, name: &str) -> TreeItem {
+        TreeItem {
+            id: format!("more-{}", name),
+            display_text: format!("  … load more {}", name),
+            item_type: TreeItemType::LoadMore(category),
+            level: 1,
+            section: None,
+        }
+    }
+
+    /// Add an artist row and, if it is expanded, its albums
+    fn push_artist(&self, items: &mut Vec<TreeItem>, artist: &Artist, level: usize) {
+        let artist_starred = match &self.starred_filter {
+            Some(filter) if !filter.shows_artist(artist) => return,
+            Some(filter) => filter.is_artist_starred(artist),
+            None => false,
+        };
+        let is_expanded = *self.expanded_artists.get(&artist.id).unwrap_or(&false);
+        let prefix = if is_expanded { "▼" } else { "▶" };
+
+        items.push(TreeItem {
+            id: artist.id.clone(),
+            display_text: format!("{}{} {}{}", "  ".repeat(level), prefix, artist.name, self.badge(&artist.id)),
+            item_type: TreeItemType::Artist(artist.clone()),
+            level,
+            section: None,
+        });
+
+        if is_expanded {
+            if let Some(albums) = self.artist_albums.get(&artist.id) {
+                for album in albums {
+                    self.push_album(items, album, level + 1, artist_starred, false);
+                }
+            }
+        }
+    }
+
+    /// Add an album row and, if it is expanded, its songs
+    fn push_album(&self, items: &mut Vec<TreeItem>, album: &Album, level: usize, artist_starred: bool, with_artist: bool) {
+        let album_starred = match &self.starred_filter {
+            Some(filter) if !filter.shows_album(album, artist_starred) => return,
+            Some(filter) => artist_starred || filter.is_album_starred(album),
+            None => false,
+        };
+        let is_expanded = *self.expanded_albums.get(&album.id).unwrap_or(&false);
+        let prefix = if is_expanded { "▼" } else { "▶" };
+        let mut name = album.name.clone();
+        if with_artist {
+            name = format!("{} - {}", name, album.artist.as_deref().unwrap_or("Unknown Artist"));
+            if let Some(year) = album.year {
+                name = format!("{} ({})", name, year);
+            }
+        }
+
+        items.push(TreeItem {
+            id: album.id.clone(),
+            display_text: format!("{}{} {}{}", "  ".repeat(level), prefix, name, self.badge(&album.id)),
+            item_type: TreeItemType::Album(album.clone()),
+            level,
+            section: None,
+        });
+
+        if is_expanded {
+            if let Some(songs) = self.album_songs.get(&album.id) {
+                for song in songs {
+                    self.push_song(items, song, level + 1, album_starred);
+                }
+            }
+        }
+    }
+
+    /// Add a directory row and, if it is expanded, its subdirectories and songs.
+    /// The starred filter only applies to the songs.
+    fn push_folder(&self, items: &mut Vec<TreeItem>, folder: &Folder, level: usize) {
+        let is_expanded = *self.expanded_folders.get(&folder.id).unwrap_or(&false);
+        let prefix = if is_expanded { "▼" } else { "▶" };
+
+        items.push(TreeItem {
+            id: folder.id.clone(),
+            display_text: format!("{}{} 📁 {}", "  ".repeat(level), prefix, folder.name),
+            item_type: TreeItemType::Folder(folder.clone()),
+            level,
+            section: None,
+        });
+
+        if is_expanded {
+            if let Some((folders, songs)) = self.folder_contents.get(&folder.id) {
+                for subfolder in folders {
+                    self.push_folder(items, subfolder, level + 1);
+                }
+                for song in songs {
+                    self.push_song(items, song, level + 1, false);
+                }
+            }
+        }
+    }
+
+    /// The item under the cursor, once the cursor has rested on it for a moment
+    fn hovered_item(&mut self) -> Option<&TreeItemType> {
+        let selected = self.list_state.selected();
+        match self.cursor_since {
+            Some((index, _)) if index == selected => {}
+            _ => self.cursor_since = Some((selected, Instant::now())),
+        }
+        if self.cursor_since?.1.elapsed() < PREVIEW_DELAY {
+            return None;
+        }
+        Some(&self.get_selected_item()?.item_type)
+    }
+
+    /// The artist the cursor rests on, whose info is shown below the tree
+    pub fn hovered_artist(&mut self) -> Option<&Artist> {
+        match self.hovered_item()? {
+            TreeItemType::Artist(artist) => Some(artist),
+            _ => None,
+        }
+    }
+
+    /// The name of the merged source an item comes from, after its name
+    fn badge(&self, id: &str) -> String {
+        let source = id.split_once(':').map(|(source, _)| source);
+        match source.filter(|source| self.sources.iter().any(|name| name == source)) {
+            Some(source) => format!(" ‹{}›", source),
+            None => String::new(),
+        }
+    }
+
+    fn is_playing(&self, song: &Song) -> bool {
+        self.selected_song.as_ref().is_some_and(|playing| playing.id == song.id)
+    }
+
+    fn push_song(&self, items: &mut Vec<TreeItem>, song: &Song, level: usize, album_starred: bool) {
+        if let Some(filter) = &self.starred_filter {
+            if !filter.shows_song(song, album_starred) {
+                return;
+            }
+        }
+
+        items.push(self.song_item(song, level, level < 2));
+    }
+
+    /// A song row, songs outside their album also show who performs them
+    fn song_item(&self, song: &Song, level: usize, with_artist: bool) -> TreeItem {
+        let icon = if self.is_playing(song) { "▶" } else { "♪" };
+        let display_text = if !with_artist {
+            format!("{}{} {}{}", "  ".repeat(level), icon, song.title, self.badge(&song.id))
+        } else {
+            format!(
+                "{}{} {} - {}{}",
+                "  ".repeat(level),
+                icon,
+                song.title,
+                song.artist.as_deref().unwrap_or("Unknown Artist"),
+                self.badge(&song.id)
+            )
+        };
+
+        TreeItem {
+            id: song.id.clone(),
+            display_text,
+            item_type: TreeItemType::Song(song.clone()),
+            level,
+            section: None,
+        }
     }
 }
 
 pub struct TreeWidget;
 
 impl TreeWidget {
-    pub fn render(state: &mut TreeState, area: Rect, buf: &mut Buffer) {
+    /// Render the tree, highlighting the selection in `accent` if given.
+    /// The playing song is drawn bold in the accent color (yellow without one).
+    /// When the cursor rests on a song or playlist, its details are shown in a footer.
+    /// A breadcrumb line above it shows where the selected row sits.
+    pub fn render(state: &mut TreeState, accent: Option<Color>, area: Rect, buf: &mut Buffer) {
+        let preview = match state.hovered_item().cloned() {
+            Some(TreeItemType::Song(song)) => Some(("Song", Self::preview_text(&song), 1)),
+            Some(TreeItemType::Playlist(playlist)) => Some(("Playlist", Self::playlist_preview_text(&playlist), 1)),
+            Some(TreeItemType::Artist(artist)) => Some(("Artist", Self::artist_preview_text(&artist, state.artist_info.get(&artist.id)), 3)),
+            _ => None,
+        };
+        let area = match preview {
+            Some((title, preview, rows)) if area.height > rows + 5 => {
+                let layout = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Min(3), Constraint::Length(rows + 2)])
+                    .split(area);
+                Paragraph::new(preview)
+                    .block(Block::default().title(title).borders(Borders::ALL))
+                    .style(Style::default().fg(Color::Gray))
+                    .wrap(Wrap { trim: true })
+                    .render(layout[1], buf);
+                layout[0]
+            }
+            _ => area,
+        };
+
+        let area = if area.height > 3 {
+            let layout = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Min(2)])
+                .split(area);
+            Self::render_breadcrumb(state, layout[0], buf);
+            layout[1]
+        } else {
+            area
+        };
+
+        let playing_style = Style::default()
+            .fg(accent.unwrap_or(Color::Yellow))
+            .add_modifier(Modifier::BOLD);
+        // Inside the borders, next to the highlight symbol
+        let row_width = area.width.saturating_sub(3) as usize;
         let items: Vec<ListItem> = state
             .items
             .iter()
-            .map(|item| ListItem::new(item.display_text.clone()))
+            .map(|item| {
+                let mut style = match &item.item_type {
+                    TreeItemType::Song(song) if state.is_playing(song) => playing_style,
+                    _ => Style::default(),
+                };
+                if state.is_marked(&item.item_type) {
+                    style = style.bg(Color::DarkGray);
+                }
+                match &item.item_type {
+                    TreeItemType::Album(album) if state.show_thumbnails => {
+                        ListItem::new(Self::album_line(state, album, &item.display_text, row_width)).style(style)
+                    }
+                    _ => ListItem::new(truncate(&item.display_text, row_width)).style(style),
+                }
+            })
             .collect();
 
+        let mut title = if state.starred_filter.is_some() { "Library ★" } else { "Library" }.to_string();
+        if let Some(folder) = &state.music_folder {
+            title.push_str(&format!(" · {}", folder.label()));
+        }
+        if state.browse_folders {
+            title.push_str(" · folders");
+            if state.folders.is_none() {
+                title.push_str(" (loading…)");
+            }
+        }
+        if !state.marked.is_empty() {
+            title.push_str(&format!(" ({} marked)", state.marked.len()));
+        }
+        if state.loading_artists && !state.browse_folders {
+            title.push_str(&format!(" (loading… {} artists)", state.artists.len()));
+        }
+        let mut title = vec![Span::raw(title)];
+        if let Some((label, color)) = state.connection.label() {
+            title.push(Span::styled(format!(" {} ", label), Style::default().fg(color)));
+        }
         let list = List::new(items)
-            .block(Block::default().title("Library").borders(Borders::ALL))
-            .highlight_style(Style::default().bg(Color::Blue).fg(Color::White))
+            .block(Block::default().title(Line::from(title)).borders(Borders::ALL))
+            .highlight_style(match accent {
+                Some(color) => Style::default().bg(color).fg(Color::Black),
+                None => Style::default().bg(Color::Blue).fg(Color::White),
+            })
             .highlight_symbol(">");
 
         StatefulWidget::render(list, area, buf, &mut state.list_state);
     }
-}
\ No newline at end of file
+
+    /// Where the selected row sits in the tree, e.g. "Library ▸ Artist ▸ Album".
+    /// Leading parts are dropped when it doesn't fit. While a jump is pending it lists
+    /// the artist index sections instead.
+    fn render_breadcrumb(state: &TreeState, area: Rect, buf: &mut Buffer) {
+        if state.jump_pending {
+            let letters: Vec<&str> = state.artist_index.iter().map(|(name, _)| name.as_str()).collect();
+            Paragraph::new(format!("Jump to: {}", letters.join(" ")))
+                .style(Style::default().fg(Color::Yellow))
+                .render(area, buf);
+            return;
+        }
+        let mut crumbs = state.breadcrumb();
+        let mut text = crumbs.join(" ▸ ");
+        while crumbs.len() > 1 && display_width(&text) > area.width as usize {
+            crumbs.remove(0);
+            text = format!("… ▸ {}", crumbs.join(" ▸ "));
+        }
+        Paragraph::new(truncate(&text, area.width as usize))
+            .style(Style::default().fg(Color::DarkGray))
+            .render(area, buf);
+    }
+
+    /// An album row with its thumbnail after the indentation, or blank space until it loaded
+    fn album_line(state: &TreeState, album: &Album, text: &str, width: usize) -> Line<'static> {
+        let name = text.trim_start();
+        let indent = &text[..text.len() - name.len()];
+        let name = truncate(name, width.saturating_sub(indent.len() + thumbnail::WIDTH + 1));
+        let mut spans = vec![Span::raw(indent.to_string())];
+        match album.cover_art.as_ref().and_then(|id| state.thumbnails.get(id)) {
+            Some(thumbnail) => spans.extend(thumbnail.spans()),
+            None => spans.push(Span::raw(" ".repeat(thumbnail::WIDTH))),
+        }
+        spans.push(Span::raw(" "));
+        spans.push(Span::raw(name));
+        Line::from(spans)
+    }
+
+    /// Duration, bitrate and format, year and genre of a song, leaving out what is unknown
+    fn preview_text(song: &Song) -> String {
+        let mut parts = Vec::new();
+        if let Some(duration) = song.duration {
+            parts.push(format!("{}:{:02}", duration / 60, duration % 60));
+        }
+        match (song.bit_rate, &song.suffix) {
+            (Some(bit_rate), Some(suffix)) => parts.push(format!("{} kbps {}", bit_rate, suffix.to_uppercase())),
+            (Some(bit_rate), None) => parts.push(format!("{} kbps", bit_rate)),
+            (None, Some(suffix)) => parts.push(suffix.to_uppercase()),
+            (None, None) => {}
+        }
+        if let Some(year) = song.year {
+            parts.push(year.to_string());
+        }
+        if let Some(genre) = &song.genre {
+            parts.push(genre.clone());
+        }
+        if parts.is_empty() {
+            return "No details".to_string();
+        }
+        parts.join(" · ")
+    }
+
+    /// Album count and similar artists, then the start of the biography once it is loaded
+    fn artist_preview_text(artist: &Artist, info: Option<&ArtistInfo>) -> String {
+        let mut parts = Vec::new();
+        if let Some(count) = artist.album_count {
+            parts.push(format!("{} albums", count));
+        }
+        let Some(info) = info else {
+            parts.push("loading…".to_string());
+            return parts.join(" · ");
+        };
+        if !info.similar_artist.is_empty() {
+            let names: Vec<&str> = info.similar_artist.iter().take(3).map(|a| a.name.as_str()).collect();
+            parts.push(format!("similar to {} (i)", names.join(", ")));
+        }
+        let mut text = parts.join(" · ");
+        if let Some(biography) = info.biography.as_deref().map(strip_html).filter(|b| !b.is_empty()) {
+            text.push('\n');
+            text.push_str(&biography);
+        }
+        text
+    }
+
+    /// Owner, visibility, length and last change of a playlist, then its comment
+    fn playlist_preview_text(playlist: &Playlist) -> String {
+        let mut parts = Vec::new();
+        if let Some(owner) = &playlist.owner {
+            parts.push(format!("by {}", owner));
+        }
+        match playlist.public {
+            Some(true) => parts.push("public".to_string()),
+            Some(false) => parts.push("private".to_string()),
+            None => {}
+        }
+        if let Some(duration) = playlist.duration {
+            parts.push(format!("{}:{:02}:{:02}", duration / 3600, duration / 60 % 60, duration % 60));
+        }
+        if let Some(changed) = &playlist.changed {
+            // Just the date of the ISO 8601 timestamp
+            parts.push(format!("changed {}", changed.get(..10).unwrap_or(changed)));
+        }
+        if let Some(comment) = playlist.comment.as_deref().filter(|c| !c.is_empty()) {
+            parts.push(comment.to_string());
+        }
+        if parts.is_empty() {
+            return "No details".to_string();
+        }
+        parts.join(" · ")
+    }
+}
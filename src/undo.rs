@@ -0,0 +1,53 @@
+use crate::queue::Queue;
+use std::collections::VecDeque;
+use subsonic::InternetRadioStation;
+
+/// Most actions that can be undone, older ones are forgotten
+const MAX_UNDO: usize = 20;
+
+/// A destructive action, with what it takes to revert it
+#[derive(Debug, Clone)]
+pub enum UndoAction {
+    /// The queue before it was cleared, replaced or had a song removed
+    Queue(Queue),
+    /// A deleted radio station, created again on undo
+    RadioStation(InternetRadioStation),
+    /// Songs appended to a playlist, from position `first` on
+    PlaylistAdd {
+        playlist_id: String,
+        name: String,
+        first: usize,
+        song_ids: Vec<String>,
+    },
+    /// Songs removed from a playlist, with the positions they had
+    PlaylistRemove {
+        playlist_id: String,
+        name: String,
+        entries: Vec<(usize, String)>,
+    },
+    /// A deleted playlist and its songs, created again on undo
+    Playlist { name: String, song_ids: Vec<String> },
+}
+
+/// The most recent destructive actions, reverted newest first with `u`
+#[derive(Debug, Default)]
+pub struct UndoStack {
+    actions: VecDeque<UndoAction>,
+}
+
+impl UndoStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, action: UndoAction) {
+        self.actions.push_back(action);
+        if self.actions.len() > MAX_UNDO {
+            self.actions.pop_front();
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<UndoAction> {
+        self.actions.pop_back()
+    }
+}
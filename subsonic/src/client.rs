@@ -0,0 +1,740 @@
+use crate::auth::{AuthMethod, Credentials};
+use crate::conditional::ConditionalCache;
+use crate::error::{Error, Result};
+use crate::mock::MockLibrary;
+use crate::one_or_many::{self, SkippedItem};
+use crate::rate_limit::RateLimiter;
+use crate::stream::IndexScanner;
+use crate::traffic::Traffic;
+use crate::types::*;
+use log::{debug, warn};
+use reqwest::{Client, StatusCode};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Number of results requested per category and page from `search3`
+pub const SEARCH_PAGE_SIZE: u32 = 20;
+
+/// Idle connections kept open to the server, enough for prefetching in parallel
+const POOL_MAX_IDLE: usize = 8;
+/// Idle connections are closed after this long
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+const TCP_KEEPALIVE: Duration = Duration::from_secs(60);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A connection to one Subsonic server. Cheap to clone, clones share the HTTP connection pool.
+#[derive(Debug, Clone)]
+pub struct SubsonicClient {
+    base_url: String,
+    credentials: Credentials,
+    /// Sent as `c` with every request, shown by servers in their list of players
+    client_name: String,
+    client: Client,
+    /// Serve requests from a fake in-memory library instead of the network
+    mock: Option<Arc<MockLibrary>>,
+    /// Record responses to disk or answer from a recording
+    traffic: Option<Traffic>,
+    rate_limit: Option<Arc<RateLimiter>>,
+    /// ID of the music folder the library is listed from, all of them when `None`
+    music_folder: Option<String>,
+    conditional: Arc<ConditionalCache>,
+    /// Malformed list elements dropped from responses, until taken by `take_skipped`
+    skipped: Arc<Mutex<Vec<SkippedItem>>>,
+    /// OpenSubsonic extensions of the server once asked for, none for plain Subsonic servers
+    extensions: Arc<Mutex<Option<Vec<OpenSubsonicExtension>>>>,
+    /// How songs are streamed, as they are when `None`
+    transcoding: Option<Transcoding>,
+}
+
+/// Receives a download piece by piece, along with its size when known
+pub type OnChunk<'a> = dyn FnMut(&[u8], Option<u64>) -> std::io::Result<()> + Send + 'a;
+
+/// A response, or the cached one when the server reported it unchanged
+enum Fetched {
+    Fresh(reqwest::Response),
+    /// Content type and body of the cached response
+    NotModified(String, Arc<[u8]>),
+}
+
+impl SubsonicClient {
+    pub fn new(base_url: String, username: String, password: String) -> Self {
+        Self {
+            base_url,
+            credentials: Credentials::new(username, password),
+            client_name: env!("CARGO_PKG_NAME").to_string(),
+            client: Self::http_client(None),
+            mock: None,
+            traffic: None,
+            rate_limit: None,
+            music_folder: None,
+            conditional: Arc::default(),
+            skipped: Arc::default(),
+            extensions: Arc::default(),
+            transcoding: None,
+        }
+    }
+
+    /// One HTTP client per server, shared by all clones, with compressed responses and
+    /// connections kept alive between requests, which matters most over high-latency links.
+    /// Without `proxy` the proxies of `HTTP_PROXY`, `HTTPS_PROXY` and `ALL_PROXY` are used.
+    fn http_client(proxy: Option<reqwest::Proxy>) -> Client {
+        let builder = match proxy {
+            Some(proxy) => Client::builder().proxy(proxy),
+            None => Client::builder(),
+        };
+        builder
+            .gzip(true)
+            .brotli(true)
+            .pool_max_idle_per_host(POOL_MAX_IDLE)
+            .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+            .tcp_keepalive(TCP_KEEPALIVE)
+            .tcp_nodelay(true)
+            .connect_timeout(CONNECT_TIMEOUT)
+            .build()
+            .unwrap_or_default()
+    }
+
+    /// Name the application to the server, defaults to the name of this crate
+    pub fn with_client_name(mut self, name: impl Into<String>) -> Self {
+        self.client_name = name.into();
+        self
+    }
+
+    /// Send every request through the HTTP or SOCKS proxy at this URL, like
+    /// "socks5://localhost:1080". `None` leaves it to the environment variables.
+    pub fn with_proxy(mut self, url: Option<&str>) -> Self {
+        let Some(url) = url else {
+            return self;
+        };
+        match reqwest::Proxy::all(url) {
+            Ok(proxy) => self.client = Self::http_client(Some(proxy)),
+            // The URL is left out, it may carry the proxy login
+            Err(e) => warn!("Ignoring the proxy setting: {}", e),
+        }
+        self
+    }
+
+    /// Send the password instead of a token, for servers older than API version 1.13
+    pub fn with_auth_method(mut self, method: AuthMethod) -> Self {
+        self.credentials = self.credentials.with_method(method);
+        self
+    }
+
+    /// Start at most this many requests per second, 0 for no limit. Stream URLs are not limited.
+    pub fn with_rate_limit(mut self, max_requests_per_second: f32) -> Self {
+        self.rate_limit = RateLimiter::new(max_requests_per_second).map(Arc::new);
+        self
+    }
+
+    /// List artists, albums, genre songs, search results and starred items only from the
+    /// music folder with this ID, see `get_music_folders`. `None` lists all of them.
+    pub fn with_music_folder(mut self, folder_id: Option<String>) -> Self {
+        self.music_folder = folder_id;
+        self
+    }
+
+    /// Ask the server to re-encode the songs of stream URLs like this, `None` streams them
+    /// as they are
+    pub fn with_transcoding(mut self, transcoding: Option<Transcoding>) -> Self {
+        self.transcoding = transcoding;
+        self
+    }
+
+    pub fn music_folder(&self) -> Option<&str> {
+        self.music_folder.as_deref()
+    }
+
+    /// Record or replay API traffic, see `Traffic`
+    pub fn with_traffic(mut self, traffic: Traffic) -> Self {
+        self.traffic = Some(traffic);
+        self
+    }
+
+    /// A client that never touches the network and answers from `MockLibrary`
+    pub fn mock() -> Self {
+        Self {
+            mock: Some(Arc::new(MockLibrary::new())),
+            ..Self::new("mock://".to_string(), "mock".to_string(), String::new())
+        }
+    }
+
+    /// Request an endpoint and parse the JSON response, turning `status="failed"` into `Error::Api`
+    async fn get_json<T: DeserializeOwned>(&self, endpoint: &str, params: &[(&str, &str)]) -> Result<T> {
+        let response = if let Some(mock) = &self.mock {
+            mock.respond(endpoint, params)?
+        } else if let Some(response) = self.traffic.as_ref().and_then(|t| t.replay(endpoint, params)) {
+            response?
+        } else {
+            let started = Instant::now();
+            let (_, body) = self.fetch(endpoint, params).await?;
+            let response: Value = serde_json::from_slice(&body)?;
+            let duration_ms = started.elapsed().as_millis() as u64;
+            debug!(target: "subsonic::api", endpoint, duration_ms; "{} took {} ms", endpoint, duration_ms);
+            // Record before parsing, so responses that fail to parse are captured too
+            if let Some(traffic) = &self.traffic {
+                traffic.record(endpoint, params, &response);
+            }
+            response
+        };
+
+        let status: SubsonicResponse<StatusResponse> = serde_json::from_value(response.clone())?;
+        status.subsonic_response.check(endpoint)?;
+        let (parsed, skipped) = one_or_many::collect_skipped(|| serde_json::from_value(response));
+        self.report_skipped(endpoint, skipped);
+        Ok(parsed?)
+    }
+
+    fn report_skipped(&self, endpoint: &str, skipped: Vec<SkippedItem>) {
+        if skipped.is_empty() {
+            return;
+        }
+        warn!("Skipped {} malformed items in the {} response", skipped.len(), endpoint);
+        self.skipped.lock().unwrap_or_else(|e| e.into_inner()).extend(skipped);
+    }
+
+    /// List elements left out of responses since the last call because they could not be
+    /// parsed, e.g. a song with malformed metadata. The rest of the response is used as usual.
+    pub fn take_skipped(&self) -> Vec<SkippedItem> {
+        std::mem::take(&mut *self.skipped.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// Send a GET request once the rate limit allows it, conditional on a cached response
+    /// being outdated if there is one
+    async fn send(&self, endpoint: &str, params: &[(&str, &str)]) -> Result<Fetched> {
+        if let Some(rate_limit) = &self.rate_limit {
+            rate_limit.wait().await;
+        }
+        let url = self.build_url(endpoint, params);
+        let cached = self.conditional.get(endpoint, params);
+        let headers = cached.as_ref().map(|cached| cached.headers.clone()).unwrap_or_default();
+        let response = self.client.get(&url).headers(headers).send().await?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some(cached) = cached {
+                debug!("{} not modified", endpoint);
+                return Ok(Fetched::NotModified(cached.content_type, cached.body));
+            }
+        }
+        Ok(Fetched::Fresh(response))
+    }
+
+    /// Content type and body of a GET request
+    async fn fetch(&self, endpoint: &str, params: &[(&str, &str)]) -> Result<(String, Vec<u8>)> {
+        match self.send(endpoint, params).await? {
+            Fetched::Fresh(response) => {
+                let status = response.status();
+                let headers = response.headers().clone();
+                let body = response.bytes().await?.to_vec();
+                if status.is_success() {
+                    self.conditional.store(endpoint, params, &headers, &body);
+                }
+                let content_type = headers
+                    .get(reqwest::header::CONTENT_TYPE)
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or_default()
+                    .to_string();
+                Ok((content_type, body))
+            }
+            Fetched::NotModified(content_type, body) => Ok((content_type, body.to_vec())),
+        }
+    }
+
+    fn build_url(&self, endpoint: &str, params: &[(&str, &str)]) -> String {
+        let mut url = format!("{}/rest/{}", self.base_url, endpoint);
+        let auth = self.credentials.query_params();
+
+        let mut query_params: Vec<(&str, &str)> = auth.iter().map(|(key, value)| (*key, value.as_str())).collect();
+        query_params.extend_from_slice(&[
+            ("v", self.credentials.api_version()),
+            ("c", &self.client_name),
+            ("f", "json"),
+        ]);
+        query_params.extend_from_slice(params);
+
+        url.push('?');
+        for (i, (key, value)) in query_params.iter().enumerate() {
+            if i > 0 {
+                url.push('&');
+            }
+            url.push_str(&format!("{}={}", key, urlencoding::encode(value)));
+        }
+
+        url
+    }
+
+    /// `params` with the music folder set by `with_music_folder`, if any
+    fn in_music_folder<'a>(&'a self, params: &[(&'a str, &'a str)]) -> Vec<(&'a str, &'a str)> {
+        let mut params = params.to_vec();
+        if let Some(folder_id) = &self.music_folder {
+            params.push(("musicFolderId", folder_id));
+        }
+        params
+    }
+
+    /// Check that the server answers and takes the credentials
+    pub async fn ping(&self) -> Result<()> {
+        self.send_status("ping", &[]).await
+    }
+
+    /// The top-level folders the server scans, to restrict the library to one of them
+    pub async fn get_music_folders(&self) -> Result<Vec<MusicFolder>> {
+        let response: SubsonicResponse<MusicFoldersResponse> = self.get_json("getMusicFolders", &[]).await?;
+        Ok(response.subsonic_response.music_folders.music_folder)
+    }
+
+    pub async fn get_artists(&self) -> Result<Vec<Artist>> {
+        let response: SubsonicResponse<ArtistsResponse> = self.get_json("getArtists", &self.in_music_folder(&[])).await?;
+        
+        let mut artists = Vec::new();
+        for index in response.subsonic_response.artists.index {
+            artists.extend(index.artist);
+        }
+        
+        Ok(artists)
+    }
+
+    /// Like `get_artists`, but hands each index section (e.g. the artists starting with A)
+    /// to `on_index` as soon as it has downloaded, for libraries too large to wait for
+    pub async fn get_artists_progressive(&self, mut on_index: impl FnMut(ArtistIndex)) -> Result<()> {
+        let params = self.in_music_folder(&[]);
+        if self.mock.is_some() || matches!(self.traffic, Some(Traffic::Replay(_))) {
+            let response: SubsonicResponse<ArtistsResponse> = self.get_json("getArtists", &params).await?;
+            response.subsonic_response.artists.index.into_iter().for_each(on_index);
+            return Ok(());
+        }
+
+        let started = Instant::now();
+        let mut scanner = IndexScanner::default();
+        let mut handed_out = 0;
+        let mut on_entry = |buffer: &[u8]| -> Result<()> {
+            handed_out += 1;
+            let (index, skipped) = one_or_many::collect_skipped(|| serde_json::from_slice(buffer));
+            self.report_skipped("getArtists", skipped);
+            on_index(index?);
+            Ok(())
+        };
+        match self.send("getArtists", &params).await? {
+            Fetched::Fresh(mut response) => {
+                let status = response.status();
+                let headers = response.headers().clone();
+                while let Some(chunk) = response.chunk().await? {
+                    for entry in scanner.feed(&chunk) {
+                        on_entry(&scanner.buffer[entry])?;
+                    }
+                }
+                if status.is_success() {
+                    self.conditional.store("getArtists", &params, &headers, &scanner.buffer);
+                }
+            }
+            Fetched::NotModified(_, body) => {
+                for entry in scanner.feed(&body) {
+                    on_entry(&scanner.buffer[entry])?;
+                }
+            }
+        }
+        let duration_ms = started.elapsed().as_millis() as u64;
+        debug!(target: "subsonic::api", endpoint = "getArtists", duration_ms; "getArtists took {} ms", duration_ms);
+
+        if let Some(traffic) = &self.traffic {
+            traffic.record("getArtists", &params, &serde_json::from_slice(&scanner.buffer)?);
+        }
+        // A failed request has no index, so nothing was handed out before this
+        let status: SubsonicResponse<StatusResponse> = serde_json::from_slice(&scanner.buffer)?;
+        status.subsonic_response.check("getArtists")?;
+
+        // The scanner only finds an `index` list, servers that send a single index
+        // section as an object get it parsed once the response is complete
+        if handed_out == 0 {
+            let (response, skipped) = one_or_many::collect_skipped(|| {
+                serde_json::from_slice::<SubsonicResponse<ArtistsResponse>>(&scanner.buffer)
+            });
+            self.report_skipped("getArtists", skipped);
+            response?.subsonic_response.artists.index.into_iter().for_each(on_index);
+        }
+        Ok(())
+    }
+
+    pub async fn get_artist(&self, artist_id: &str) -> Result<Vec<Album>> {
+        let response: Value = self.get_json("getArtist", &[("id", artist_id)]).await?;
+        
+        let albums = response
+            .get("subsonic-response")
+            .and_then(|r| r.get("artist"))
+            .and_then(|a| a.get("album"))
+            .cloned()
+            .unwrap_or_default();
+        let (albums, skipped) = one_or_many::collect_skipped(|| one_or_many::deserialize(albums).unwrap_or_default());
+        self.report_skipped("getArtist", skipped);
+
+        Ok(albums)
+    }
+
+    /// Biography and up to `count` similar artists of an artist, leaving out similar
+    /// artists that are not in the library
+    pub async fn get_artist_info(&self, artist_id: &str, count: u32) -> Result<ArtistInfo> {
+        let count = count.to_string();
+        let response: SubsonicResponse<ArtistInfo2Response> = self.get_json("getArtistInfo2", &[("id", artist_id), ("count", &count)]).await?;
+        Ok(response.subsonic_response.artist_info2)
+    }
+
+    /// Top directories of all music folders, for browsing by folder instead of by tags
+    pub async fn get_indexes(&self) -> Result<Indexes> {
+        let response: SubsonicResponse<IndexesResponse> = self.get_json("getIndexes", &self.in_music_folder(&[])).await?;
+        Ok(response.subsonic_response.indexes)
+    }
+
+    /// Subdirectories and songs of a directory of the music folders
+    pub async fn get_music_directory(&self, directory_id: &str) -> Result<MusicDirectory> {
+        let response: SubsonicResponse<MusicDirectoryResponse> = self.get_json("getMusicDirectory", &[("id", directory_id)]).await?;
+        Ok(response.subsonic_response.directory)
+    }
+
+    /// Albums of the whole library in the given order, `size` of them starting at `offset`
+    pub async fn get_album_list(&self, list: AlbumListType, size: u32, offset: u32) -> Result<Vec<Album>> {
+        let size = size.to_string();
+        let offset = offset.to_string();
+        let mut params = vec![("type", list.param()), ("size", size.as_str()), ("offset", offset.as_str())];
+        if list == AlbumListType::ByYear {
+            // A range running backwards lists the newest years first
+            params.extend([("fromYear", "9999"), ("toYear", "0")]);
+        }
+        let response: SubsonicResponse<AlbumList2Response> = self.get_json("getAlbumList2", &self.in_music_folder(&params)).await?;
+        Ok(response.subsonic_response.album_list2.album)
+    }
+
+    pub async fn get_album(&self, album_id: &str) -> Result<AlbumDetail> {
+        let response: SubsonicResponse<AlbumResponse> = self.get_json("getAlbum", &[("id", album_id)]).await?;
+        Ok(response.subsonic_response.album)
+    }
+
+    pub async fn get_cover_art(&self, cover_art_id: &str, size: Option<u32>) -> Result<Vec<u8>> {
+        if self.mock.is_some() {
+            return Err(Error::Unsupported("The mock library has no cover art".to_string()));
+        }
+        if matches!(self.traffic, Some(Traffic::Replay(_))) {
+            return Err(Error::Unsupported("Cover art is not recorded".to_string()));
+        }
+        // Without a size the server sends the original image
+        let size_param = size.map(|s| s.to_string());
+        let mut params = vec![("id", cover_art_id)];
+        if let Some(size) = &size_param {
+            params.push(("size", size));
+        }
+        let (content_type, body) = self.fetch("getCoverArt", &params).await?;
+
+        // Errors such as a missing image come back as a regular API response
+        if content_type.contains("json") || content_type.contains("xml") {
+            let status: SubsonicResponse<StatusResponse> = serde_json::from_slice(&body)?;
+            status.subsonic_response.check("getCoverArt")?;
+            return Err(Error::Unsupported("No cover art returned".to_string()));
+        }
+        Ok(body)
+    }
+
+    /// Download the original file of a song, handing it to `on_chunk` piece by piece as it
+    /// arrives, along with its size when the server tells it
+    pub async fn download(&self, song_id: &str, on_chunk: &mut OnChunk<'_>) -> Result<()> {
+        if self.mock.is_some() {
+            return Err(Error::Unsupported("The mock library has no files to download".to_string()));
+        }
+        if matches!(self.traffic, Some(Traffic::Replay(_))) {
+            return Err(Error::Unsupported("Downloads are not recorded".to_string()));
+        }
+        if let Some(rate_limit) = &self.rate_limit {
+            rate_limit.wait().await;
+        }
+        let url = self.build_url("download", &[("id", song_id)]);
+        let mut response = self.client.get(&url).send().await?.error_for_status()?;
+
+        // Errors such as a missing song come back as a regular API response
+        let content_type = response.headers().get(reqwest::header::CONTENT_TYPE).and_then(|v| v.to_str().ok()).unwrap_or_default();
+        if content_type.contains("json") || content_type.contains("xml") {
+            let body = response.bytes().await?;
+            let status: SubsonicResponse<StatusResponse> = serde_json::from_slice(&body)?;
+            status.subsonic_response.check("download")?;
+            return Err(Error::Unsupported("No file returned".to_string()));
+        }
+        let size = response.content_length();
+        while let Some(chunk) = response.chunk().await? {
+            on_chunk(&chunk, size)?;
+        }
+        Ok(())
+    }
+
+    pub async fn get_lyrics(&self, artist: &str, title: &str) -> Result<Option<String>> {
+        let response: SubsonicResponse<LyricsResponse> = self.get_json("getLyrics", &[("artist", artist), ("title", title)]).await?;
+        Ok(response.subsonic_response.lyrics.and_then(|l| l.text))
+    }
+
+    /// Lyrics with the time each line starts at where the server has them, in every
+    /// language it has. Servers without the OpenSubsonic `songLyrics` extension answer
+    /// with `Error::Unsupported`, fall back to `get_lyrics` for them.
+    pub async fn get_lyrics_by_song_id(&self, song_id: &str) -> Result<Vec<StructuredLyrics>> {
+        if !self.supports_extension("songLyrics").await {
+            return Err(Error::Unsupported("The server has no lyrics by song".to_string()));
+        }
+        let response: SubsonicResponse<LyricsListResponse> = self.get_json("getLyricsBySongId", &[("id", song_id)]).await?;
+        Ok(response.subsonic_response.lyrics_list.structured_lyrics)
+    }
+
+    /// The OpenSubsonic extensions the server implements
+    pub async fn get_open_subsonic_extensions(&self) -> Result<Vec<OpenSubsonicExtension>> {
+        let response: SubsonicResponse<OpenSubsonicExtensionsResponse> = self.get_json("getOpenSubsonicExtensions", &[]).await?;
+        Ok(response.subsonic_response.open_subsonic_extensions)
+    }
+
+    /// Whether the server implements an OpenSubsonic extension, asking it only the first time
+    pub async fn supports_extension(&self, name: &str) -> bool {
+        let known = self.extensions.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let extensions = match known {
+            Some(extensions) => extensions,
+            None => {
+                let extensions = self.get_open_subsonic_extensions().await.unwrap_or_else(|e| {
+                    debug!("No OpenSubsonic extensions: {}", e);
+                    Vec::new()
+                });
+                *self.extensions.lock().unwrap_or_else(|e| e.into_inner()) = Some(extensions.clone());
+                extensions
+            }
+        };
+        extensions.iter().any(|extension| extension.name == name)
+    }
+
+    pub fn get_stream_url(&self, song_id: &str) -> String {
+        if let Some(mock) = &self.mock {
+            return mock.stream_url(song_id);
+        }
+        let max_bit_rate = self.transcoding.as_ref().map(|t| t.max_bit_rate.to_string());
+        let mut params = vec![("id", song_id)];
+        if let Some(rate) = max_bit_rate.as_deref().filter(|rate| *rate != "0") {
+            params.push(("maxBitRate", rate));
+        }
+        if let Some(format) = self.transcoding.as_ref().and_then(|t| t.format.as_deref()) {
+            params.push(("format", format));
+        }
+        self.build_url("stream", &params)
+    }
+
+    pub async fn get_genres(&self) -> Result<Vec<Genre>> {
+        let response: SubsonicResponse<GenresResponse> = self.get_json("getGenres", &[]).await?;
+        Ok(response.subsonic_response.genres.genre)
+    }
+
+    pub async fn get_songs_by_genre(&self, genre: &str, count: u32, offset: u32) -> Result<Vec<Song>> {
+        let count = count.to_string();
+        let offset = offset.to_string();
+        let response: SubsonicResponse<SongsByGenreResponse> = self.get_json("getSongsByGenre", &self.in_music_folder(&[("genre", genre), ("count", &count), ("offset", &offset)])).await?;
+        Ok(response.subsonic_response.songs_by_genre.song)
+    }
+
+    /// Up to `count` songs by the artist and by artists similar to them, as the server's
+    /// instant mix would pick them
+    pub async fn get_similar_songs(&self, artist_id: &str, count: u32) -> Result<Vec<Song>> {
+        let count = count.to_string();
+        let response: SubsonicResponse<SimilarSongs2Response> =
+            self.get_json("getSimilarSongs2", &[("id", artist_id), ("count", &count)]).await?;
+        Ok(response.subsonic_response.similar_songs2.song)
+    }
+
+    pub async fn get_playlists(&self) -> Result<Vec<Playlist>> {
+        let response: SubsonicResponse<PlaylistsResponse> = self.get_json("getPlaylists", &[]).await?;
+        Ok(response.subsonic_response.playlists.playlist)
+    }
+
+    pub async fn get_playlist(&self, playlist_id: &str) -> Result<PlaylistDetail> {
+        let response: SubsonicResponse<PlaylistResponse> = self.get_json("getPlaylist", &[("id", playlist_id)]).await?;
+        Ok(response.subsonic_response.playlist)
+    }
+
+    /// Search artists, albums and songs. Without `more` the first page of every category is
+    /// returned, with `Some((category, offset))` only the next page of that category.
+    pub async fn search(&self, query: &str, more: Option<(SearchCategory, u32)>) -> Result<SearchResult> {
+        let page_size = SEARCH_PAGE_SIZE.to_string();
+        let count = |category| match more {
+            Some((c, _)) if c != category => "0",
+            _ => page_size.as_str(),
+        };
+        let offset = match more {
+            Some((_, offset)) => offset.to_string(),
+            None => "0".to_string(),
+        };
+
+        let response: SubsonicResponse<SearchResult3Response> = self.get_json("search3", &self.in_music_folder(&[
+            ("query", query),
+            ("artistCount", count(SearchCategory::Artists)),
+            ("artistOffset", &offset),
+            ("albumCount", count(SearchCategory::Albums)),
+            ("albumOffset", &offset),
+            ("songCount", count(SearchCategory::Songs)),
+            ("songOffset", &offset),
+        ])).await?;
+        Ok(response.subsonic_response.search_result3)
+    }
+
+    /// Star songs and albums
+    pub async fn star(&self, song_ids: &[String], album_ids: &[String]) -> Result<()> {
+        self.send_status("star", &Self::star_params(song_ids, album_ids)).await
+    }
+
+    /// Remove the star from songs and albums
+    pub async fn unstar(&self, song_ids: &[String], album_ids: &[String]) -> Result<()> {
+        self.send_status("unstar", &Self::star_params(song_ids, album_ids)).await
+    }
+
+    fn star_params<'a>(song_ids: &'a [String], album_ids: &'a [String]) -> Vec<(&'static str, &'a str)> {
+        let songs = song_ids.iter().map(|id| ("id", id.as_str()));
+        let albums = album_ids.iter().map(|id| ("albumId", id.as_str()));
+        songs.chain(albums).collect()
+    }
+
+    /// Append songs to a playlist
+    pub async fn add_to_playlist(&self, playlist_id: &str, song_ids: &[String]) -> Result<()> {
+        let mut params = vec![("playlistId", playlist_id)];
+        params.extend(song_ids.iter().map(|id| ("songIdToAdd", id.as_str())));
+        self.send_status("updatePlaylist", &params).await
+    }
+
+    /// Remove the songs at the given positions from a playlist
+    pub async fn remove_from_playlist(&self, playlist_id: &str, indexes: &[usize]) -> Result<()> {
+        let indexes: Vec<String> = indexes.iter().map(|index| index.to_string()).collect();
+        let mut params = vec![("playlistId", playlist_id)];
+        params.extend(indexes.iter().map(|index| ("songIndexToRemove", index.as_str())));
+        self.send_status("updatePlaylist", &params).await
+    }
+
+    /// Share a playlist with the other users of the server, or stop sharing it
+    pub async fn set_playlist_public(&self, playlist_id: &str, public: bool) -> Result<()> {
+        let public = if public { "true" } else { "false" };
+        self.send_status("updatePlaylist", &[("playlistId", playlist_id), ("public", public)]).await
+    }
+
+    /// Create a new playlist holding the given songs
+    pub async fn create_playlist(&self, name: &str, song_ids: &[String]) -> Result<()> {
+        let mut params = vec![("name", name)];
+        params.extend(song_ids.iter().map(|id| ("songId", id.as_str())));
+        self.send_status("createPlaylist", &params).await
+    }
+
+    pub async fn delete_playlist(&self, playlist_id: &str) -> Result<()> {
+        self.send_status("deletePlaylist", &[("id", playlist_id)]).await
+    }
+
+    pub async fn get_starred(&self) -> Result<Starred> {
+        let response: SubsonicResponse<Starred2Response> = self.get_json("getStarred2", &self.in_music_folder(&[])).await?;
+        Ok(response.subsonic_response.starred2)
+    }
+
+    /// The queue bookmark saved on the server, if any
+    pub async fn get_play_queue(&self) -> Result<Option<PlayQueue>> {
+        let response: SubsonicResponse<PlayQueueResponse> = self.get_json("getPlayQueue", &[]).await?;
+        Ok(response.subsonic_response.play_queue)
+    }
+
+    /// Save the queue on the server so other clients can resume it. `current` must be one of `song_ids`.
+    pub async fn save_play_queue(&self, song_ids: &[String], current: Option<&str>, position_ms: u64) -> Result<()> {
+        let position = position_ms.to_string();
+        let mut params: Vec<(&str, &str)> = song_ids.iter().map(|id| ("id", id.as_str())).collect();
+        if let Some(current) = current {
+            params.push(("current", current));
+            params.push(("position", &position));
+        }
+        self.send_status("savePlayQueue", &params).await
+    }
+
+    pub async fn jukebox_status(&self) -> Result<JukeboxStatus> {
+        self.jukebox_control("status", &[]).await
+    }
+
+    /// Replace the jukebox playlist with these songs, keeping it stopped or playing
+    pub async fn jukebox_set(&self, song_ids: &[String]) -> Result<JukeboxStatus> {
+        let params: Vec<(&str, &str)> = song_ids.iter().map(|id| ("id", id.as_str())).collect();
+        self.jukebox_control("set", &params).await
+    }
+
+    pub async fn jukebox_start(&self) -> Result<JukeboxStatus> {
+        self.jukebox_control("start", &[]).await
+    }
+
+    pub async fn jukebox_stop(&self) -> Result<JukeboxStatus> {
+        self.jukebox_control("stop", &[]).await
+    }
+
+    /// Jump to the song at `index` of the jukebox playlist, `offset` seconds into it
+    pub async fn jukebox_skip(&self, index: usize, offset: u32) -> Result<JukeboxStatus> {
+        let (index, offset) = (index.to_string(), offset.to_string());
+        self.jukebox_control("skip", &[("index", &index), ("offset", &offset)]).await
+    }
+
+    /// Set the jukebox volume, between 0 and 1
+    pub async fn jukebox_set_gain(&self, gain: f32) -> Result<JukeboxStatus> {
+        let gain = gain.clamp(0.0, 1.0).to_string();
+        self.jukebox_control("setGain", &[("gain", &gain)]).await
+    }
+
+    /// Control the server's jukebox. Every action but `get` answers with its status.
+    async fn jukebox_control(&self, action: &str, params: &[(&str, &str)]) -> Result<JukeboxStatus> {
+        let mut params = params.to_vec();
+        params.push(("action", action));
+        let response: SubsonicResponse<JukeboxStatusResponse> = self.get_json("jukeboxControl", &params).await?;
+        Ok(response.subsonic_response.jukebox_status)
+    }
+
+    /// Ask the server to rescan its media folders, which only admins may do
+    pub async fn start_scan(&self) -> Result<ScanStatus> {
+        let response: SubsonicResponse<ScanStatusResponse> = self.get_json("startScan", &[]).await?;
+        Ok(response.subsonic_response.scan_status)
+    }
+
+    pub async fn get_scan_status(&self) -> Result<ScanStatus> {
+        let response: SubsonicResponse<ScanStatusResponse> = self.get_json("getScanStatus", &[]).await?;
+        Ok(response.subsonic_response.scan_status)
+    }
+
+    /// Tell the server a song started playing, without counting it as played
+    pub async fn now_playing(&self, song_id: &str) -> Result<()> {
+        self.send_status("scrobble", &[("id", song_id), ("submission", "false")]).await
+    }
+
+    /// Submit plays, given as song ID and start time in milliseconds since the epoch
+    pub async fn scrobble(&self, plays: &[(String, u64)]) -> Result<()> {
+        let times: Vec<String> = plays.iter().map(|(_, time)| time.to_string()).collect();
+        let mut params = vec![("submission", "true")];
+        for ((id, _), time) in plays.iter().zip(&times) {
+            params.push(("id", id));
+            params.push(("time", time));
+        }
+        self.send_status("scrobble", &params).await
+    }
+
+    pub async fn get_internet_radio_stations(&self) -> Result<Vec<InternetRadioStation>> {
+        let response: SubsonicResponse<InternetRadioStationsResponse> = self.get_json("getInternetRadioStations", &[]).await?;
+        Ok(response.subsonic_response.internet_radio_stations.internet_radio_station)
+    }
+
+    pub async fn create_internet_radio_station(&self, name: &str, stream_url: &str, home_page_url: Option<&str>) -> Result<()> {
+        let mut params = vec![("name", name), ("streamUrl", stream_url)];
+        if let Some(home_page_url) = home_page_url {
+            params.push(("homepageUrl", home_page_url));
+        }
+        self.send_status("createInternetRadioStation", &params).await
+    }
+
+    pub async fn update_internet_radio_station(&self, id: &str, name: &str, stream_url: &str, home_page_url: Option<&str>) -> Result<()> {
+        let mut params = vec![("id", id), ("name", name), ("streamUrl", stream_url)];
+        if let Some(home_page_url) = home_page_url {
+            params.push(("homepageUrl", home_page_url));
+        }
+        self.send_status("updateInternetRadioStation", &params).await
+    }
+
+    pub async fn delete_internet_radio_station(&self, id: &str) -> Result<()> {
+        self.send_status("deleteInternetRadioStation", &[("id", id)]).await
+    }
+
+    /// Call an endpoint that only answers with a status
+    async fn send_status(&self, endpoint: &str, params: &[(&str, &str)]) -> Result<()> {
+        let _: SubsonicResponse<StatusResponse> = self.get_json(endpoint, params).await?;
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,222 @@
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Endpoints whose responses are kept and revalidated with `If-None-Match` and
+/// `If-Modified-Since` when requested again: the lists. Cover art is left to the
+/// cover cache of the app, which keeps it on disk.
+const CONDITIONAL_ENDPOINTS: [&str; 12] = [
+    "getMusicFolders",
+    "getArtists",
+    "getArtist",
+    "getArtistInfo2",
+    "getAlbum",
+    "getIndexes",
+    "getMusicDirectory",
+    "getGenres",
+    "getPlaylists",
+    "getPlaylist",
+    "getStarred2",
+    "getInternetRadioStations",
+];
+
+/// Most bytes of response bodies kept, the least recently used go first
+const MAX_BYTES: usize = 32 * 1024 * 1024;
+
+#[derive(Debug)]
+struct Entry {
+    etag: Option<HeaderValue>,
+    last_modified: Option<HeaderValue>,
+    content_type: String,
+    body: Arc<[u8]>,
+    /// Value of the use counter when the entry was last stored or read
+    used: u64,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    map: HashMap<String, Entry>,
+    /// Sum of the body sizes
+    bytes: usize,
+    /// Counts stores and reads, to tell the least recently used entry
+    uses: u64,
+}
+
+impl Entries {
+    fn touch(&mut self) -> u64 {
+        self.uses += 1;
+        self.uses
+    }
+
+    /// Drop the least recently used entries until `extra` more bytes fit
+    fn make_room(&mut self, extra: usize) {
+        while self.bytes + extra > MAX_BYTES {
+            let Some(key) = self.map.iter().min_by_key(|(_, entry)| entry.used).map(|(key, _)| key.clone()) else {
+                break;
+            };
+            if let Some(entry) = self.map.remove(&key) {
+                self.bytes -= entry.body.len();
+            }
+        }
+    }
+}
+
+/// A kept response, see [`ConditionalCache::get`]
+pub(crate) struct Cached {
+    pub headers: HeaderMap,
+    pub content_type: String,
+    pub body: Arc<[u8]>,
+}
+
+/// Responses the server sent validators (`ETag` or `Last-Modified`) for, so a refresh
+/// of unchanged data costs a `304 Not Modified` instead of the whole response.
+/// At most [`MAX_BYTES`] of them are kept.
+#[derive(Debug, Default)]
+pub(crate) struct ConditionalCache {
+    entries: Mutex<Entries>,
+}
+
+impl ConditionalCache {
+    /// Requests differ in their random auth salt, so the key is built from the endpoint
+    /// and its own parameters only
+    fn key(endpoint: &str, params: &[(&str, &str)]) -> Option<String> {
+        if !CONDITIONAL_ENDPOINTS.contains(&endpoint) {
+            return None;
+        }
+        let params: Vec<_> = params.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+        Some(format!("{}?{}", endpoint, params.join("&")))
+    }
+
+    /// The cached response with the headers making a request conditional on it being
+    /// outdated. They come together so the body is at hand for a `304 Not Modified` even
+    /// if the entry is dropped while the request is on its way.
+    pub fn get(&self, endpoint: &str, params: &[(&str, &str)]) -> Option<Cached> {
+        let key = Self::key(endpoint, params)?;
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let used = entries.touch();
+        let entry = entries.map.get_mut(&key)?;
+        entry.used = used;
+        let mut headers = HeaderMap::new();
+        if let Some(etag) = &entry.etag {
+            headers.insert(IF_NONE_MATCH, etag.clone());
+        }
+        if let Some(last_modified) = &entry.last_modified {
+            headers.insert(IF_MODIFIED_SINCE, last_modified.clone());
+        }
+        Some(Cached {
+            headers,
+            content_type: entry.content_type.clone(),
+            body: entry.body.clone(),
+        })
+    }
+
+    /// Keep a successful response if the server sent validators for it
+    pub fn store(&self, endpoint: &str, params: &[(&str, &str)], headers: &HeaderMap, body: &[u8]) {
+        let Some(key) = Self::key(endpoint, params) else {
+            return;
+        };
+        let etag = headers.get(ETAG).cloned();
+        let last_modified = headers.get(LAST_MODIFIED).cloned();
+        if (etag.is_none() && last_modified.is_none()) || body.len() > MAX_BYTES {
+            return;
+        }
+        let content_type = headers
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(old) = entries.map.remove(&key) {
+            entries.bytes -= old.body.len();
+        }
+        entries.make_room(body.len());
+        let entry = Entry {
+            etag,
+            last_modified,
+            content_type,
+            body: body.into(),
+            used: entries.touch(),
+        };
+        entries.bytes += body.len();
+        entries.map.insert(key, entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validators(etag: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(ETAG, HeaderValue::from_str(etag).unwrap());
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        headers
+    }
+
+    #[test]
+    fn key_covers_endpoint_and_params() {
+        assert_eq!(ConditionalCache::key("getAlbum", &[("id", "1")]).as_deref(), Some("getAlbum?id=1"));
+        assert_ne!(
+            ConditionalCache::key("getAlbum", &[("id", "1")]),
+            ConditionalCache::key("getAlbum", &[("id", "2")])
+        );
+        assert_eq!(ConditionalCache::key("getCoverArt", &[("id", "1")]), None);
+        assert_eq!(ConditionalCache::key("stream", &[("id", "1")]), None);
+    }
+
+    #[test]
+    fn stores_only_with_validators() {
+        let cache = ConditionalCache::default();
+        cache.store("getAlbum", &[("id", "1")], &HeaderMap::new(), b"{}");
+        assert!(cache.get("getAlbum", &[("id", "1")]).is_none());
+
+        cache.store("getAlbum", &[("id", "1")], &validators("\"v1\""), b"{}");
+        let cached = cache.get("getAlbum", &[("id", "1")]).unwrap();
+        assert_eq!(cached.headers.get(IF_NONE_MATCH).unwrap(), "\"v1\"");
+        assert!(cached.headers.get(IF_MODIFIED_SINCE).is_none());
+        assert_eq!(cached.content_type, "application/json");
+        assert_eq!(&*cached.body, b"{}");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(LAST_MODIFIED, HeaderValue::from_static("Sat, 17 Oct 2026 10:00:00 GMT"));
+        cache.store("getGenres", &[], &headers, b"{}");
+        let cached = cache.get("getGenres", &[]).unwrap();
+        assert_eq!(cached.headers.get(IF_MODIFIED_SINCE).unwrap(), "Sat, 17 Oct 2026 10:00:00 GMT");
+    }
+
+    #[test]
+    fn skips_endpoints_and_bodies_it_does_not_keep() {
+        let cache = ConditionalCache::default();
+        cache.store("getCoverArt", &[("id", "1")], &validators("\"v1\""), b"image");
+        assert!(cache.get("getCoverArt", &[("id", "1")]).is_none());
+
+        cache.store("getAlbum", &[("id", "1")], &validators("\"v1\""), &vec![0; MAX_BYTES + 1]);
+        assert!(cache.get("getAlbum", &[("id", "1")]).is_none());
+    }
+
+    #[test]
+    fn evicts_least_recently_used_beyond_max_bytes() {
+        let cache = ConditionalCache::default();
+        let body = vec![0; MAX_BYTES / 2];
+        cache.store("getAlbum", &[("id", "1")], &validators("\"1\""), &body);
+        cache.store("getAlbum", &[("id", "2")], &validators("\"2\""), &body);
+        // Reading the first makes the second the least recently used
+        assert!(cache.get("getAlbum", &[("id", "1")]).is_some());
+        cache.store("getAlbum", &[("id", "3")], &validators("\"3\""), &body);
+
+        assert!(cache.get("getAlbum", &[("id", "1")]).is_some());
+        assert!(cache.get("getAlbum", &[("id", "2")]).is_none());
+        assert!(cache.get("getAlbum", &[("id", "3")]).is_some());
+        assert_eq!(cache.entries.lock().unwrap().bytes, MAX_BYTES);
+    }
+
+    #[test]
+    fn replacing_an_entry_keeps_the_byte_count() {
+        let cache = ConditionalCache::default();
+        cache.store("getAlbum", &[("id", "1")], &validators("\"v1\""), b"old body");
+        cache.store("getAlbum", &[("id", "1")], &validators("\"v2\""), b"new");
+        let cached = cache.get("getAlbum", &[("id", "1")]).unwrap();
+        assert_eq!(cached.headers.get(IF_NONE_MATCH).unwrap(), "\"v2\"");
+        assert_eq!(cache.entries.lock().unwrap().bytes, 3);
+    }
+}
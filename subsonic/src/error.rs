@@ -0,0 +1,167 @@
+use serde_json::error::Category;
+use std::fmt;
+
+/// Everything that can go wrong talking to a Subsonic server
+#[derive(Debug)]
+pub enum Error {
+    /// The request did not complete, e.g. the server is unreachable
+    Http(reqwest::Error),
+    /// The response is not the JSON we expect
+    Json(serde_json::Error),
+    /// The server answered with `status="failed"`, with its message if it gave one
+    Api { error: SubsonicError, message: String },
+    /// The request cannot be answered, e.g. by the mock library or a recording
+    Unsupported(String),
+    /// Handing on a download failed, e.g. writing it to disk
+    Io(std::io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The error codes of the Subsonic API, sent along with `status="failed"`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubsonicError {
+    Generic,
+    MissingParameter,
+    /// The server wants a newer API version than the client speaks
+    ClientTooOld,
+    /// The server speaks an older API version than the client
+    ServerTooOld,
+    WrongCredentials,
+    /// Token authentication is not supported for LDAP users
+    TokenAuthUnsupported,
+    AuthMechanismUnsupported,
+    /// More than one authentication mechanism was given
+    ConflictingAuth,
+    InvalidApiKey,
+    /// The user may not do this, e.g. delete another user's playlist
+    NotAuthorized,
+    /// The trial period of the server is over
+    TrialExpired,
+    /// The requested data was not found
+    NotFound,
+    /// A code not in the API documentation
+    Other(u32),
+}
+
+impl SubsonicError {
+    pub fn from_code(code: u32) -> Self {
+        match code {
+            0 => SubsonicError::Generic,
+            10 => SubsonicError::MissingParameter,
+            20 => SubsonicError::ClientTooOld,
+            30 => SubsonicError::ServerTooOld,
+            40 => SubsonicError::WrongCredentials,
+            41 => SubsonicError::TokenAuthUnsupported,
+            42 => SubsonicError::AuthMechanismUnsupported,
+            43 => SubsonicError::ConflictingAuth,
+            44 => SubsonicError::InvalidApiKey,
+            50 => SubsonicError::NotAuthorized,
+            60 => SubsonicError::TrialExpired,
+            70 => SubsonicError::NotFound,
+            code => SubsonicError::Other(code),
+        }
+    }
+
+    pub fn code(self) -> u32 {
+        match self {
+            SubsonicError::Generic => 0,
+            SubsonicError::MissingParameter => 10,
+            SubsonicError::ClientTooOld => 20,
+            SubsonicError::ServerTooOld => 30,
+            SubsonicError::WrongCredentials => 40,
+            SubsonicError::TokenAuthUnsupported => 41,
+            SubsonicError::AuthMechanismUnsupported => 42,
+            SubsonicError::ConflictingAuth => 43,
+            SubsonicError::InvalidApiKey => 44,
+            SubsonicError::NotAuthorized => 50,
+            SubsonicError::TrialExpired => 60,
+            SubsonicError::NotFound => 70,
+            SubsonicError::Other(code) => code,
+        }
+    }
+
+    /// Whether the server turned down the login, as opposed to the request
+    pub fn is_auth(self) -> bool {
+        matches!(
+            self,
+            SubsonicError::WrongCredentials
+                | SubsonicError::TokenAuthUnsupported
+                | SubsonicError::AuthMechanismUnsupported
+                | SubsonicError::ConflictingAuth
+                | SubsonicError::InvalidApiKey
+        )
+    }
+}
+
+impl fmt::Display for SubsonicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubsonicError::Generic => write!(f, "Server error"),
+            SubsonicError::MissingParameter => write!(f, "Missing parameter"),
+            SubsonicError::ClientTooOld => write!(f, "The server needs a newer client"),
+            SubsonicError::ServerTooOld => write!(f, "The server is too old for this client"),
+            SubsonicError::WrongCredentials => write!(f, "Wrong username or password"),
+            SubsonicError::TokenAuthUnsupported => write!(f, "The server does not support token authentication"),
+            SubsonicError::AuthMechanismUnsupported => {
+                write!(f, "The server does not support this authentication mechanism")
+            }
+            SubsonicError::ConflictingAuth => write!(f, "Conflicting authentication mechanisms"),
+            SubsonicError::InvalidApiKey => write!(f, "Invalid API key"),
+            SubsonicError::NotAuthorized => write!(f, "Not allowed for this user"),
+            SubsonicError::TrialExpired => write!(f, "The trial period of the server is over"),
+            SubsonicError::NotFound => write!(f, "Not found"),
+            SubsonicError::Other(code) => write!(f, "Error {}", code),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Http(e) => write!(f, "Request failed: {}", e),
+            // Usually an HTML page, e.g. from a proxy in front of the server or a wrong URL
+            Error::Json(e) if matches!(e.classify(), Category::Syntax | Category::Eof) => {
+                write!(f, "Not a Subsonic response, is the server URL right? ({})", e)
+            }
+            Error::Json(e) => write!(f, "Invalid response: {}", e),
+            // Servers tend to repeat the meaning of the code in their message
+            Error::Api { error, message } if message.is_empty() || message.eq_ignore_ascii_case(&error.to_string()) => {
+                write!(f, "{}", error)
+            }
+            Error::Api { error, message } => write!(f, "{}: {}", error, message),
+            Error::Unsupported(message) => write!(f, "{}", message),
+            Error::Io(e) => write!(f, "Saving failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Http(e) => Some(e),
+            Error::Json(e) => Some(e),
+            Error::Io(e) => Some(e),
+            Error::Api { .. } | Error::Unsupported(_) => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    /// The URL is dropped, its query carries the credentials
+    fn from(e: reqwest::Error) -> Self {
+        Error::Http(e.without_url())
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
@@ -0,0 +1,47 @@
+//! An async client for the [Subsonic API](http://www.subsonic.org/pages/api.jsp),
+//! spoken by Subsonic, Navidrome, Airsonic, Gonic and others.
+//!
+//! ```no_run
+//! # async fn example() -> subsonic::Result<()> {
+//! let client = subsonic::SubsonicClient::new(
+//!     "https://music.example.com".to_string(),
+//!     "user".to_string(),
+//!     "secret".to_string(),
+//! );
+//! for artist in client.get_artists().await? {
+//!     println!("{}", artist.name);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! - Every endpoint is a typed method on [`SubsonicClient`], answering with the
+//!   types in [`types`].
+//! - Requests authenticate with salted tokens, or the password for servers older than
+//!   API version 1.13, see [`Credentials`] and [`AuthMethod`].
+//! - Lists are revalidated with `ETag` / `If-Modified-Since` where the server supports
+//!   it, so refreshing unchanged data is cheap. The last 32 MiB of them are kept.
+//! - [`SubsonicClient::with_rate_limit`] keeps background work polite toward small servers.
+//! - Failures are reported as [`Error`], with the server's error code
+//!   as a [`SubsonicError`] in [`Error::Api`].
+//!   List elements that fail to parse are left out instead, see [`SubsonicClient::take_skipped`].
+//! - [`SubsonicClient::mock`] serves a fake library without a server and
+//!   [`Traffic`] records and replays real responses, for testing consumers.
+
+pub mod auth;
+pub mod client;
+mod conditional;
+pub mod error;
+pub mod mock;
+mod one_or_many;
+mod rate_limit;
+mod stream;
+pub mod traffic;
+pub mod types;
+
+pub use auth::{AuthMethod, Credentials};
+pub use client::{OnChunk, SubsonicClient, SEARCH_PAGE_SIZE};
+pub use error::{Error, Result, SubsonicError};
+pub use one_or_many::SkippedItem;
+pub use traffic::Traffic;
+pub use types::*;
@@ -0,0 +1,437 @@
+use crate::error::{Error, Result};
+use crate::types::*;
+use serde_json::{json, Value};
+use std::sync::Mutex;
+
+const ARTIST_NAMES: [&str; 12] = [
+    "Aurora Lane", "Brass Meridian", "Cobalt Choir", "Delta Static", "Echo Harbor", "Fern & Flint",
+    "Glass Orchard", "Hollow Pines", "Iron Lantern", "Juniper Road", "Kite Season", "Low Tide Radio",
+];
+const ALBUM_WORDS: [&str; 8] = ["Northern", "Quiet", "Electric", "Paper", "Golden", "Midnight", "Silver", "Wild"];
+const ALBUM_NOUNS: [&str; 6] = ["Lights", "Rivers", "Letters", "Engines", "Gardens", "Signals"];
+const SONG_WORDS: [&str; 10] = ["Falling", "Drift", "Home", "Static", "Harbor", "Ember", "Orbit", "Hollow", "Bloom", "Tides"];
+const GENRES: [&str; 5] = ["Rock", "Electronic", "Folk", "Jazz", "Ambient"];
+const ALBUMS_PER_ARTIST: usize = 3;
+const SONGS_PER_ALBUM: usize = 8;
+
+/// A small deterministic fake library, served instead of a real server by `SubsonicClient::mock`.
+///
+/// It answers endpoints with the same JSON a Subsonic server would send, so the
+/// client's parsing code is exercised as well.
+#[derive(Debug)]
+pub struct MockLibrary {
+    artists: Vec<Artist>,
+    albums: Vec<Album>,
+    songs: Vec<Song>,
+    playlists: Mutex<Vec<(Playlist, Vec<String>)>>,
+    radio_stations: Mutex<Vec<InternetRadioStation>>,
+    /// The jukebox playlist and status, the position doesn't advance
+    jukebox: Mutex<(Vec<String>, JukeboxStatus)>,
+    /// The library scan, which gets through a quarter of the songs on each status request
+    scan: Mutex<ScanStatus>,
+}
+
+impl Default for MockLibrary {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockLibrary {
+    pub fn new() -> Self {
+        let mut artists = Vec::new();
+        let mut albums = Vec::new();
+        let mut songs = Vec::new();
+
+        for (a, artist_name) in ARTIST_NAMES.iter().enumerate() {
+            let artist_id = format!("ar-{}", a + 1);
+            artists.push(Artist {
+                id: artist_id.clone(),
+                name: artist_name.to_string(),
+                album_count: Some(ALBUMS_PER_ARTIST as u32),
+                starred: a.is_multiple_of(4).then(|| "2024-01-01T00:00:00Z".to_string()),
+            });
+
+            for b in 0..ALBUMS_PER_ARTIST {
+                let n = a * ALBUMS_PER_ARTIST + b;
+                let album_id = format!("al-{}", n + 1);
+                let album_name = format!("{} {}", ALBUM_WORDS[n % ALBUM_WORDS.len()], ALBUM_NOUNS[n % ALBUM_NOUNS.len()]);
+                let genre = GENRES[n % GENRES.len()].to_string();
+                let year = 1995 + (n as u32 * 7) % 30;
+
+                let mut album_duration = 0;
+                for t in 0..SONGS_PER_ALBUM {
+                    let s = n * SONGS_PER_ALBUM + t;
+                    let duration = 150 + (s as u32 * 37) % 180;
+                    album_duration += duration;
+                    songs.push(Song {
+                        id: format!("so-{}", s + 1),
+                        title: format!("{} {}", SONG_WORDS[s % SONG_WORDS.len()], SONG_WORDS[(s / 3 + 1) % SONG_WORDS.len()]),
+                        artist: Some(artist_name.to_string()),
+                        album: Some(album_name.clone()),
+                        album_id: Some(album_id.clone()),
+                        artist_id: Some(artist_id.clone()),
+                        track: Some(t as u32 + 1),
+                        year: Some(year),
+                        genre: Some(genre.clone()),
+                        cover_art: None,
+                        size: Some(duration as u64 * 40_000),
+                        content_type: Some("audio/mpeg".to_string()),
+                        suffix: Some("mp3".to_string()),
+                        duration: Some(duration),
+                        bit_rate: Some(320),
+                        path: Some(format!("{}/{}/{:02}.mp3", artist_name, album_name, t + 1)),
+                        starred: s.is_multiple_of(11).then(|| "2024-01-01T00:00:00Z".to_string()),
+                        kind: Some("music".to_string()),
+                        replay_gain: Some(ReplayGain {
+                            track_gain: Some(-6.0 - (s % 5) as f32),
+                            album_gain: Some(-6.0 - (n % 3) as f32),
+                            track_peak: Some(0.98),
+                            album_peak: Some(1.0),
+                            ..ReplayGain::default()
+                        }),
+                    });
+                }
+
+                albums.push(Album {
+                    id: album_id,
+                    name: album_name,
+                    artist: Some(artist_name.to_string()),
+                    artist_id: Some(artist_id.clone()),
+                    year: Some(year),
+                    song_count: Some(SONGS_PER_ALBUM as u32),
+                    duration: Some(album_duration),
+                    cover_art: None,
+                    genre: Some(genre),
+                    created: Some(format!("2024-{:02}-{:02}T12:00:00Z", n % 12 + 1, n % 28 + 1)),
+                    starred: n.is_multiple_of(5).then(|| "2024-01-01T00:00:00Z".to_string()),
+                });
+            }
+        }
+
+        let radio_stations = vec![InternetRadioStation {
+            id: "rs-1".to_string(),
+            name: "Mock FM".to_string(),
+            stream_url: "av://lavfi:sine=frequency=220".to_string(),
+            home_page_url: None,
+        }];
+
+        let mut library = Self {
+            artists,
+            albums,
+            songs,
+            playlists: Mutex::default(),
+            radio_stations: Mutex::new(radio_stations),
+            jukebox: Mutex::new((Vec::new(), JukeboxStatus { current_index: -1, gain: 1.0, ..Default::default() })),
+            scan: Mutex::default(),
+        };
+        let playlists = vec![
+            library.playlist("pl-1", "Road Trip", (1..=20).step_by(3).map(|i| format!("so-{}", i)).collect()),
+            library.playlist("pl-2", "Late Night", (40..=80).step_by(5).map(|i| format!("so-{}", i)).collect()),
+        ];
+        library.playlists = Mutex::new(playlists);
+        library
+    }
+
+    /// A playlist entry with its song count and duration filled in from the songs
+    fn playlist(&self, id: &str, name: &str, song_ids: Vec<String>) -> (Playlist, Vec<String>) {
+        let duration = song_ids
+            .iter()
+            .filter_map(|id| self.songs.iter().find(|s| &s.id == id))
+            .filter_map(|s| s.duration)
+            .sum();
+        (
+            Playlist {
+                id: id.to_string(),
+                name: name.to_string(),
+                song_count: Some(song_ids.len() as u32),
+                duration: Some(duration),
+                owner: Some("mock".to_string()),
+                comment: None,
+                public: Some(false),
+                created: Some("2024-01-01T12:00:00Z".to_string()),
+                changed: Some("2024-01-01T12:00:00Z".to_string()),
+            },
+            song_ids,
+        )
+    }
+
+    /// A playable URL for a song: a sine tone of the song's length, generated by mpv itself
+    pub fn stream_url(&self, song_id: &str) -> String {
+        let song = self.songs.iter().find(|s| s.id == song_id);
+        let index = self.songs.iter().position(|s| s.id == song_id).unwrap_or(0);
+        let duration = song.and_then(|s| s.duration).unwrap_or(180);
+        format!("av://lavfi:sine=frequency={}:duration={}", 220 + (index % 24) * 20, duration)
+    }
+
+    /// Answer an API request with the JSON a server would send
+    pub fn respond(&self, endpoint: &str, params: &[(&str, &str)]) -> Result<Value> {
+        let param = |name: &str| params.iter().find(|(key, _)| *key == name).map(|(_, value)| *value);
+        let number = |name: &str, default: usize| param(name).and_then(|v| v.parse().ok()).unwrap_or(default);
+
+        let body = match endpoint {
+            "getArtists" => json!({ "artists": { "index": [{ "name": "#", "artist": self.artists }] } }),
+            "getArtist" => {
+                let id = param("id").unwrap_or_default();
+                let albums: Vec<_> = self.albums.iter().filter(|a| a.artist_id.as_deref() == Some(id)).collect();
+                json!({ "artist": { "id": id, "album": albums } })
+            }
+            "getArtistInfo2" => {
+                // The artists next to it in the list count as similar
+                let id = param("id").unwrap_or_default();
+                let Some(index) = self.artists.iter().position(|a| a.id == id) else {
+                    return Ok(Self::failed(70, "Artist not found"));
+                };
+                let similar: Vec<_> = (1..self.artists.len())
+                    .map(|offset| &self.artists[(index + offset) % self.artists.len()])
+                    .take(number("count", 20))
+                    .collect();
+                json!({ "artistInfo2": {
+                    "biography": format!("{} is a made-up artist of the mock library. <a href=\"https://www.last.fm\">Read more on Last.fm</a>", self.artists[index].name),
+                    "similarArtist": similar,
+                } })
+            }
+            "getAlbum" => {
+                let id = param("id").unwrap_or_default();
+                let Some(album) = self.albums.iter().find(|a| a.id == id) else {
+                    return Ok(Self::failed(70, "Album not found"));
+                };
+                let songs: Vec<_> = self.songs.iter().filter(|s| s.album_id.as_deref() == Some(id)).collect();
+                let mut album = serde_json::to_value(album)?;
+                album["song"] = json!(songs);
+                json!({ "album": album })
+            }
+            // Folders are laid out as artist/album/song, reusing the IDs of the tags
+            "getMusicFolders" => json!({ "musicFolders": { "musicFolder": [{ "id": 1, "name": "Music" }] } }),
+            "getIndexes" => json!({ "indexes": { "index": [{ "name": "#", "artist": self.artists }] } }),
+            "getMusicDirectory" => {
+                let id = param("id").unwrap_or_default();
+                let (name, child): (_, Vec<_>) = if let Some(artist) = self.artists.iter().find(|a| a.id == id) {
+                    let albums = self.albums.iter().filter(|a| a.artist_id.as_deref() == Some(id));
+                    (&artist.name, albums.map(|a| json!({ "id": a.id, "isDir": true, "title": a.name, "artist": artist.name })).collect())
+                } else if let Some(album) = self.albums.iter().find(|a| a.id == id) {
+                    let songs = self.songs.iter().filter(|s| s.album_id.as_deref() == Some(id));
+                    (&album.name, songs.map(|s| json!(DirectoryChild { is_dir: false, song: s.clone() })).collect())
+                } else {
+                    return Ok(Self::failed(70, "Directory not found"));
+                };
+                json!({ "directory": { "id": id, "name": name, "child": child } })
+            }
+            "getAlbumList2" => {
+                // Without play counts or randomness, fixed shuffles stand in for them
+                let shuffled = |step: usize| -> Vec<&Album> {
+                    let len = self.albums.len();
+                    (0..len).map(|i| &self.albums[i * step % len]).collect()
+                };
+                let mut albums: Vec<_> = self.albums.iter().collect();
+                match param("type").unwrap_or_default() {
+                    "newest" => albums.sort_by(|a, b| b.created.cmp(&a.created)),
+                    "byYear" => albums.sort_by_key(|a| std::cmp::Reverse(a.year)),
+                    "recent" => albums = shuffled(5),
+                    "frequent" => albums = shuffled(11),
+                    _ => albums = shuffled(7),
+                }
+                let albums: Vec<_> = albums.into_iter().skip(number("offset", 0)).take(number("size", 10)).collect();
+                json!({ "albumList2": { "album": albums } })
+            }
+            "getLyrics" => json!({ "lyrics": { "$text": "These lyrics are made up\nfor the mock library" } }),
+            "getOpenSubsonicExtensions" => json!({ "openSubsonicExtensions": [{ "name": "songLyrics", "versions": [1] }] }),
+            "getLyricsBySongId" => {
+                let id = param("id").unwrap_or_default();
+                let Some(song) = self.songs.iter().find(|s| s.id == id) else {
+                    return Ok(Self::failed(70, "Song not found"));
+                };
+                // A line every few seconds, for as long as the song lasts
+                let lines: Vec<_> = (0..song.duration.unwrap_or(0) / 6)
+                    .map(|i| json!({ "start": i * 6000, "value": format!("Line {} of {}", i + 1, song.title) }))
+                    .collect();
+                json!({ "lyricsList": { "structuredLyrics": [{
+                    "displayArtist": song.artist,
+                    "displayTitle": song.title,
+                    "lang": "eng",
+                    "offset": 0,
+                    "synced": true,
+                    "line": lines,
+                }] } })
+            }
+            "getGenres" => {
+                let genres: Vec<_> = GENRES
+                    .iter()
+                    .map(|genre| {
+                        let songs = self.songs.iter().filter(|s| s.genre.as_deref() == Some(genre)).count();
+                        let albums = self.albums.iter().filter(|a| a.genre.as_deref() == Some(genre)).count();
+                        json!({ "value": genre, "songCount": songs, "albumCount": albums })
+                    })
+                    .collect();
+                json!({ "genres": { "genre": genres } })
+            }
+            "getSongsByGenre" => {
+                let genre = param("genre").unwrap_or_default();
+                let songs: Vec<_> = self
+                    .songs
+                    .iter()
+                    .filter(|s| s.genre.as_deref() == Some(genre))
+                    .skip(number("offset", 0))
+                    .take(number("count", 10))
+                    .collect();
+                json!({ "songsByGenre": { "song": songs } })
+            }
+            "getSimilarSongs2" => {
+                // Songs in the genres the artist plays, theirs and everyone else's
+                let id = param("id").unwrap_or_default();
+                let genres: Vec<_> = self
+                    .albums
+                    .iter()
+                    .filter(|a| a.artist_id.as_deref() == Some(id))
+                    .filter_map(|a| a.genre.as_deref())
+                    .collect();
+                let songs: Vec<_> = self
+                    .songs
+                    .iter()
+                    .filter(|s| s.genre.as_deref().is_some_and(|g| genres.contains(&g)))
+                    .step_by(3)
+                    .take(number("count", 50))
+                    .collect();
+                json!({ "similarSongs2": { "song": songs } })
+            }
+            "search3" => {
+                let query = param("query").unwrap_or_default().to_lowercase();
+                let matches = |text: &str| text.to_lowercase().contains(&query);
+                let page = |kind: &str| (number(&format!("{}Offset", kind), 0), number(&format!("{}Count", kind), 20));
+
+                let (offset, count) = page("artist");
+                let artists: Vec<_> = self.artists.iter().filter(|a| matches(&a.name)).skip(offset).take(count).collect();
+                let (offset, count) = page("album");
+                let albums: Vec<_> = self.albums.iter().filter(|a| matches(&a.name)).skip(offset).take(count).collect();
+                let (offset, count) = page("song");
+                let songs: Vec<_> = self.songs.iter().filter(|s| matches(&s.title)).skip(offset).take(count).collect();
+                json!({ "searchResult3": { "artist": artists, "album": albums, "song": songs } })
+            }
+            "getStarred2" => {
+                let artists: Vec<_> = self.artists.iter().filter(|a| a.starred.is_some()).collect();
+                let albums: Vec<_> = self.albums.iter().filter(|a| a.starred.is_some()).collect();
+                let songs: Vec<_> = self.songs.iter().filter(|s| s.starred.is_some()).collect();
+                json!({ "starred2": { "artist": artists, "album": albums, "song": songs } })
+            }
+            "getPlaylists" => {
+                let playlists = self.playlists.lock().unwrap_or_else(|e| e.into_inner());
+                let playlists: Vec<_> = playlists.iter().map(|(playlist, _)| playlist).collect();
+                json!({ "playlists": { "playlist": playlists } })
+            }
+            "getPlaylist" => {
+                let id = param("id").unwrap_or_default();
+                let playlists = self.playlists.lock().unwrap_or_else(|e| e.into_inner());
+                let Some((playlist, song_ids)) = playlists.iter().find(|(p, _)| p.id == id) else {
+                    return Ok(Self::failed(70, "Playlist not found"));
+                };
+                let songs: Vec<_> =
+                    song_ids.iter().filter_map(|id| self.songs.iter().find(|s| &s.id == id)).collect();
+                json!({ "playlist": { "id": playlist.id, "name": playlist.name, "entry": songs } })
+            }
+            "createPlaylist" | "updatePlaylist" | "deletePlaylist" => {
+                let mut playlists = self.playlists.lock().unwrap_or_else(|e| e.into_inner());
+                let values = |name: &'static str| params.iter().filter(move |(key, _)| *key == name).map(|(_, value)| *value);
+                match endpoint {
+                    "createPlaylist" => {
+                        let song_ids: Vec<String> = values("songId").map(str::to_string).collect();
+                        let id = format!("pl-{}", uuid::Uuid::new_v4());
+                        playlists.push(self.playlist(&id, param("name").unwrap_or_default(), song_ids));
+                    }
+                    "updatePlaylist" => {
+                        let id = param("playlistId").unwrap_or_default();
+                        let Some(index) = playlists.iter().position(|(p, _)| p.id == id) else {
+                            return Ok(Self::failed(70, "Playlist not found"));
+                        };
+                        let (playlist, mut song_ids) = playlists.remove(index);
+                        let mut removed: Vec<usize> = values("songIndexToRemove").filter_map(|i| i.parse().ok()).collect();
+                        removed.sort_unstable();
+                        for i in removed.into_iter().rev() {
+                            if i < song_ids.len() {
+                                song_ids.remove(i);
+                            }
+                        }
+                        song_ids.extend(values("songIdToAdd").map(str::to_string));
+                        let (mut updated, song_ids) = self.playlist(&playlist.id, param("name").unwrap_or(&playlist.name), song_ids);
+                        updated.comment = param("comment").map(str::to_string).or(playlist.comment);
+                        updated.public = param("public").map(|public| public == "true").or(playlist.public);
+                        updated.created = playlist.created;
+                        updated.changed = Some("2024-06-01T12:00:00Z".to_string());
+                        playlists.insert(index, (updated, song_ids));
+                    }
+                    _ => {
+                        let id = param("id").unwrap_or_default();
+                        playlists.retain(|(p, _)| p.id != id);
+                    }
+                }
+                json!({})
+            }
+            "getInternetRadioStations" => {
+                let stations = self.radio_stations.lock().unwrap_or_else(|e| e.into_inner());
+                json!({ "internetRadioStations": { "internetRadioStation": *stations } })
+            }
+            "ping" | "scrobble" | "savePlayQueue" | "star" | "unstar" => json!({}),
+            "getPlayQueue" => json!({}),
+            "jukeboxControl" => {
+                let mut jukebox = self.jukebox.lock().unwrap_or_else(|e| e.into_inner());
+                let (playlist, status) = &mut *jukebox;
+                match param("action").unwrap_or_default() {
+                    "set" => {
+                        *playlist = params.iter().filter(|(key, _)| *key == "id").map(|(_, id)| id.to_string()).collect();
+                        status.current_index = if playlist.is_empty() { -1 } else { 0 };
+                        status.position = Some(0);
+                    }
+                    "start" => status.playing = status.current_index >= 0,
+                    "stop" => status.playing = false,
+                    "skip" => {
+                        status.current_index = number("index", 0).min(playlist.len().saturating_sub(1)) as i32;
+                        status.position = Some(number("offset", 0) as u32);
+                    }
+                    "setGain" => status.gain = param("gain").and_then(|gain| gain.parse().ok()).unwrap_or(status.gain),
+                    _ => {}
+                }
+                json!({ "jukeboxStatus": status })
+            }
+            "startScan" | "getScanStatus" => {
+                let mut scan = self.scan.lock().unwrap_or_else(|e| e.into_inner());
+                if endpoint == "startScan" && !scan.scanning {
+                    *scan = ScanStatus { scanning: true, count: Some(0) };
+                } else if scan.scanning {
+                    let count = scan.count.unwrap_or(0) + self.songs.len() as u64 / 4;
+                    scan.scanning = count < self.songs.len() as u64;
+                    scan.count = Some(count.min(self.songs.len() as u64));
+                }
+                json!({ "scanStatus": *scan })
+            }
+            "createInternetRadioStation" | "updateInternetRadioStation" | "deleteInternetRadioStation" => {
+                let mut stations = self.radio_stations.lock().unwrap_or_else(|e| e.into_inner());
+                let id = param("id").map(str::to_string);
+                stations.retain(|s| Some(&s.id) != id.as_ref());
+                if endpoint != "deleteInternetRadioStation" {
+                    let id = id.unwrap_or_else(|| format!("rs-{}", uuid::Uuid::new_v4()));
+                    stations.push(InternetRadioStation {
+                        id,
+                        name: param("name").unwrap_or_default().to_string(),
+                        stream_url: param("streamUrl").unwrap_or_default().to_string(),
+                        home_page_url: param("homepageUrl").map(str::to_string),
+                    });
+                }
+                json!({})
+            }
+            _ => return Err(Error::Unsupported(format!("{} is not supported by the mock library", endpoint))),
+        };
+
+        let mut response = body;
+        response["status"] = json!("ok");
+        response["version"] = json!("1.16.1");
+        Ok(json!({ "subsonic-response": response }))
+    }
+
+    /// The error response a server sends, e.g. code 70 for missing data
+    fn failed(code: u32, message: &str) -> Value {
+        json!({ "subsonic-response": {
+            "status": "failed",
+            "version": "1.16.1",
+            "error": { "code": code, "message": message },
+        } })
+    }
+}
@@ -0,0 +1,86 @@
+//! Some servers serialize a list with a single element as the element itself, and an
+//! empty one as `null`. Fields using [`deserialize`] accept all of these as a list.
+//!
+//! Elements that fail to parse, like a song with malformed metadata, are dropped instead of
+//! failing the whole response. They are handed to [`collect_skipped`] for reporting.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use std::cell::RefCell;
+use std::fmt;
+
+/// A list element dropped because it could not be parsed
+#[derive(Debug, Clone)]
+pub struct SkippedItem {
+    /// Type of the element, e.g. `Song`
+    pub kind: &'static str,
+    /// ID, title or name of the element, if it has one
+    pub label: Option<String>,
+    pub error: String,
+}
+
+impl fmt::Display for SkippedItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.label {
+            Some(label) => write!(f, "{} {}: {}", self.kind, label, self.error),
+            None => write!(f, "{}: {}", self.kind, self.error),
+        }
+    }
+}
+
+thread_local! {
+    /// Elements skipped while parsing inside `collect_skipped`, `None` outside of it
+    static SKIPPED: RefCell<Option<Vec<SkippedItem>>> = const { RefCell::new(None) };
+}
+
+/// Run a parse and return the list elements it skipped along with its result
+pub(crate) fn collect_skipped<R>(parse: impl FnOnce() -> R) -> (R, Vec<SkippedItem>) {
+    let outer = SKIPPED.with(|skipped| skipped.borrow_mut().replace(Vec::new()));
+    let result = parse();
+    let skipped = SKIPPED.with(|skipped| std::mem::replace(&mut *skipped.borrow_mut(), outer));
+    (result, skipped.unwrap_or_default())
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    Many(Vec<T>),
+    One(T),
+}
+
+pub(crate) fn deserialize<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    let values = match Option::<OneOrMany<Value>>::deserialize(deserializer)? {
+        Some(OneOrMany::Many(list)) => list,
+        Some(OneOrMany::One(item)) => vec![item],
+        None => Vec::new(),
+    };
+
+    Ok(values
+        .into_iter()
+        .filter_map(|value| {
+            let label = ["id", "title", "name"]
+                .iter()
+                .find_map(|key| value.get(key))
+                .map(|label| label.as_str().map_or_else(|| label.to_string(), str::to_string));
+            serde_json::from_value(value)
+                .map_err(|e| skip::<T>(label, e))
+                .ok()
+        })
+        .collect())
+}
+
+fn skip<T>(label: Option<String>, error: serde_json::Error) {
+    let kind = std::any::type_name::<T>().rsplit("::").next().unwrap_or_default();
+    let item = SkippedItem { kind, label, error: error.to_string() };
+    log::warn!("Skipping malformed list element: {}", item);
+    SKIPPED.with(|skipped| {
+        if let Some(skipped) = skipped.borrow_mut().as_mut() {
+            skipped.push(item);
+        }
+    });
+}
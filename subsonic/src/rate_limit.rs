@@ -0,0 +1,34 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Spaces requests evenly so no more than a given number start per second.
+/// Shared by all clones of a client, so concurrent tasks queue up behind each other.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    interval: Duration,
+    /// Earliest start of the next request
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    /// `None` for no limit: zero, a negative or NaN rate, or one so low the interval
+    /// between requests overflows
+    pub fn new(max_requests_per_second: f32) -> Option<Self> {
+        let interval = Duration::try_from_secs_f32(1.0 / max_requests_per_second).ok()?;
+        Some(Self {
+            interval,
+            next: Mutex::new(Instant::now()),
+        })
+    }
+
+    /// Wait for this request's turn
+    pub async fn wait(&self) {
+        let start = {
+            let mut next = self.next.lock().unwrap_or_else(|e| e.into_inner());
+            let start = (*next).max(Instant::now());
+            *next = start + self.interval;
+            start
+        };
+        tokio::time::sleep_until(start.into()).await;
+    }
+}
@@ -0,0 +1,145 @@
+use std::ops::Range;
+
+/// Finds the entries of the `index` array in a `getArtists` response while it downloads,
+/// so each section can be parsed before the rest of the response has arrived
+#[derive(Debug, Default)]
+pub(crate) struct IndexScanner {
+    /// Everything received so far
+    pub buffer: Vec<u8>,
+    /// How far `buffer` has been scanned
+    scanned: usize,
+    /// Number of objects and arrays open at `scanned`
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    string_start: usize,
+    /// The last complete string, which is the key when an array opens
+    last_string: Range<usize>,
+    /// Depth inside the `index` array, while in it
+    index_depth: Option<usize>,
+    entry_start: usize,
+}
+
+impl IndexScanner {
+    /// Add a chunk of the response, returning the byte ranges of the entries it completed
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<Range<usize>> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut entries = Vec::new();
+        while self.scanned < self.buffer.len() {
+            let i = self.scanned;
+            let byte = self.buffer[i];
+            self.scanned += 1;
+
+            if self.in_string {
+                if self.escaped {
+                    self.escaped = false;
+                } else if byte == b'\\' {
+                    self.escaped = true;
+                } else if byte == b'"' {
+                    self.in_string = false;
+                    self.last_string = self.string_start..i;
+                }
+                continue;
+            }
+
+            match byte {
+                b'"' => {
+                    self.in_string = true;
+                    self.string_start = i + 1;
+                }
+                b'[' | b'{' => {
+                    self.depth += 1;
+                    if byte == b'[' && self.index_depth.is_none() && &self.buffer[self.last_string.clone()] == b"index" {
+                        self.index_depth = Some(self.depth);
+                    } else if byte == b'{' && self.index_depth == Some(self.depth - 1) {
+                        self.entry_start = i;
+                    }
+                }
+                b']' | b'}' => {
+                    if byte == b'}' && self.index_depth == Some(self.depth.saturating_sub(1)) {
+                        entries.push(self.entry_start..i + 1);
+                    } else if byte == b']' && self.index_depth == Some(self.depth) {
+                        self.index_depth = None;
+                    }
+                    self.depth = self.depth.saturating_sub(1);
+                }
+                _ => {}
+            }
+        }
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Strings holding brackets, escaped quotes and backslashes, and an `index` key outside
+    /// the artists that must not be taken for the list
+    const RESPONSE: &str = concat!(
+        r#"{"subsonic-response":{"status":"ok","version":"1.16.1","artists":{"ignoredArticles":"The [index] {","#,
+        r#""index":[{"name":"A","artist":[{"id":"1","name":"AC/DC \"}]\" \\"}]},"#,
+        r#"{"name":"\\","artist":[{"id":"2","name":"{\"index\":[{"}]}],"#,
+        r#""other":{"index":"x"}}}}"#,
+    );
+
+    fn entries(chunks: &[&[u8]]) -> Vec<String> {
+        let mut scanner = IndexScanner::default();
+        let mut ranges = Vec::new();
+        for chunk in chunks {
+            ranges.extend(scanner.feed(chunk));
+        }
+        ranges.into_iter().map(|range| String::from_utf8(scanner.buffer[range].to_vec()).unwrap()).collect()
+    }
+
+    fn expected() -> Vec<String> {
+        vec![
+            r#"{"name":"A","artist":[{"id":"1","name":"AC/DC \"}]\" \\"}]}"#.to_string(),
+            r#"{"name":"\\","artist":[{"id":"2","name":"{\"index\":[{"}]}"#.to_string(),
+        ]
+    }
+
+    #[test]
+    fn finds_entries_in_one_chunk() {
+        serde_json::from_str::<serde_json::Value>(RESPONSE).unwrap();
+        let found = entries(&[RESPONSE.as_bytes()]);
+        assert_eq!(found, expected());
+        for entry in found {
+            serde_json::from_str::<serde_json::Value>(&entry).unwrap();
+        }
+    }
+
+    #[test]
+    fn finds_entries_split_anywhere() {
+        let bytes = RESPONSE.as_bytes();
+        for split in 0..=bytes.len() {
+            let (first, second) = bytes.split_at(split);
+            assert_eq!(entries(&[first, second]), expected(), "split at {}", split);
+        }
+    }
+
+    #[test]
+    fn finds_entries_split_twice_inside_strings_and_escapes() {
+        let bytes = RESPONSE.as_bytes();
+        // Right after each backslash, so the escaped character arrives in the next chunk
+        for (i, _) in bytes.iter().enumerate().filter(|(_, byte)| **byte == b'\\') {
+            for j in i + 1..=bytes.len() {
+                let chunks = [&bytes[..i + 1], &bytes[i + 1..j], &bytes[j..]];
+                assert_eq!(entries(&chunks), expected(), "split at {} and {}", i + 1, j);
+            }
+        }
+    }
+
+    #[test]
+    fn finds_entries_byte_by_byte() {
+        let chunks: Vec<&[u8]> = RESPONSE.as_bytes().chunks(1).collect();
+        assert_eq!(entries(&chunks), expected());
+    }
+
+    #[test]
+    fn finds_nothing_in_a_failed_response() {
+        let response = r#"{"subsonic-response":{"status":"failed","error":{"code":40,"message":"Wrong password"}}}"#;
+        assert!(entries(&[response.as_bytes()]).is_empty());
+    }
+}
@@ -0,0 +1,67 @@
+use crate::error::{Error, Result};
+use log::{debug, warn};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::PathBuf;
+
+/// Records API responses to a directory, or answers requests from such a recording.
+///
+/// Each exchange is stored as `<endpoint>-<hash of the parameters>.json`, holding the
+/// endpoint, its parameters and the raw JSON response, so a recording sent in by a user
+/// can be replayed against the exact same parsing code.
+#[derive(Debug, Clone)]
+pub enum Traffic {
+    Record(PathBuf),
+    Replay(PathBuf),
+}
+
+impl Traffic {
+    fn path(dir: &std::path::Path, endpoint: &str, params: &[(&str, &str)]) -> PathBuf {
+        let mut params: Vec<_> = params
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        params.sort();
+        let hash = md5::compute(params.join("&"));
+        dir.join(format!("{}-{:x}.json", endpoint, hash))
+    }
+
+    /// Save a response if recording
+    pub fn record(&self, endpoint: &str, params: &[(&str, &str)], response: &Value) {
+        let Traffic::Record(dir) = self else {
+            return;
+        };
+
+        let path = Self::path(dir, endpoint, params);
+        let params: serde_json::Map<_, _> = params
+            .iter()
+            .map(|(key, value)| (key.to_string(), json!(value)))
+            .collect();
+        let exchange = json!({ "endpoint": endpoint, "params": params, "response": response });
+
+        let result = fs::create_dir_all(dir)
+            .and_then(|_| fs::write(&path, serde_json::to_string_pretty(&exchange).unwrap_or_default()));
+        match result {
+            Ok(()) => debug!("Recorded {} to {}", endpoint, path.display()),
+            Err(e) => warn!("Failed to record {} to {}: {}", endpoint, path.display(), e),
+        }
+    }
+
+    /// The recorded response for a request, if replaying
+    pub fn replay(&self, endpoint: &str, params: &[(&str, &str)]) -> Option<Result<Value>> {
+        let Traffic::Replay(dir) = self else {
+            return None;
+        };
+
+        let path = Self::path(dir, endpoint, params);
+        debug!("Replaying {} from {}", endpoint, path.display());
+        Some(
+            fs::read_to_string(&path)
+                .map_err(|e| Error::Unsupported(format!("No recording of {} at {}: {}", endpoint, path.display(), e)))
+                .and_then(|content| {
+                    let mut exchange: Value = serde_json::from_str(&content)?;
+                    Ok(exchange["response"].take())
+                }),
+        )
+    }
+}
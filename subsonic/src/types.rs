@@ -0,0 +1,661 @@
+use serde::{Deserialize, Deserializer, Serialize};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Artist {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "albumCount")]
+    pub album_count: Option<u32>,
+    pub starred: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Album {
+    pub id: String,
+    pub name: String,
+    pub artist: Option<String>,
+    #[serde(rename = "artistId")]
+    pub artist_id: Option<String>,
+    pub year: Option<u32>,
+    #[serde(rename = "songCount")]
+    pub song_count: Option<u32>,
+    pub duration: Option<u32>,
+    #[serde(rename = "coverArt")]
+    pub cover_art: Option<String>,
+    pub genre: Option<String>,
+    pub created: Option<String>,
+    pub starred: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Song {
+    pub id: String,
+    pub title: String,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    #[serde(rename = "albumId")]
+    pub album_id: Option<String>,
+    #[serde(rename = "artistId")]
+    pub artist_id: Option<String>,
+    pub track: Option<u32>,
+    pub year: Option<u32>,
+    pub genre: Option<String>,
+    #[serde(rename = "coverArt")]
+    pub cover_art: Option<String>,
+    pub size: Option<u64>,
+    #[serde(rename = "contentType")]
+    pub content_type: Option<String>,
+    pub suffix: Option<String>,
+    pub duration: Option<u32>,
+    #[serde(rename = "bitRate")]
+    pub bit_rate: Option<u32>,
+    pub path: Option<String>,
+    pub starred: Option<String>,
+    /// "music", "podcast", "audiobook" or "video"
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    /// Loudness normalization values computed by OpenSubsonic servers
+    #[serde(rename = "replayGain", default)]
+    pub replay_gain: Option<ReplayGain>,
+}
+
+impl Song {
+    /// A podcast episode or audiobook chapter rather than music, told by its type or,
+    /// for servers that don't set one, its genre
+    pub fn is_spoken_word(&self) -> bool {
+        let spoken = |name: &str| {
+            let name = name.to_lowercase();
+            ["podcast", "audiobook", "spoken word"].iter().any(|word| name.contains(word))
+        };
+        self.kind.as_deref().is_some_and(spoken) || self.genre.as_deref().is_some_and(spoken)
+    }
+}
+
+/// The OpenSubsonic `replayGain` of a song, gains in dB
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ReplayGain {
+    pub track_gain: Option<f32>,
+    pub album_gain: Option<f32>,
+    pub track_peak: Option<f32>,
+    pub album_peak: Option<f32>,
+    /// Added to the other gains, e.g. the header gain of Opus files
+    pub base_gain: Option<f32>,
+    /// Used when neither track nor album gain are known
+    pub fallback_gain: Option<f32>,
+}
+
+impl ReplayGain {
+    /// The gain to apply, from the album values if `album` is set and the track values
+    /// otherwise, each falling back to the other. It is lowered if needed so the peak
+    /// doesn't clip.
+    pub fn gain(&self, album: bool) -> Option<f32> {
+        let (gain, peak) = if album {
+            (self.album_gain.or(self.track_gain), self.album_peak.or(self.track_peak))
+        } else {
+            (self.track_gain.or(self.album_gain), self.track_peak.or(self.album_peak))
+        };
+        let gain = gain.or(self.fallback_gain)? + self.base_gain.unwrap_or(0.0);
+        match peak.filter(|peak| *peak > 0.0) {
+            Some(peak) => Some(gain.min(-20.0 * peak.log10())),
+            None => Some(gain),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubsonicResponse<T> {
+    #[serde(rename = "subsonic-response")]
+    pub subsonic_response: T,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ArtistsResponse {
+    pub artists: ArtistsIndex,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ArtistsIndex {
+    #[serde(default, deserialize_with = "crate::one_or_many::deserialize")]
+    pub index: Vec<ArtistIndex>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ArtistIndex {
+    /// Usually the first letter of the artists' names
+    pub name: String,
+    #[serde(default, deserialize_with = "crate::one_or_many::deserialize")]
+    pub artist: Vec<Artist>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ArtistInfo2Response {
+    #[serde(rename = "artistInfo2")]
+    pub artist_info2: ArtistInfo,
+}
+
+/// Biography, pictures and similar artists of an artist, mostly taken from Last.fm
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ArtistInfo {
+    /// May contain HTML, commonly a link to the Last.fm page
+    pub biography: Option<String>,
+    #[serde(rename = "musicBrainzId")]
+    pub music_brainz_id: Option<String>,
+    #[serde(rename = "lastFmUrl")]
+    pub last_fm_url: Option<String>,
+    #[serde(rename = "smallImageUrl")]
+    pub small_image_url: Option<String>,
+    #[serde(rename = "mediumImageUrl")]
+    pub medium_image_url: Option<String>,
+    #[serde(rename = "largeImageUrl")]
+    pub large_image_url: Option<String>,
+    /// Similar artists in the library, most similar first
+    #[serde(rename = "similarArtist", default, deserialize_with = "crate::one_or_many::deserialize")]
+    pub similar_artist: Vec<Artist>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MusicFoldersResponse {
+    #[serde(rename = "musicFolders")]
+    pub music_folders: MusicFolders,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MusicFolders {
+    #[serde(rename = "musicFolder", default, deserialize_with = "crate::one_or_many::deserialize")]
+    pub music_folder: Vec<MusicFolder>,
+}
+
+/// A top-level folder the server scans, e.g. one for music and one for audiobooks
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct MusicFolder {
+    /// A number in the JSON of most servers
+    #[serde(deserialize_with = "string_or_number")]
+    pub id: String,
+    pub name: Option<String>,
+}
+
+impl MusicFolder {
+    /// The name, or the ID for servers that leave it out
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.id)
+    }
+}
+
+fn string_or_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Id {
+        String(String),
+        Number(u64),
+    }
+    Ok(match Id::deserialize(deserializer)? {
+        Id::String(id) => id,
+        Id::Number(id) => id.to_string(),
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IndexesResponse {
+    pub indexes: Indexes,
+}
+
+/// The top directories of the music folders, as listed by `getIndexes` for folder browsing
+#[derive(Debug, Deserialize)]
+pub struct Indexes {
+    /// Sections of top directories, each directory given by its ID and name
+    #[serde(default, deserialize_with = "crate::one_or_many::deserialize")]
+    pub index: Vec<ArtistIndex>,
+    /// Songs lying directly in a music folder
+    #[serde(default, deserialize_with = "crate::one_or_many::deserialize")]
+    pub child: Vec<Song>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MusicDirectoryResponse {
+    pub directory: MusicDirectory,
+}
+
+/// A directory of the music folders with its subdirectories and songs
+#[derive(Debug, Deserialize)]
+pub struct MusicDirectory {
+    pub id: String,
+    pub name: String,
+    #[serde(default, deserialize_with = "crate::one_or_many::deserialize")]
+    pub child: Vec<DirectoryChild>,
+}
+
+/// Entry of a music directory, a subdirectory (named by its title) or a song
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DirectoryChild {
+    #[serde(rename = "isDir", default)]
+    pub is_dir: bool,
+    #[serde(flatten)]
+    pub song: Song,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AlbumResponse {
+    pub album: AlbumDetail,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AlbumDetail {
+    #[allow(dead_code)]
+    pub id: String,
+    #[allow(dead_code)]
+    pub name: String,
+    #[allow(dead_code)]
+    pub artist: Option<String>,
+    #[serde(rename = "artistId")]
+    #[allow(dead_code)]
+    pub artist_id: Option<String>,
+    #[serde(rename = "coverArt")]
+    #[allow(dead_code)]
+    pub cover_art: Option<String>,
+    #[serde(rename = "songCount")]
+    #[allow(dead_code)]
+    pub song_count: Option<u32>,
+    #[allow(dead_code)]
+    pub duration: Option<u32>,
+    #[allow(dead_code)]
+    pub year: Option<u32>,
+    #[serde(default, deserialize_with = "crate::one_or_many::deserialize")]
+    pub song: Vec<Song>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LyricsResponse {
+    pub lyrics: Option<Lyrics>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Lyrics {
+    #[allow(dead_code)]
+    pub artist: Option<String>,
+    #[allow(dead_code)]
+    pub title: Option<String>,
+    #[serde(rename = "$text")]
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LyricsListResponse {
+    #[serde(rename = "lyricsList", default)]
+    pub lyrics_list: LyricsList,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LyricsList {
+    #[serde(rename = "structuredLyrics", default, deserialize_with = "crate::one_or_many::deserialize")]
+    pub structured_lyrics: Vec<StructuredLyrics>,
+}
+
+/// Lyrics of a song from `getLyricsBySongId` (OpenSubsonic), one version per language
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StructuredLyrics {
+    pub display_artist: Option<String>,
+    pub display_title: Option<String>,
+    pub lang: Option<String>,
+    /// Milliseconds to show all lines earlier by, later when negative
+    pub offset: i64,
+    /// The lines come with start times
+    pub synced: bool,
+    #[serde(deserialize_with = "crate::one_or_many::deserialize")]
+    pub line: Vec<LyricsLine>,
+}
+
+impl StructuredLyrics {
+    /// The lines as plain text
+    pub fn text(&self) -> String {
+        self.line.iter().map(|line| line.value.as_str()).collect::<Vec<_>>().join("\n")
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LyricsLine {
+    /// Milliseconds into the song, for synced lyrics
+    pub start: Option<u64>,
+    pub value: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OpenSubsonicExtensionsResponse {
+    #[serde(rename = "openSubsonicExtensions", default, deserialize_with = "crate::one_or_many::deserialize")]
+    pub open_subsonic_extensions: Vec<OpenSubsonicExtension>,
+}
+
+/// An OpenSubsonic API extension the server implements, like `songLyrics`
+#[derive(Debug, Clone, Deserialize)]
+pub struct OpenSubsonicExtension {
+    pub name: String,
+    #[serde(default)]
+    pub versions: Vec<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatusResponse {
+    pub status: String,
+    pub error: Option<ApiError>,
+}
+
+impl StatusResponse {
+    /// `Error::Api` unless the status is `ok`
+    pub fn check(self, endpoint: &str) -> crate::Result<()> {
+        if self.status == "ok" {
+            return Ok(());
+        }
+        let (code, message) = match self.error {
+            Some(error) => (error.code, error.message),
+            None => (0, None),
+        };
+        Err(crate::Error::Api {
+            error: crate::SubsonicError::from_code(code),
+            message: message.unwrap_or_else(|| format!("{} failed", endpoint)),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiError {
+    pub code: u32,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct InternetRadioStation {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "streamUrl")]
+    pub stream_url: String,
+    #[serde(rename = "homePageUrl")]
+    pub home_page_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InternetRadioStationsResponse {
+    #[serde(rename = "internetRadioStations")]
+    pub internet_radio_stations: InternetRadioStations,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InternetRadioStations {
+    #[serde(rename = "internetRadioStation", default, deserialize_with = "crate::one_or_many::deserialize")]
+    pub internet_radio_station: Vec<InternetRadioStation>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Genre {
+    pub value: String,
+    #[serde(rename = "songCount")]
+    pub song_count: Option<u32>,
+    #[serde(rename = "albumCount")]
+    pub album_count: Option<u32>,
+    pub starred: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GenresResponse {
+    pub genres: Genres,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Genres {
+    #[serde(default, deserialize_with = "crate::one_or_many::deserialize")]
+    pub genre: Vec<Genre>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SongsByGenreResponse {
+    #[serde(rename = "songsByGenre")]
+    pub songs_by_genre: SongList,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SimilarSongs2Response {
+    #[serde(rename = "similarSongs2", default)]
+    pub similar_songs2: SongList,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct SongList {
+    #[serde(default, deserialize_with = "crate::one_or_many::deserialize")]
+    pub song: Vec<Song>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Starred2Response {
+    pub starred2: Starred,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Starred {
+    #[serde(default, deserialize_with = "crate::one_or_many::deserialize")]
+    pub artist: Vec<Artist>,
+    #[serde(default, deserialize_with = "crate::one_or_many::deserialize")]
+    pub album: Vec<Album>,
+    #[serde(default, deserialize_with = "crate::one_or_many::deserialize")]
+    pub song: Vec<Song>,
+}
+
+/// An order `getAlbumList2` can list the whole library's albums in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AlbumListType {
+    /// Recently added
+    Newest,
+    /// Recently played
+    Recent,
+    /// Most played
+    Frequent,
+    Random,
+    /// Newest release year first
+    ByYear,
+}
+
+impl AlbumListType {
+    pub const ALL: [AlbumListType; 5] = [
+        AlbumListType::Newest,
+        AlbumListType::Recent,
+        AlbumListType::Frequent,
+        AlbumListType::Random,
+        AlbumListType::ByYear,
+    ];
+
+    /// Value of the `type` parameter
+    pub fn param(&self) -> &'static str {
+        match self {
+            AlbumListType::Newest => "newest",
+            AlbumListType::Recent => "recent",
+            AlbumListType::Frequent => "frequent",
+            AlbumListType::Random => "random",
+            AlbumListType::ByYear => "byYear",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AlbumList2Response {
+    #[serde(rename = "albumList2", default)]
+    pub album_list2: AlbumList,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AlbumList {
+    #[serde(default, deserialize_with = "crate::one_or_many::deserialize")]
+    pub album: Vec<Album>,
+}
+
+/// One of the result lists returned by `search3`, which can be paged independently
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchCategory {
+    Artists,
+    Albums,
+    Songs,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchResult3Response {
+    #[serde(rename = "searchResult3")]
+    pub search_result3: SearchResult,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SearchResult {
+    #[serde(default, deserialize_with = "crate::one_or_many::deserialize")]
+    pub artist: Vec<Artist>,
+    #[serde(default, deserialize_with = "crate::one_or_many::deserialize")]
+    pub album: Vec<Album>,
+    #[serde(default, deserialize_with = "crate::one_or_many::deserialize")]
+    pub song: Vec<Song>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Playlist {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "songCount")]
+    pub song_count: Option<u32>,
+    pub duration: Option<u32>,
+    pub owner: Option<String>,
+    pub comment: Option<String>,
+    /// Visible to the other users of the server
+    pub public: Option<bool>,
+    pub created: Option<String>,
+    pub changed: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlaylistsResponse {
+    pub playlists: Playlists,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Playlists {
+    #[serde(default, deserialize_with = "crate::one_or_many::deserialize")]
+    pub playlist: Vec<Playlist>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlaylistResponse {
+    pub playlist: PlaylistDetail,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlaylistDetail {
+    #[allow(dead_code)]
+    pub id: String,
+    #[allow(dead_code)]
+    pub name: String,
+    #[serde(default, deserialize_with = "crate::one_or_many::deserialize")]
+    pub entry: Vec<Song>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JukeboxStatusResponse {
+    #[serde(rename = "jukeboxStatus")]
+    pub jukebox_status: JukeboxStatus,
+}
+
+/// State of the jukebox, the audio output of the server itself, as `jukeboxControl` reports it
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct JukeboxStatus {
+    /// Index of the current song in the jukebox playlist, -1 when there is none
+    #[serde(rename = "currentIndex")]
+    pub current_index: i32,
+    pub playing: bool,
+    /// Volume between 0 and 1
+    pub gain: f32,
+    /// Position in the current song in seconds
+    pub position: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScanStatusResponse {
+    #[serde(rename = "scanStatus")]
+    pub scan_status: ScanStatus,
+}
+
+/// Whether the server is scanning its media folders, as `startScan` and `getScanStatus` report it
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ScanStatus {
+    pub scanning: bool,
+    /// Files scanned so far, or in the last scan once it is done
+    pub count: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlayQueueResponse {
+    #[serde(rename = "playQueue")]
+    pub play_queue: Option<PlayQueue>,
+}
+
+/// The queue bookmark stored on the server by `savePlayQueue`
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlayQueue {
+    #[serde(default, deserialize_with = "crate::one_or_many::deserialize")]
+    pub entry: Vec<Song>,
+    /// ID of the current song, a number on some servers
+    pub current: Option<serde_json::Value>,
+    /// Position in the current song in milliseconds
+    pub position: Option<u64>,
+    /// When the queue was saved, as an ISO 8601 timestamp
+    pub changed: Option<String>,
+    #[serde(rename = "changedBy")]
+    pub changed_by: Option<String>,
+}
+
+impl PlayQueue {
+    pub fn current_id(&self) -> Option<String> {
+        match self.current.as_ref()? {
+            serde_json::Value::String(id) => Some(id.clone()),
+            other => Some(other.to_string()),
+        }
+    }
+
+    /// `changed` as Unix time in seconds
+    pub fn changed_at(&self) -> Option<u64> {
+        parse_timestamp(self.changed.as_deref()?)
+    }
+}
+
+/// How the server re-encodes songs for streaming, as the `maxBitRate` and `format`
+/// parameters of `stream` ask for
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Transcoding {
+    /// Highest bitrate in kbit/s, 0 for no limit
+    pub max_bit_rate: u32,
+    /// Format to transcode to, like "opus" or "mp3", `None` for the server's choice.
+    /// "raw" streams the original file.
+    pub format: Option<String>,
+}
+
+impl std::fmt::Display for Transcoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match (self.max_bit_rate, &self.format) {
+            (0, Some(format)) => write!(f, "{}", format),
+            (0, None) => write!(f, "original"),
+            (rate, Some(format)) => write!(f, "{} kbps {}", rate, format),
+            (rate, None) => write!(f, "{} kbps", rate),
+        }
+    }
+}
+
+/// Parse the date and time of an ISO 8601 timestamp such as `2024-05-01T12:30:00.000Z`
+/// into Unix time in seconds. Time zone offsets other than UTC are not supported.
+fn parse_timestamp(timestamp: &str) -> Option<u64> {
+    let number = |range: std::ops::Range<usize>| timestamp.get(range)?.parse::<i64>().ok();
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+
+    // Days since the epoch of the civil date, after Howard Hinnant's algorithm
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    u64::try_from(days * 86400 + hour * 3600 + minute * 60 + second).ok()
+}